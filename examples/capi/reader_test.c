@@ -0,0 +1,65 @@
+/*
+ * Smoke test of the C API: open the archive given on the command line, list its op_ids, fetch
+ * every blob, check the signature and the error paths. Exits non-zero on the first failure.
+ *
+ *   cargo rustc --release --lib --features capi --crate-type staticlib
+ *   cargo run --bin clf -- -o /tmp/capi.clf 1:README.md 50:LICENSE
+ *   cc -Iinclude examples/capi/reader_test.c target/release/libclf.a -lpthread -ldl -lm \
+ *       -o /tmp/clf_capi_test
+ *   /tmp/clf_capi_test /tmp/capi.clf
+ */
+#include <stdio.h>
+
+#include "clf.h"
+
+#define CHECK(cond)                                                     \
+    do {                                                                \
+        if (!(cond)) {                                                  \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__,     \
+                    __LINE__, #cond);                                   \
+            return 1;                                                   \
+        }                                                               \
+    } while (0)
+
+int main(int argc, char **argv) {
+    CHECK(argc == 2);
+
+    CHECK(clf_reader_open("/nonexistent/archive.clf") == NULL);
+    CHECK(clf_last_error_message(NULL) != NULL);
+
+    ClfReaderHandle *reader = clf_reader_open(argv[1]);
+    if (reader == NULL) {
+        fprintf(stderr, "open failed: %s\n", clf_last_error_message(NULL));
+        return 1;
+    }
+    CHECK(clf_last_error_message(NULL) == NULL);
+
+    size_t count = clf_reader_op_count(reader);
+    CHECK(count > 0);
+    for (size_t i = 0; i < count; i++) {
+        uint32_t op_id = 0;
+        CHECK(clf_reader_op_id_at(reader, i, &op_id) == CLF_OK);
+        uint8_t *blob = NULL;
+        size_t len = 0;
+        CHECK(clf_reader_get_blob(reader, op_id, &blob, &len) == CLF_OK);
+        printf("op_id %u: %zu bytes\n", op_id, len);
+        clf_blob_free(blob, len);
+    }
+
+    uint32_t op_id = 0;
+    CHECK(clf_reader_op_id_at(reader, count, &op_id) == CLF_ERR_INVALID_ARGUMENT);
+    CHECK(clf_last_error_message(reader) != NULL);
+    uint8_t *blob = NULL;
+    size_t len = 0;
+    CHECK(clf_reader_get_blob(reader, 0xFFFFFFFFu, &blob, &len) == CLF_ERR_NOT_FOUND);
+    CHECK(blob == NULL);
+
+    int scope = clf_reader_verify_signature(reader);
+    CHECK(scope >= CLF_SIGNATURE_NONE && scope <= CLF_SIGNATURE_KEYED);
+    CHECK(clf_last_error_message(reader) == NULL);
+    static const char *const scopes[] = {"none", "valid", "valid (index only)", "keyed"};
+    printf("signature: %s\n", scopes[scope]);
+
+    clf_reader_close(reader);
+    return 0;
+}
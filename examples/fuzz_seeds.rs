@@ -0,0 +1,161 @@
+//! Write seed archives for the fuzz targets: one small archive per format version and per packer
+//! option that changes the layout (alignment, CRCs, variants, target table, header sections,
+//! signature trailers, compression). The fuzzer mutates these rather than starting from nothing.
+//! Every seed is checked with `parse_untrusted` before it is written.
+//!
+//! Run: cargo run --example fuzz_seeds --all-features -- fuzz/corpus/parse_untrusted
+
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::path::PathBuf;
+
+use clf::{
+    pack_and_sign, pack_clf_variants, parse_untrusted, ClfKind, ClfLimits, HmacKey, PackOptions,
+};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = PathBuf::from(
+        std::env::args()
+            .nth(1)
+            .unwrap_or_else(|| "fuzz/corpus/parse_untrusted".to_string()),
+    );
+    std::fs::create_dir_all(&dir)?;
+
+    let entries: Vec<(u32, Vec<u8>)> = vec![
+        (1, b"add".to_vec()),
+        (2, b"sub".to_vec()),
+        (50, b"matmul".to_vec()),
+    ];
+    let base = PackOptions {
+        vendor: "seed".to_string(),
+        target: "cpu".to_string(),
+        deterministic: true,
+        ..PackOptions::default()
+    };
+    let mut seeds: Vec<(&str, PackOptions)> = vec![
+        ("v3_plain", base.clone()),
+        (
+            "v3_empty_strings",
+            PackOptions {
+                vendor: String::new(),
+                target: String::new(),
+                ..base.clone()
+            },
+        ),
+        (
+            "v1",
+            PackOptions {
+                version: 1,
+                ..base.clone()
+            },
+        ),
+        (
+            "v2_executor",
+            PackOptions {
+                version: 2,
+                kind: ClfKind::Executor,
+                ..base.clone()
+            },
+        ),
+        (
+            "v3_aligned_crc",
+            PackOptions {
+                blob_alignment: 16,
+                per_blob_crc: true,
+                ..base.clone()
+            },
+        ),
+        (
+            "v3_sections",
+            PackOptions {
+                metadata: vec![("driver".to_string(), "1.2.3".to_string())],
+                created_at: Some(1_700_000_000),
+                tool_version: Some("clf".to_string()),
+                deterministic: false,
+                entry_names: HashMap::from([(50, "matmul".to_string())]),
+                extensions: vec![(0x0001, b"ext".to_vec())],
+                ..base.clone()
+            },
+        ),
+        (
+            "v3_targets",
+            PackOptions {
+                entry_targets: HashMap::from([((2, 0), "gpu".to_string())]),
+                ..base.clone()
+            },
+        ),
+        (
+            "v3_sig0",
+            PackOptions {
+                sign: true,
+                ..base.clone()
+            },
+        ),
+        (
+            "v3_sig1_index_only",
+            PackOptions {
+                sign: true,
+                sign_index_only: true,
+                per_blob_crc: true,
+                ..base.clone()
+            },
+        ),
+    ];
+    seeds.push((
+        "v3_hmac",
+        PackOptions {
+            hmac_key: Some(HmacKey::new(b"seed key".to_vec())),
+            ..base.clone()
+        },
+    ));
+    #[cfg(feature = "compression")]
+    seeds.push((
+        "v3_zstd",
+        PackOptions {
+            compression: Some(clf::Compression::Zstd(3)),
+            per_blob_crc: true,
+            ..base.clone()
+        },
+    ));
+    #[cfg(feature = "ed25519")]
+    seeds.push((
+        "v3_ed25519",
+        PackOptions {
+            sign_key: Some(clf::ed25519_dalek::SigningKey::from_bytes(&[7; 32])),
+            ..base.clone()
+        },
+    ));
+
+    for (name, options) in &seeds {
+        let mut buf = Cursor::new(Vec::new());
+        pack_and_sign(&mut buf, &entries, options)?;
+        write_seed(&dir, name, buf.get_ref())?;
+    }
+
+    let variants: Vec<(u32, u16, Vec<u8>)> = vec![
+        (1, 0, b"add".to_vec()),
+        (1, 2, b"add_avx2".to_vec()),
+        (50, 0, b"matmul".to_vec()),
+    ];
+    let mut buf = Cursor::new(Vec::new());
+    pack_clf_variants(&mut buf, &variants, &base)?;
+    write_seed(&dir, "v3_variants", buf.get_ref())?;
+
+    let mut buf = Cursor::new(Vec::new());
+    pack_and_sign(&mut buf, Vec::<(u32, Vec<u8>)>::new(), &base)?;
+    write_seed(&dir, "v3_no_entries", buf.get_ref())?;
+    Ok(())
+}
+
+/// Write `bytes` as `<name>.clf` after checking that `parse_untrusted` accepts it.
+fn write_seed(
+    dir: &std::path::Path,
+    name: &str,
+    bytes: &[u8],
+) -> Result<(), Box<dyn std::error::Error>> {
+    parse_untrusted(bytes, &ClfLimits::default())?;
+    let path = dir.join(format!("{name}.clf"));
+    std::fs::write(&path, bytes)?;
+    println!("{} ({} bytes)", path.display(), bytes.len());
+    Ok(())
+}
@@ -0,0 +1,34 @@
+//! Round-trip an archive without touching the file system: pack into a `Vec<u8>`, parse it back
+//! with `ClfReaderFromBytes`. Only these in-memory paths are usable on `wasm32-unknown-unknown`,
+//! where the crate also builds (path-based APIs compile there but fail with an I/O error).
+//!
+//! Run: cargo run --example in_memory
+
+use std::io::Cursor;
+
+use clf::{pack_clf, ClfReaderFromBytes, PackOptions};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let entries: Vec<(u32, Vec<u8>)> = vec![
+        (1, b"blob_for_add".to_vec()),
+        (50, b"blob_for_matmul".to_vec()),
+    ];
+    let options = PackOptions {
+        vendor: "example".to_string(),
+        ..PackOptions::default()
+    };
+
+    let mut buf = Cursor::new(Vec::new());
+    pack_clf(&mut buf, &entries, &options)?;
+    let bytes = buf.into_inner();
+    println!("packed {} bytes", bytes.len());
+
+    let reader = ClfReaderFromBytes::open(&bytes, None)?;
+    reader.validate()?;
+    println!("vendor: {}", reader.header.vendor);
+    for entry in reader.manifest_entries() {
+        let blob = reader.get_blob(entry.op_id)?.unwrap_or_default();
+        println!("  op_id {}: {} bytes", entry.op_id, blob.len());
+    }
+    Ok(())
+}
@@ -0,0 +1,22 @@
+#![no_main]
+
+use clf::ClfLimits;
+use libfuzzer_sys::fuzz_target;
+
+// Tighter than the defaults so a mutated raw size cannot ask for hundreds of MiB.
+const LIMITS: ClfLimits = ClfLimits {
+    max_text_len: 4 * 1024,
+    max_section_len: 64 * 1024,
+    max_entries: 4096,
+    max_targets: 64,
+    max_blob_len: 1 << 20,
+};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(parsed) = clf::parse_untrusted(data, &LIMITS) else {
+        return;
+    };
+    for entry in &parsed.manifest.entries {
+        let _ = parsed.get_blob_variant(entry.op_id, entry.variant);
+    }
+});
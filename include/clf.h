@@ -0,0 +1,70 @@
+/*
+ * C API for the CLF reader (Rust crate `clf`, feature `capi`).
+ *
+ * Build:  cargo rustc --release --lib --features capi --crate-type staticlib
+ *         (or --crate-type cdylib) and link target/release/libclf.a (or the shared library).
+ *
+ * Functions returning int return CLF_OK or a negative CLF_ERR_* code; clf_last_error_message
+ * then describes the failure. A handle must be used from one thread at a time.
+ */
+#ifndef CLF_H
+#define CLF_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define CLF_OK 0
+#define CLF_ERR_INVALID_ARGUMENT (-1)
+#define CLF_ERR_IO (-2)
+#define CLF_ERR_FORMAT (-3)
+#define CLF_ERR_SIGNATURE (-4)
+#define CLF_ERR_NOT_FOUND (-5)
+#define CLF_ERR_PANIC (-6)
+
+/* Results of clf_reader_verify_signature. */
+#define CLF_SIGNATURE_NONE 0
+#define CLF_SIGNATURE_FULL 1
+#define CLF_SIGNATURE_INDEX_ONLY 2
+#define CLF_SIGNATURE_KEYED 3
+
+typedef struct ClfReaderHandle ClfReaderHandle;
+
+/* Open the archive at path. NULL on failure: see clf_last_error_message(NULL). */
+ClfReaderHandle *clf_reader_open(const char *path);
+
+/* Close a handle; NULL is ignored. */
+void clf_reader_close(ClfReaderHandle *handle);
+
+/* Copy the blob for op_id into a new buffer (*out_ptr, *out_len); free with clf_blob_free.
+ * CLF_ERR_NOT_FOUND when the archive has no such op_id. */
+int clf_reader_get_blob(ClfReaderHandle *handle, uint32_t op_id, uint8_t **out_ptr,
+                        size_t *out_len);
+
+/* Free a blob from clf_reader_get_blob, passing the length it reported. */
+void clf_blob_free(uint8_t *ptr, size_t len);
+
+/* What a matching signature covers: CLF_SIGNATURE_FULL (whole file), CLF_SIGNATURE_INDEX_ONLY
+ * (header and manifest only; blobs unchecked), CLF_SIGNATURE_NONE if unsigned, or
+ * CLF_SIGNATURE_KEYED for an HMAC / Ed25519 block, which needs a key and is not checked.
+ * CLF_ERR_SIGNATURE on mismatch. */
+int clf_reader_verify_signature(ClfReaderHandle *handle);
+
+/* Number of distinct op_ids; 0 for NULL. */
+size_t clf_reader_op_count(const ClfReaderHandle *handle);
+
+/* The index-th op_id in ascending order. */
+int clf_reader_op_id_at(ClfReaderHandle *handle, size_t index, uint32_t *out_op_id);
+
+/* Message for the last failed call on handle (or the last failed open on this thread when
+ * handle is NULL); NULL if that call succeeded. Valid until the next call on the handle. */
+const char *clf_last_error_message(const ClfReaderHandle *handle);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* CLF_H */
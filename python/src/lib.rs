@@ -0,0 +1,171 @@
+//! Python bindings for the CLF reader and packer (`import clf`).
+//!
+//! Build a wheel with `maturin build --release` from this directory, or install into the
+//! current virtualenv with `maturin develop`. Not part of the `clf` crate's build: pyo3 is only
+//! pulled in here.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyOSError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+create_exception!(
+    clf,
+    ClfError,
+    PyException,
+    "Malformed archive, failed signature check or packing error."
+);
+
+/// `ClfError` (or `OSError` for I/O failures) carrying the Rust error's message, which names
+/// the file for errors from `ClfReader`.
+fn reader_err(e: clf::ClfError) -> PyErr {
+    match e.root() {
+        clf::ClfError::Io(_) => PyOSError::new_err(e.to_string()),
+        _ => ClfError::new_err(e.to_string()),
+    }
+}
+
+fn pack_err(e: clf::PackError) -> PyErr {
+    match e {
+        clf::PackError::Io(e) => PyOSError::new_err(e.to_string()),
+        e => ClfError::new_err(e.to_string()),
+    }
+}
+
+/// Reader over a .clf file: header attributes, op_ids and blobs.
+#[pyclass(name = "ClfReader", module = "clf")]
+struct PyClfReader {
+    inner: clf::ClfReader,
+}
+
+#[pymethods]
+impl PyClfReader {
+    /// Open the archive at `path`. Raises `OSError` if it cannot be read and `ClfError` if it
+    /// is not a valid archive.
+    #[staticmethod]
+    fn open(path: PathBuf) -> PyResult<Self> {
+        clf::ClfReader::open(path)
+            .map(|inner| Self { inner })
+            .map_err(reader_err)
+    }
+
+    /// Distinct op_ids in ascending order.
+    fn op_ids(&self) -> Vec<u32> {
+        self.inner.op_ids()
+    }
+
+    /// Blob for `op_id` (default variant, decompressed), or `None` if the archive has none.
+    fn get_blob<'py>(
+        &mut self,
+        py: Python<'py>,
+        op_id: u32,
+    ) -> PyResult<Option<Bound<'py, PyBytes>>> {
+        let blob = self.inner.get_blob(op_id).map_err(reader_err)?;
+        Ok(blob.map(|blob| PyBytes::new(py, &blob)))
+    }
+
+    /// `True` if the signature matches and covers the whole file, `False` if the archive is
+    /// unsigned or its signature leaves the blobs unchecked (see `verify_signature_scope`).
+    /// Raises `ClfError` when a signature is present but does not match.
+    fn verify_signature(&mut self) -> PyResult<bool> {
+        self.inner.verify_signature().map_err(reader_err)
+    }
+
+    /// What a matching signature covers: `"full"`, `"index_only"` (header and manifest; blobs
+    /// unchecked), `"keyed"` for an HMAC or Ed25519 block (not checked: it needs a key), or
+    /// `None` if the archive is unsigned. Raises `ClfError` on a mismatch.
+    fn verify_signature_scope(&mut self) -> PyResult<Option<&'static str>> {
+        let scope = self.inner.verify_signature_scope().map_err(reader_err)?;
+        Ok(match scope {
+            clf::SignatureScope::Full => Some("full"),
+            clf::SignatureScope::IndexOnly => Some("index_only"),
+            clf::SignatureScope::None if self.inner.signature_block_present() => Some("keyed"),
+            clf::SignatureScope::None => None,
+        })
+    }
+
+    /// Format version of the file.
+    #[getter]
+    fn version(&self) -> u8 {
+        self.inner.header.version
+    }
+
+    #[getter]
+    fn vendor(&self) -> &str {
+        &self.inner.header.vendor
+    }
+
+    #[getter]
+    fn target(&self) -> &str {
+        &self.inner.header.target
+    }
+
+    /// Kind name, e.g. `"compute"`.
+    #[getter]
+    fn kind(&self) -> &'static str {
+        self.inner.header.kind.name()
+    }
+
+    #[getter]
+    fn blob_alignment(&self) -> u8 {
+        self.inner.header.blob_alignment
+    }
+
+    /// Build metadata key/value pairs.
+    #[getter]
+    fn metadata(&self) -> BTreeMap<String, String> {
+        self.inner.header.metadata.iter().cloned().collect()
+    }
+
+    fn __repr__(&self) -> String {
+        let header = &self.inner.header;
+        format!(
+            "ClfReader(vendor={:?}, target={:?}, kind={:?}, version={}, ops={})",
+            header.vendor,
+            header.target,
+            header.kind.name(),
+            header.version,
+            self.inner.op_ids().len()
+        )
+    }
+}
+
+/// Pack `entries` (op_id -> blob) into an archive and return its bytes. Entries are written in
+/// op_id order. `kind` takes any name `ClfKind` accepts (`"compute"`, `"clfe"`, ...); with
+/// `sign=True` a SHA-256 integrity trailer is appended.
+#[pyfunction]
+#[pyo3(signature = (entries, vendor = String::new(), target = String::new(), kind = "compute", sign = false))]
+fn pack<'py>(
+    py: Python<'py>,
+    entries: BTreeMap<u32, Vec<u8>>,
+    vendor: String,
+    target: String,
+    kind: &str,
+    sign: bool,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let kind = clf::ClfKind::from_str(kind).map_err(PyValueError::new_err)?;
+    let options = clf::PackOptions {
+        vendor,
+        target,
+        kind,
+        sign,
+        ..clf::PackOptions::default()
+    };
+    let mut out = std::io::Cursor::new(Vec::new());
+    clf::pack_and_sign(&mut out, entries, &options).map_err(pack_err)?;
+    Ok(PyBytes::new(py, out.get_ref()))
+}
+
+#[pymodule]
+#[pyo3(name = "clf")]
+fn clf_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyClfReader>()?;
+    m.add_function(wrap_pyfunction!(pack, m)?)?;
+    m.add("ClfError", m.py().get_type::<ClfError>())?;
+    m.add("CLF_VERSION", clf::CLF_VERSION)?;
+    Ok(())
+}
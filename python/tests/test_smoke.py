@@ -0,0 +1,95 @@
+"""Smoke test of the Python bindings.
+
+Run after `maturin develop` (or with the built extension on PYTHONPATH):
+
+    python -m pytest tests/test_smoke.py
+
+Plain asserts only, so `python tests/test_smoke.py` works without pytest as well.
+"""
+
+import os
+import tempfile
+
+import clf
+
+
+def _write(data):
+    fd, path = tempfile.mkstemp(suffix=".clfc")
+    with os.fdopen(fd, "wb") as f:
+        f.write(data)
+    return path
+
+
+def test_pack_and_read_back():
+    data = clf.pack({50: b"matmul", 1: b"add"}, vendor="acme", target="cpu")
+    path = _write(data)
+    try:
+        reader = clf.ClfReader.open(path)
+        assert reader.op_ids() == [1, 50]
+        assert reader.get_blob(1) == b"add"
+        assert reader.get_blob(50) == b"matmul"
+        assert reader.get_blob(2) is None
+        assert reader.vendor == "acme"
+        assert reader.target == "cpu"
+        assert reader.kind == "compute"
+        assert reader.version == clf.CLF_VERSION
+        assert reader.verify_signature() is False
+        assert reader.verify_signature_scope() is None
+    finally:
+        os.remove(path)
+
+
+def test_signed_archive_verifies_and_tampering_is_detected():
+    data = bytearray(clf.pack({1: b"add"}, kind="clfe", sign=True))
+    path = _write(bytes(data))
+    try:
+        reader = clf.ClfReader.open(path)
+        assert reader.kind == "executor"
+        assert reader.verify_signature() is True
+        assert reader.verify_signature_scope() == "full"
+    finally:
+        os.remove(path)
+
+    data[data.index(b"add")] ^= 0xFF
+    path = _write(bytes(data))
+    try:
+        reader = clf.ClfReader.open(path)
+        try:
+            reader.verify_signature()
+        except clf.ClfError:
+            pass
+        else:
+            raise AssertionError("tampered archive verified")
+    finally:
+        os.remove(path)
+
+
+def test_errors():
+    try:
+        clf.ClfReader.open("/nonexistent/archive.clf")
+    except OSError:
+        pass
+    else:
+        raise AssertionError("opened a missing file")
+    path = _write(b"not a clf")
+    try:
+        clf.ClfReader.open(path)
+    except clf.ClfError:
+        pass
+    else:
+        raise AssertionError("opened garbage")
+    finally:
+        os.remove(path)
+    try:
+        clf.pack({1: b"x"}, kind="nonsense")
+    except ValueError:
+        pass
+    else:
+        raise AssertionError("accepted an unknown kind")
+
+
+if __name__ == "__main__":
+    test_pack_and_read_back()
+    test_signed_archive_verifies_and_tampering_is_detected()
+    test_errors()
+    print("ok")
@@ -1,20 +1,30 @@
-// CLF packer CLI: build .clf archives, inspect them, or verify SIG0 + SHA-256.
+// CLF packer CLI: build .clf archives, inspect, verify, diff, merge, upgrade, remap, recompress or
+// hash them, check them against a model's op list, or extract their blobs.
 // Installed as `clf` or `coelanox-packer` (same behavior; see src/bin/clf.rs).
 
-use std::fs::{File, OpenOptions};
-use std::collections::HashMap;
-use std::io::Read;
+use std::cell::RefCell;
+use std::fs::File;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufReader, Read};
 use std::path::{Path, PathBuf};
 use std::process::ExitCode;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use clap::Parser;
 use sha2::{Digest, Sha256};
 
 use clf::{
-    append_signature, load_pack_manifest, pack_clf, parse_op_blob_arg, sidecar, ClfReader,
-    PackManifestBlob, PackManifestResolved, PackOptions, VerificationPolicy, CLF_VERSION,
+    convert_version_to_path, load_op_list, load_pack_manifest, load_remap_file, merge_clf_to_path,
+    pack_clf_streaming_to_path, parse_op_blob_arg, plan_merge, plan_remap, remap_op_ids_to_path,
+    sidecar, AppendOptions, ClfError, ClfReader, ConflictPolicy, MergeOptions, MismatchPolicy,
+    OpRegistry, PackManifestBlob, PackManifestResolved, PackOptions, RepackOptions, SignatureScope,
+    VerificationPolicy, VersionFeatures, CLF_VERSION,
 };
 
+/// Written to the header's `tool_version` unless `--deterministic` is given.
+const TOOL_VERSION: &str = concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"));
+
 #[derive(clap::ValueEnum, Clone, Debug)]
 enum VerifyPolicyArg {
     IntegrityOnly,
@@ -30,52 +40,241 @@ impl From<VerifyPolicyArg> for VerificationPolicy {
     }
 }
 
+#[cfg(feature = "elf")]
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum InputFormat {
+    Raw,
+    Elf,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default)]
+enum OnConflictArg {
+    #[default]
+    Error,
+    PreferFirst,
+    #[value(alias = "prefer-second")]
+    PreferLast,
+}
+
+impl From<OnConflictArg> for ConflictPolicy {
+    fn from(value: OnConflictArg) -> Self {
+        match value {
+            OnConflictArg::Error => ConflictPolicy::Error,
+            OnConflictArg::PreferFirst => ConflictPolicy::PreferFirst,
+            OnConflictArg::PreferLast => ConflictPolicy::PreferLast,
+        }
+    }
+}
+
 #[derive(Parser)]
 #[command(
     name = env!("CARGO_BIN_NAME"),
     version,
     about = "Build, inspect, or verify Coelanox Library Files (.clf, .clfc, …)",
-    long_about = "Pack: write a CLF from op_id:path pairs or a TOML manifest (--from).\n\
+    long_about = "Pack: write a CLF from op:path pairs (op_id or op name) or a TOML/JSON manifest (--manifest).\n\
                   Inspect: print header and manifest (-i), optional JSON (--json).\n\
-                  Verify: check SIG0 + SHA-256 only (--verify).\n\
+                  Verify: check structure and SIG0 + SHA-256 if present (--verify).\n\
                   \n\
                   Examples:\n\
                     clf -o out.clfc --align 16 1:a.bin 50:b.bin\n\
-                    clf --from pack.toml -o out.clfc --dry-run\n\
+                    clf -o out.clfc add:a.bin matmul:b.bin 300:custom.bin\n\
+                    clf --manifest pack.toml -o out.clfc --dry-run\n\
+                    clf --from-dir build/kernels -o cpu.clfc 50:tuned_matmul.bin\n\
+                    clf -o out.clfc --max-size 2GiB --from-dir build/kernels\n\
                     clf -i out.clfc --json\n\
-                    clf --verify out.clfc\n\
+                    clf --verify out.clfc --require-signature\n\
+                    clf --extract out.clfc --op 50 -o matmul.bin\n\
+                    clf --extract out.clfc --all -o blobs/\n\
+                    clf --merge a.clfc b.clfc -o combined.clfc --on-conflict prefer-first\n\
+                    clf --diff old.clfc new.clfc --exit-code\n\
+                    clf --sign-archive out.clfc --force\n\
+                    clf --append out.clfc 301:new_kernel.bin --replace\n\
+                    clf --upgrade legacy.clf -o legacy.clfc --kind compute --sign\n\
+                    clf --remap old.clfc --map remap.toml -o new.clfc --sign\n\
+                    clf --recompress out.clfc -o small.clfc --zstd 9\n\
+                    clf --stats out.clfc --top 10\n\
+                    clf --hash out.clfc --op matmul\n\
+                    clf --coverage cpu.clfc --ops model_ops.txt --allow-missing 2\n\
+                    clf --dump-op-ids --json\n\
                     clf --verify out.clfc --verify-policy integrity-only\n"
 )]
 struct Cli {
     /// Print header and manifest (human-readable); use --json for machine output
-    #[arg(long, short = 'i', value_name = "FILE", conflicts_with_all = ["verify", "output", "from_manifest"])]
+    #[arg(long, short = 'i', value_name = "FILE", group = "read_mode", conflicts_with_all = ["verify", "output", "from_manifest", "from_dir"])]
     inspect: Option<PathBuf>,
 
-    /// Verify SIG0 + SHA-256 and exit 0 (ok) or 1 (missing/invalid); for CI
-    #[arg(long, value_name = "FILE", conflicts_with_all = ["inspect", "output", "from_manifest", "entries"])]
+    /// Write blobs from FILE to disk: one with --op, or every entry with --all as
+    /// `<op_id>_<op name>.bin` in the -o directory
+    #[arg(long, short = 'x', value_name = "FILE", group = "read_mode", group = "op_mode", conflicts_with_all = ["verify", "from_manifest", "from_dir", "entries"])]
+    extract: Option<PathBuf>,
+
+    /// With --extract: the op to write, as an op_id or canonical name (default variant). With
+    /// --hash: list only that op
+    #[arg(
+        long,
+        value_name = "OP",
+        value_parser = clf::parse_op_id,
+        requires = "op_mode",
+        conflicts_with = "all"
+    )]
+    op: Option<u32>,
+
+    /// With --extract: write every entry
+    #[arg(long, requires = "extract")]
+    all: bool,
+
+    /// With --extract: overwrite existing files. With --sign-archive: replace an existing
+    /// signature block
+    #[arg(long)]
+    force: bool,
+
+    /// Append a SIG0 signature block to an already-built archive (or remove it with --strip)
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["inspect", "verify", "extract", "merge", "diff", "output", "from_manifest", "from_dir", "entries"])]
+    sign_archive: Option<PathBuf>,
+
+    /// With --sign-archive: remove the signature block instead of adding one
+    #[arg(long, requires = "sign_archive")]
+    strip: bool,
+
+    /// Add the OP:PATH entries to an existing unsigned archive in place, leaving its blobs where
+    /// they are when the manifest still fits in front of them; --sign signs the result
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["inspect", "verify", "extract", "merge", "diff", "sign_archive", "stats", "output", "from_manifest", "from_dir"])]
+    append: Option<PathBuf>,
+
+    /// With --append: replace entries whose op_id the archive already has instead of failing
+    #[arg(long, requires = "append")]
+    replace: bool,
+
+    /// Rewrite FILE (e.g. a legacy v1 archive) in format --to-version at -o, and check that
+    /// every blob reads back unchanged before the output is put in place. --kind, --vendor and
+    /// --target override its header; --align/--sign/--crc/--dedup/--zstd apply to the output
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["inspect", "verify", "extract", "merge", "diff", "sign_archive", "stats", "append", "from_manifest", "from_dir", "entries"])]
+    upgrade: Option<PathBuf>,
+
+    /// With --upgrade: format version to write (default: the newest). Older versions are refused
+    /// when the archive uses features they cannot express
+    #[arg(long, value_name = "N", requires = "upgrade")]
+    to_version: Option<u8>,
+
+    /// Renumber the op_ids of FILE as --map (and/or --use-registry-aliases) says and write the
+    /// result to -o. Blobs are copied through untouched; the signature is dropped unless --sign
+    /// is given
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["inspect", "verify", "extract", "merge", "diff", "sign_archive", "stats", "append", "upgrade", "from_manifest", "from_dir", "entries"])]
+    remap: Option<PathBuf>,
+
+    /// With --remap: TOML (or .json) file of `old = new` op_id pairs, e.g. `50 = 300`
+    #[arg(long, value_name = "MAP", requires = "remap")]
+    map: Option<PathBuf>,
+
+    /// With --remap: also map every deprecated op_id in FILE to its canonical replacement from
+    /// the registry (pairs in --map take precedence)
+    #[arg(long, requires = "remap")]
+    use_registry_aliases: bool,
+
+    /// Re-encode the blobs of FILE into -o: zstd at --zstd LEVEL, or uncompressed with
+    /// --decompress. Header, names and targets are kept, blobs that zstd would grow are stored
+    /// raw, and every blob is checked to read back unchanged. --sign signs the output
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["inspect", "verify", "extract", "merge", "diff", "sign_archive", "stats", "hash", "coverage", "append", "upgrade", "remap", "from_manifest", "from_dir", "entries"])]
+    recompress: Option<PathBuf>,
+
+    /// With --recompress: store every blob uncompressed
+    #[arg(long, requires = "recompress")]
+    decompress: bool,
+
+    /// Merge two or more archives of the same kind into -o; --vendor/--target override the
+    /// output header, and --align/--sign/--crc/--dedup/--zstd apply to the output
+    #[arg(long, value_name = "FILE", num_args = 2.., conflicts_with_all = ["inspect", "verify", "extract", "from_manifest", "from_dir", "entries"])]
+    merge: Vec<PathBuf>,
+
+    /// Compare two archives: header fields, added and removed entries, and entries whose blob
+    /// contents differ (by SHA-256)
+    #[arg(long, value_names = ["OLD", "NEW"], num_args = 2, conflicts_with_all = ["inspect", "verify", "extract", "merge", "output", "from_manifest", "from_dir", "entries"])]
+    diff: Vec<PathBuf>,
+
+    /// Size breakdown of FILE: header and manifest overhead, bytes per op (largest first),
+    /// alignment padding, and entries with identical contents
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["inspect", "verify", "extract", "merge", "diff", "sign_archive", "output", "from_manifest", "from_dir", "entries"])]
+    stats: Option<PathBuf>,
+
+    /// SHA-256 of every blob in FILE (decoded bytes, streamed) with its op_id, name and size,
+    /// then of the whole file and of the region its signature covers. For supply-chain audits
+    #[arg(long, value_name = "FILE", group = "op_mode", conflicts_with_all = ["inspect", "verify", "extract", "merge", "diff", "sign_archive", "stats", "append", "upgrade", "remap", "output", "from_manifest", "from_dir", "entries"])]
+    hash: Option<PathBuf>,
+
+    /// Check that FILE has every op listed in --ops: present and missing ops with names and
+    /// sizes, and the share covered. Exits 1 when ops are missing (see --allow-missing)
+    #[arg(long, value_name = "FILE", requires = "ops", conflicts_with_all = ["inspect", "verify", "extract", "merge", "diff", "sign_archive", "stats", "hash", "append", "upgrade", "remap", "output", "from_manifest", "from_dir", "entries"])]
+    coverage: Option<PathBuf>,
+
+    /// With --coverage: text file with one op_id or op name per line (`#` comments allowed)
+    #[arg(long, value_name = "OPS", requires = "coverage")]
+    ops: Option<PathBuf>,
+
+    /// With --coverage: succeed as long as at most N ops are missing
+    #[arg(long, value_name = "N", default_value_t = 0, requires = "coverage")]
+    allow_missing: usize,
+
+    /// Print the op_id registry (op_id, name, category, since, aliases) as Markdown, or JSON
+    /// with --json
+    #[arg(long, conflicts_with_all = ["inspect", "verify", "extract", "merge", "diff", "sign_archive", "stats", "output", "from_manifest", "from_dir", "entries"])]
+    dump_op_ids: bool,
+
+    /// With --stats: list only the N largest entries
+    #[arg(long, value_name = "N", requires = "stats")]
+    top: Option<usize>,
+
+    /// With --diff: exit 1 when the archives differ
+    #[arg(long, requires = "diff")]
+    exit_code: bool,
+
+    /// With --merge: what to do when several inputs have the same op_id
+    #[arg(long, value_enum, value_name = "POLICY", requires = "merge")]
+    on_conflict: Option<OnConflictArg>,
+
+    /// Check structure (bounds, duplicates, overlaps) and SIG0 + SHA-256 if present; exit 0 only
+    /// when everything passes. For deployment gates
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["inspect", "output", "from_manifest", "from_dir", "entries"])]
     verify: Option<PathBuf>,
 
+    /// With --verify: fail when the file has no signature block
+    #[arg(long, requires = "verify")]
+    require_signature: bool,
+
     /// Verification policy for `--verify` or `--inspect --verify-signature`.
     /// `require-authenticity` is reserved for future authenticated signatures.
     #[arg(long, value_enum)]
     verify_policy: Option<VerifyPolicyArg>,
 
-    /// With --inspect: verify hash before printing
-    #[arg(long, requires = "inspect")]
+    /// With --inspect or --extract: verify hash before reading
+    #[arg(long, requires = "read_mode")]
     verify_signature: bool,
 
-    /// With --inspect: print JSON to stdout (stable for scripts)
-    #[arg(long, requires = "inspect")]
+    /// With --inspect, --verify, --stats, --hash, --coverage or --dump-op-ids: print a JSON
+    /// document to stdout (stable for scripts); errors become `{"error": {"message": ...}}`
+    #[arg(long)]
     json: bool,
 
-    /// Output path (required when packing)
+    /// Output path (required when packing); with --extract, the file (--op) or directory (--all)
     #[arg(short, long, value_name = "PATH", conflicts_with_all = ["inspect", "verify"])]
     output: Option<PathBuf>,
 
-    /// Pack using a TOML manifest (see PRODUCER_GUIDE.md); merges with CLI flags where set
-    #[arg(long, value_name = "PATH", conflicts_with = "entries")]
+    /// Pack the entries listed in a TOML (or .json) manifest (see PRODUCER_GUIDE.md); CLI flags
+    /// override its top-level fields
+    #[arg(
+        long = "manifest",
+        visible_alias = "from",
+        alias = "from-manifest",
+        value_name = "PATH",
+        conflicts_with = "entries"
+    )]
     from_manifest: Option<PathBuf>,
 
+    /// Pack every file in DIR named `<op_id>[_suffix][.ext]` (e.g. `0050_matmul.bin`), sorted by
+    /// op_id; other files are skipped with a warning. OP:PATH entries replace files with the
+    /// same op_id
+    #[arg(long, value_name = "DIR", conflicts_with = "from_manifest")]
+    from_dir: Option<PathBuf>,
+
     /// Validate inputs and print summary; do not write a .clf
     #[arg(long)]
     dry_run: bool,
@@ -90,31 +289,174 @@ struct Cli {
     #[arg(long)]
     target: Option<String>,
 
-    #[arg(long, value_parser = clap::value_parser!(clf::ClfKind))]
+    /// Role of the archive (default: compute)
+    #[arg(long, value_parser = kind_parser(), ignore_case = true)]
     kind: Option<clf::ClfKind>,
 
+    /// Fail instead of warning when the output extension does not match the kind
+    #[arg(long)]
+    enforce_extension: bool,
+
+    /// Append (or replace a CLF) extension so the output path matches the kind, e.g.
+    /// `out.clfc` becomes `out.clfmm` with `--kind memory-movement`
+    #[arg(long)]
+    auto_extension: bool,
+
     #[arg(long, value_name = "N")]
     align: Option<u8>,
 
     #[arg(long)]
     sign: bool,
 
-    #[arg(value_name = "OP_ID:PATH")]
+    /// With --sign or --sign-archive: hash only the header and manifest (SHA-256 SIG1 block),
+    /// so verification skips the blob store; combine with --crc for per-blob checks
+    #[arg(long)]
+    sign_index_only: bool,
+
+    /// Store byte-identical blobs once; their manifest entries share the same offset/size
+    #[arg(long)]
+    dedup: bool,
+
+    /// Record a CRC-32 per blob so readers catch corruption of individual blobs
+    #[arg(long)]
+    crc: bool,
+
+    /// Reject op_ids in the canonical range (1-255) that the registry does not assign
+    #[arg(long)]
+    strict_op_ids: bool,
+
+    /// Compress each blob with zstd at LEVEL (1–22); readers decompress transparently
+    #[cfg(feature = "compression")]
+    #[arg(long, value_name = "LEVEL")]
+    zstd: Option<i32>,
+
+    /// Encrypt each blob with AES-256-GCM under the 32-byte key in FILE (raw bytes); readers need
+    /// the same key to get the blobs back. With --recompress, also the key of the input
+    #[cfg(feature = "encryption")]
+    #[arg(long, value_name = "FILE")]
+    encrypt_key: Option<PathBuf>,
+
+    /// Fail instead of writing an archive larger than SIZE (e.g. `2GiB`, `500MB`, `4096`),
+    /// signature included; nothing is left at the output path
+    #[arg(long, value_name = "SIZE", value_parser = parse_byte_size)]
+    max_size: Option<u64>,
+
+    /// Omit the build timestamp and tool version so identical inputs give identical archives
+    #[arg(long)]
+    deterministic: bool,
+
+    /// How to read blob files: as is (raw), or as ELF objects whose --section is packed
+    #[cfg(feature = "elf")]
+    #[arg(long, value_enum, default_value_t = InputFormat::Raw)]
+    input_format: InputFormat,
+
+    /// With --input-format elf: the section to pack (e.g. `.text.mykernel`)
+    #[cfg(feature = "elf")]
+    #[arg(long, value_name = "NAME", default_value = clf::elf::DEFAULT_SECTION)]
+    section: String,
+
+    /// Blobs to pack; OP is a decimal op_id or a canonical op name (e.g. `matmul`)
+    #[arg(value_name = "OP:PATH")]
     entries: Vec<String>,
 }
 
+/// Version of the `--json` documents. Bumped when a field is removed or changes meaning; new
+/// fields may appear without a bump.
+const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// `--kind` parser built from `ClfKind::ALL`, so help lists the canonical names and every
+/// alias `ClfKind::from_str` accepts still parses.
+fn kind_parser() -> impl clap::builder::TypedValueParser<Value = clf::ClfKind> {
+    use clap::builder::{PossibleValue, PossibleValuesParser, TypedValueParser};
+    PossibleValuesParser::new(
+        clf::ClfKind::ALL.map(|kind| PossibleValue::new(kind.name()).aliases(kind.aliases())),
+    )
+    .map(|s| {
+        s.parse::<clf::ClfKind>()
+            .expect("possible values are ClfKind names")
+    })
+}
+
+/// Returned once the outcome has already been printed (e.g. a failing `--verify --json`
+/// document), so `main` only sets the exit code.
+#[derive(Debug)]
+struct Reported;
+
+impl std::fmt::Display for Reported {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("failure already reported")
+    }
+}
+
+impl std::error::Error for Reported {}
+
 fn main() -> ExitCode {
-    match run() {
+    let cli = Cli::parse();
+    let json = cli.json;
+    match run(cli) {
         Ok(()) => ExitCode::SUCCESS,
+        Err(e) if e.is::<Reported>() => ExitCode::from(1),
         Err(e) => {
-            eprintln!("{e}");
+            if json {
+                let doc = serde_json::json!({
+                    "schema_version": JSON_SCHEMA_VERSION,
+                    "error": { "message": e.to_string() },
+                });
+                println!("{doc:#}");
+            } else {
+                eprintln!("{e}");
+            }
             ExitCode::from(1)
         }
     }
 }
 
-fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let cli = Cli::parse();
+fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
+    if cli.json
+        && cli.inspect.is_none()
+        && cli.verify.is_none()
+        && cli.stats.is_none()
+        && cli.hash.is_none()
+        && cli.coverage.is_none()
+        && !cli.dump_op_ids
+    {
+        return Err(
+            "--json requires --inspect, --verify, --stats, --hash, --coverage or --dump-op-ids"
+                .into(),
+        );
+    }
+    if cli.force && cli.extract.is_none() && cli.sign_archive.is_none() {
+        return Err("--force requires --extract or --sign-archive".into());
+    }
+
+    if cli.dump_op_ids {
+        if cli.json {
+            println!("{}", clf::op_registry::export_json());
+        } else {
+            print!("{}", clf::op_registry::export_markdown());
+        }
+        return Ok(());
+    }
+
+    if let Some(path) = &cli.sign_archive {
+        return sign_archive(path, cli.strip, cli.force, cli.sign_index_only);
+    }
+
+    if let Some(path) = &cli.append {
+        return append_to_archive(path, &cli.entries, cli.replace, cli.sign);
+    }
+
+    if let Some(path) = &cli.upgrade {
+        return upgrade_archive(path, &cli);
+    }
+
+    if let Some(path) = &cli.remap {
+        return remap_archive(path, &cli);
+    }
+
+    if let Some(path) = &cli.recompress {
+        return recompress_archive(path, &cli);
+    }
 
     if let Some(path) = &cli.verify {
         let policy = cli
@@ -122,20 +464,19 @@ fn run() -> Result<(), Box<dyn std::error::Error>> {
             .clone()
             .map(Into::into)
             .unwrap_or(VerificationPolicy::IntegrityOnly);
-        return verify_file(path, policy);
+        return verify_file(path, policy, cli.require_signature, cli.json);
+    }
+
+    if cli.verify_policy.is_some() && !cli.verify_signature {
+        return Err("--verify-policy requires --verify, or --inspect / --extract with --verify-signature".into());
     }
+    let policy = cli
+        .verify_policy
+        .clone()
+        .map(Into::into)
+        .unwrap_or(VerificationPolicy::IntegrityOnly);
 
     if let Some(path) = &cli.inspect {
-        if cli.verify_policy.is_some() && !cli.verify_signature {
-            return Err(
-                "--verify-policy requires --verify, or --inspect with --verify-signature".into(),
-            );
-        }
-        let policy = cli
-            .verify_policy
-            .clone()
-            .map(Into::into)
-            .unwrap_or(VerificationPolicy::IntegrityOnly);
         return inspect_file(
             path,
             cli.verify_signature,
@@ -144,20 +485,72 @@ fn run() -> Result<(), Box<dyn std::error::Error>> {
         );
     }
 
-    if cli.verify_policy.is_some() {
-        return Err("--verify-policy requires --verify, or --inspect with --verify-signature".into());
+    if let Some(path) = &cli.extract {
+        let verify = cli.verify_signature.then_some(policy);
+        return match (cli.op, cli.all) {
+            (Some(op_id), _) => extract_one(path, op_id, cli.output.as_deref(), cli.force, verify),
+            (None, true) => extract_all(path, cli.output.as_deref(), cli.force, verify),
+            (None, false) => Err("--extract requires --op OP or --all".into()),
+        };
+    }
+
+    if let Some(path) = &cli.stats {
+        return stats_file(path, cli.top, cli.json);
+    }
+
+    if let Some(path) = &cli.hash {
+        return hash_file(path, cli.op, cli.json);
+    }
+
+    if let (Some(path), Some(ops)) = (&cli.coverage, &cli.ops) {
+        return coverage_file(path, ops, cli.allow_missing, cli.json);
+    }
+
+    if let [old, new] = cli.diff.as_slice() {
+        let differences = diff_files(old, new)?;
+        if cli.exit_code && differences > 0 {
+            return Err(format!("diff: {differences} difference(s)").into());
+        }
+        return Ok(());
+    }
+
+    if !cli.merge.is_empty() {
+        return merge_files(&cli);
     }
 
     // Pack
     let output_path = cli
         .output
         .clone()
-        .ok_or("packing requires --output / -o (or use --inspect / --verify)")?;
+        .ok_or("packing requires --output / -o (or use --inspect / --verify / --extract)")?;
 
     let (resolved, from_manifest) = if let Some(manifest_path) = &cli.from_manifest {
         let m = load_pack_manifest(manifest_path)?;
         (m, true)
-    } else if !cli.entries.is_empty() {
+    } else if cli.from_dir.is_some() || !cli.entries.is_empty() {
+        let mut from_dir = match &cli.from_dir {
+            Some(dir) => scan_blob_dir(dir)?,
+            None => BTreeMap::new(),
+        };
+        let mut blobs = cli
+            .entries
+            .iter()
+            .map(|arg| {
+                let (op_id, p) = parse_op_blob_arg(arg)?;
+                from_dir.remove(&op_id);
+                Ok(PackManifestBlob {
+                    op_id,
+                    path: PathBuf::from(p),
+                    name: None,
+                    symbol: None,
+                    notes: None,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        if cli.from_dir.is_some() {
+            blobs.extend(from_dir.into_values());
+            blobs.sort_by_key(|b| b.op_id);
+        }
         (
             PackManifestResolved {
                 vendor: String::new(),
@@ -165,44 +558,33 @@ fn run() -> Result<(), Box<dyn std::error::Error>> {
                 kind: clf::ClfKind::Compute,
                 align: 0,
                 sign: false,
-                blobs: cli
-                    .entries
-                    .iter()
-                    .map(|arg| {
-                        let (op_id, p) = parse_op_blob_arg(arg)?;
-                        Ok(PackManifestBlob {
-                            op_id,
-                            path: PathBuf::from(p),
-                            symbol: None,
-                            notes: None,
-                        })
-                    })
-                    .collect::<Result<Vec<_>, String>>()?,
+                blobs,
             },
             false,
         )
     } else {
-        return Err("packing requires at least one OP_ID:PATH or --from MANIFEST.toml".into());
+        return Err(
+            "packing requires at least one OP:PATH, --manifest FILE or --from-dir DIR".into(),
+        );
     };
 
+    if resolved.blobs.iter().filter(|b| is_stdin(&b.path)).count() > 1 {
+        return Err("only one entry may read its blob from stdin (`-`)".into());
+    }
+    let sections = elf_sections(&cli, &resolved.blobs)?;
+
     let vendor = cli.vendor.unwrap_or_else(|| resolved.vendor.clone());
     let target = cli.target.unwrap_or_else(|| resolved.target.clone());
     let kind = cli.kind.unwrap_or(resolved.kind);
+    let output_path =
+        output_path_for_kind(output_path, kind, cli.enforce_extension, cli.auto_extension)?;
     let blob_alignment = cli.align.unwrap_or(resolved.align);
     let sign = if cli.sign { true } else { resolved.sign };
 
-    let blobs: Vec<(u32, Vec<u8>)> = resolved
-        .blobs
-        .iter()
-        .map(|b| {
-            let mut f =
-                File::open(&b.path).map_err(|e| format!("open {}: {e}", b.path.display()))?;
-            let mut blob = Vec::new();
-            f.read_to_end(&mut blob)
-                .map_err(|e| format!("read {}: {e}", b.path.display()))?;
-            Ok((b.op_id, blob))
-        })
-        .collect::<Result<Vec<_>, String>>()?;
+    #[cfg(feature = "compression")]
+    let compression = cli.zstd.map(clf::Compression::Zstd);
+    #[cfg(not(feature = "compression"))]
+    let compression = None;
 
     let options = PackOptions {
         vendor,
@@ -211,14 +593,52 @@ fn run() -> Result<(), Box<dyn std::error::Error>> {
         kind,
         version: CLF_VERSION,
         sign,
+        dedup_blobs: cli.dedup,
+        compression,
+        per_blob_crc: cli.crc,
+        metadata: Vec::new(),
+        created_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|d| d.as_secs()),
+        tool_version: Some(TOOL_VERSION.to_string()),
+        deterministic: cli.deterministic,
+        entry_names: resolved
+            .blobs
+            .iter()
+            .filter_map(|b| Some((b.op_id, b.name.clone()?)))
+            .collect(),
+        entry_targets: HashMap::new(),
+        extensions: Vec::new(),
+        strict_op_ids: cli.strict_op_ids,
+        #[cfg(feature = "ed25519")]
+        sign_key: None,
+        hmac_key: None,
+        sign_index_only: cli.sign_index_only,
+        #[cfg(feature = "encryption")]
+        encrypt: cli
+            .encrypt_key
+            .as_deref()
+            .map(read_encryption_key)
+            .transpose()?,
+        max_total_size: cli.max_size,
     };
 
     if cli.dry_run {
-        let total_blob: usize = blobs.iter().map(|(_, b)| b.len()).sum();
+        let mut total_blob: u64 = 0;
+        if let Some(sections) = &sections {
+            total_blob = sections.iter().map(|s| s.len() as u64).sum();
+        } else {
+            for b in resolved.blobs.iter().filter(|b| !is_stdin(&b.path)) {
+                let meta = std::fs::metadata(&b.path)
+                    .map_err(|e| format!("open {}: {e}", b.path.display()))?;
+                total_blob += meta.len();
+            }
+        }
         eprintln!(
             "dry-run: would write {} ({} blobs, {} raw bytes, align={}, sign={})",
             output_path.display(),
-            blobs.len(),
+            resolved.blobs.len(),
             total_blob,
             options.blob_alignment,
             options.sign
@@ -229,39 +649,54 @@ fn run() -> Result<(), Box<dyn std::error::Error>> {
         return Ok(());
     }
 
+    // Stdin can only be read once, so its sidecar hash is taken while the packer streams it.
+    let stdin_hasher = Rc::new(RefCell::new(Sha256::new()));
     let mut sidecar_blobs = Vec::new();
     if cli.write_sidecar {
-        let blob_by_id: HashMap<u32, &[u8]> = blobs
-            .iter()
-            .map(|(id, data)| (*id, data.as_slice()))
-            .collect();
-        for bmeta in &resolved.blobs {
-            let data = blob_by_id
-                .get(&bmeta.op_id)
-                .copied()
-                .ok_or("internal: missing blob for sidecar")?;
+        for (i, bmeta) in resolved.blobs.iter().enumerate() {
             sidecar_blobs.push(sidecar::SidecarBlob {
                 op_id: bmeta.op_id,
                 path: bmeta.path.display().to_string(),
-                sha256_hex: sha256_hex(data),
+                sha256_hex: if let Some(sections) = &sections {
+                    hex(&Sha256::digest(&sections[i]))
+                } else if is_stdin(&bmeta.path) {
+                    String::new()
+                } else {
+                    sha256_hex_file(&bmeta.path)?
+                },
                 symbol: bmeta.symbol.clone(),
                 notes: bmeta.notes.clone(),
             });
         }
     }
 
-    let mut out = OpenOptions::new()
-        .create(true)
-        .truncate(true)
-        .read(true)
-        .write(true)
-        .open(&output_path)?;
-    let data_len = pack_clf(&mut out, &blobs, &options)?;
-    if options.sign {
-        out.sync_all()?;
-        append_signature(&mut out, data_len)?;
-    }
-    out.sync_all()?;
+    // Open every source up front so a missing file fails before the output is truncated;
+    // the blob bytes themselves are streamed by the packer.
+    let sources: Vec<(u32, Box<dyn Read>)> = resolved
+        .blobs
+        .iter()
+        .enumerate()
+        .map(|(i, b)| {
+            if let Some(sections) = &sections {
+                let section = std::io::Cursor::new(sections[i].as_slice());
+                return Ok((b.op_id, Box::new(section) as Box<dyn Read>));
+            }
+            if is_stdin(&b.path) {
+                let stdin = HashingReader {
+                    inner: std::io::stdin().lock(),
+                    hasher: Rc::clone(&stdin_hasher),
+                };
+                return Ok((b.op_id, Box::new(stdin) as Box<dyn Read>));
+            }
+            let f = File::open(&b.path).map_err(|e| format!("open {}: {e}", b.path.display()))?;
+            Ok((b.op_id, Box::new(BufReader::new(f)) as Box<dyn Read>))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    // Written to a temp file and renamed into place, so an interrupted pack never clobbers
+    // an existing archive at the output path.
+    let summary = pack_clf_streaming_to_path(&output_path, sources, &options)?;
+    let data_len = summary.data_len;
 
     let total = data_len
         + if options.sign {
@@ -269,9 +704,27 @@ fn run() -> Result<(), Box<dyn std::error::Error>> {
         } else {
             0
         };
-    eprintln!("wrote {} ({} bytes)", output_path.display(), total);
+    eprintln!(
+        "wrote {} ({} bytes): {} entries, {} blob bytes, {} padding bytes, kind {}{}",
+        output_path.display(),
+        total,
+        summary.entries.len(),
+        summary.total_blob_bytes,
+        summary.padding_bytes,
+        summary.kind,
+        if options.sign { ", signed" } else { "" }
+    );
+    if options.dedup_blobs {
+        eprintln!("dedup: saved {} bytes", summary.dedup_bytes_saved);
+    }
 
     if cli.write_sidecar {
+        if let Some(blob) = sidecar_blobs
+            .iter_mut()
+            .find(|b| b.path == "-" && b.sha256_hex.is_empty())
+        {
+            blob.sha256_hex = hex(&stdin_hasher.take().finalize());
+        }
         let side = sidecar_path(&output_path);
         let doc = sidecar::SidecarDocument::new(output_path.display().to_string(), sidecar_blobs);
         sidecar::write_sidecar_json(&side, &doc)?;
@@ -281,30 +734,1143 @@ fn run() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Print one line per difference between `old` and `new` and return how many there were.
+/// Blobs are compared by the SHA-256 of their decoded bytes, streamed from each archive.
+fn diff_files(old: &Path, new: &Path) -> Result<usize, Box<dyn std::error::Error>> {
+    let open = |path: &Path| ClfReader::open(path).map_err(|e| format!("open {e}"));
+    let (old_reader, new_reader) = (open(old)?, open(new)?);
+    let mut differences = 0;
+
+    let (oh, nh) = (&old_reader.header, &new_reader.header);
+    let header_fields = [
+        ("version", oh.version.to_string(), nh.version.to_string()),
+        ("kind", oh.kind.to_string(), nh.kind.to_string()),
+        (
+            "vendor",
+            format!("{:?}", oh.vendor),
+            format!("{:?}", nh.vendor),
+        ),
+        (
+            "target",
+            format!("{:?}", oh.target),
+            format!("{:?}", nh.target),
+        ),
+        (
+            "alignment",
+            oh.blob_alignment.to_string(),
+            nh.blob_alignment.to_string(),
+        ),
+    ];
+    for (field, before, after) in header_fields {
+        if before != after {
+            println!("header {field}: {before} -> {after}");
+            differences += 1;
+        }
+    }
+
+    let hash = |reader: &ClfReader, e: &clf::ManifestEntry| -> Result<String, String> {
+        let mut blob = reader
+            .blob_reader_variant(e.op_id, e.variant)
+            .map_err(|err| format!("op_id {}: {err}", e.op_id))?
+            .ok_or_else(|| format!("op_id {} variant {} is unreadable", e.op_id, e.variant))?;
+        sha256_hex(&mut blob).map_err(|err| format!("op_id {}: {err}", e.op_id))
+    };
+    let describe = |reader: &ClfReader, e: &clf::ManifestEntry| {
+        let name = reader
+            .entry_label(e.op_id, OpRegistry::builtin())
+            .unwrap_or("-")
+            .to_string();
+        if e.variant == 0 {
+            format!("{:>8}  {name}", e.op_id)
+        } else {
+            format!("{:>8}  {name} (variant {})", e.op_id, e.variant)
+        }
+    };
+    let key = |e: &clf::ManifestEntry| (e.op_id, e.variant);
+    let old_entries: BTreeMap<_, _> = old_reader
+        .manifest_entries()
+        .into_iter()
+        .map(|e| (key(&e), e))
+        .collect();
+    let new_entries: BTreeMap<_, _> = new_reader
+        .manifest_entries()
+        .into_iter()
+        .map(|e| (key(&e), e))
+        .collect();
+    let keys: std::collections::BTreeSet<_> =
+        old_entries.keys().chain(new_entries.keys()).collect();
+    for k in keys {
+        match (old_entries.get(k), new_entries.get(k)) {
+            (Some(o), None) => {
+                println!(
+                    "- {}  removed ({} bytes)",
+                    describe(&old_reader, o),
+                    o.exact_size
+                );
+            }
+            (None, Some(n)) => {
+                println!(
+                    "+ {}  added ({} bytes)",
+                    describe(&new_reader, n),
+                    n.exact_size
+                );
+            }
+            (Some(o), Some(n)) => {
+                if hash(&old_reader, o)? == hash(&new_reader, n)? {
+                    continue;
+                }
+                println!(
+                    "~ {}  changed ({} -> {} bytes)",
+                    describe(&new_reader, n),
+                    o.exact_size,
+                    n.exact_size
+                );
+            }
+            (None, None) => unreachable!("key comes from one of the manifests"),
+        }
+        differences += 1;
+    }
+
+    if differences == 0 {
+        println!("no differences");
+    }
+    Ok(differences)
+}
+
+/// Print the SHA-256 of each blob of `path` (or only `op`'s variants), then of the whole file
+/// and of its signed region. Every digest is taken over a stream, so memory use does not depend
+/// on blob or file size.
+/// Check `path` against the op list in `ops` (see `clf::load_op_list`): fails when more than
+/// `allow_missing` listed ops are missing.
+fn coverage_file(
+    path: &Path,
+    ops: &Path,
+    allow_missing: usize,
+    json: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let required = load_op_list(ops)?;
+    let reader = ClfReader::open(path)?;
+    let report = reader.coverage(&required);
+    // Size of the entry `coverage` counts: the lowest variant.
+    let mut sizes = HashMap::new();
+    for e in reader.manifest_entries() {
+        sizes.entry(e.op_id).or_insert(e.raw_size);
+    }
+    let rows: Vec<CoverageOpJson> = report
+        .present
+        .iter()
+        .map(|op_id| (op_id, true))
+        .chain(report.missing.iter().map(|op_id| (op_id, false)))
+        .map(|(&op_id, present)| CoverageOpJson {
+            op_id,
+            op_name: registry_name(op_id),
+            name: reader.entry_name(op_id).map(str::to_string),
+            present,
+            size: sizes.get(&op_id).copied(),
+        })
+        .collect();
+    let required_count = rows.len();
+    let percent = report.fraction_covered * 100.0;
+    let ok = report.missing.len() <= allow_missing;
+
+    if json {
+        let doc = CoverageJson {
+            schema_version: JSON_SCHEMA_VERSION,
+            file: path.display().to_string(),
+            ops_file: ops.display().to_string(),
+            ok,
+            required: required_count,
+            present: report.present.len(),
+            missing: report.missing.clone(),
+            allow_missing,
+            percent,
+            present_bytes: report.present_bytes,
+            ops: rows,
+        };
+        println!("{}", serde_json::to_string_pretty(&doc)?);
+        return if ok { Ok(()) } else { Err(Reported.into()) };
+    }
+    println!(
+        "{:<8}  {:>8}  {:<20}  {:>12}",
+        "status", "op_id", "op", "size (bytes)"
+    );
+    for row in &rows {
+        let op = row
+            .name
+            .as_deref()
+            .or(row.op_name.as_deref())
+            .unwrap_or("-");
+        let size = row.size.map_or_else(|| "-".to_string(), |s| s.to_string());
+        let status = if row.present { "present" } else { "missing" };
+        println!("{status:<8}  {:>8}  {op:<20}  {size:>12}", row.op_id);
+    }
+    println!(
+        "\n{} of {required_count} ops present ({percent:.1}%), {} bytes; {} missing",
+        report.present.len(),
+        report.present_bytes,
+        report.missing.len()
+    );
+    if ok {
+        return Ok(());
+    }
+    Err(format!(
+        "coverage: {} is missing {} op(s) from {} (allowed: {allow_missing})",
+        path.display(),
+        report.missing.len(),
+        ops.display()
+    )
+    .into())
+}
+
+fn hash_file(path: &Path, op: Option<u32>, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let reader = ClfReader::open(path)?;
+    let entries: Vec<_> = reader
+        .manifest_entries()
+        .into_iter()
+        .filter(|e| op.map_or(true, |op_id| e.op_id == op_id))
+        .collect();
+    if let (Some(op_id), true) = (op, entries.is_empty()) {
+        return Err(format!("op_id {op_id} is not in {}", path.display()).into());
+    }
+    let mut hashes = Vec::with_capacity(entries.len());
+    for e in &entries {
+        let mut blob = reader
+            .blob_reader_variant(e.op_id, e.variant)?
+            .ok_or_else(|| format!("op_id {} variant {} is unreadable", e.op_id, e.variant))?;
+        let sha256 = sha256_hex(&mut blob).map_err(|err| format!("op_id {}: {err}", e.op_id))?;
+        hashes.push(HashEntryJson {
+            op_id: e.op_id,
+            variant: e.variant,
+            op_name: registry_name(e.op_id),
+            name: reader.entry_name(e.op_id).map(str::to_string),
+            size: e.raw_size,
+            sha256,
+        });
+    }
+    let file_size = std::fs::metadata(path)?.len();
+    let file_sha256 = sha256_hex_file(path)?;
+    let signed_len = reader.signed_len()?;
+    let signed_sha256 = match signed_len {
+        Some(len) => Some(sha256_hex(&mut File::open(path)?.take(len))?),
+        None => None,
+    };
+
+    if json {
+        let out = HashJson {
+            schema_version: JSON_SCHEMA_VERSION,
+            file: path.display().to_string(),
+            file_size,
+            file_sha256,
+            signed_len,
+            signed_sha256,
+            entries: hashes,
+        };
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&out).map_err(|e| e.to_string())?
+        );
+        return Ok(());
+    }
+    let has_variants = hashes.iter().any(|h| h.variant != 0);
+    let variant_col = |v: &dyn std::fmt::Display| {
+        if has_variants {
+            format!("  {v:>7}")
+        } else {
+            String::new()
+        }
+    };
+    println!(
+        "{:>8}{}  {:<20}  {:>12}  sha256",
+        "op_id",
+        variant_col(&"variant"),
+        "op",
+        "size (bytes)"
+    );
+    for h in &hashes {
+        let op = h.name.as_deref().or(h.op_name.as_deref()).unwrap_or("-");
+        println!(
+            "{:>8}{}  {op:<20}  {:>12}  {}",
+            h.op_id,
+            variant_col(&h.variant),
+            h.size,
+            h.sha256
+        );
+    }
+    println!("\nfile    {file_sha256}  ({file_size} bytes)");
+    match (signed_len, signed_sha256) {
+        (Some(len), Some(sha256)) => println!("signed  {sha256}  ({len} bytes)"),
+        _ => println!("signed  -  (unsigned)"),
+    }
+    Ok(())
+}
+
+/// Print the size breakdown of an archive. Blobs are hashed one at a time through
+/// `blob_reader_variant`, so memory use does not grow with the archive; entries sharing a
+/// stored blob (`--dedup`) are hashed once.
+fn stats_file(
+    path: &Path,
+    top: Option<usize>,
+    json: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let reader = ClfReader::open(path).map_err(|e| format!("open {e}"))?;
+    let file_size = std::fs::metadata(path)?.len();
+    let header_bytes = reader.header_len();
+    let manifest_bytes = reader.blob_store_offset() - header_bytes;
+    let blob_store_bytes = reader.blob_store_len();
+    let signature_bytes =
+        file_size.saturating_sub(reader.blob_store_offset() + reader.file_blob_store_len());
+
+    let mut entries = reader.manifest_entries();
+    let mut by_extent: BTreeMap<(u32, u32), String> = BTreeMap::new();
+    let mut by_hash: BTreeMap<String, Vec<clf::ManifestEntry>> = BTreeMap::new();
+    for e in &entries {
+        let extent = (e.offset, e.exact_size);
+        let digest = match by_extent.get(&extent) {
+            Some(digest) => digest.clone(),
+            None => {
+                let mut blob = reader
+                    .blob_reader_variant(e.op_id, e.variant)
+                    .map_err(|err| format!("op_id {}: {err}", e.op_id))?
+                    .ok_or_else(|| {
+                        format!("op_id {} variant {} is unreadable", e.op_id, e.variant)
+                    })?;
+                let digest =
+                    sha256_hex(&mut blob).map_err(|err| format!("op_id {}: {err}", e.op_id))?;
+                by_extent.insert(extent, digest.clone());
+                digest
+            }
+        };
+        by_hash.entry(digest).or_default().push(*e);
+    }
+    let blob_bytes: u64 = by_extent.keys().map(|&(_, len)| u64::from(len)).sum();
+    let padding_bytes = blob_store_bytes.saturating_sub(blob_bytes);
+    let duplicates: Vec<StatsDuplicateJson> = by_hash
+        .into_iter()
+        .filter(|(_, group)| group.len() > 1)
+        .map(|(sha256, group)| {
+            // Every stored copy beyond the smallest one could be dropped.
+            let copies: Vec<u64> = group
+                .iter()
+                .map(|e| (e.offset, e.exact_size))
+                .collect::<std::collections::BTreeSet<_>>()
+                .into_iter()
+                .map(|(_, len)| u64::from(len))
+                .collect();
+            StatsDuplicateJson {
+                sha256,
+                wasted_bytes: copies.iter().sum::<u64>() - copies.iter().min().unwrap_or(&0),
+                entries: group
+                    .iter()
+                    .map(|e| StatsKeyJson {
+                        op_id: e.op_id,
+                        variant: e.variant,
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+
+    entries.sort_by(|a, b| {
+        b.exact_size
+            .cmp(&a.exact_size)
+            .then((a.op_id, a.variant).cmp(&(b.op_id, b.variant)))
+    });
+    let total_entries = entries.len();
+    entries.truncate(top.unwrap_or(usize::MAX));
+    let ops: Vec<StatsOpJson> = entries
+        .iter()
+        .map(|e| StatsOpJson {
+            op_id: e.op_id,
+            variant: e.variant,
+            op_name: registry_name(e.op_id),
+            name: reader.entry_name(e.op_id).map(str::to_string),
+            stored_bytes: e.exact_size,
+            raw_bytes: e.raw_size,
+            padding_bytes: e.size - e.exact_size,
+        })
+        .collect();
+
+    if json {
+        let out = StatsJson {
+            schema_version: JSON_SCHEMA_VERSION,
+            file: path.display().to_string(),
+            file_size,
+            header_bytes,
+            manifest_bytes,
+            blob_store_bytes,
+            blob_bytes,
+            padding_bytes,
+            signature_bytes,
+            entries: total_entries,
+            ops,
+            duplicates,
+        };
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&out).map_err(|e| e.to_string())?
+        );
+        return Ok(());
+    }
+
+    let percent = |n: u64| {
+        if file_size == 0 {
+            0.0
+        } else {
+            n as f64 * 100.0 / file_size as f64
+        }
+    };
+    println!("File: {} ({file_size} bytes)", path.display());
+    for (label, n) in [
+        ("Header", header_bytes),
+        ("Manifest", manifest_bytes),
+        ("Blob data", blob_bytes),
+        ("Padding", padding_bytes),
+        ("Signature", signature_bytes),
+    ] {
+        println!("  {label:<10} {n:>12} bytes  {:>5.1}%", percent(n));
+    }
+
+    let label = |key: &StatsKeyJson| {
+        let name = reader.entry_label(key.op_id, OpRegistry::builtin());
+        let mut s = key.op_id.to_string();
+        if let Some(name) = name {
+            s.push_str(&format!(" {name}"));
+        }
+        if key.variant != 0 {
+            s.push_str(&format!(" (variant {})", key.variant));
+        }
+        s
+    };
+    match top {
+        Some(n) if n < total_entries => {
+            println!("\nLargest {n} of {total_entries} entries:")
+        }
+        _ => println!("\nEntries by size ({total_entries}):"),
+    }
+    println!(
+        "{:>8}  {:>7}  {:>12}  {:>12}  {:>8}  op",
+        "op_id", "variant", "stored", "decoded", "padding"
+    );
+    for op in &ops {
+        let row = format!(
+            "{:>8}  {:>7}  {:>12}  {:>12}  {:>8}  {}",
+            op.op_id,
+            op.variant,
+            op.stored_bytes,
+            op.raw_bytes,
+            op.padding_bytes,
+            op.op_name.as_deref().unwrap_or("-")
+        );
+        match &op.name {
+            Some(name) => println!("{row}  {name}"),
+            None => println!("{row}"),
+        }
+    }
+
+    if !duplicates.is_empty() {
+        println!("\nIdentical contents ({} group(s)):", duplicates.len());
+        for group in &duplicates {
+            let members: Vec<String> = group.entries.iter().map(label).collect();
+            let cost = if group.wasted_bytes == 0 {
+                "stored once".to_string()
+            } else {
+                format!("{} bytes reclaimable with --dedup", group.wasted_bytes)
+            };
+            println!(
+                "  {} ops have identical bytes: {} ({cost})",
+                members.len(),
+                members.join(", ")
+            );
+        }
+    }
+    Ok(())
+}
+
+fn merge_files(cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let output_path = cli
+        .output
+        .as_ref()
+        .ok_or("--merge requires --output / -o")?;
+    if cli.kind.is_some() {
+        return Err(
+            "--kind cannot be used with --merge (the output keeps the inputs' kind)".into(),
+        );
+    }
+    let readers = cli
+        .merge
+        .iter()
+        .map(|path| ClfReader::open(path).map_err(|e| format!("open {e}")))
+        .collect::<Result<Vec<_>, String>>()?;
+    let on_conflict = ConflictPolicy::from(cli.on_conflict.unwrap_or_default());
+    let plan = plan_merge(&readers, on_conflict)?;
+    let output_path = &output_path_for_kind(
+        output_path.clone(),
+        readers[0].header.kind,
+        cli.enforce_extension,
+        cli.auto_extension,
+    )?;
+
+    #[cfg(feature = "compression")]
+    let compression = cli.zstd.map(clf::Compression::Zstd);
+    #[cfg(not(feature = "compression"))]
+    let compression = None;
+    let header_policy = |value: &Option<String>| {
+        value
+            .clone()
+            .map_or(MismatchPolicy::Error, MismatchPolicy::Override)
+    };
+    let options = MergeOptions {
+        on_conflict,
+        on_vendor_mismatch: header_policy(&cli.vendor),
+        on_target_mismatch: header_policy(&cli.target),
+        pack: PackOptions {
+            // Keep the strictest input alignment unless --align says otherwise.
+            blob_alignment: cli.align.unwrap_or_else(|| {
+                readers
+                    .iter()
+                    .map(|r| r.header.blob_alignment)
+                    .max()
+                    .unwrap_or(0)
+            }),
+            sign: cli.sign,
+            sign_index_only: cli.sign_index_only,
+            dedup_blobs: cli.dedup,
+            compression,
+            per_blob_crc: cli.crc,
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|d| d.as_secs()),
+            tool_version: Some(TOOL_VERSION.to_string()),
+            deterministic: cli.deterministic,
+            strict_op_ids: cli.strict_op_ids,
+            ..PackOptions::default()
+        },
+    };
+    let summary = merge_clf_to_path(&readers, &options, output_path)?;
+
+    eprintln!(
+        "merged {} inputs into {} ({} bytes): {} entries, kind {}{}",
+        cli.merge.len(),
+        output_path.display(),
+        std::fs::metadata(output_path)?.len(),
+        summary.entries.len(),
+        summary.kind,
+        if cli.sign { ", signed" } else { "" }
+    );
+    for (i, path) in cli.merge.iter().enumerate() {
+        eprintln!("  {}: {} op_ids", path.display(), plan.entries_from(i));
+    }
+    if !plan.conflicts.is_empty() {
+        let conflicts: Vec<String> = plan
+            .conflicts
+            .iter()
+            .map(|op_id| {
+                format!(
+                    "{op_id} (from {})",
+                    cli.merge[plan.sources[op_id]].display()
+                )
+            })
+            .collect();
+        eprintln!("conflicts: {}", conflicts.join(", "));
+    }
+    Ok(())
+}
+
+/// Blob files in `dir` keyed by op_id. A file qualifies when its name, up to the last extension,
+/// is a decimal op_id optionally followed by `_` and any suffix (`0001_add.bin`, `50.bin`);
+/// other files are skipped with a warning. Two files with the same op_id are an error.
+fn scan_blob_dir(dir: &Path) -> Result<BTreeMap<u32, PackManifestBlob>, String> {
+    let read_err = |e: std::io::Error| format!("read {}: {e}", dir.display());
+    let mut blobs: BTreeMap<u32, PackManifestBlob> = BTreeMap::new();
+    for entry in std::fs::read_dir(dir).map_err(read_err)? {
+        let entry = entry.map_err(read_err)?;
+        let path = entry.path();
+        if !entry.file_type().map_err(read_err)?.is_file() {
+            continue;
+        }
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+        let digits = stem.split_once('_').map_or(stem, |(digits, _)| digits);
+        let op_id = match digits.parse::<u32>() {
+            Ok(op_id) if digits.bytes().all(|b| b.is_ascii_digit()) => op_id,
+            _ => {
+                eprintln!(
+                    "warning: skipping {}: name does not start with a decimal op_id",
+                    path.display()
+                );
+                continue;
+            }
+        };
+        if let Some(other) = blobs.get(&op_id) {
+            return Err(format!(
+                "op_id {op_id} is claimed by both {} and {}",
+                other.path.display(),
+                path.display()
+            ));
+        }
+        blobs.insert(
+            op_id,
+            PackManifestBlob {
+                op_id,
+                path,
+                name: None,
+                symbol: None,
+                notes: None,
+            },
+        );
+    }
+    Ok(blobs)
+}
+
+/// Check the output extension against `kind` (see `ClfKind::matches_path`). A mismatch is a
+/// warning, an error with `enforce`, or fixed with `auto`: a CLF extension is replaced and any
+/// other extension is kept with the kind's appended (`out.bin` becomes `out.bin.clfc`).
+fn output_path_for_kind(
+    path: PathBuf,
+    kind: clf::ClfKind,
+    enforce: bool,
+    auto: bool,
+) -> Result<PathBuf, String> {
+    if kind.matches_path(&path) {
+        return Ok(path);
+    }
+    if auto {
+        let has_clf_extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .and_then(clf::ClfKind::from_extension)
+            .is_some();
+        let fixed = if has_clf_extension {
+            path.with_extension(kind.extension())
+        } else {
+            let mut p = path.into_os_string();
+            p.push(".");
+            p.push(kind.extension());
+            PathBuf::from(p)
+        };
+        return Ok(fixed);
+    }
+    let message = format!(
+        "output {} does not use the .{} extension for kind {}",
+        path.display(),
+        kind.extension(),
+        kind
+    );
+    if enforce {
+        return Err(format!("{message} (--enforce-extension)"));
+    }
+    eprintln!(
+        "warning: {message}; use --auto-extension to fix it or --enforce-extension to make this an error"
+    );
+    Ok(path)
+}
+
 fn sidecar_path(output: &Path) -> PathBuf {
     let mut p = output.as_os_str().to_owned();
     p.push(".meta.json");
     PathBuf::from(p)
 }
 
-fn sha256_hex(data: &[u8]) -> String {
-    let h = Sha256::digest(data);
-    h.iter().map(|b| format!("{b:02x}")).collect()
+fn sha256_hex_file(path: &Path) -> Result<String, String> {
+    let mut f = File::open(path).map_err(|e| format!("open {}: {e}", path.display()))?;
+    sha256_hex(&mut f).map_err(|e| format!("read {}: {e}", path.display()))
+}
+
+/// SHA-256 of everything `source` yields, read in chunks.
+fn sha256_hex(source: &mut impl Read) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = source.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex(&hasher.finalize()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// With `--input-format elf`, the selected section of every blob, in `blobs` order; `None` in
+/// raw mode, where blob files are streamed as they are.
+#[cfg(feature = "elf")]
+fn elf_sections(cli: &Cli, blobs: &[PackManifestBlob]) -> Result<Option<Vec<Vec<u8>>>, String> {
+    if cli.input_format == InputFormat::Raw {
+        return Ok(None);
+    }
+    blobs
+        .iter()
+        .map(|b| {
+            let mut data = Vec::new();
+            let read = if is_stdin(&b.path) {
+                std::io::stdin().lock().read_to_end(&mut data)
+            } else {
+                File::open(&b.path).and_then(|mut f| f.read_to_end(&mut data))
+            };
+            read.map_err(|e| format!("read {}: {e}", b.path.display()))?;
+            clf::elf::section_bytes(&data, &cli.section)
+                .map(<[u8]>::to_vec)
+                .map_err(|e| format!("{}: {e}", b.path.display()))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+#[cfg(not(feature = "elf"))]
+fn elf_sections(_cli: &Cli, _blobs: &[PackManifestBlob]) -> Result<Option<Vec<Vec<u8>>>, String> {
+    Ok(None)
+}
+
+/// A blob path of `-` means "read the blob from stdin until EOF".
+fn is_stdin(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+/// Feeds everything read through it into a shared SHA-256.
+struct HashingReader<R> {
+    inner: R,
+    hasher: Rc<RefCell<Sha256>>,
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.borrow_mut().update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Byte count with an optional unit: `B`, SI `kB`/`MB`/`GB`/`TB` (powers of 1000) or IEC
+/// `KiB`/`MiB`/`GiB`/`TiB` and the bare `K`/`M`/`G`/`T` (powers of 1024). Units ignore case.
+fn parse_byte_size(arg: &str) -> Result<u64, String> {
+    let arg = arg.trim();
+    let split = arg.find(|c: char| !c.is_ascii_digit()).unwrap_or(arg.len());
+    let (digits, unit) = arg.split_at(split);
+    let count: u64 = digits
+        .parse()
+        .map_err(|_| format!("expected a size like 2GiB or 4096, got {arg:?}"))?;
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1000,
+        "mb" => 1000_u64.pow(2),
+        "gb" => 1000_u64.pow(3),
+        "tb" => 1000_u64.pow(4),
+        "k" | "kib" => 1 << 10,
+        "m" | "mib" => 1 << 20,
+        "g" | "gib" => 1 << 30,
+        "t" | "tib" => 1 << 40,
+        _ => return Err(format!("unknown size unit {unit:?} in {arg:?}")),
+    };
+    count
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("size {arg:?} does not fit in 64 bits"))
+}
+
+/// The AES-256 key stored as 32 raw bytes in the file at `path`.
+#[cfg(feature = "encryption")]
+fn read_encryption_key(path: &Path) -> Result<clf::EncryptionKey, Box<dyn std::error::Error>> {
+    let bytes = std::fs::read(path).map_err(|e| format!("read {}: {e}", path.display()))?;
+    clf::EncryptionKey::from_slice(&bytes).ok_or_else(|| {
+        format!(
+            "{}: expected a 32-byte key, got {} bytes",
+            path.display(),
+            bytes.len()
+        )
+        .into()
+    })
+}
+
+/// Rewrite the archive at `path` in format `--to-version` at `-o` (see
+/// `clf::convert_version_to_path`). CRC-32s, compression and build info carry over when the
+/// output version can hold them.
+fn upgrade_archive(path: &Path, cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let output = cli.output.as_deref().ok_or("--upgrade requires -o PATH")?;
+    let version = cli.to_version.unwrap_or(CLF_VERSION);
+    if !clf::format::is_supported(version) {
+        return Err(
+            format!("--to-version {version}: supported versions are 1-{CLF_VERSION}").into(),
+        );
+    }
+    let mut reader = ClfReader::open(path)?;
+    let header = &reader.header;
+    let from = header.version;
+    let features = VersionFeatures::for_version(version);
+    let entries = reader.manifest_entries();
+    let keep_flags = |used: bool| used && features.has_flags;
+
+    #[cfg(feature = "compression")]
+    let zstd = cli.zstd.map(clf::Compression::Zstd);
+    #[cfg(not(feature = "compression"))]
+    let zstd = None;
+    let compressed = entries
+        .iter()
+        .any(|e| e.flags & clf::format::ENTRY_FLAG_ZSTD != 0);
+    let (created_at, tool_version) = if features.has_header_sections {
+        (header.created_at, header.tool_version.clone())
+    } else {
+        (None, None)
+    };
+    let options = RepackOptions {
+        vendor: cli.vendor.clone(),
+        target: cli.target.clone(),
+        kind: cli.kind,
+        pack: PackOptions {
+            version,
+            blob_alignment: cli.align.unwrap_or(header.blob_alignment),
+            sign: cli.sign,
+            sign_index_only: cli.sign_index_only,
+            dedup_blobs: cli.dedup,
+            compression: zstd.or(keep_flags(compressed).then_some(clf::Compression::Zstd(0))),
+            per_blob_crc: cli.crc || keep_flags(entries.iter().any(|e| e.crc32.is_some())),
+            created_at,
+            tool_version,
+            deterministic: cli.deterministic,
+            strict_op_ids: cli.strict_op_ids,
+            max_total_size: cli.max_size,
+            ..PackOptions::default()
+        },
+        ..RepackOptions::default()
+    };
+    let summary = convert_version_to_path(&mut reader, &options, output)?;
+    eprintln!(
+        "upgraded {} (v{from}) to {} (v{version}, {} bytes): {} entries verified, kind {}{}",
+        path.display(),
+        output.display(),
+        std::fs::metadata(output)?.len(),
+        summary.entries.len(),
+        summary.kind,
+        if cli.sign { ", signed" } else { "" }
+    );
+    Ok(())
+}
+
+/// Renumber the op_ids of the archive at `path` into `-o` (see `clf::remap_op_ids`).
+fn remap_archive(path: &Path, cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let output = cli.output.as_deref().ok_or("--remap requires -o PATH")?;
+    if cli.map.is_none() && !cli.use_registry_aliases {
+        return Err("--remap requires --map MAP or --use-registry-aliases".into());
+    }
+    let reader = ClfReader::open(path)?;
+    let mut map = BTreeMap::new();
+    if cli.use_registry_aliases {
+        let registry = OpRegistry::builtin();
+        for op_id in reader.op_ids() {
+            if registry.is_deprecated(op_id) {
+                map.insert(op_id, registry.canonical_op_id(op_id));
+            }
+        }
+    }
+    let explicit = match &cli.map {
+        Some(map_path) => load_remap_file(map_path)?,
+        None => BTreeMap::new(),
+    };
+    map.extend(&explicit);
+
+    let plan = plan_remap(&reader, &map)?;
+    let options = PackOptions {
+        sign: cli.sign,
+        sign_index_only: cli.sign_index_only,
+        ..PackOptions::default()
+    };
+    let summary = remap_op_ids_to_path(&reader, &map, &options, output)?;
+    for op_id in plan
+        .absent
+        .iter()
+        .filter(|op_id| explicit.contains_key(op_id))
+    {
+        eprintln!(
+            "warning: {} maps op_id {op_id}, which {} does not have",
+            cli.map.as_deref().unwrap_or(path).display(),
+            path.display()
+        );
+    }
+    println!(
+        "remapped {} op_id{} of {} into {} ({} bytes{})",
+        plan.remapped.len(),
+        if plan.remapped.len() == 1 { "" } else { "s" },
+        path.display(),
+        output.display(),
+        summary.data_len,
+        if cli.sign { ", signed" } else { "" }
+    );
+    for (from, to) in &plan.remapped {
+        println!("  {from} -> {to}");
+    }
+    if !plan.unmapped.is_empty() {
+        let ids: Vec<String> = plan.unmapped.iter().map(u32::to_string).collect();
+        println!("left as-is: {}", ids.join(", "));
+    }
+    if reader.signature_block_present() && !cli.sign {
+        eprintln!(
+            "note: the signature of {} was not carried over; pass --sign to sign the output",
+            path.display()
+        );
+    }
+    Ok(())
 }
 
-fn verify_file(path: &Path, policy: VerificationPolicy) -> Result<(), Box<dyn std::error::Error>> {
+/// Re-encode the blobs of the archive at `path` into `-o` at its own format version (see
+/// `clf::convert_version_to_path`), and report how the size changed.
+fn recompress_archive(path: &Path, cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let output = cli
+        .output
+        .as_deref()
+        .ok_or("--recompress requires -o PATH")?;
+    #[cfg(feature = "compression")]
+    let zstd = cli.zstd.map(clf::Compression::Zstd);
+    #[cfg(not(feature = "compression"))]
+    let zstd: Option<clf::Compression> = None;
+    match (zstd.is_some(), cli.decompress) {
+        (false, false) => return Err("--recompress requires --zstd LEVEL or --decompress".into()),
+        (true, true) => return Err("--zstd and --decompress cannot be used together".into()),
+        _ => {}
+    }
     let mut reader = ClfReader::open(path)?;
-    if !reader.signature_block_present() {
-        return Err("verify: no SIG0 signature block".into());
+    let encrypted = reader
+        .manifest_entries()
+        .iter()
+        .filter(|e| e.flags & clf::format::ENTRY_FLAG_AES256GCM != 0)
+        .count();
+    #[cfg(feature = "encryption")]
+    let encrypt = cli
+        .encrypt_key
+        .as_deref()
+        .map(read_encryption_key)
+        .transpose()?;
+    #[cfg(not(feature = "encryption"))]
+    let encrypt: Option<()> = None;
+    if encrypted > 0 && encrypt.is_none() {
+        return Err(format!(
+            "{}: {encrypted} blob(s) are encrypted; recompressing needs their key (--encrypt-key)",
+            path.display()
+        )
+        .into());
+    }
+    #[cfg(feature = "encryption")]
+    if let Some(key) = &encrypt {
+        reader.set_decryption_key(key.clone());
+    }
+
+    let header = &reader.header;
+    let options = RepackOptions {
+        pack: PackOptions {
+            version: header.version,
+            blob_alignment: cli.align.unwrap_or(header.blob_alignment),
+            sign: cli.sign,
+            sign_index_only: cli.sign_index_only,
+            dedup_blobs: cli.dedup,
+            compression: zstd,
+            per_blob_crc: cli.crc || reader.manifest_entries().iter().any(|e| e.crc32.is_some()),
+            created_at: header.created_at,
+            tool_version: header.tool_version.clone(),
+            deterministic: cli.deterministic,
+            strict_op_ids: cli.strict_op_ids,
+            #[cfg(feature = "encryption")]
+            encrypt,
+            max_total_size: cli.max_size,
+            ..PackOptions::default()
+        },
+        ..RepackOptions::default()
+    };
+    let before = std::fs::metadata(path)?.len();
+    convert_version_to_path(&mut reader, &options, output)?;
+
+    let after = std::fs::metadata(output)?.len();
+    let written = ClfReader::open(output)?.manifest_entries();
+    let compressed = written
+        .iter()
+        .filter(|e| e.flags & clf::format::ENTRY_FLAG_ZSTD != 0)
+        .count();
+    let change = if before == 0 {
+        0.0
+    } else {
+        (after as f64 - before as f64) * 100.0 / before as f64
+    };
+    println!(
+        "recompressed {} ({before} bytes) into {} ({after} bytes, {change:+.1}%): {compressed} of {} blobs zstd-compressed{}",
+        path.display(),
+        output.display(),
+        written.len(),
+        if cli.sign { ", signed" } else { "" }
+    );
+    if compressed < written.len() && zstd.is_some() {
+        println!(
+            "{} blob(s) stored raw: zstd would not make them smaller",
+            written.len() - compressed
+        );
     }
-    match reader.verify_with_policy(policy) {
-        Ok(true) => {
-            println!("verify: OK ({})", path.display());
-            Ok(())
+    Ok(())
+}
+
+/// Append `OP:PATH` entries to the archive at `path` in place (see `clf::append_entries`).
+fn append_to_archive(
+    path: &Path,
+    args: &[String],
+    replace: bool,
+    sign: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if args.is_empty() {
+        return Err("--append requires at least one OP:PATH".into());
+    }
+    let mut entries = Vec::with_capacity(args.len());
+    for arg in args {
+        let (op_id, blob_path) = parse_op_blob_arg(arg)?;
+        let data = std::fs::read(&blob_path).map_err(|e| format!("read {blob_path}: {e}"))?;
+        entries.push((op_id, data));
+    }
+    let options = AppendOptions {
+        replace,
+        sign,
+        ..AppendOptions::default()
+    };
+    let summary = clf::append_entries(path, &entries, &options)?;
+    println!(
+        "appended {} entr{} to {} ({} bytes{})",
+        entries.len(),
+        if entries.len() == 1 { "y" } else { "ies" },
+        path.display(),
+        summary.data_len,
+        if summary.store_shift == 0 {
+            String::new()
+        } else {
+            format!(", blob store moved by {} bytes", summary.store_shift)
+        }
+    );
+    Ok(())
+}
+
+/// Sign (or with `strip`, unsign) an existing archive in place. The file must parse and pass
+/// `validate` first, so a damaged file is never given a valid-looking signature. With
+/// `index_only` the hash covers only the header and manifest.
+fn sign_archive(
+    path: &Path,
+    strip: bool,
+    force: bool,
+    index_only: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let reader = ClfReader::open(path).map_err(|e| format!("refusing to sign {e}"))?;
+    reader
+        .validate()
+        .map_err(|e| format!("refusing to sign {}: {e}", path.display()))?;
+    let signed = reader.signature_block_present();
+    drop(reader);
+
+    if strip {
+        if clf::strip_signature(path)? {
+            println!("removed signature block from {}", path.display());
+        } else {
+            println!("{} has no signature block", path.display());
+        }
+        return Ok(());
+    }
+    if signed {
+        if !force {
+            return Err(format!(
+                "{} is already signed (use --force to replace the signature block)",
+                path.display()
+            )
+            .into());
         }
-        Ok(false) => Err("verify: invalid or unreadable SIG0 block".into()),
-        Err(e) => Err(format!("verify: {e}").into()),
+        clf::strip_signature(path)?;
     }
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)?;
+    let data_len = file.metadata()?.len();
+    let scope = if index_only {
+        SignatureScope::IndexOnly
+    } else {
+        SignatureScope::Full
+    };
+    clf::append_signature_scoped(&mut file, data_len, scope)?;
+    file.sync_all()?;
+    println!(
+        "{} {}",
+        if signed { "re-signed" } else { "signed" },
+        path.display()
+    );
+    Ok(())
+}
+
+/// `verify_with_policy`, except an index-only signature also passes once the per-blob CRC-32s
+/// check out, since the signature itself leaves the blob store unchecked.
+fn verify_signed(reader: &mut ClfReader, policy: VerificationPolicy) -> Result<bool, ClfError> {
+    if policy != VerificationPolicy::IntegrityOnly {
+        return reader.verify_with_policy(policy);
+    }
+    match reader.verify_signature_scope()? {
+        SignatureScope::None => Ok(false),
+        SignatureScope::IndexOnly => reader.verify_blobs().map(|()| true),
+        SignatureScope::Full => Ok(true),
+    }
+}
+
+/// Every failure is reported on stderr before returning; the signature hash is streamed, so
+/// memory use does not grow with the file.
+fn verify_file(
+    path: &Path,
+    policy: VerificationPolicy,
+    require_signature: bool,
+    json: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut reader = ClfReader::open(path).map_err(|e| format!("verify: {e}"))?;
+    let mut failures: Vec<String> = structural_problems(&reader)
+        .into_iter()
+        .map(|p| p.message)
+        .collect();
+    let signed = reader.signature_block_present();
+    let mut signature_verified = None;
+    if signed {
+        let verified = match verify_signed(&mut reader, policy) {
+            Ok(true) => true,
+            Ok(false) => {
+                failures.push("invalid or unreadable SIG0 block".to_string());
+                false
+            }
+            Err(e) => {
+                failures.push(e.to_string());
+                false
+            }
+        };
+        signature_verified = Some(verified);
+    } else if require_signature {
+        failures.push("no signature block (--require-signature)".to_string());
+    }
+
+    if json {
+        let doc = VerifyJson {
+            schema_version: JSON_SCHEMA_VERSION,
+            file: path.display().to_string(),
+            ok: failures.is_empty(),
+            signature_block_present: signed,
+            signature_verified,
+            failures,
+        };
+        println!("{}", serde_json::to_string_pretty(&doc)?);
+        return if doc.ok { Ok(()) } else { Err(Reported.into()) };
+    }
+    if failures.is_empty() {
+        let signature = match reader.signature_scope() {
+            SignatureScope::IndexOnly => "signature OK (header and manifest only)",
+            _ if signed => "signature OK",
+            _ => "unsigned",
+        };
+        println!("verify: OK, {signature} ({})", path.display());
+        return Ok(());
+    }
+    for failure in &failures {
+        eprintln!("verify: {failure}");
+    }
+    Err(format!(
+        "verify: {} failed ({} problem(s))",
+        path.display(),
+        failures.len()
+    )
+    .into())
 }
 
 fn inspect_file(
@@ -316,7 +1882,7 @@ fn inspect_file(
     let mut reader = ClfReader::open(path)?;
 
     if verify {
-        match reader.verify_with_policy(policy) {
+        match verify_signed(&mut reader, policy) {
             Ok(true) => {}
             Ok(false) => return Err("SIG0 missing or unreadable".into()),
             Err(e) => return Err(format!("signature verification failed: {e}").into()),
@@ -324,7 +1890,7 @@ fn inspect_file(
     }
 
     if json {
-        return inspect_json(&reader, path);
+        return inspect_json(&reader, path, verify);
     }
 
     let h = &reader.header;
@@ -346,6 +1912,18 @@ fn inspect_file(
         println!("Target: {}", h.target);
     }
     println!("Blob alignment: {} bytes", h.blob_alignment);
+    for (key, value) in &h.metadata {
+        println!("Metadata: {key} = {value}");
+    }
+    if let Some(secs) = h.created_at {
+        println!("Created at: {secs} (Unix seconds)");
+    }
+    if let Some(tool) = &h.tool_version {
+        println!("Tool version: {tool}");
+    }
+    for (tag, bytes) in &h.extensions {
+        println!("Extension: tag {tag:#06x} ({} bytes)", bytes.len());
+    }
     println!(
         "Blob store: offset {}  length {}",
         reader.blob_store_offset(),
@@ -362,16 +1940,372 @@ fn inspect_file(
 
     let entries = reader.manifest_entries();
     println!("\nManifest ({} entries):", entries.len());
-    println!("{:>8}  {:>10}  {:>12}", "op_id", "offset", "size (bytes)");
+    // The variant column only appears for archives that pack several variants of an op.
+    let has_variants = entries.iter().any(|e| e.variant != 0);
+    let variant_col = |v: &dyn std::fmt::Display| {
+        if has_variants {
+            format!("  {v:>7}")
+        } else {
+            String::new()
+        }
+    };
+    // Likewise the target column, for archives that tag entries with their own targets.
+    let has_targets = entries.iter().any(|e| e.target_index != 0);
+    let target_col = |t: &str| {
+        if has_targets {
+            format!("  {t:<16}")
+        } else {
+            String::new()
+        }
+    };
+    println!(
+        "{:>8}{}  {:>10}  {:>12}  {:>12}{}  {:<20}",
+        "op_id",
+        variant_col(&"variant"),
+        "offset",
+        "size (bytes)",
+        "exact",
+        target_col("target"),
+        "op"
+    );
     for e in &entries {
-        println!("{:>8}  {:>10}  {:>12}", e.op_id, e.offset, e.size);
+        let row = format!(
+            "{:>8}{}  {:>10}  {:>12}  {:>12}{}  {:<20}",
+            e.op_id,
+            variant_col(&e.variant),
+            e.offset,
+            e.size,
+            e.exact_size,
+            target_col(reader.entry_target(e)),
+            registry_name(e.op_id).unwrap_or_else(|| "-".to_string())
+        );
+        match reader.entry_name(e.op_id) {
+            Some(name) => println!("{}", format!("{row}  {name}").trim_end()),
+            None => println!("{}", row.trim_end()),
+        }
+    }
+
+    let problems = structural_problems(&reader);
+    if !problems.is_empty() {
+        println!("\nProblems ({}):", problems.len());
+        for problem in &problems {
+            println!("  {}", problem.message);
+        }
     }
 
     Ok(())
 }
 
+/// Registry name for `op_id`, if the registry knows it.
+fn registry_name(op_id: u32) -> Option<String> {
+    OpRegistry::builtin().name(op_id).map(str::to_string)
+}
+
+/// A manifest inconsistency and the op_ids it concerns.
+struct Problem {
+    op_ids: Vec<u32>,
+    message: String,
+}
+
+/// Manifest inconsistencies worth flagging in inspect output. The file still parsed, so these
+/// are reported rather than failing the command.
+fn structural_problems(reader: &ClfReader) -> Vec<Problem> {
+    let store_len = reader.file_blob_store_len();
+    let mut problems: Vec<Problem> = reader
+        .manifest_entries()
+        .iter()
+        .filter(|e| u64::from(e.offset) + u64::from(e.size) > store_len)
+        .map(|e| Problem {
+            op_ids: vec![e.op_id],
+            message: format!(
+                "op_id {} (variant {}) ends at {} but the blob store is {store_len} bytes",
+                e.op_id,
+                e.variant,
+                u64::from(e.offset) + u64::from(e.size)
+            ),
+        })
+        .collect();
+    // validate() reports out-of-bounds entries first; those are already listed above.
+    if let Err(e @ ClfError::OverlappingBlobs { first, second }) = reader.validate() {
+        problems.push(Problem {
+            op_ids: vec![first, second],
+            message: e.to_string(),
+        });
+    }
+    let trailing = reader.trailing_data_len();
+    if trailing > 0 {
+        problems.push(Problem {
+            op_ids: Vec::new(),
+            message: format!("{trailing} bytes of unexpected data after the archive"),
+        });
+    }
+    problems
+}
+
+/// Open `path` for extraction, checking the signature first when `verify` is set.
+fn open_for_extract(
+    path: &Path,
+    verify: Option<VerificationPolicy>,
+) -> Result<ClfReader, Box<dyn std::error::Error>> {
+    let mut reader = ClfReader::open(path)?;
+    if let Some(policy) = verify {
+        match verify_signed(&mut reader, policy) {
+            Ok(true) => {}
+            Ok(false) => return Err("SIG0 missing or unreadable".into()),
+            Err(e) => return Err(format!("signature verification failed: {e}").into()),
+        }
+    }
+    Ok(reader)
+}
+
+/// `<op_id>_<op name>.bin` (lowercase registry name), or `<op_id>.bin` for ids the registry does
+/// not know; variants other than 0 get a `_v<variant>` suffix. `--from-dir` accepts these names.
+fn extract_file_name(op_id: u32, variant: u16) -> String {
+    let mut name = op_id.to_string();
+    if let Some(op) = registry_name(op_id) {
+        name = format!("{name}_{}", op.to_lowercase());
+    }
+    if variant != 0 {
+        name = format!("{name}_v{variant}");
+    }
+    name + ".bin"
+}
+
+/// Write `bytes` to `path`, refusing to replace an existing file unless `force`.
+fn write_extracted(path: &Path, bytes: &[u8], force: bool) -> Result<(), String> {
+    let mut open = std::fs::OpenOptions::new();
+    open.write(true);
+    if force {
+        open.create(true).truncate(true);
+    } else {
+        open.create_new(true);
+    }
+    let mut file = open.open(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::AlreadyExists => {
+            format!(
+                "{} already exists (use --force to overwrite)",
+                path.display()
+            )
+        }
+        _ => format!("write {}: {e}", path.display()),
+    })?;
+    std::io::Write::write_all(&mut file, bytes)
+        .map_err(|e| format!("write {}: {e}", path.display()))
+}
+
+fn extract_one(
+    path: &Path,
+    op_id: u32,
+    output: Option<&Path>,
+    force: bool,
+    verify: Option<VerificationPolicy>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut reader = open_for_extract(path, verify)?;
+    let Some(blob) = reader.get_blob(op_id)? else {
+        let available: Vec<String> = reader.op_ids().iter().map(u32::to_string).collect();
+        return Err(format!(
+            "op_id {op_id} not found in {}; available: {}",
+            path.display(),
+            if available.is_empty() {
+                "(none)".to_string()
+            } else {
+                available.join(", ")
+            }
+        )
+        .into());
+    };
+    let out = output.map_or_else(
+        || PathBuf::from(extract_file_name(op_id, 0)),
+        Path::to_path_buf,
+    );
+    write_extracted(&out, &blob, force)?;
+    println!("wrote {} ({} bytes)", out.display(), blob.len());
+    Ok(())
+}
+
+fn extract_all(
+    path: &Path,
+    output: Option<&Path>,
+    force: bool,
+    verify: Option<VerificationPolicy>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut reader = open_for_extract(path, verify)?;
+    let dir = output.unwrap_or(Path::new("."));
+    std::fs::create_dir_all(dir).map_err(|e| format!("create {}: {e}", dir.display()))?;
+    let entries = reader.manifest_entries();
+    // Check every destination before writing so a clash does not leave a partial extraction.
+    if !force {
+        for e in &entries {
+            let out = dir.join(extract_file_name(e.op_id, e.variant));
+            if out.exists() {
+                return Err(format!(
+                    "{} already exists (use --force to overwrite)",
+                    out.display()
+                )
+                .into());
+            }
+        }
+    }
+    let mut total = 0u64;
+    for e in &entries {
+        let blob = reader
+            .get_blob_variant(e.op_id, e.variant)?
+            .ok_or_else(|| format!("op_id {} variant {} is unreadable", e.op_id, e.variant))?;
+        write_extracted(
+            &dir.join(extract_file_name(e.op_id, e.variant)),
+            &blob,
+            force,
+        )?;
+        total += blob.len() as u64;
+    }
+    println!(
+        "wrote {} blobs ({total} bytes) to {}",
+        entries.len(),
+        dir.display()
+    );
+    Ok(())
+}
+
+#[derive(serde::Serialize)]
+struct VerifyJson {
+    schema_version: u32,
+    file: String,
+    ok: bool,
+    signature_block_present: bool,
+    /// `None` when there is no signature block to check.
+    signature_verified: Option<bool>,
+    failures: Vec<String>,
+}
+
+#[derive(serde::Serialize)]
+struct StatsKeyJson {
+    op_id: u32,
+    variant: u16,
+}
+
+#[derive(serde::Serialize)]
+struct StatsOpJson {
+    op_id: u32,
+    variant: u16,
+    op_name: Option<String>,
+    name: Option<String>,
+    /// Bytes in the blob store, excluding alignment padding.
+    stored_bytes: u32,
+    /// Bytes after decompression.
+    raw_bytes: u32,
+    padding_bytes: u32,
+}
+
+#[derive(serde::Serialize)]
+struct HashEntryJson {
+    op_id: u32,
+    variant: u16,
+    /// Canonical registry name, if the op_id has one.
+    op_name: Option<String>,
+    /// Name from the archive's name table.
+    name: Option<String>,
+    /// Decoded blob length.
+    size: u32,
+    /// SHA-256 of the decoded blob, lowercase hex.
+    sha256: String,
+}
+
+#[derive(serde::Serialize)]
+struct HashJson {
+    schema_version: u32,
+    file: String,
+    file_size: u64,
+    file_sha256: String,
+    /// Bytes the signature covers from the start of the file; `None` when unsigned.
+    signed_len: Option<u64>,
+    signed_sha256: Option<String>,
+    entries: Vec<HashEntryJson>,
+}
+
+#[derive(serde::Serialize)]
+struct CoverageOpJson {
+    op_id: u32,
+    /// Canonical registry name, if the op_id has one.
+    op_name: Option<String>,
+    /// Name from the archive's name table.
+    name: Option<String>,
+    present: bool,
+    /// Decoded blob length of the lowest variant; `None` when missing.
+    size: Option<u32>,
+}
+
+#[derive(serde::Serialize)]
+struct CoverageJson {
+    schema_version: u32,
+    file: String,
+    ops_file: String,
+    /// At most `allow_missing` ops are missing.
+    ok: bool,
+    /// Distinct ops listed in `ops_file`.
+    required: usize,
+    present: usize,
+    /// Missing op_ids, in the order the ops file lists them.
+    missing: Vec<u32>,
+    allow_missing: usize,
+    /// Share of the listed ops present, 0-100.
+    percent: f64,
+    /// Decoded bytes of the present ops.
+    present_bytes: u64,
+    /// Present ops first, then missing ones, each in ops-file order.
+    ops: Vec<CoverageOpJson>,
+}
+
+#[derive(serde::Serialize)]
+struct StatsDuplicateJson {
+    /// SHA-256 of the decoded contents shared by `entries`.
+    sha256: String,
+    entries: Vec<StatsKeyJson>,
+    /// Bytes the copies beyond the first occupy (0 when the entries already share a blob).
+    wasted_bytes: u64,
+}
+
+#[derive(serde::Serialize)]
+struct StatsJson {
+    schema_version: u32,
+    file: String,
+    file_size: u64,
+    header_bytes: u64,
+    manifest_bytes: u64,
+    blob_store_bytes: u64,
+    /// Distinct stored blob bytes (shared blobs counted once).
+    blob_bytes: u64,
+    /// Blob store bytes not covered by a blob: alignment padding.
+    padding_bytes: u64,
+    signature_bytes: u64,
+    /// Total entries; `ops` holds at most `--top` of them, largest first.
+    entries: usize,
+    ops: Vec<StatsOpJson>,
+    duplicates: Vec<StatsDuplicateJson>,
+}
+
+#[derive(serde::Serialize)]
+struct InspectEntryJson {
+    #[serde(flatten)]
+    entry: clf::ManifestEntry,
+    /// Canonical registry name, if the op_id has one.
+    op_name: Option<String>,
+    /// Name from the archive's name table.
+    name: Option<String>,
+    target: String,
+    /// Structural problems involving this entry.
+    findings: Vec<String>,
+}
+
+#[derive(serde::Serialize)]
+struct InspectExtensionJson {
+    tag: u16,
+    /// Record bytes, lowercase hex.
+    data: String,
+}
+
 #[derive(serde::Serialize)]
 struct InspectJson {
+    schema_version: u32,
     file: String,
     format_version: u8,
     kind: String,
@@ -379,15 +2313,49 @@ struct InspectJson {
     vendor: String,
     target: String,
     blob_alignment: u8,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    metadata: Vec<(String, String)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_version: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    entry_names: BTreeMap<u32, String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    extensions: Vec<InspectExtensionJson>,
     blob_store_offset: u64,
     blob_store_len: u64,
     signature_block_present: bool,
-    manifest: Vec<clf::ManifestEntry>,
+    /// `None` unless `--verify-signature` was given (inspect fails when the check does).
+    signature_verified: Option<bool>,
+    manifest: Vec<InspectEntryJson>,
+    problems: Vec<String>,
 }
 
-fn inspect_json(reader: &ClfReader, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+fn inspect_json(
+    reader: &ClfReader,
+    path: &Path,
+    verified: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
     let h = &reader.header;
+    let problems = structural_problems(reader);
+    let manifest = reader
+        .manifest_entries()
+        .into_iter()
+        .map(|entry| InspectEntryJson {
+            op_name: registry_name(entry.op_id),
+            name: reader.entry_name(entry.op_id).map(str::to_string),
+            target: reader.entry_target(&entry).to_string(),
+            findings: problems
+                .iter()
+                .filter(|p| p.op_ids.contains(&entry.op_id))
+                .map(|p| p.message.clone())
+                .collect(),
+            entry,
+        })
+        .collect();
     let out = InspectJson {
+        schema_version: JSON_SCHEMA_VERSION,
         file: path.display().to_string(),
         format_version: h.version,
         kind: h.kind.to_string(),
@@ -395,10 +2363,24 @@ fn inspect_json(reader: &ClfReader, path: &Path) -> Result<(), Box<dyn std::erro
         vendor: h.vendor.clone(),
         target: h.target.clone(),
         blob_alignment: h.blob_alignment,
+        metadata: h.metadata.clone(),
+        created_at: h.created_at,
+        tool_version: h.tool_version.clone(),
+        entry_names: h.entry_names.clone(),
+        extensions: h
+            .extensions
+            .iter()
+            .map(|(tag, bytes)| InspectExtensionJson {
+                tag: *tag,
+                data: hex(bytes),
+            })
+            .collect(),
         blob_store_offset: reader.blob_store_offset(),
         blob_store_len: reader.blob_store_len(),
         signature_block_present: reader.signature_block_present(),
-        manifest: reader.manifest_entries(),
+        signature_verified: verified.then_some(true),
+        manifest,
+        problems: problems.into_iter().map(|p| p.message).collect(),
     };
     println!(
         "{}",
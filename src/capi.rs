@@ -0,0 +1,296 @@
+//! C API for the reader (feature `capi`): open an archive, list its op_ids, fetch blobs and check
+//! the signature from C or C++. The declarations are in `include/clf.h`; build the library with
+//! `cargo rustc --release --lib --features capi --crate-type staticlib` (or `cdylib`).
+//!
+//! Functions returning `int` return `CLF_OK` (0) or a negative `CLF_ERR_*` code. After an error,
+//! `clf_last_error_message(handle)` describes it; errors from `clf_reader_open`, which has no
+//! handle yet, are reported by `clf_last_error_message(NULL)` on the same thread. No panic crosses
+//! the boundary: one is caught and reported as `CLF_ERR_PANIC`.
+//!
+//! A handle is not thread-safe: use it from one thread at a time.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::ptr;
+
+use crate::format::SignatureScope;
+use crate::reader::{ClfError, ClfReader};
+
+/// Success.
+pub const CLF_OK: c_int = 0;
+/// A required pointer was NULL, the path was not valid, or an index was out of range.
+pub const CLF_ERR_INVALID_ARGUMENT: c_int = -1;
+/// Reading the file failed (including truncated files).
+pub const CLF_ERR_IO: c_int = -2;
+/// The file is not a well-formed CLF archive.
+pub const CLF_ERR_FORMAT: c_int = -3;
+/// The signature does not match or cannot be checked.
+pub const CLF_ERR_SIGNATURE: c_int = -4;
+/// The archive has no blob for the requested op_id.
+pub const CLF_ERR_NOT_FOUND: c_int = -5;
+/// The library panicked; the handle should be closed.
+pub const CLF_ERR_PANIC: c_int = -6;
+
+/// `clf_reader_verify_signature`: no signature trailer.
+pub const CLF_SIGNATURE_NONE: c_int = 0;
+/// `clf_reader_verify_signature`: the signature matches and covers the whole file.
+pub const CLF_SIGNATURE_FULL: c_int = 1;
+/// `clf_reader_verify_signature`: the signature matches but covers only the header and manifest;
+/// the blob store is not checked.
+pub const CLF_SIGNATURE_INDEX_ONLY: c_int = 2;
+/// `clf_reader_verify_signature`: a keyed (HMAC or Ed25519) SIG1 block, which needs a key the C
+/// API does not take, so it is left unchecked.
+pub const CLF_SIGNATURE_KEYED: c_int = 3;
+
+/// Open reader plus the state the C API keeps for it.
+pub struct ClfReaderHandle {
+    reader: ClfReader,
+    /// op_ids in ascending order, for `clf_reader_op_id_at`.
+    op_ids: Vec<u32>,
+    last_error: Option<CString>,
+}
+
+thread_local! {
+    /// Last error of a call made without a handle (`clf_reader_open`).
+    static OPEN_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// `CLF_ERR_*` code for `err`.
+fn error_code(err: &ClfError) -> c_int {
+    match err.root() {
+        ClfError::Io(_) => CLF_ERR_IO,
+        ClfError::SignatureInvalid
+        | ClfError::UnknownSignatureAlgorithm(_)
+        | ClfError::UntrustedKey { .. }
+        | ClfError::AuthenticityVerificationUnsupported => CLF_ERR_SIGNATURE,
+        ClfError::MissingOpId(_) | ClfError::MissingOpIds(_) => CLF_ERR_NOT_FOUND,
+        _ => CLF_ERR_FORMAT,
+    }
+}
+
+/// `message` as a C string, dropping any interior NUL bytes.
+fn c_message(message: String) -> CString {
+    CString::new(message.replace('\0', "")).unwrap_or_default()
+}
+
+/// Run `f` on the handle behind `handle`, recording its error (or a caught panic) there.
+///
+/// # Safety
+///
+/// `handle` must be NULL or a live pointer returned by `clf_reader_open`.
+unsafe fn with_handle(
+    handle: *mut ClfReaderHandle,
+    f: impl FnOnce(&mut ClfReaderHandle) -> Result<c_int, (c_int, String)>,
+) -> c_int {
+    // SAFETY: the caller guarantees `handle` is NULL or live and not used concurrently.
+    let Some(handle) = (unsafe { handle.as_mut() }) else {
+        return CLF_ERR_INVALID_ARGUMENT;
+    };
+    let result = catch_unwind(AssertUnwindSafe(|| f(handle)))
+        .unwrap_or_else(|_| Err((CLF_ERR_PANIC, "panic inside the CLF library".to_string())));
+    match result {
+        Ok(code) => {
+            handle.last_error = None;
+            code
+        }
+        Err((code, message)) => {
+            handle.last_error = Some(c_message(message));
+            code
+        }
+    }
+}
+
+/// Path from a NUL-terminated C string: raw bytes on Unix, UTF-8 elsewhere.
+fn path_from_c(path: &CStr) -> Option<PathBuf> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        Some(std::ffi::OsStr::from_bytes(path.to_bytes()).into())
+    }
+    #[cfg(not(unix))]
+    {
+        path.to_str().ok().map(PathBuf::from)
+    }
+}
+
+/// Open the archive at `path` (NUL-terminated). Returns NULL on failure; the reason is then
+/// available from `clf_last_error_message(NULL)` on the calling thread.
+///
+/// # Safety
+///
+/// `path` must be NULL or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn clf_reader_open(path: *const c_char) -> *mut ClfReaderHandle {
+    let result = catch_unwind(|| {
+        if path.is_null() {
+            return Err("path is NULL".to_string());
+        }
+        // SAFETY: non-NULL and NUL-terminated per the contract above.
+        let path = unsafe { CStr::from_ptr(path) };
+        let path = path_from_c(path).ok_or_else(|| "path is not valid UTF-8".to_string())?;
+        let reader = ClfReader::open(&path).map_err(|e| e.to_string())?;
+        let op_ids = reader.op_ids();
+        Ok(ClfReaderHandle {
+            reader,
+            op_ids,
+            last_error: None,
+        })
+    })
+    .unwrap_or_else(|_| Err("panic inside the CLF library".to_string()));
+    match result {
+        Ok(handle) => {
+            OPEN_ERROR.with(|e| *e.borrow_mut() = None);
+            Box::into_raw(Box::new(handle))
+        }
+        Err(message) => {
+            OPEN_ERROR.with(|e| *e.borrow_mut() = Some(c_message(message)));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Close a handle from `clf_reader_open`. NULL is ignored.
+///
+/// # Safety
+///
+/// `handle` must be NULL or a live handle; it must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn clf_reader_close(handle: *mut ClfReaderHandle) {
+    if !handle.is_null() {
+        // SAFETY: the handle came from `Box::into_raw` in `clf_reader_open` and is closed once.
+        let _ = catch_unwind(AssertUnwindSafe(|| drop(unsafe { Box::from_raw(handle) })));
+    }
+}
+
+/// Fetch the default-variant blob for `op_id` into a new buffer stored in `*out_ptr` /
+/// `*out_len`; free it with `clf_blob_free`. Returns `CLF_ERR_NOT_FOUND` (leaving the outputs
+/// untouched) when the archive has no such op_id.
+///
+/// # Safety
+///
+/// `handle` must be NULL or live; `out_ptr` and `out_len` must be NULL or writable.
+#[no_mangle]
+pub unsafe extern "C" fn clf_reader_get_blob(
+    handle: *mut ClfReaderHandle,
+    op_id: u32,
+    out_ptr: *mut *mut u8,
+    out_len: *mut usize,
+) -> c_int {
+    if out_ptr.is_null() || out_len.is_null() {
+        return CLF_ERR_INVALID_ARGUMENT;
+    }
+    // SAFETY: forwarded caller contract.
+    unsafe {
+        with_handle(handle, |h| {
+            let blob = h
+                .reader
+                .get_blob(op_id)
+                .map_err(|e| (error_code(&e), e.to_string()))?
+                .ok_or_else(|| (CLF_ERR_NOT_FOUND, format!("op_id {op_id} not in archive")))?;
+            let len = blob.len();
+            let blob = Box::into_raw(blob.into_boxed_slice());
+            // SAFETY: both outputs were checked non-NULL and are writable per the contract.
+            *out_len = len;
+            *out_ptr = blob.cast::<u8>();
+            Ok(CLF_OK)
+        })
+    }
+}
+
+/// Free a blob returned by `clf_reader_get_blob`, passing the length it reported. NULL is
+/// ignored.
+///
+/// # Safety
+///
+/// `ptr` and `len` must come from one successful `clf_reader_get_blob` call, freed once.
+#[no_mangle]
+pub unsafe extern "C" fn clf_blob_free(ptr: *mut u8, len: usize) {
+    if !ptr.is_null() {
+        // SAFETY: `ptr` / `len` describe a boxed slice leaked by `clf_reader_get_blob`.
+        drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(ptr, len)) });
+    }
+}
+
+/// Check the signature trailer and return what it covers: `CLF_SIGNATURE_FULL`,
+/// `CLF_SIGNATURE_INDEX_ONLY`, `CLF_SIGNATURE_NONE` when the archive is unsigned, or
+/// `CLF_SIGNATURE_KEYED` (unchecked) for an HMAC or Ed25519 block. `CLF_ERR_SIGNATURE` when it
+/// does not match.
+///
+/// # Safety
+///
+/// `handle` must be NULL or live.
+#[no_mangle]
+pub unsafe extern "C" fn clf_reader_verify_signature(handle: *mut ClfReaderHandle) -> c_int {
+    // SAFETY: forwarded caller contract.
+    unsafe {
+        with_handle(handle, |h| match h.reader.verify_signature_scope() {
+            Ok(SignatureScope::Full) => Ok(CLF_SIGNATURE_FULL),
+            Ok(SignatureScope::IndexOnly) => Ok(CLF_SIGNATURE_INDEX_ONLY),
+            Ok(SignatureScope::None) if h.reader.signature_block_present() => {
+                Ok(CLF_SIGNATURE_KEYED)
+            }
+            Ok(SignatureScope::None) => Ok(CLF_SIGNATURE_NONE),
+            Err(e) => Err((error_code(&e), e.to_string())),
+        })
+    }
+}
+
+/// Number of distinct op_ids in the archive; 0 for a NULL handle.
+///
+/// # Safety
+///
+/// `handle` must be NULL or live.
+#[no_mangle]
+pub unsafe extern "C" fn clf_reader_op_count(handle: *const ClfReaderHandle) -> usize {
+    // SAFETY: forwarded caller contract.
+    unsafe { handle.as_ref() }.map_or(0, |h| h.op_ids.len())
+}
+
+/// Store the `index`-th op_id (ascending order) in `*out_op_id`.
+///
+/// # Safety
+///
+/// `handle` must be NULL or live; `out_op_id` must be NULL or writable.
+#[no_mangle]
+pub unsafe extern "C" fn clf_reader_op_id_at(
+    handle: *mut ClfReaderHandle,
+    index: usize,
+    out_op_id: *mut u32,
+) -> c_int {
+    if out_op_id.is_null() {
+        return CLF_ERR_INVALID_ARGUMENT;
+    }
+    // SAFETY: forwarded caller contract.
+    unsafe {
+        with_handle(handle, |h| {
+            let op_id = *h.op_ids.get(index).ok_or_else(|| {
+                (
+                    CLF_ERR_INVALID_ARGUMENT,
+                    format!("index {index} out of range ({} op_ids)", h.op_ids.len()),
+                )
+            })?;
+            // SAFETY: checked non-NULL and writable per the contract.
+            *out_op_id = op_id;
+            Ok(CLF_OK)
+        })
+    }
+}
+
+/// Message for the last failed call on `handle`, or for the last failed `clf_reader_open` on
+/// this thread when `handle` is NULL. NULL when that call succeeded. The string is owned by the
+/// library and valid until the next call on the same handle (or the next open on this thread).
+///
+/// # Safety
+///
+/// `handle` must be NULL or live.
+#[no_mangle]
+pub unsafe extern "C" fn clf_last_error_message(handle: *const ClfReaderHandle) -> *const c_char {
+    // SAFETY: forwarded caller contract.
+    match unsafe { handle.as_ref() } {
+        Some(h) => h.last_error.as_ref().map_or(ptr::null(), |m| m.as_ptr()),
+        None => OPEN_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |m| m.as_ptr())),
+    }
+}
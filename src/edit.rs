@@ -0,0 +1,586 @@
+//! Edit an existing .clf in place of re-packing it: replace, remove or add single entries.
+//!
+//! Edits are staged on a `ClfEditor` and applied by `save`, which rewrites the whole archive
+//! (untouched blobs are streamed from the original) and atomically replaces the file.
+//! `append_entries` instead adds blobs to a large archive in place, leaving existing blobs where
+//! they are unless the manifest outgrows the room in front of them. `remap_op_ids` renumbers
+//! entries, rewriting only the header and manifest and copying the blob store byte for byte.
+
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::format::{self, ManifestEntry, VersionFeatures, ENTRY_FIELD_CRC32, ENTRY_FLAG_ZSTD};
+use crate::packer::{
+    append_signature, manifest_bytes, pack_clf_streaming_variants, write_atomically, Compression,
+    PackError, PackOptions, PackSummary,
+};
+use crate::reader::{ClfError, ClfReader};
+
+/// Errors produced by `ClfEditor`.
+#[derive(Debug, Error)]
+pub enum EditError {
+    #[error("op_id {0} is not in the archive")]
+    UnknownOpId(u32),
+    #[error("op_id {0} is already in the archive")]
+    DuplicateOpId(u32),
+    #[error("op_ids {first} and {second} would both become op_id {to}")]
+    RemapCollision { to: u32, first: u32, second: u32 },
+    #[error("reading archive: {0}")]
+    Read(#[from] ClfError),
+    #[error("writing archive: {0}")]
+    Pack(#[from] PackError),
+}
+
+/// Where an entry's blob comes from when the archive is rewritten.
+#[derive(Debug)]
+enum Blob {
+    /// Stream it from the original file.
+    Original,
+    /// Bytes supplied by `replace_blob` / `add`.
+    New(Vec<u8>),
+}
+
+/// Staged edits to one .clf file. Nothing is written until `save`.
+///
+/// The rewrite keeps the source header (version, vendor, target, kind, alignment, metadata,
+/// build info, extensions, and names of entries that remain) and re-applies per-blob CRCs, zstd
+/// (at the default level) and blob dedup when the source used them.
+#[derive(Debug)]
+pub struct ClfEditor {
+    path: PathBuf,
+    reader: ClfReader,
+    /// Entries in output order: source entries by (op_id, variant), then added ones.
+    entries: Vec<(u32, u16, Blob)>,
+}
+
+impl ClfEditor {
+    /// Open `path` for editing. Rejects archives whose manifest fails `ClfReader::validate`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, EditError> {
+        let path = path.as_ref().to_path_buf();
+        let reader = ClfReader::open(&path)?;
+        reader.validate()?;
+        let entries = reader
+            .manifest_entries()
+            .into_iter()
+            .map(|e| (e.op_id, e.variant, Blob::Original))
+            .collect();
+        Ok(Self {
+            path,
+            reader,
+            entries,
+        })
+    }
+
+    /// Op_ids the archive will hold after `save`, in output order (each once, whatever its
+    /// variants).
+    #[must_use]
+    pub fn op_ids(&self) -> Vec<u32> {
+        let mut ids: Vec<u32> = Vec::with_capacity(self.entries.len());
+        for (op_id, _, _) in &self.entries {
+            if !ids.contains(op_id) {
+                ids.push(*op_id);
+            }
+        }
+        ids
+    }
+
+    /// Replace the blob of an existing entry's default variant (the new blob may be any size).
+    pub fn replace_blob(&mut self, op_id: u32, data: &[u8]) -> Result<(), EditError> {
+        let slot = self.position(op_id).ok_or(EditError::UnknownOpId(op_id))?;
+        self.entries[slot].2 = Blob::New(data.to_vec());
+        Ok(())
+    }
+
+    /// Remove an existing entry, with all its variants. Removing every entry leaves a valid,
+    /// empty archive.
+    pub fn remove(&mut self, op_id: u32) -> Result<(), EditError> {
+        let before = self.entries.len();
+        self.entries.retain(|(id, _, _)| *id != op_id);
+        if self.entries.len() == before {
+            return Err(EditError::UnknownOpId(op_id));
+        }
+        Ok(())
+    }
+
+    /// Add a new entry (default variant); errors if `op_id` already has one.
+    pub fn add(&mut self, op_id: u32, data: &[u8]) -> Result<(), EditError> {
+        if self.position(op_id).is_some() {
+            return Err(EditError::DuplicateOpId(op_id));
+        }
+        self.entries.push((op_id, 0, Blob::New(data.to_vec())));
+        Ok(())
+    }
+
+    /// Rewrite the archive with the staged edits and atomically replace the file.
+    /// Any existing signature no longer matches the contents, so it is dropped unless `resign`
+    /// is set, in which case a fresh one is appended.
+    pub fn save(self, resign: bool) -> Result<PackSummary, EditError> {
+        let options = self.pack_options(resign);
+        let mut sources: Vec<(u32, u16, Box<dyn Read + '_>)> =
+            Vec::with_capacity(self.entries.len());
+        for (op_id, variant, blob) in &self.entries {
+            let source: Box<dyn Read + '_> = match blob {
+                Blob::Original => Box::new(
+                    self.reader
+                        .blob_reader_variant(*op_id, *variant)?
+                        .expect("original entry is in the source manifest"),
+                ),
+                Blob::New(data) => Box::new(Cursor::new(data.as_slice())),
+            };
+            sources.push((*op_id, *variant, source));
+        }
+        Ok(write_atomically(&self.path, &options, |file| {
+            pack_clf_streaming_variants(file, sources, &options)
+        })?)
+    }
+
+    /// Index of `op_id`'s default variant.
+    fn position(&self, op_id: u32) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|(id, variant, _)| *id == op_id && *variant == 0)
+    }
+
+    /// Pack options that reproduce the source archive's layout.
+    fn pack_options(&self, sign: bool) -> PackOptions {
+        let header = &self.reader.header;
+        let manifest = self.reader.manifest_entries();
+        let mut offsets = HashSet::with_capacity(manifest.len());
+        PackOptions {
+            vendor: header.vendor.clone(),
+            target: header.target.clone(),
+            blob_alignment: header.blob_alignment,
+            kind: header.kind,
+            version: header.version,
+            sign,
+            dedup_blobs: !manifest.iter().all(|e| offsets.insert(e.offset)),
+            compression: manifest
+                .iter()
+                .any(|e| e.flags & ENTRY_FLAG_ZSTD != 0)
+                .then_some(Compression::Zstd(0)),
+            per_blob_crc: manifest.iter().any(|e| e.crc32.is_some()),
+            metadata: header.metadata.clone(),
+            created_at: header.created_at,
+            tool_version: header.tool_version.clone(),
+            deterministic: false,
+            entry_names: header
+                .entry_names
+                .iter()
+                .filter(|(op_id, _)| self.entries.iter().any(|(id, _, _)| id == *op_id))
+                .map(|(&op_id, name)| (op_id, name.clone()))
+                .collect(),
+            entry_targets: manifest
+                .iter()
+                .filter(|e| e.target_index != 0)
+                .filter(|e| {
+                    self.entries
+                        .iter()
+                        .any(|(id, variant, _)| (*id, *variant) == (e.op_id, e.variant))
+                })
+                .map(|e| {
+                    (
+                        (e.op_id, e.variant),
+                        self.reader.entry_target(e).to_string(),
+                    )
+                })
+                .collect(),
+            extensions: header.extensions.clone(),
+            strict_op_ids: false,
+            #[cfg(feature = "ed25519")]
+            sign_key: None,
+            hmac_key: None,
+            sign_index_only: false,
+            #[cfg(feature = "encryption")]
+            encrypt: None,
+            max_total_size: None,
+        }
+    }
+}
+
+/// Options for `append_entries`.
+#[derive(Debug, Clone)]
+pub struct AppendOptions {
+    /// Replace the default variant of op_ids the archive already has (the old blob stays in the
+    /// file as unused bytes). Without it such op_ids fail with `EditError::DuplicateOpId`.
+    pub replace: bool,
+    /// When the blob store has to move to make room for the manifest, move it far enough that
+    /// this many more entries fit before the next move.
+    pub reserve_entries: u32,
+    /// Append a SIG0 signature block afterwards. For other signatures, call
+    /// `append_signature_with` on the file with the returned `data_len`.
+    pub sign: bool,
+}
+
+impl Default for AppendOptions {
+    fn default() -> Self {
+        Self {
+            replace: false,
+            reserve_entries: 256,
+            sign: false,
+        }
+    }
+}
+
+/// What `append_entries` did to the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AppendSummary {
+    /// Bytes of archive data (header + manifest + blob store), before any signature block.
+    pub data_len: u64,
+    /// How far the existing blob store was moved towards the end of the file; 0 when the new
+    /// manifest fit in front of the first blob.
+    pub store_shift: u64,
+    /// Entries a later append can add before the blob store has to move again.
+    pub spare_entries: u64,
+}
+
+/// Chunk size for moving the blob store.
+const SHIFT_CHUNK: usize = 1 << 20;
+
+/// Append `entries` (op_id, blob) to the unsigned archive at `path` in place, as default-variant
+/// entries stored uncompressed (with a CRC-32 if the archive records them).
+///
+/// The header is kept as it is and the manifest is rewritten in place with the new entries at
+/// its end; the new blobs go after the end of the blob store. Existing blobs are not copied as
+/// long as the larger manifest fits in the bytes before the first blob (alignment fill, or
+/// room reserved by an earlier append). Otherwise the blob store is moved towards the end of
+/// the file, by a multiple of the blob alignment, to make room for the manifest plus
+/// `reserve_entries` more entries. Offsets in the manifest are relative to its end, so every
+/// existing entry's offset is rewritten either way.
+///
+/// A signed archive fails with `PackError::AlreadySigned`: strip the signature first (the data
+/// it covers changes). The file is modified in place and is not valid while this runs, so a
+/// crash part-way leaves a damaged archive; use `ClfEditor`, which writes a new file and renames
+/// it, where that matters.
+pub fn append_entries<P, I>(
+    path: P,
+    entries: I,
+    options: &AppendOptions,
+) -> Result<AppendSummary, EditError>
+where
+    P: AsRef<Path>,
+    I: IntoIterator,
+    I::Item: Borrow<(u32, Vec<u8>)>,
+{
+    let path = path.as_ref();
+    let reader = ClfReader::open(path)?;
+    reader.validate()?;
+    if reader.signature_block_present() {
+        return Err(PackError::AlreadySigned.into());
+    }
+    let version = reader.header.version;
+    let align = u64::from(reader.header.blob_alignment.max(1));
+    let header_end = reader.header.header_end;
+    let store_start = reader.blob_store_offset();
+    let store_len = reader.declared_blob_store_len();
+    let old_entries = reader.manifest_entries();
+    // The entry_fields mask and target table are kept byte for byte.
+    let raw_manifest = reader.raw_manifest()?;
+    drop(reader);
+    let prefix_len = ManifestEntry::manifest_prefix_len(version);
+    let entry_fields = if VersionFeatures::for_version(version).has_flags {
+        u32::from_le_bytes(raw_manifest[4..8].try_into().expect("manifest prefix"))
+    } else {
+        0
+    };
+    let entry_size = ManifestEntry::entry_size(version, entry_fields) as u64;
+    let targets =
+        &raw_manifest[prefix_len..raw_manifest.len() - old_entries.len() * entry_size as usize];
+
+    let mut added: Vec<(u32, &[u8])> = Vec::new();
+    let entries: Vec<I::Item> = entries.into_iter().collect();
+    for item in &entries {
+        let (op_id, data) = item.borrow();
+        let exists = old_entries
+            .iter()
+            .any(|e| e.op_id == *op_id && e.variant == 0);
+        if (exists && !options.replace) || added.iter().any(|(id, _)| id == op_id) {
+            return Err(EditError::DuplicateOpId(*op_id));
+        }
+        added.push((*op_id, data.as_slice()));
+    }
+    let kept: Vec<ManifestEntry> = old_entries
+        .into_iter()
+        .filter(|e| e.variant != 0 || !added.iter().any(|(id, _)| *id == e.op_id))
+        .collect();
+
+    let num_entries = kept.len() + added.len();
+    let new_store_start =
+        header_end + (prefix_len + targets.len()) as u64 + num_entries as u64 * entry_size;
+    // Bytes at the front of the blob store that no kept blob uses.
+    let slack = kept
+        .iter()
+        .filter(|e| e.size != 0)
+        .map(|e| u64::from(e.offset))
+        .min()
+        .unwrap_or(store_len);
+    let growth = new_store_start.saturating_sub(store_start);
+    let store_shift = if growth > slack {
+        (growth - slack + u64::from(options.reserve_entries) * entry_size).next_multiple_of(align)
+    } else {
+        0
+    };
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(PackError::Io)?;
+    if store_shift != 0 {
+        shift_up(
+            &mut file,
+            store_start + slack,
+            store_start + store_len,
+            store_shift,
+        )
+        .map_err(PackError::Io)?;
+    }
+    let relocated = |offset: u32| {
+        (store_start + u64::from(offset) + store_shift).saturating_sub(new_store_start)
+    };
+    let mut manifest: Vec<ManifestEntry> = Vec::with_capacity(num_entries);
+    for entry in &kept {
+        manifest.push(ManifestEntry {
+            offset: store_offset(entry.op_id, relocated(entry.offset))?,
+            ..*entry
+        });
+    }
+
+    let mut pos = (store_start + store_shift + store_len)
+        .max(new_store_start)
+        .next_multiple_of(align);
+    file.seek(SeekFrom::Start(pos)).map_err(PackError::Io)?;
+    for (op_id, data) in &added {
+        let len = data.len() as u64;
+        if len > u64::from(u32::MAX) {
+            return Err(PackError::BlobTooLarge(*op_id).into());
+        }
+        let padded = len.next_multiple_of(align);
+        let offset = store_offset(*op_id, pos - new_store_start)?;
+        store_offset(*op_id, pos + padded - new_store_start)?;
+        file.write_all(data).map_err(PackError::Io)?;
+        file.write_all(&vec![0; (padded - len) as usize])
+            .map_err(PackError::Io)?;
+        manifest.push(ManifestEntry {
+            op_id: *op_id,
+            variant: 0,
+            offset,
+            size: padded as u32,
+            exact_size: len as u32,
+            flags: 0,
+            raw_size: len as u32,
+            crc32: (entry_fields & ENTRY_FIELD_CRC32 != 0).then(|| crc32fast::hash(data)),
+            target_index: 0,
+        });
+        pos += padded;
+    }
+    let data_len = if added.is_empty() {
+        (store_start + store_shift + store_len).max(new_store_start)
+    } else {
+        pos
+    };
+
+    let mut index = Vec::with_capacity((new_store_start - header_end) as usize);
+    index.extend_from_slice(&(num_entries as u32).to_le_bytes());
+    index.extend_from_slice(&raw_manifest[4..prefix_len]);
+    index.extend_from_slice(targets);
+    index.extend(manifest_bytes(&manifest, version, entry_fields).map_err(PackError::Io)?);
+    let first_blob = manifest
+        .iter()
+        .filter(|e| e.size != 0)
+        .map(|e| new_store_start + u64::from(e.offset))
+        .min()
+        .unwrap_or(data_len);
+    file.seek(SeekFrom::Start(header_end))
+        .map_err(PackError::Io)?;
+    file.write_all(&index).map_err(PackError::Io)?;
+    // Zero the stale manifest bytes between the new manifest and the first blob.
+    std::io::copy(
+        &mut std::io::repeat(0).take(first_blob - new_store_start),
+        &mut file,
+    )
+    .map_err(PackError::Io)?;
+    file.set_len(data_len).map_err(PackError::Io)?;
+    if options.sign {
+        append_signature(&mut file, data_len)?;
+    }
+    file.sync_all().map_err(PackError::Io)?;
+    Ok(AppendSummary {
+        data_len,
+        store_shift,
+        spare_entries: (first_blob - new_store_start) / entry_size,
+    })
+}
+
+/// How `remap_op_ids` renumbers an archive, as decided by `plan_remap`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RemapPlan {
+    /// (old op_id, new op_id) for each op_id of the archive that changes, ascending by old id.
+    pub remapped: Vec<(u32, u32)>,
+    /// op_ids of the archive the map leaves as they are, ascending.
+    pub unmapped: Vec<u32>,
+    /// op_ids the map renumbers that the archive does not have, ascending.
+    pub absent: Vec<u32>,
+}
+
+/// Check `map` (old op_id -> new op_id) against the op_ids of `reader` without reading any
+/// blobs. Fails with `EditError::RemapCollision` when two op_ids would end up with the same id:
+/// two remaps with one target, or a remap onto an op_id that is not itself remapped away. Swaps
+/// and cycles are fine.
+pub fn plan_remap(reader: &ClfReader, map: &BTreeMap<u32, u32>) -> Result<RemapPlan, EditError> {
+    let op_ids = reader.op_ids();
+    let mut plan = RemapPlan::default();
+    // New op_id -> the op_id of the archive that becomes it.
+    let mut sources = BTreeMap::new();
+    for &op_id in &op_ids {
+        let to = match map.get(&op_id) {
+            Some(&to) if to != op_id => {
+                plan.remapped.push((op_id, to));
+                to
+            }
+            _ => {
+                plan.unmapped.push(op_id);
+                op_id
+            }
+        };
+        if let Some(other) = sources.insert(to, op_id) {
+            return Err(EditError::RemapCollision {
+                to,
+                first: other.min(op_id),
+                second: other.max(op_id),
+            });
+        }
+    }
+    plan.absent = map
+        .keys()
+        .filter(|op_id| op_ids.binary_search(op_id).is_err())
+        .copied()
+        .collect();
+    Ok(plan)
+}
+
+/// Write `reader`'s archive to `out` with its op_ids renumbered by `map` (see `plan_remap`),
+/// unsigned. Every variant of a remapped op_id moves with it, and so does its entry name; names
+/// of op_ids the archive does not have are dropped. The rest of the header, the entry fields,
+/// the target table and the blob store are kept byte for byte, so compressed, encrypted and
+/// checksummed blobs are copied as they are and stay at the same offsets modulo the alignment.
+/// Manifest entries are written in (op_id, variant) order.
+pub fn remap_op_ids<W: Write>(
+    reader: &ClfReader,
+    map: &BTreeMap<u32, u32>,
+    mut out: W,
+) -> Result<PackSummary, EditError> {
+    reader.validate()?;
+    plan_remap(reader, map)?;
+    let old_entries = reader.manifest_entries();
+    let new_id = |op_id: u32| map.get(&op_id).copied().unwrap_or(op_id);
+
+    let mut header = reader.header.clone();
+    header.entry_names = std::mem::take(&mut header.entry_names)
+        .into_iter()
+        .filter(|(op_id, _)| !reader.variants(*op_id).is_empty())
+        .map(|(op_id, name)| (new_id(op_id), name))
+        .collect();
+    let mut index = Vec::new();
+    format::write_header(&mut index, &header).map_err(PackError::Io)?;
+
+    let version = header.version;
+    let raw_manifest = reader.raw_manifest()?;
+    let entry_fields = if VersionFeatures::for_version(version).has_flags {
+        u32::from_le_bytes(raw_manifest[4..8].try_into().expect("manifest prefix"))
+    } else {
+        0
+    };
+    let entry_size = ManifestEntry::entry_size(version, entry_fields);
+    // Entry count, entry_fields mask and target table.
+    let prefix = &raw_manifest[..raw_manifest.len() - old_entries.len() * entry_size];
+    index.extend_from_slice(prefix);
+
+    // A header of another length (dropped names) would move the blobs off their alignment, so
+    // the blob store gets `fill` more leading bytes.
+    let align = u64::from(header.blob_alignment.max(1));
+    let store_start = (index.len() + old_entries.len() * entry_size) as u64;
+    let fill = (reader.blob_store_offset() % align + align - store_start % align) % align;
+    let store_len = reader.declared_blob_store_len();
+    let mut manifest = Vec::with_capacity(old_entries.len());
+    for entry in &old_entries {
+        manifest.push(ManifestEntry {
+            op_id: new_id(entry.op_id),
+            offset: store_offset(entry.op_id, u64::from(entry.offset) + fill)?,
+            ..*entry
+        });
+    }
+    store_offset(0, fill + store_len)?;
+    manifest.sort_by_key(|e| (e.op_id, e.variant));
+    index.extend(manifest_bytes(&manifest, version, entry_fields).map_err(PackError::Io)?);
+    index.resize(index.len() + fill as usize, 0);
+    out.write_all(&index).map_err(PackError::Io)?;
+    std::io::copy(&mut reader.blob_store_reader(), &mut out).map_err(PackError::Io)?;
+
+    let mut stored = HashSet::new();
+    let aliased: Vec<bool> = manifest
+        .iter()
+        .map(|e| e.size != 0 && !stored.insert(e.offset))
+        .collect();
+    let lead = manifest
+        .iter()
+        .filter(|e| e.size != 0)
+        .map(|e| e.offset)
+        .min()
+        .unwrap_or(0);
+    let options = PackOptions {
+        vendor: header.vendor,
+        kind: header.kind,
+        ..PackOptions::default()
+    };
+    Ok(PackSummary::new(
+        store_start + fill + store_len,
+        lead,
+        &manifest,
+        &aliased,
+        &options,
+    ))
+}
+
+/// `remap_op_ids` into a temp file next to `path`, append the signature `options` requests
+/// (`sign`, `sign_index_only`, `hmac_key` or `sign_key`; its other fields are unused), then
+/// move it into place. `path` may be the file `reader` was opened from. The source's own
+/// signature is never carried over: it covers the old manifest.
+pub fn remap_op_ids_to_path<P: AsRef<Path>>(
+    reader: &ClfReader,
+    map: &BTreeMap<u32, u32>,
+    options: &PackOptions,
+    path: P,
+) -> Result<PackSummary, EditError> {
+    write_atomically(path.as_ref(), options, |file| {
+        remap_op_ids(reader, map, file)
+    })
+}
+
+/// `offset` as a manifest offset, or `BlobStoreTooLarge` if it does not fit the u32 field.
+fn store_offset(op_id: u32, offset: u64) -> Result<u32, PackError> {
+    u32::try_from(offset).map_err(|_| PackError::BlobStoreTooLarge {
+        at_op_id: op_id,
+        total: offset,
+    })
+}
+
+/// Move the bytes in `start..end` up by `by`, last chunk first so none is overwritten before it
+/// has been copied.
+fn shift_up(file: &mut File, start: u64, end: u64, by: u64) -> std::io::Result<()> {
+    let mut buf = vec![0u8; SHIFT_CHUNK.min((end - start) as usize)];
+    let mut pos = end;
+    while pos > start {
+        let chunk = &mut buf[..(pos - start).min(SHIFT_CHUNK as u64) as usize];
+        pos -= chunk.len() as u64;
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(chunk)?;
+        file.seek(SeekFrom::Start(pos + by))?;
+        file.write_all(chunk)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,56 @@
+//! Pull one section's bytes out of an ELF object file, so vendors can pack `.o` files without
+//! running objcopy first (CLI `--input-format elf`).
+
+use object::{Object, ObjectSection, RelocationFlags};
+use thiserror::Error;
+
+/// Section packed when the caller does not name one.
+pub const DEFAULT_SECTION: &str = ".text";
+
+/// Errors produced by `section_bytes`.
+#[derive(Debug, Error)]
+pub enum ElfError {
+    #[error("not an ELF object: {0}")]
+    Parse(#[from] object::Error),
+    #[error("no section {0:?} in the object file")]
+    MissingSection(String),
+    #[error("section {section:?} has no file data (SHT_NOBITS)")]
+    NoData { section: String },
+    #[error(
+        "section {section:?} has {count} relocation(s) (first at offset {first_offset:#x}); link or resolve them before packing"
+    )]
+    Relocations {
+        section: String,
+        count: usize,
+        first_offset: u64,
+    },
+}
+
+/// The bytes of section `name` in the ELF file `data` (any alignment). The section must exist,
+/// occupy file space, and carry no relocations other than `R_*_NONE`: a relocation means the
+/// bytes are not final until linked, so packing them as a kernel would be wrong.
+pub fn section_bytes<'a>(data: &'a [u8], name: &str) -> Result<&'a [u8], ElfError> {
+    let file = object::File::parse(data)?;
+    let section = file
+        .section_by_name(name)
+        .ok_or_else(|| ElfError::MissingSection(name.to_string()))?;
+    if section.file_range().is_none() {
+        return Err(ElfError::NoData {
+            section: name.to_string(),
+        });
+    }
+    // r_type 0 is R_<arch>_NONE on every ELF machine.
+    let relocations: Vec<u64> = section
+        .relocations()
+        .filter(|(_, r)| !matches!(r.flags(), RelocationFlags::Elf { r_type: 0 }))
+        .map(|(offset, _)| offset)
+        .collect();
+    if let Some(&first_offset) = relocations.iter().min() {
+        return Err(ElfError::Relocations {
+            section: name.to_string(),
+            count: relocations.len(),
+            first_offset,
+        });
+    }
+    Ok(section.data()?)
+}
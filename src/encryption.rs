@@ -0,0 +1,63 @@
+//! AES-256-GCM encryption of individual blobs (feature `encryption`), for licensed kernels that
+//! must not be extractable from the file alone.
+//!
+//! Each blob is encrypted with a fresh random nonce, stored in front of the ciphertext, and
+//! flagged `ENTRY_FLAG_AES256GCM` in the manifest. Header and manifest stay plaintext, so an
+//! encrypted archive can still be inspected and validated without the key.
+
+use aes_gcm::aead::{Aead, Generate, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+
+use crate::format::{AES_GCM_NONCE_LEN, AES_GCM_TAG_LEN};
+use crate::reader::ClfError;
+
+/// AES-256 key for `PackOptions::encrypt` and `ClfReader::set_decryption_key`. `Debug` does not
+/// print the key bytes.
+#[derive(Clone, PartialEq, Eq)]
+pub struct EncryptionKey([u8; 32]);
+
+impl EncryptionKey {
+    /// Wrap the 32 key bytes.
+    #[must_use]
+    pub fn new(key: [u8; 32]) -> Self {
+        Self(key)
+    }
+
+    /// Key from a slice, or `None` unless it is exactly 32 bytes long.
+    #[must_use]
+    pub fn from_slice(key: &[u8]) -> Option<Self> {
+        key.try_into().ok().map(Self)
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(&self.0.into())
+    }
+
+    /// `plain` encrypted under a new random nonce: nonce, ciphertext and tag.
+    pub(crate) fn seal(&self, plain: &[u8]) -> std::io::Result<Vec<u8>> {
+        let nonce = Nonce::try_generate().map_err(std::io::Error::other)?;
+        let sealed = self
+            .cipher()
+            .encrypt(&nonce, plain)
+            .map_err(|_| std::io::Error::other("blob too large for AES-GCM"))?;
+        Ok([nonce.as_slice(), &sealed].concat())
+    }
+
+    /// The plaintext of `op_id`'s `stored` bytes (as written by `seal`). A wrong key or altered
+    /// bytes fail with `DecryptionFailed`.
+    pub(crate) fn open(&self, op_id: u32, stored: &[u8]) -> Result<Vec<u8>, ClfError> {
+        let failed = ClfError::DecryptionFailed { op_id };
+        if stored.len() < AES_GCM_NONCE_LEN + AES_GCM_TAG_LEN {
+            return Err(failed);
+        }
+        let (nonce, sealed) = stored.split_at(AES_GCM_NONCE_LEN);
+        let nonce = Nonce::try_from(nonce).expect("nonce slice has the nonce length");
+        self.cipher().decrypt(&nonce, sealed).map_err(|_| failed)
+    }
+}
+
+impl std::fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EncryptionKey(<redacted>)")
+    }
+}
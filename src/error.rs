@@ -0,0 +1,295 @@
+//! `ClfError`, the error type of the reader and of the parsers shared with `ClfSlice`.
+//!
+//! `Display` is written out by hand rather than derived so the type builds without `std`;
+//! `std::error::Error` is implemented when the `std` feature is on.
+
+#[cfg(feature = "std")]
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::format::ClfKind;
+#[cfg(feature = "std")]
+use crate::op_registry::OpType;
+
+/// Errors produced by the CLF reader. Errors from a `ClfReader` opened by path, and from its
+/// later file reads, come wrapped in `WithPath`; match on `root()` to see the cause.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ClfError {
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    /// The data ended inside the header or manifest, in the field `while_reading` starting at
+    /// byte `at_offset` (offsets count from the start of the archive).
+    Truncated {
+        at_offset: u64,
+        while_reading: &'static str,
+    },
+    /// Header or manifest bytes that no valid archive contains: `what` is wrong with the field
+    /// at byte `at_offset`.
+    Malformed {
+        at_offset: u64,
+        what: &'static str,
+    },
+    InvalidMagic,
+    UnsupportedVersion(u8, u8),
+    InvalidVendorUtf8,
+    InvalidTargetUtf8,
+    InvalidKindByte(u8),
+    AuthenticityVerificationUnsupported,
+    SignatureInvalid,
+    Cancelled,
+    UnknownSignatureAlgorithm(u8),
+    TrailingGarbage {
+        expected_len: u64,
+        actual_len: u64,
+    },
+    UntrustedKey {
+        fingerprint: Vec<u8>,
+    },
+    InvalidMetadata(&'static str),
+    MissingOpId(u32),
+    /// Every requested op_id the CLF lacks (`MissingOpIdPolicy::Collect`).
+    MissingOpIds(Vec<u32>),
+    KindMismatch {
+        expected: ClfKind,
+        actual: ClfKind,
+    },
+    ExtensionMismatch {
+        extension: String,
+        kind: ClfKind,
+    },
+    TargetMismatch {
+        expected: String,
+        actual: String,
+    },
+    #[cfg(feature = "std")]
+    UnregisteredOpType(OpType),
+    BlobOutOfBounds {
+        op_id: u32,
+    },
+    OverlappingBlobs {
+        first: u32,
+        second: u32,
+    },
+    DuplicateEntry {
+        op_id: u32,
+        variant: u16,
+    },
+    UnsupportedExtension(u16),
+    UnsupportedEntryFields(u32),
+    CompressionUnsupported {
+        op_id: u32,
+        flags: u32,
+    },
+    BlobChecksumMismatch {
+        op_id: u32,
+        expected: u32,
+        actual: u32,
+    },
+    /// The blob for this op_id is encrypted and the reader has no decryption key.
+    Encrypted(u32),
+    /// An encrypted blob failed authentication: the key is wrong or the stored bytes were
+    /// altered after encryption.
+    DecryptionFailed {
+        op_id: u32,
+    },
+    #[cfg(feature = "std")]
+    Decompress {
+        op_id: u32,
+        source: std::io::Error,
+    },
+    /// A length or count within the format's maxima but over the caller's `ClfLimits`.
+    LimitExceeded {
+        what: &'static str,
+        value: u64,
+        limit: u64,
+    },
+    /// `source`, raised while opening or reading the file at `path`.
+    #[cfg(feature = "std")]
+    WithPath {
+        path: std::path::PathBuf,
+        source: Box<ClfError>,
+    },
+}
+
+impl ClfError {
+    /// The error itself, or for `WithPath` the error it wraps.
+    #[must_use]
+    pub fn root(&self) -> &ClfError {
+        match self {
+            #[cfg(feature = "std")]
+            Self::WithPath { source, .. } => source.root(),
+            e => e,
+        }
+    }
+
+    /// Path of the file the error concerns, if it carries one.
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn path(&self) -> Option<&std::path::Path> {
+        match self {
+            Self::WithPath { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Wrap in `WithPath` for `path`, unless the error already names a file.
+    #[cfg(feature = "std")]
+    pub(crate) fn with_path(self, path: &std::path::Path) -> Self {
+        match self {
+            e @ Self::WithPath { .. } => e,
+            e => Self::WithPath {
+                path: path.to_path_buf(),
+                source: Box::new(e),
+            },
+        }
+    }
+}
+
+impl fmt::Display for ClfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            Self::Io(e) => write!(f, "IO error: {e}"),
+            Self::Truncated {
+                at_offset,
+                while_reading,
+            } => write!(
+                f,
+                "unexpected end of data at byte {at_offset} while reading {while_reading}"
+            ),
+            Self::Malformed { at_offset, what } => write!(f, "{what} at byte {at_offset}"),
+            Self::InvalidMagic => f.write_str("invalid magic: expected CLF1"),
+            Self::UnsupportedVersion(version, max) => write!(
+                f,
+                "unsupported CLF version {version} (supported: 1-{max}): {}",
+                crate::format::unsupported_version_detail(*version)
+            ),
+            Self::InvalidVendorUtf8 => f.write_str("invalid vendor: UTF-8 error"),
+            Self::InvalidTargetUtf8 => f.write_str("invalid target: UTF-8 error"),
+            Self::InvalidKindByte(b) => write!(
+                f,
+                "invalid kind byte {b}: unknown kind (open with lenient_kind to read it as Compute)"
+            ),
+            Self::AuthenticityVerificationUnsupported => f.write_str(
+                "verification policy requires authenticity, but current CLF format only supports integrity",
+            ),
+            Self::SignatureInvalid => f.write_str("signature missing or invalid"),
+            Self::Cancelled => f.write_str("operation cancelled by the caller"),
+            Self::UnknownSignatureAlgorithm(id) => write!(f, "unknown signature algorithm id {id}"),
+            Self::TrailingGarbage {
+                expected_len,
+                actual_len,
+            } => write!(
+                f,
+                "{} bytes of unexpected data after the archive (expected {expected_len} bytes, file has {actual_len})",
+                actual_len - expected_len
+            ),
+            Self::UntrustedKey { fingerprint } => write!(
+                f,
+                "archive is signed by key {}, which is not trusted",
+                crate::format::fingerprint_hex(fingerprint)
+            ),
+            Self::InvalidMetadata(detail) => write!(f, "invalid header metadata: {detail}"),
+            #[cfg(feature = "std")]
+            Self::MissingOpId(op_id) => write!(
+                f,
+                "missing op_id {op_id} ({}) in CLF (policy: Fail)",
+                crate::op_registry::describe_op_id(*op_id)
+            ),
+            #[cfg(not(feature = "std"))]
+            Self::MissingOpId(op_id) => write!(f, "missing op_id {op_id} in CLF (policy: Fail)"),
+            Self::MissingOpIds(op_ids) => {
+                write!(f, "{} op_ids missing in CLF (policy: Collect): ", op_ids.len())?;
+                for (i, op_id) in op_ids.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{op_id}")?;
+                }
+                Ok(())
+            }
+            Self::KindMismatch { expected, actual } => write!(
+                f,
+                "CLF kind mismatch: expected {expected:?}, got {actual:?}"
+            ),
+            Self::ExtensionMismatch { extension, kind } => write!(
+                f,
+                "file extension {extension:?} does not match CLF kind {kind} (expected .{})",
+                kind.extension()
+            ),
+            Self::TargetMismatch { expected, actual } => write!(
+                f,
+                "CLF has no entries for target {expected:?} (header target {actual:?})"
+            ),
+            #[cfg(feature = "std")]
+            Self::UnregisteredOpType(op) => write!(f, "op type {op:?} has no registered CLF op_id"),
+            Self::BlobOutOfBounds { op_id } => {
+                write!(f, "blob for op_id {op_id} extends past the blob store")
+            }
+            Self::OverlappingBlobs { first, second } => {
+                write!(f, "blobs for op_id {first} and {second} partially overlap")
+            }
+            Self::DuplicateEntry { op_id, variant } => write!(
+                f,
+                "manifest lists op_id {op_id} (variant {variant}) more than once"
+            ),
+            Self::UnsupportedExtension(tag) => write!(
+                f,
+                "header extension {tag:#06x} must be understood, but this reader does not know it"
+            ),
+            Self::UnsupportedEntryFields(fields) => {
+                write!(f, "manifest uses unknown entry fields {fields:#x}")
+            }
+            Self::CompressionUnsupported { op_id, flags } => write!(
+                f,
+                "blob for op_id {op_id} is encoded with flags {flags:#x}, which this build cannot decode (compression feature?)"
+            ),
+            Self::BlobChecksumMismatch {
+                op_id,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "CRC-32 mismatch for op_id {op_id}: expected {expected:#010x}, got {actual:#010x}"
+            ),
+            Self::Encrypted(op_id) => write!(
+                f,
+                "blob for op_id {op_id} is encrypted and no decryption key is set"
+            ),
+            Self::DecryptionFailed { op_id } => write!(
+                f,
+                "blob for op_id {op_id} failed authentication: wrong key or tampered ciphertext"
+            ),
+            #[cfg(feature = "std")]
+            Self::Decompress { op_id, source } => {
+                write!(f, "failed to decompress blob for op_id {op_id}: {source}")
+            }
+            Self::LimitExceeded { what, value, limit } => {
+                write!(f, "{what} {value} exceeds the limit of {limit}")
+            }
+            #[cfg(feature = "std")]
+            Self::WithPath { path, source } => write!(f, "{}: {source}", path.display()),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ClfError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) | Self::Decompress { source: e, .. } => Some(e),
+            Self::WithPath { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for ClfError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
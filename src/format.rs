@@ -3,8 +3,20 @@
 //! Defines header layout, manifest entries, and magic/signature constants
 //! for the Coelanox Library File (.clf) format. All multi-byte fields are little-endian.
 
-use std::fmt;
-use std::str::FromStr;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::fmt;
+use core::str::FromStr;
+#[cfg(feature = "std")]
+use std::io::{Read, Write};
+#[cfg(feature = "std")]
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+
+use crate::error::ClfError;
 
 /// Magic bytes at the start of every CLF file: "CLF1".
 pub const CLF_MAGIC: [u8; 4] = [0x43, 0x4C, 0x46, 0x31];
@@ -12,7 +24,86 @@ pub const CLF_MAGIC: [u8; 4] = [0x43, 0x4C, 0x46, 0x31];
 /// Current format version written by the packer; readers reject version > CLF_VERSION.
 /// Version 1 = header without kind (legacy); kind defaults to Compute.
 /// Version 2 = header with kind field (Compute / MemoryMovement / MemoryProtection).
-pub const CLF_VERSION: u8 = 2;
+/// Version 3 = v2 header; manifest entries also record the exact (unpadded) blob size, and an
+/// `entry_fields` mask after the entry count declares optional per-entry fields.
+pub const CLF_VERSION: u8 = 3;
+
+/// What a format version's layout contains. Code that reads or writes version-dependent fields
+/// asks this instead of comparing version numbers, so each feature's first version is recorded
+/// in one place (`for_version`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionFeatures {
+    /// The version described.
+    pub version: u8,
+    /// Header kind byte after the blob alignment (v2+); v1 files are Compute.
+    pub has_kind: bool,
+    /// Header metadata, build-info, name-table and extension sections (v3+).
+    pub has_header_sections: bool,
+    /// Manifest entries record the exact (unpadded) blob size (v3+).
+    pub has_exact_size: bool,
+    /// `entry_fields` mask after the entry count, enabling the optional per-entry fields:
+    /// flags / raw size (compression), CRC-32, variant and target (v3+).
+    pub has_flags: bool,
+    /// Manifest offsets and sizes are 64-bit. No version has them yet; all are u32.
+    pub has_64bit_offsets: bool,
+}
+
+impl VersionFeatures {
+    /// Features of `version`. Versions this crate does not support report the features of the
+    /// nearest version it knows; check `is_supported` first.
+    #[must_use]
+    pub const fn for_version(version: u8) -> Self {
+        Self {
+            version,
+            has_kind: version >= 2,
+            has_header_sections: version >= 3,
+            has_exact_size: version >= 3,
+            has_flags: version >= 3,
+            has_64bit_offsets: false,
+        }
+    }
+
+    /// Names of the features this version has, oldest first (for messages).
+    #[must_use]
+    pub fn names(&self) -> Vec<&'static str> {
+        [
+            (self.has_kind, "kind byte"),
+            (self.has_header_sections, "header sections"),
+            (self.has_exact_size, "exact blob sizes"),
+            (self.has_flags, "per-entry fields"),
+            (self.has_64bit_offsets, "64-bit offsets"),
+        ]
+        .into_iter()
+        .filter_map(|(has, name)| has.then_some(name))
+        .collect()
+    }
+}
+
+/// True if this crate reads and writes format `version` (1 through `CLF_VERSION`).
+#[must_use]
+pub const fn is_supported(version: u8) -> bool {
+    version >= 1 && version <= CLF_VERSION
+}
+
+/// Newest format version this crate reads and writes (`CLF_VERSION`).
+#[must_use]
+pub const fn max_supported() -> u8 {
+    CLF_VERSION
+}
+
+/// Why `version` is not supported, naming what the file requires and what this build offers.
+pub(crate) fn unsupported_version_detail(version: u8) -> String {
+    let newest = VersionFeatures::for_version(max_supported());
+    if version == 0 {
+        return "0 is not a CLF format version".to_string();
+    }
+    format!(
+        "the file requires format features introduced after v{} (this build reads v1-v{}, up to {})",
+        newest.version,
+        newest.version,
+        newest.names().join(", ")
+    )
+}
 
 /// CLF file kind: role of the file in the Coelanox ecosystem.
 /// Used for discovery and routing via extensions (.clfc, .clfmm, .clfmp, .clfe).
@@ -32,12 +123,47 @@ pub enum ClfKind {
 }
 
 impl ClfKind {
+    /// Every kind, in kind-byte order (for help text and exhaustive iteration).
+    pub const ALL: [ClfKind; 4] = [
+        Self::Compute,
+        Self::MemoryMovement,
+        Self::MemoryProtection,
+        Self::Executor,
+    ];
+
+    /// Canonical lowercase name, as printed by `Display` and accepted by `FromStr`.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Compute => "compute",
+            Self::MemoryMovement => "memory-movement",
+            Self::MemoryProtection => "memory-protection",
+            Self::Executor => "executor",
+        }
+    }
+
+    /// Other spellings `FromStr` accepts (case-insensitive): a short form, the name without
+    /// dashes, and the file extension.
+    pub const fn aliases(&self) -> &'static [&'static str] {
+        match self {
+            Self::Compute => &["c", "clfc"],
+            Self::MemoryMovement => &["memorymovement", "mm", "clfmm"],
+            Self::MemoryProtection => &["memoryprotection", "mp", "clfmp"],
+            Self::Executor => &["e", "clfe"],
+        }
+    }
+
     /// Default kind for v1 files (backwards compatibility).
     pub const fn default_for_v1() -> Self {
         Self::Compute
     }
 
-    /// Parse from on-disk kind byte (v2+). Returns `None` for unknown values.
+    /// On-disk kind byte (v2+ header).
+    pub const fn as_byte(&self) -> u8 {
+        *self as u8
+    }
+
+    /// Parse from on-disk kind byte (v2+). Returns `None` for unknown values; the
+    /// `TryFrom<u8>` impl is the same check with a `ClfError`.
     pub fn try_from_byte(b: u8) -> Option<Self> {
         match b {
             0 => Some(Self::Compute),
@@ -48,6 +174,12 @@ impl ClfKind {
         }
     }
 
+    /// Lenient parse: unknown values map to Compute. Only for tools that must open files
+    /// written by newer packers; readers use the strict `try_from_byte` unless told otherwise.
+    pub fn from_byte(b: u8) -> Self {
+        Self::try_from_byte(b).unwrap_or(Self::Compute)
+    }
+
     /// Extension for this kind (for discovery/routing).
     pub const fn extension(&self) -> &'static str {
         match self {
@@ -57,33 +189,155 @@ impl ClfKind {
             Self::Executor => "clfe",
         }
     }
+
+    /// Kind implied by a file extension (without the dot, case-insensitive). Legacy `clf`
+    /// maps to Compute; anything else is `None`.
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() {
+            "clfc" | "clf" => Some(Self::Compute),
+            "clfmm" => Some(Self::MemoryMovement),
+            "clfmp" => Some(Self::MemoryProtection),
+            "clfe" => Some(Self::Executor),
+            _ => None,
+        }
+    }
+
+    /// True if `path` has an extension that `from_extension` maps to this kind.
+    #[cfg(feature = "std")]
+    pub fn matches_path(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .and_then(Self::from_extension)
+            == Some(*self)
+    }
+}
+
+/// Strict conversion from the kind byte: unknown values fail with `ClfError::InvalidKindByte`
+/// (readers use this unless `ClfReaderOptions::lenient_kind` is set).
+impl TryFrom<u8> for ClfKind {
+    type Error = ClfError;
+
+    fn try_from(b: u8) -> Result<Self, Self::Error> {
+        Self::try_from_byte(b).ok_or(ClfError::InvalidKindByte(b))
+    }
 }
 
 impl fmt::Display for ClfKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let s = match self {
-            ClfKind::Compute => "compute",
-            ClfKind::MemoryMovement => "memory-movement",
-            ClfKind::MemoryProtection => "memory-protection",
-            ClfKind::Executor => "executor",
-        };
-        f.write_str(s)
+        f.write_str(self.name())
     }
 }
 
 impl FromStr for ClfKind {
     type Err = String;
 
+    /// Parse a canonical name or alias (see `aliases`), ignoring case.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
-            "compute" | "c" | "clfc" => Ok(Self::Compute),
-            "memory-movement" | "memorymovement" | "mm" | "clfmm" => Ok(Self::MemoryMovement),
-            "memory-protection" | "memoryprotection" | "mp" | "clfmp" => Ok(Self::MemoryProtection),
-            "executor" | "e" | "clfe" => Ok(Self::Executor),
-            _ => Err(format!(
-                "unknown kind {s:?} (expected compute, memory-movement, memory-protection, executor)"
-            )),
+        let lower = s.to_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|k| k.name() == lower || k.aliases().contains(&lower.as_str()))
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::ALL.iter().map(Self::name).collect();
+                format!("unknown kind {s:?} (expected {})", names.join(", "))
+            })
+    }
+}
+
+/// Built-in target families as (member, family) pairs, loaded by `TargetMatcher::new`. Names
+/// are in `TargetMatcher::normalize` form, and a family may itself be a member of a wider one.
+pub const TARGET_FAMILIES: &[(&str, &str)] = &[
+    ("amd64", "x86_64"),
+    ("x86_64", "cpu"),
+    ("arm64", "aarch64"),
+    ("aarch64", "cpu"),
+    ("riscv64", "cpu"),
+    ("gfx908", "cdna1"),
+    ("gfx90a", "cdna2"),
+    ("gfx940", "cdna3"),
+    ("gfx941", "cdna3"),
+    ("gfx942", "cdna3"),
+    ("sm_80", "ampere"),
+    ("sm_86", "ampere"),
+    ("sm_89", "ada"),
+    ("sm_90", "hopper"),
+];
+
+/// Compares target strings the way vendors write them: ignoring case, surrounding whitespace and
+/// `-` vs `_`, and treating a target as matching its families (`gfx90a` and `CDNA2`, `x86_64`
+/// and `CPU`). Starts from `TARGET_FAMILIES`; `add_alias` extends it at runtime.
+#[derive(Debug, Clone)]
+pub struct TargetMatcher {
+    /// Normalized member -> normalized family.
+    parents: BTreeMap<String, String>,
+}
+
+impl Default for TargetMatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TargetMatcher {
+    /// Matcher with the built-in `TARGET_FAMILIES`.
+    #[must_use]
+    pub fn new() -> Self {
+        let mut matcher = Self {
+            parents: BTreeMap::new(),
+        };
+        for (child, parent) in TARGET_FAMILIES {
+            matcher.add_alias(child, parent);
         }
+        matcher
+    }
+
+    /// Record `child` as a member of `parent` (e.g. `("gfx1100", "rdna3")`), replacing any
+    /// family `child` had.
+    pub fn add_alias(&mut self, child: &str, parent: &str) {
+        self.parents
+            .insert(Self::normalize(child), Self::normalize(parent));
+    }
+
+    /// `target` trimmed, in ASCII lowercase, with `-` written as `_`.
+    #[must_use]
+    pub fn normalize(target: &str) -> String {
+        target
+            .trim()
+            .chars()
+            .map(|c| {
+                if c == '-' {
+                    '_'
+                } else {
+                    c.to_ascii_lowercase()
+                }
+            })
+            .collect()
+    }
+
+    /// Whether an archive built for `file_target` suits a caller asking for `wanted`: both
+    /// name the same target, or one is a family the other belongs to, directly or through
+    /// wider families. Two members of one family do not match each other.
+    #[must_use]
+    pub fn matches(&self, file_target: &str, wanted: &str) -> bool {
+        let file_target = Self::normalize(file_target);
+        let wanted = Self::normalize(wanted);
+        file_target == wanted
+            || self.is_member(&file_target, &wanted)
+            || self.is_member(&wanted, &file_target)
+    }
+
+    /// Whether `family` is reached by following `target`'s families upward. Stops after as
+    /// many steps as there are aliases, so a cycle added with `add_alias` cannot loop forever.
+    fn is_member(&self, target: &str, family: &str) -> bool {
+        let mut current = target;
+        for _ in 0..self.parents.len() {
+            match self.parents.get(current) {
+                Some(parent) if parent == family => return true,
+                Some(parent) => current = parent,
+                None => return false,
+            }
+        }
+        false
     }
 }
 
@@ -96,6 +350,219 @@ pub const SIG_HASH_LEN: usize = 32;
 /// Total signature block size: magic + hash.
 pub const SIG_BLOCK_LEN: usize = 4 + SIG_HASH_LEN;
 
+/// Magic of the variable-length SIG1 signature block: "SIG1". Written at both ends of the block.
+pub const SIG1_MAGIC: [u8; 4] = [0x53, 0x49, 0x47, 0x31];
+
+/// Smallest SIG1 block: magic (4) + algorithm (1) + fingerprint length (1) + signature length (2)
+/// + block length (4) + trailing magic (4), with an empty fingerprint and signature.
+pub const SIG1_MIN_LEN: usize = 4 + 1 + 1 + 2 + 4 + 4;
+
+/// SIG1 algorithm: Ed25519 signature over the SHA-256 of everything before the block.
+pub const SIG_ALG_ED25519: u8 = 1;
+
+/// SIG1 algorithm: HMAC-SHA256 (shared secret) over everything before the block; no fingerprint.
+pub const SIG_ALG_HMAC_SHA256: u8 = 2;
+
+/// SIG1 algorithm: plain SHA-256 of the covered bytes; no key, integrity only. Written for
+/// index-only signatures, which SIG0 cannot express.
+pub const SIG_ALG_SHA256: u8 = 3;
+
+/// Algorithm of a SIG1 block, as named by its algorithm byte (`SIG_ALG_*`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SignatureAlgorithm {
+    /// Ed25519 signature over the SHA-256 of the covered bytes (`SIG_ALG_ED25519`).
+    Ed25519,
+    /// HMAC-SHA256 under a shared secret (`SIG_ALG_HMAC_SHA256`).
+    HmacSha256,
+    /// Unkeyed SHA-256 digest (`SIG_ALG_SHA256`; also what a legacy SIG0 block holds).
+    Sha256,
+}
+
+impl SignatureAlgorithm {
+    /// Every algorithm, in id order.
+    pub const ALL: [SignatureAlgorithm; 3] = [Self::Ed25519, Self::HmacSha256, Self::Sha256];
+
+    /// Algorithm byte written to SIG1 blocks.
+    #[must_use]
+    pub fn id(self) -> u8 {
+        match self {
+            Self::Ed25519 => SIG_ALG_ED25519,
+            Self::HmacSha256 => SIG_ALG_HMAC_SHA256,
+            Self::Sha256 => SIG_ALG_SHA256,
+        }
+    }
+
+    /// Algorithm for a SIG1 algorithm byte (scope flag cleared); `None` for unknown ids.
+    #[must_use]
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.id() == id)
+    }
+
+    /// Lowercase name for display ("ed25519", "hmac-sha256", "sha256").
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Ed25519 => "ed25519",
+            Self::HmacSha256 => "hmac-sha256",
+            Self::Sha256 => "sha256",
+        }
+    }
+
+    /// Whether verifying needs a key (false only for the unkeyed SHA-256 digest).
+    #[must_use]
+    pub fn is_keyed(self) -> bool {
+        self != Self::Sha256
+    }
+}
+
+impl fmt::Display for SignatureAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Flag set in the SIG1 algorithm byte when the signature covers only the header and manifest
+/// (bytes `[0, blob_store_offset)`) instead of everything before the block.
+pub const SIG_FLAG_INDEX_ONLY: u8 = 0x80;
+
+/// What a verified signature covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SignatureScope {
+    /// Nothing verified (unsigned, or no verification has succeeded yet).
+    None,
+    /// Header and manifest only; blob contents need per-blob checks (CRC-32 entries).
+    IndexOnly,
+    /// Everything before the signature block.
+    Full,
+}
+
+/// Length of the key fingerprint written to SIG1 blocks (leading bytes of SHA-256 of the public key).
+pub const SIG_KEY_FINGERPRINT_LEN: usize = 8;
+
+/// Variable-length SIG1 signature block appended after the blob store (see SPEC.md §3.4).
+///
+/// Layout: `SIG1`, algorithm u8 (`SIG_FLAG_INDEX_ONLY` may be set), fingerprint length u8,
+/// fingerprint, signature length u16, signature, block length u32 (whole block), `SIG1`. The
+/// trailing length + magic let a reader find the block from the end of the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureBlock {
+    /// Signing algorithm (`SIG_ALG_*`), without the scope flag.
+    pub algorithm: u8,
+    /// Whether the signature covers only the header and manifest (`SIG_FLAG_INDEX_ONLY`).
+    pub index_only: bool,
+    /// Identifies the key that made the signature; empty if the algorithm has none.
+    pub key_fingerprint: Vec<u8>,
+    /// Signature (or MAC / digest) bytes.
+    pub signature: Vec<u8>,
+}
+
+impl SignatureBlock {
+    /// The block's algorithm, or `None` if this reader does not know its id.
+    #[must_use]
+    pub fn signature_algorithm(&self) -> Option<SignatureAlgorithm> {
+        SignatureAlgorithm::from_id(self.algorithm)
+    }
+
+    /// Scope the block's signature covers: `IndexOnly` or `Full`.
+    #[must_use]
+    pub fn scope(&self) -> SignatureScope {
+        if self.index_only {
+            SignatureScope::IndexOnly
+        } else {
+            SignatureScope::Full
+        }
+    }
+
+    /// Serialize the block. Fails with `ClfError::LimitExceeded` if the fingerprint exceeds 255
+    /// bytes or the signature 65535, which their length prefixes cannot record.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ClfError> {
+        let too_long = |what, value: usize, limit| ClfError::LimitExceeded {
+            what,
+            value: value as u64,
+            limit,
+        };
+        let fingerprint_len = u8::try_from(self.key_fingerprint.len())
+            .map_err(|_| too_long("SIG1 fingerprint length", self.key_fingerprint.len(), 255))?;
+        let signature_len = u16::try_from(self.signature.len())
+            .map_err(|_| too_long("SIG1 signature length", self.signature.len(), 65535))?;
+        let block_len = SIG1_MIN_LEN + self.key_fingerprint.len() + self.signature.len();
+        let mut out = Vec::with_capacity(block_len);
+        out.extend_from_slice(&SIG1_MAGIC);
+        out.push(if self.index_only {
+            self.algorithm | SIG_FLAG_INDEX_ONLY
+        } else {
+            self.algorithm
+        });
+        out.push(fingerprint_len);
+        out.extend_from_slice(&self.key_fingerprint);
+        out.extend_from_slice(&signature_len.to_le_bytes());
+        out.extend_from_slice(&self.signature);
+        out.extend_from_slice(&(block_len as u32).to_le_bytes());
+        out.extend_from_slice(&SIG1_MAGIC);
+        Ok(out)
+    }
+
+    /// Parse a complete block (as located by its trailing length). `None` if the bytes are not
+    /// a well-formed SIG1 block.
+    #[must_use]
+    pub fn parse(block: &[u8]) -> Option<Self> {
+        let len = block.len();
+        if len < SIG1_MIN_LEN || block[..4] != SIG1_MAGIC || block[len - 4..] != SIG1_MAGIC {
+            return None;
+        }
+        let declared = u32::from_le_bytes(block[len - 8..len - 4].try_into().ok()?);
+        if declared as usize != len {
+            return None;
+        }
+        let algorithm = block[4];
+        let fingerprint_len = block[5] as usize;
+        let fingerprint_end = 6 + fingerprint_len;
+        let signature_start = fingerprint_end + 2;
+        let signature_len = u16::from_le_bytes(
+            block
+                .get(fingerprint_end..signature_start)?
+                .try_into()
+                .ok()?,
+        ) as usize;
+        if signature_start + signature_len + 8 != len {
+            return None;
+        }
+        Some(Self {
+            algorithm: algorithm & !SIG_FLAG_INDEX_ONLY,
+            index_only: algorithm & SIG_FLAG_INDEX_ONLY != 0,
+            key_fingerprint: block[6..fingerprint_end].to_vec(),
+            signature: block[signature_start..signature_start + signature_len].to_vec(),
+        })
+    }
+}
+
+/// Compare two digests or signatures in time that depends only on their lengths, not on where
+/// they first differ, so a mismatch does not leak how many leading bytes were right. Used for
+/// every signature and digest check in the reader.
+#[must_use]
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y));
+    core::hint::black_box(diff) == 0
+}
+
+/// Fingerprint recorded in SIG1 blocks for a public key: the first `SIG_KEY_FINGERPRINT_LEN`
+/// bytes of its SHA-256.
+#[must_use]
+pub fn key_fingerprint(public_key: &[u8]) -> [u8; SIG_KEY_FINGERPRINT_LEN] {
+    let digest = Sha256::digest(public_key);
+    let mut fingerprint = [0u8; SIG_KEY_FINGERPRINT_LEN];
+    fingerprint.copy_from_slice(&digest[..SIG_KEY_FINGERPRINT_LEN]);
+    fingerprint
+}
+
+/// Lowercase hex of a key fingerprint, for messages.
+pub(crate) fn fingerprint_hex(fingerprint: &[u8]) -> String {
+    fingerprint.iter().map(|b| format!("{b:02x}")).collect()
+}
+
 /// No blob alignment (blobs stored back-to-back).
 pub const BLOB_ALIGN_NONE: u8 = 0;
 
@@ -116,14 +583,800 @@ pub struct ClfHeader {
     pub blob_alignment: u8,
     /// File kind (Compute / MemoryMovement / MemoryProtection). v1: defaults to Compute; v2: read from header.
     pub kind: ClfKind,
-    /// Byte offset in file where header ends (start of manifest).
-    #[cfg_attr(feature = "serde", serde(skip_serializing))]
+    /// Build metadata key/value pairs (v3 metadata section), in file order; empty if absent.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub metadata: Vec<(String, String)>,
+    /// Build time in Unix seconds (v3 build-info section); `None` if not recorded.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub created_at: Option<u64>,
+    /// Name/version of the tool that wrote the file (v3 build-info section); `None` if not recorded.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub tool_version: Option<String>,
+    /// Human-readable entry names by op_id (v3 name table); informational only.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "BTreeMap::is_empty")
+    )]
+    pub entry_names: BTreeMap<u32, String>,
+    /// Extension records (v3 TLV area) as (tag, bytes), in file order. Tags this reader has
+    /// no typed accessor for are kept here raw; see `extension`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub extensions: Vec<(u16, Vec<u8>)>,
+    /// Byte offset in file where header ends (start of manifest). Not serialized; 0 after
+    /// deserializing.
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub header_end: u64,
 }
 
+impl ClfHeader {
+    /// Value of metadata `key`, if present.
+    #[must_use]
+    pub fn get_meta(&self, key: &str) -> Option<&str> {
+        self.metadata
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Name recorded for `op_id` in the name table, if any.
+    #[must_use]
+    pub fn entry_name(&self, op_id: u32) -> Option<&str> {
+        self.entry_names.get(&op_id).map(String::as_str)
+    }
+
+    /// Bytes of the extension record with `tag`, if present.
+    pub fn extension(&self, tag: u16) -> Option<&[u8]> {
+        self.extensions
+            .iter()
+            .find(|(t, _)| *t == tag)
+            .map(|(_, bytes)| bytes.as_slice())
+    }
+}
+
+// --- Header codec: the one serializer and parser of the header, shared by the packer, both
+// readers and the slice parsers. Version-dependent fields are decided here only. ---
+
+/// Serialized length of `header` as `write_header` writes it (where the manifest starts).
+#[must_use]
+pub fn header_len(header: &ClfHeader) -> usize {
+    let features = VersionFeatures::for_version(header.version);
+    let mut len = 4 + 1 + 4 + header.vendor.len() + 4 + header.target.len() + 1;
+    if features.has_kind {
+        len += 1;
+    }
+    if features.has_header_sections {
+        len += 4 + metadata_section_len(&header.metadata);
+        len += 4 + build_info_section_len(header.created_at, header.tool_version.as_deref());
+        len += 4 + name_table_len(header.entry_names.values());
+        len += 4 + extensions_len(&header.extensions);
+    }
+    len
+}
+
+/// Body length of the metadata section: count, then per pair 1 + key + 4 + value bytes.
+/// Zero when there is no metadata.
+pub(crate) fn metadata_section_len(metadata: &[(String, String)]) -> usize {
+    if metadata.is_empty() {
+        return 0;
+    }
+    4 + metadata
+        .iter()
+        .map(|(k, v)| 1 + k.len() + 4 + v.len())
+        .sum::<usize>()
+}
+
+/// Body length of the build-info section: fields mask, then 8 bytes for `created_at` and
+/// 2 + len bytes for `tool_version` when present. Zero when neither is written.
+pub(crate) fn build_info_section_len(created_at: Option<u64>, tool_version: Option<&str>) -> usize {
+    match (created_at, tool_version) {
+        (None, None) => 0,
+        (created_at, tool_version) => {
+            1 + created_at.map_or(0, |_| 8) + tool_version.map_or(0, |v| 2 + v.len())
+        }
+    }
+}
+
+/// Body length of the name table: count, then per entry 4 + 1 + name bytes. Zero when there
+/// are no names.
+pub(crate) fn name_table_len<'a>(names: impl IntoIterator<Item = &'a String>) -> usize {
+    let mut names = names.into_iter().peekable();
+    if names.peek().is_none() {
+        return 0;
+    }
+    4 + names.map(|name| 4 + 1 + name.len()).sum::<usize>()
+}
+
+/// Serialized extension area length: count, then per record 2 + 4 + its bytes. Zero (empty
+/// area) when there are no records.
+pub(crate) fn extensions_len(extensions: &[(u16, Vec<u8>)]) -> usize {
+    if extensions.is_empty() {
+        return 0;
+    }
+    4 + extensions
+        .iter()
+        .map(|(_, bytes)| 2 + 4 + bytes.len())
+        .sum::<usize>()
+}
+
+/// Reject a header `read_header` would not read back: an unsupported version, vendor/target
+/// over `MAX_HEADER_TEXT_LEN`, v3-only fields on an older version, or fields over the limits
+/// of their length prefixes.
+#[cfg(feature = "std")]
+fn check_header(header: &ClfHeader) -> std::io::Result<()> {
+    let invalid = |what: String| Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, what));
+    if !is_supported(header.version) {
+        return invalid(format!(
+            "unsupported header version {}: {}",
+            header.version,
+            unsupported_version_detail(header.version)
+        ));
+    }
+    for (field, text) in [("vendor", &header.vendor), ("target", &header.target)] {
+        if text.len() > MAX_HEADER_TEXT_LEN {
+            return invalid(format!("{field} longer than {MAX_HEADER_TEXT_LEN} bytes"));
+        }
+    }
+    if !VersionFeatures::for_version(header.version).has_header_sections {
+        let v3_only = !header.metadata.is_empty()
+            || header.created_at.is_some()
+            || header.tool_version.is_some()
+            || !header.entry_names.is_empty()
+            || !header.extensions.is_empty();
+        if v3_only {
+            return invalid(format!(
+                "metadata, build info, entry names and extensions need format version 3 (header is v{})",
+                header.version
+            ));
+        }
+        return Ok(());
+    }
+    if header
+        .metadata
+        .iter()
+        .any(|(k, _)| k.len() > MAX_META_KEY_LEN)
+        || metadata_section_len(&header.metadata) > MAX_METADATA_LEN
+    {
+        return invalid("metadata section too large".to_string());
+    }
+    if header
+        .tool_version
+        .as_ref()
+        .is_some_and(|v| v.len() > usize::from(u16::MAX))
+    {
+        return invalid("tool_version longer than 65535 bytes".to_string());
+    }
+    if header
+        .entry_names
+        .values()
+        .any(|name| name.len() > MAX_ENTRY_NAME_LEN)
+        || name_table_len(header.entry_names.values()) > MAX_NAME_TABLE_LEN
+    {
+        return invalid("name table too large".to_string());
+    }
+    if extensions_len(&header.extensions) > MAX_EXTENSIONS_LEN {
+        return invalid("extension area too large".to_string());
+    }
+    Ok(())
+}
+
+/// Write `header` in the layout of `header.version`: magic, version, vendor, target, blob
+/// alignment, kind (v2+), then the metadata, build-info, name-table and extension sections
+/// (v3+). `header_end` is ignored. Fails with `InvalidInput`, before writing anything, on a
+/// header `read_header` would reject.
+#[cfg(feature = "std")]
+pub fn write_header<W: Write>(out: &mut W, header: &ClfHeader) -> std::io::Result<()> {
+    check_header(header)?;
+    out.write_all(&CLF_MAGIC)?;
+    out.write_all(&[header.version])?;
+    out.write_all(&(header.vendor.len() as u32).to_le_bytes())?;
+    out.write_all(header.vendor.as_bytes())?;
+    out.write_all(&(header.target.len() as u32).to_le_bytes())?;
+    out.write_all(header.target.as_bytes())?;
+    out.write_all(&[header.blob_alignment])?;
+    let features = VersionFeatures::for_version(header.version);
+    // v2: kind byte. v1: omitted (readers assume Compute).
+    if features.has_kind {
+        out.write_all(&[header.kind.as_byte()])?;
+    }
+    if !features.has_header_sections {
+        return Ok(());
+    }
+    // v3: metadata section, length-prefixed so readers can skip it.
+    let section_len = metadata_section_len(&header.metadata);
+    out.write_all(&(section_len as u32).to_le_bytes())?;
+    if section_len > 0 {
+        out.write_all(&(header.metadata.len() as u32).to_le_bytes())?;
+        for (key, value) in &header.metadata {
+            out.write_all(&[key.len() as u8])?;
+            out.write_all(key.as_bytes())?;
+            out.write_all(&(value.len() as u32).to_le_bytes())?;
+            out.write_all(value.as_bytes())?;
+        }
+    }
+    // v3: build-info section, also length-prefixed.
+    let tool_version = header.tool_version.as_deref();
+    let section_len = build_info_section_len(header.created_at, tool_version);
+    out.write_all(&(section_len as u32).to_le_bytes())?;
+    if section_len > 0 {
+        let mut fields = 0u8;
+        if header.created_at.is_some() {
+            fields |= BUILD_INFO_CREATED_AT;
+        }
+        if tool_version.is_some() {
+            fields |= BUILD_INFO_TOOL_VERSION;
+        }
+        out.write_all(&[fields])?;
+        if let Some(secs) = header.created_at {
+            out.write_all(&secs.to_le_bytes())?;
+        }
+        if let Some(version) = tool_version {
+            out.write_all(&(version.len() as u16).to_le_bytes())?;
+            out.write_all(version.as_bytes())?;
+        }
+    }
+    // v3: name table, in op_id order.
+    let section_len = name_table_len(header.entry_names.values());
+    out.write_all(&(section_len as u32).to_le_bytes())?;
+    if section_len > 0 {
+        out.write_all(&(header.entry_names.len() as u32).to_le_bytes())?;
+        for (op_id, name) in &header.entry_names {
+            out.write_all(&op_id.to_le_bytes())?;
+            out.write_all(&[name.len() as u8])?;
+            out.write_all(name.as_bytes())?;
+        }
+    }
+    // v3: extension area (TLV records), in the header's order.
+    let section_len = extensions_len(&header.extensions);
+    out.write_all(&(section_len as u32).to_le_bytes())?;
+    if section_len > 0 {
+        out.write_all(&(header.extensions.len() as u32).to_le_bytes())?;
+        for (tag, bytes) in &header.extensions {
+            out.write_all(&tag.to_le_bytes())?;
+            out.write_all(&(bytes.len() as u32).to_le_bytes())?;
+            out.write_all(bytes)?;
+        }
+    }
+    Ok(())
+}
+
+/// Parse the header (magic through kind byte, plus the v3 metadata, build-info, name-table and
+/// extension sections) from the start of `reader`, with strict kind parsing. Every truncation
+/// fails with an error. `header_end` is computed from the field sizes, so `reader` need not be
+/// seekable.
+#[cfg(feature = "std")]
+pub fn read_header<R: Read>(reader: &mut R) -> Result<ClfHeader, ClfError> {
+    read_header_with(reader, false, &ClfLimits::FORMAT_MAX)
+}
+
+/// Byte source the header and manifest parsers pull from: any `std::io::Read` with the `std`
+/// feature, else a byte slice (which advances past what was read).
+pub(crate) trait Source {
+    /// Fill `buf`. `Ok(false)` if the data ends first; errors are I/O failures only.
+    fn fill(&mut self, buf: &mut [u8]) -> Result<bool, ClfError>;
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + ?Sized> Source for R {
+    fn fill(&mut self, buf: &mut [u8]) -> Result<bool, ClfError> {
+        match Read::read_exact(self, buf) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+            Err(e) => Err(ClfError::Io(e)),
+        }
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Source for &[u8] {
+    fn fill(&mut self, buf: &mut [u8]) -> Result<bool, ClfError> {
+        if self.len() < buf.len() {
+            return Ok(false);
+        }
+        let (head, tail) = self.split_at(buf.len());
+        buf.copy_from_slice(head);
+        *self = tail;
+        Ok(true)
+    }
+}
+
+/// Reads named fields from a `Source` and keeps the offset of the next one, so a parse failure
+/// can say where it happened: running out of data is `Truncated` at the offset of the field
+/// that did not fit.
+pub(crate) struct FieldReader<'r, R: ?Sized> {
+    source: &'r mut R,
+    pos: u64,
+}
+
+impl<'r, R: Source + ?Sized> FieldReader<'r, R> {
+    /// Reader over `source`, whose next byte is at offset `pos` of the archive.
+    pub(crate) fn at(source: &'r mut R, pos: u64) -> Self {
+        Self { source, pos }
+    }
+
+    /// Offset of the next field.
+    pub(crate) fn pos(&self) -> u64 {
+        self.pos
+    }
+
+    /// Fill `buf` with the field `what`.
+    pub(crate) fn read(&mut self, buf: &mut [u8], what: &'static str) -> Result<(), ClfError> {
+        if !self.source.fill(buf)? {
+            return Err(ClfError::Truncated {
+                at_offset: self.pos,
+                while_reading: what,
+            });
+        }
+        self.pos += buf.len() as u64;
+        Ok(())
+    }
+
+    pub(crate) fn u8(&mut self, what: &'static str) -> Result<u8, ClfError> {
+        let mut buf = [0u8; 1];
+        self.read(&mut buf, what)?;
+        Ok(buf[0])
+    }
+
+    pub(crate) fn u32(&mut self, what: &'static str) -> Result<u32, ClfError> {
+        let mut buf = [0u8; 4];
+        self.read(&mut buf, what)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+}
+
+/// Name of the length prefix of `field` in `Truncated` errors.
+fn length_field(field: &'static str) -> &'static str {
+    match field {
+        "vendor" => "vendor length",
+        "target" => "target length",
+        "metadata section" => "metadata section length",
+        "build-info section" => "build-info section length",
+        "name table" => "name table length",
+        "extension area" => "extension area length",
+        _ => "length",
+    }
+}
+
+/// Read a u32 length followed by that many bytes of UTF-8 (vendor or target). Lengths over
+/// `MAX_HEADER_TEXT_LEN` are malformed; lengths within it but over `limit` exceed the limits.
+pub(crate) fn read_len_prefixed_utf8<R: Source + ?Sized>(
+    reader: &mut FieldReader<'_, R>,
+    field_name: &'static str,
+    limit: usize,
+) -> Result<String, ClfError> {
+    let at_offset = reader.pos();
+    let len = reader.u32(length_field(field_name))? as usize;
+    if len > MAX_HEADER_TEXT_LEN {
+        return Err(ClfError::Malformed {
+            at_offset,
+            what: match field_name {
+                "vendor" => "vendor too large",
+                "target" => "target too large",
+                _ => "text field too large",
+            },
+        });
+    }
+    if len > limit {
+        return Err(ClfError::LimitExceeded {
+            what: field_name,
+            value: len as u64,
+            limit: limit as u64,
+        });
+    }
+
+    let at_offset = reader.pos();
+    let mut bytes = vec![0u8; len];
+    reader.read(&mut bytes, field_name)?;
+
+    String::from_utf8(bytes).map_err(|_| match field_name {
+        "vendor" => ClfError::InvalidVendorUtf8,
+        "target" => ClfError::InvalidTargetUtf8,
+        _ => ClfError::Malformed {
+            at_offset,
+            what: "invalid UTF-8 field",
+        },
+    })
+}
+
+/// `read_header` with a choice of kind parsing and limits: `lenient_kind` maps unknown kind bytes
+/// to Compute (`ClfKind::from_byte`) instead of failing, and text fields and v3 sections longer
+/// than `limits` allows are rejected before they are allocated. Both readers parse through this
+/// with `ClfLimits::FORMAT_MAX`. `reader` must be at the start of the archive: error offsets
+/// count from there.
+pub(crate) fn read_header_with<R: Source + ?Sized>(
+    reader: &mut R,
+    lenient_kind: bool,
+    limits: &ClfLimits,
+) -> Result<ClfHeader, ClfError> {
+    let reader = &mut FieldReader::at(reader, 0);
+    let mut magic = [0u8; 4];
+    reader.read(&mut magic, "magic")?;
+    if magic != CLF_MAGIC {
+        return Err(ClfError::InvalidMagic);
+    }
+
+    let version = reader.u8("version")?;
+    if !is_supported(version) {
+        return Err(ClfError::UnsupportedVersion(version, max_supported()));
+    }
+    let features = VersionFeatures::for_version(version);
+
+    let vendor = read_len_prefixed_utf8(reader, "vendor", limits.max_text_len)?;
+
+    // Target length (4 B LE), target (M bytes), blob alignment (1 B).
+    let target = read_len_prefixed_utf8(reader, "target", limits.max_text_len)?;
+    let blob_alignment = reader.u8("blob alignment")?;
+
+    // v2: read kind byte; v1: default to Compute (backwards compatibility).
+    let kind = if features.has_kind {
+        let kind_byte = reader.u8("kind")?;
+        if lenient_kind {
+            ClfKind::from_byte(kind_byte)
+        } else {
+            ClfKind::try_from(kind_byte)?
+        }
+    } else {
+        ClfKind::default_for_v1()
+    };
+
+    // v3: metadata section (byte length, then count + key/value pairs).
+    let metadata = if features.has_header_sections {
+        let section = read_section(
+            reader,
+            "metadata section",
+            MAX_METADATA_LEN,
+            "section too large",
+            limits,
+        )?;
+        parse_metadata(&section)?
+    } else {
+        Vec::new()
+    };
+
+    // v3: build-info section (byte length, then fields mask + created_at / tool_version).
+    let (created_at, tool_version) = if features.has_header_sections {
+        let section = read_section(
+            reader,
+            "build-info section",
+            MAX_METADATA_LEN,
+            "build-info section too large",
+            limits,
+        )?;
+        parse_build_info(&section)?
+    } else {
+        (None, None)
+    };
+
+    // v3: name table (byte length, then count + op_id/name pairs).
+    let entry_names = if features.has_header_sections {
+        let section = read_section(
+            reader,
+            "name table",
+            MAX_NAME_TABLE_LEN,
+            "name table too large",
+            limits,
+        )?;
+        parse_name_table(&section)?
+    } else {
+        BTreeMap::new()
+    };
+
+    // v3: extension area (byte length, then count + tag/length/bytes records).
+    let extensions = if features.has_header_sections {
+        let section = read_section(
+            reader,
+            "extension area",
+            MAX_EXTENSIONS_LEN,
+            "extension area too large",
+            limits,
+        )?;
+        parse_extensions(&section)?
+    } else {
+        Vec::new()
+    };
+    let header_end = reader.pos();
+
+    Ok(ClfHeader {
+        version,
+        vendor,
+        target,
+        blob_alignment,
+        kind,
+        metadata,
+        created_at,
+        tool_version,
+        entry_names,
+        extensions,
+        header_end,
+    })
+}
+
+/// Read the v3 header section `name`: a u32 byte length, then that many bytes. Lengths over
+/// `format_max` fail with `InvalidMetadata(too_large)`, lengths within it but over
+/// `limits.max_section_len` with `LimitExceeded`; either way before anything is allocated.
+fn read_section<R: Source + ?Sized>(
+    reader: &mut FieldReader<'_, R>,
+    name: &'static str,
+    format_max: usize,
+    too_large: &'static str,
+    limits: &ClfLimits,
+) -> Result<Vec<u8>, ClfError> {
+    let len = reader.u32(length_field(name))? as usize;
+    if len > format_max {
+        return Err(ClfError::InvalidMetadata(too_large));
+    }
+    if len > limits.max_section_len {
+        return Err(ClfError::LimitExceeded {
+            what: "header section",
+            value: len as u64,
+            limit: limits.max_section_len as u64,
+        });
+    }
+    let mut section = vec![0u8; len];
+    reader.read(&mut section, name)?;
+    Ok(section)
+}
+
+/// Cursor over a byte slice for the section and manifest entry parsers. Every read is
+/// bounds-checked and returns `None` past the end, so no length field can make a parser panic.
+pub(crate) struct ByteCursor<'a>(&'a [u8]);
+
+impl<'a> ByteCursor<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self(bytes)
+    }
+
+    /// The next `n` bytes, or `None` if fewer remain.
+    pub(crate) fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Some(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.take(N)?.try_into().ok()
+    }
+
+    pub(crate) fn u8(&mut self) -> Option<u8> {
+        self.array().map(|[b]: [u8; 1]| b)
+    }
+
+    pub(crate) fn u16(&mut self) -> Option<u16> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    pub(crate) fn u32(&mut self) -> Option<u32> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    pub(crate) fn u64(&mut self) -> Option<u64> {
+        self.array().map(u64::from_le_bytes)
+    }
+}
+
+/// Parse the body of a v3 metadata section: count u32, then per pair key length u8, key,
+/// value length u32, value (UTF-8). Bytes after the last pair are ignored.
+fn parse_metadata(section: &[u8]) -> Result<Vec<(String, String)>, ClfError> {
+    if section.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut rest = ByteCursor::new(section);
+    let truncated = || ClfError::InvalidMetadata("truncated section");
+    let utf8 = |bytes: &[u8]| {
+        String::from_utf8(bytes.to_vec()).map_err(|_| ClfError::InvalidMetadata("invalid UTF-8"))
+    };
+    let count = rest.u32().ok_or_else(truncated)?;
+    let mut pairs = Vec::new();
+    for _ in 0..count {
+        let key_len = rest.u8().ok_or_else(truncated)? as usize;
+        let key = utf8(rest.take(key_len).ok_or_else(truncated)?)?;
+        let value_len = rest.u32().ok_or_else(truncated)? as usize;
+        let value = utf8(rest.take(value_len).ok_or_else(truncated)?)?;
+        pairs.push((key, value));
+    }
+    Ok(pairs)
+}
+
+/// Parse the body of a v3 build-info section: fields mask u8, then `created_at` u64 and
+/// `tool_version` (length u16, UTF-8) when their bits are set. Bytes after the known fields are ignored.
+fn parse_build_info(section: &[u8]) -> Result<(Option<u64>, Option<String>), ClfError> {
+    let Some((&fields, rest)) = section.split_first() else {
+        return Ok((None, None));
+    };
+    let mut rest = ByteCursor::new(rest);
+    let truncated = || ClfError::InvalidMetadata("truncated build-info section");
+    let created_at = if fields & BUILD_INFO_CREATED_AT != 0 {
+        Some(rest.u64().ok_or_else(truncated)?)
+    } else {
+        None
+    };
+    let tool_version = if fields & BUILD_INFO_TOOL_VERSION != 0 {
+        let len = rest.u16().ok_or_else(truncated)? as usize;
+        let bytes = rest.take(len).ok_or_else(truncated)?.to_vec();
+        Some(String::from_utf8(bytes).map_err(|_| ClfError::InvalidMetadata("invalid UTF-8"))?)
+    } else {
+        None
+    };
+    Ok((created_at, tool_version))
+}
+
+/// Parse the body of a v3 extension area: count u32, then per record tag u16, length u32,
+/// bytes. Tags must be unique, and an unknown tag with `EXTENSION_MUST_UNDERSTAND` set fails
+/// with `ClfError::UnsupportedExtension`. Bytes after the last record are ignored.
+fn parse_extensions(section: &[u8]) -> Result<Vec<(u16, Vec<u8>)>, ClfError> {
+    if section.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut rest = ByteCursor::new(section);
+    let truncated = || ClfError::InvalidMetadata("truncated extension area");
+    let count = rest.u32().ok_or_else(truncated)?;
+    let mut records: Vec<(u16, Vec<u8>)> = Vec::new();
+    for _ in 0..count {
+        let tag = rest.u16().ok_or_else(truncated)?;
+        let len = rest.u32().ok_or_else(truncated)? as usize;
+        let bytes = rest.take(len).ok_or_else(truncated)?.to_vec();
+        if tag & EXTENSION_MUST_UNDERSTAND != 0 && !KNOWN_EXTENSION_TAGS.contains(&tag) {
+            return Err(ClfError::UnsupportedExtension(tag));
+        }
+        if records.iter().any(|(t, _)| *t == tag) {
+            return Err(ClfError::InvalidMetadata("duplicate extension tag"));
+        }
+        records.push((tag, bytes));
+    }
+    Ok(records)
+}
+
+/// Parse the body of a v3 name table: count u32, then per entry op_id u32, name length u8,
+/// name (UTF-8). Bytes after the last entry are ignored.
+fn parse_name_table(section: &[u8]) -> Result<BTreeMap<u32, String>, ClfError> {
+    if section.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+    let mut rest = ByteCursor::new(section);
+    let truncated = || ClfError::InvalidMetadata("truncated name table");
+    let count = rest.u32().ok_or_else(truncated)?;
+    let mut names = BTreeMap::new();
+    for _ in 0..count {
+        let op_id = rest.u32().ok_or_else(truncated)?;
+        let len = rest.u8().ok_or_else(truncated)? as usize;
+        let name = String::from_utf8(rest.take(len).ok_or_else(truncated)?.to_vec())
+            .map_err(|_| ClfError::InvalidMetadata("invalid UTF-8"))?;
+        names.insert(op_id, name);
+    }
+    Ok(names)
+}
+
+/// Parse the header at the start of `bytes` without a file or the rest of the reader: returns
+/// the header and the number of bytes it occupies (where the manifest starts). Handles every
+/// version up to `CLF_VERSION` with strict kind parsing. Never panics: truncated or malformed
+/// input is a `ClfError`.
+pub fn parse_header(bytes: &[u8]) -> Result<(ClfHeader, usize), ClfError> {
+    let mut rest = bytes;
+    let header = read_header_with(&mut rest, false, &ClfLimits::FORMAT_MAX)?;
+    Ok((header, bytes.len() - rest.len()))
+}
+
+/// Manifest parsed by `parse_manifest`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedManifest {
+    /// Entries ordered by (op_id, variant).
+    pub entries: Vec<ManifestEntry>,
+    /// Target table: an entry's `target_index` n > 0 names `targets[n - 1]`.
+    pub targets: Vec<String>,
+}
+
+/// Parse the manifest at the start of `bytes` (the bytes after the header) for a file of format
+/// `version` (`ClfHeader::version`): entry count, v3 entry-fields mask and target table, then
+/// the entries. Returns the manifest and the number of bytes it occupies (where the blob store
+/// starts). Counts larger than `bytes` could hold are rejected before allocating, as are
+/// duplicate entries. Never panics. Error offsets count from the start of `bytes`.
+pub fn parse_manifest(bytes: &[u8], version: u8) -> Result<(ParsedManifest, usize), ClfError> {
+    let mut rest = bytes;
+    let (manifest, targets, len) = crate::slice::read_manifest(
+        &mut FieldReader::at(&mut rest, 0),
+        version,
+        bytes.len() as u64,
+        &ClfLimits::FORMAT_MAX,
+    )?;
+    let manifest = ParsedManifest {
+        entries: manifest.into_values(),
+        targets,
+    };
+    Ok((manifest, len as usize))
+}
+
+/// Longest vendor or target string in bytes that readers accept (and the packer writes).
+pub const MAX_HEADER_TEXT_LEN: usize = 64 * 1024;
+
+/// Longest metadata key in bytes (keys are stored with a 1-byte length).
+pub const MAX_META_KEY_LEN: usize = 255;
+
+/// Largest metadata section (bytes after its length field) the packer writes and readers accept.
+pub const MAX_METADATA_LEN: usize = 1024 * 1024;
+
+/// Longest entry name in bytes (names are stored with a 1-byte length).
+pub const MAX_ENTRY_NAME_LEN: usize = 255;
+
+/// Largest name table (bytes after its length field) the packer writes and readers accept.
+pub const MAX_NAME_TABLE_LEN: usize = 16 * 1024 * 1024;
+
+/// Largest extension area (bytes after its length field) the packer writes and readers accept.
+pub const MAX_EXTENSIONS_LEN: usize = 16 * 1024 * 1024;
+
+/// Caps on what parsing an archive may allocate, on top of the format maxima above. Every reader
+/// applies `FORMAT_MAX`, which adds nothing to them; `parse_untrusted` takes tighter ones (the
+/// `Default`) so no length or count field can make it allocate more than the caller allows.
+/// Exceeding a limit fails with `ClfError::LimitExceeded`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClfLimits {
+    /// Longest vendor, target or target-table string, in bytes.
+    pub max_text_len: usize,
+    /// Largest v3 header section (metadata, build info, name table, extension area), in bytes.
+    pub max_section_len: usize,
+    /// Most manifest entries.
+    pub max_entries: u32,
+    /// Most strings in the manifest target table.
+    pub max_targets: u32,
+    /// Largest decoded blob, in bytes: the buffer a compressed entry is inflated into.
+    pub max_blob_len: u32,
+}
+
+impl ClfLimits {
+    /// No limits beyond the format's own.
+    pub const FORMAT_MAX: Self = Self {
+        max_text_len: MAX_HEADER_TEXT_LEN,
+        max_section_len: MAX_NAME_TABLE_LEN,
+        max_entries: u32::MAX,
+        max_targets: u32::MAX,
+        max_blob_len: u32::MAX,
+    };
+}
+
+impl Default for ClfLimits {
+    /// Generous for real archives: 4 KiB strings, 1 MiB header sections, 65 536 entries, 1024
+    /// targets and 256 MiB blobs.
+    fn default() -> Self {
+        Self {
+            max_text_len: 4 * 1024,
+            max_section_len: 1024 * 1024,
+            max_entries: 1 << 16,
+            max_targets: 1024,
+            max_blob_len: 256 * 1024 * 1024,
+        }
+    }
+}
+
+/// Extension tag bit marking a record readers must understand: a reader that meets an unknown
+/// tag with this bit set rejects the file instead of skipping the record.
+pub const EXTENSION_MUST_UNDERSTAND: u16 = 0x8000;
+
+/// Extension tags this version of the crate interprets. None are defined yet; every record is
+/// exposed raw through `ClfHeader::extensions`.
+pub const KNOWN_EXTENSION_TAGS: &[u16] = &[];
+
+/// v3 build-info `fields` bit: a `created_at` timestamp (8 B, Unix seconds) follows.
+pub const BUILD_INFO_CREATED_AT: u8 = 1 << 0;
+
+/// v3 build-info `fields` bit: a `tool_version` string (2 B length + UTF-8) follows.
+pub const BUILD_INFO_TOOL_VERSION: u8 = 1 << 1;
+
 /// Single manifest entry: op_id → (offset, size) into blob store.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ManifestEntry {
     /// Canonical op identifier (see op_registry / docs/op_ids.md).
     pub op_id: u32,
@@ -131,9 +1384,94 @@ pub struct ManifestEntry {
     pub offset: u32,
     /// Stored length in the blob store (includes tail padding when alignment is used).
     pub size: u32,
+    /// Original blob length before alignment padding (v3+). Equals `size` for v1/v2 files.
+    /// For compressed entries this is the compressed length.
+    pub exact_size: u32,
+    /// Per-entry encoding flags (`ENTRY_FLAG_*`); 0 when the manifest carries no compression fields.
+    pub flags: u32,
+    /// Blob length after decoding (decompressed size). Equals `exact_size` for unencoded entries.
+    pub raw_size: u32,
+    /// CRC-32 (IEEE) of the stored bytes (the first `exact_size` bytes), when the manifest carries checksums.
+    pub crc32: Option<u32>,
+    /// Variant discriminator for ops compiled several ways (v3); 0 is the default variant and
+    /// the only one in manifests without variant fields.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub variant: u16,
+    /// Per-entry target tag (v3): 0 means the header target, n the n-th string of the
+    /// manifest's target table.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub target_index: u32,
 }
 
+/// v3 manifest `entry_fields` bit: every entry carries `flags` (4 B) and `raw_size` (4 B).
+pub const ENTRY_FIELD_COMPRESSION: u32 = 1 << 0;
+
+/// v3 manifest `entry_fields` bit: every entry carries a CRC-32 (4 B) of its stored bytes.
+pub const ENTRY_FIELD_CRC32: u32 = 1 << 1;
+
+/// v3 manifest `entry_fields` bit: every entry carries a variant (2 B) and 2 reserved zero bytes.
+pub const ENTRY_FIELD_VARIANT: u32 = 1 << 2;
+
+/// v3 manifest `entry_fields` bit: every entry carries a target index (4 B), and a target table
+/// follows the `entry_fields` mask.
+pub const ENTRY_FIELD_TARGET: u32 = 1 << 3;
+
+/// All `entry_fields` bits this crate understands; readers reject manifests using others.
+pub const KNOWN_ENTRY_FIELDS: u32 =
+    ENTRY_FIELD_COMPRESSION | ENTRY_FIELD_CRC32 | ENTRY_FIELD_VARIANT | ENTRY_FIELD_TARGET;
+
+/// Entry `flags` bit: the stored bytes are a zstd frame that decompresses to `raw_size` bytes.
+pub const ENTRY_FLAG_ZSTD: u32 = 1 << 0;
+
+/// Entry `flags` bit: the stored bytes are AES-256-GCM encrypted, laid out as an
+/// `AES_GCM_NONCE_LEN`-byte nonce, the ciphertext and an `AES_GCM_TAG_LEN`-byte tag. With
+/// `ENTRY_FLAG_ZSTD` as well, the blob was compressed before it was encrypted.
+pub const ENTRY_FLAG_AES256GCM: u32 = 1 << 1;
+
+/// Length of the nonce in front of an encrypted blob.
+pub const AES_GCM_NONCE_LEN: usize = 12;
+
+/// Length of the authentication tag at the end of an encrypted blob.
+pub const AES_GCM_TAG_LEN: usize = 16;
+
 impl ManifestEntry {
-    /// Size of one manifest entry in the file: op_id (4) + offset (4) + size (4).
+    /// Size of one v1/v2 manifest entry in the file: op_id (4) + offset (4) + size (4).
     pub const ENTRY_SIZE: usize = 4 + 4 + 4;
+
+    /// Size of one v3 manifest entry: op_id (4) + offset (4) + size (4) + exact_size (4).
+    pub const ENTRY_SIZE_V3: usize = Self::ENTRY_SIZE + 4;
+
+    /// Largest on-disk entry this crate reads or writes (v3 with every known entry field).
+    pub const MAX_ENTRY_SIZE: usize = Self::entry_size(CLF_VERSION, KNOWN_ENTRY_FIELDS);
+
+    /// On-disk size of one manifest entry for the given format version and v3 `entry_fields`.
+    pub const fn entry_size(version: u8, entry_fields: u32) -> usize {
+        if !VersionFeatures::for_version(version).has_exact_size {
+            return Self::ENTRY_SIZE;
+        }
+        let mut size = Self::ENTRY_SIZE_V3;
+        if entry_fields & ENTRY_FIELD_COMPRESSION != 0 {
+            size += 4 + 4;
+        }
+        if entry_fields & ENTRY_FIELD_CRC32 != 0 {
+            size += 4;
+        }
+        if entry_fields & ENTRY_FIELD_VARIANT != 0 {
+            size += 2 + 2;
+        }
+        if entry_fields & ENTRY_FIELD_TARGET != 0 {
+            size += 4;
+        }
+        size
+    }
+
+    /// Bytes before the first entry: entry count (4), plus `entry_fields` (4) for v3. The target
+    /// table, when `ENTRY_FIELD_TARGET` is set, follows on top of this.
+    pub const fn manifest_prefix_len(version: u8) -> usize {
+        if VersionFeatures::for_version(version).has_flags {
+            8
+        } else {
+            4
+        }
+    }
 }
@@ -1,34 +1,127 @@
 //! CLF (Coelanox Library File) — reader and packer for pre-compiled hardware kernel archives.
 //!
 //! This crate provides:
-//! - **Format types** (`format`): header, manifest entry, constants (CLF_MAGIC, etc.).
-//! - **Op ID registry** (`op_registry`): canonical op_id list and `op_type_to_clf_id` / `clf_id_to_op_type`.
+//! - **Format types** (`format`): header, manifest entry, constants (CLF_MAGIC, etc.), the header codec (`write_header` / `read_header`) shared by packer and reader, and `parse_header` / `parse_manifest` over a byte slice.
+//! - **Op ID registry** (`op_registry`): canonical op_id list, `op_type_to_clf_id` / `clf_id_to_op_type`, enumeration (`OpType::ALL`, `all_op_ids`), categories (`category`, `ops_in_category`), name lookup (`op_type_from_name`, `parse_op_id`), and `OpRegistry` for vendor ops registered at runtime.
+//! - **Slice** (`slice`): `ClfSlice` parses an archive already in memory and lends blobs as subslices of it. With `format` (less `read_header` / `write_header`) and `ClfError`, this is all that builds without the default `std` feature, for `no_std` + `alloc` loaders; everything below needs `std`.
 //! - **Reader** (`reader`): `ClfReader::open`, `get_blob`, `blobs_iter`, `manifest_entries`, optional `verify_signature`.
+//! - **Manifest views** (`view`): `ClfReader::manifest_view` snapshots the header and manifest as plain (serde) data, and `ManifestView::diff` compares two snapshots without the files.
+//! - **Untrusted input** (`untrusted`): `parse_untrusted(bytes, &ClfLimits)` checks a whole in-memory archive with every allocation bounded by the limits; the entry point the fuzz targets drive.
+//! - **Edit** (`edit`): `ClfEditor` replaces, removes or adds single entries of an existing file;
+//!   `append_entries` adds entries in place without moving existing blobs where it can;
+//!   `remap_op_ids_to_path` renumbers op_ids, copying the blobs through unchanged.
+//! - **Merge** (`merge`): `merge_clf` / `merge_clf_to_path` union several archives into one.
+//! - **Repack** (`repack`): `repack` copies a subset of an archive into a new one;
+//!   `convert_version_to_path` rewrites a whole archive in another format version.
+//! - **Trust** (`trust`, feature `ed25519`): `TrustedKeys` and `ClfReader::verify_against` accept archives signed by any of a set of vendor keys.
+//! - **C API** (`capi`, feature `capi`): `extern "C"` functions over `ClfReader` for C and C++ loaders, declared in `include/clf.h`.
+//! - **ELF** (`elf`, feature `elf`): `section_bytes` extracts a section (e.g. `.text`) from an object file for packing.
+//! - **Encryption** (`encryption`, feature `encryption`): `EncryptionKey` for AES-256-GCM encrypted blobs (`PackOptions::encrypt`, `ClfReader::set_decryption_key`).
 //! - **Packer** (`clf` / `coelanox-packer` binaries): pack / `--inspect` / `--verify` / `--from` manifest / `--dry-run` / sidecar JSON.
 //!
 //! See [SPEC.md](SPEC.md) and [docs/op_ids.md](docs/op_ids.md) for the full specification and op_id registry.
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod error;
 pub mod format;
+pub mod slice;
+
+#[cfg(feature = "std")]
+pub mod edit;
+#[cfg(feature = "std")]
+pub mod merge;
+#[cfg(feature = "std")]
 pub mod op_registry;
+#[cfg(feature = "std")]
 pub mod packer;
+#[cfg(feature = "std")]
 pub mod reader;
+#[cfg(feature = "std")]
+pub mod repack;
+#[cfg(feature = "std")]
+pub mod untrusted;
+#[cfg(feature = "std")]
+pub mod view;
 
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "elf")]
+pub mod elf;
+#[cfg(feature = "encryption")]
+pub mod encryption;
 #[cfg(feature = "serde")]
 pub mod manifest_file;
 #[cfg(feature = "serde")]
 pub mod sidecar;
+#[cfg(feature = "ed25519")]
+pub mod trust;
 
+pub use error::ClfError;
 pub use format::{
-    ClfHeader, ClfKind, ManifestEntry, CLF_MAGIC, CLF_VERSION, SIG_BLOCK_LEN, SIG_MAGIC,
+    parse_header, parse_manifest, ClfHeader, ClfKind, ClfLimits, ManifestEntry, ParsedManifest,
+    SignatureAlgorithm, SignatureScope, TargetMatcher, VersionFeatures, CLF_MAGIC, CLF_VERSION,
+    EXTENSION_MUST_UNDERSTAND, SIG_BLOCK_LEN, SIG_MAGIC,
+};
+pub use slice::ClfSlice;
+
+#[cfg(feature = "std")]
+pub use edit::{
+    append_entries, plan_remap, remap_op_ids, remap_op_ids_to_path, AppendOptions, AppendSummary,
+    ClfEditor, EditError, RemapPlan,
 };
-pub use op_registry::{clf_id_to_op_type, op_type_to_clf_id, OpType};
-pub use packer::{append_signature, pack_clf, parse_op_blob_arg, PackError, PackOptions};
+#[cfg(feature = "std")]
+pub use format::{read_header, write_header};
+#[cfg(feature = "std")]
+pub use merge::{
+    merge_clf, merge_clf_to_path, plan_merge, ConflictPolicy, MergeError, MergeOptions, MergePlan,
+    MismatchPolicy,
+};
+#[cfg(feature = "std")]
+pub use op_registry::{
+    all_op_ids, all_ops, canonical_op_id, category, clf_id_to_op_type, describe_op_id,
+    is_canonical_op_id, is_deprecated, is_registered_op_id, is_vendor_op_id, op_id_name,
+    op_name_to_clf_id, op_type_from_name, op_type_to_clf_id, ops_in_category, parse_op_id,
+    similar_op_names, try_op_type_to_clf_id, OpCategory, OpInfo, OpRegistry, OpType, RegistryError,
+    CANONICAL_OP_ID_MAX, DEPRECATED_OP_IDS, VENDOR_OP_ID_MAX, VENDOR_OP_ID_MIN,
+};
+#[cfg(feature = "std")]
+pub use packer::{
+    append_signature, append_signature_hmac, append_signature_scoped, append_signature_with,
+    pack_and_sign, pack_clf, pack_clf_from_sources, pack_clf_from_sources_to_path,
+    pack_clf_streaming, pack_clf_streaming_to_path, pack_clf_streaming_variants, pack_clf_to_path,
+    pack_clf_variants, pack_clf_variants_from_sources, pack_clf_with_summary, parse_op_blob_arg,
+    resign, strip_signature, BlobSource, Compression, HmacKey, PackError, PackOptions, PackSummary,
+    PackedEntry, SignWith,
+};
+#[cfg(feature = "std")]
 pub use reader::{
-    BlobIter, BlobIterFromBytes, ClfError, ClfReader, ClfReaderFromBytes, MissingOpIdPolicy,
-    VerificationPolicy,
+    BlobIter, BlobIterFromBytes, BlobLocation, BlobReader, ClfReader, ClfReaderFromBytes,
+    ClfReaderOptions, CodeSectionPlan, CodeSectionResult, CoverageReport, MissingOpIdPolicy,
+    ProgressEvent, ReadOrder, UniqueCodeSection, VerificationPolicy,
 };
+#[cfg(feature = "std")]
+pub use repack::{convert_version_to_path, repack, RepackError, RepackOptions};
+#[cfg(feature = "std")]
+pub use untrusted::{parse_untrusted, ParsedClf};
+#[cfg(feature = "std")]
+pub use view::{ManifestDiff, ManifestView, ManifestViewEntry};
 
+#[cfg(feature = "ed25519")]
+pub use ed25519_dalek;
+#[cfg(feature = "elf")]
+pub use elf::ElfError;
+#[cfg(feature = "encryption")]
+pub use encryption::EncryptionKey;
 #[cfg(feature = "serde")]
-pub use manifest_file::{load_pack_manifest, PackManifestBlob, PackManifestResolved};
+pub use manifest_file::{
+    load_op_list, load_pack_manifest, load_remap_file, PackManifestBlob, PackManifestResolved,
+};
+#[cfg(feature = "ed25519")]
+pub use packer::append_signature_ed25519;
 #[cfg(feature = "serde")]
 pub use sidecar::{write_sidecar_json, SidecarBlob, SidecarDocument};
+#[cfg(feature = "ed25519")]
+pub use trust::{TrustedKey, TrustedKeys, VerifiedBy};
@@ -1,35 +1,50 @@
-//! TOML manifest for `coelanox-packer --from` (batch pack with optional per-blob metadata for sidecars).
+//! Pack manifest for `coelanox-packer --manifest` (batch pack with optional per-blob metadata for
+//! sidecars), and op_id map for `--remap`. TOML by default; files ending in `.json` are read as
+//! JSON with the same fields. Also the plain-text op list `--coverage` checks against.
 
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
 use serde::Deserialize;
 
 use crate::format::ClfKind;
+use crate::op_registry::parse_op_id;
 
 #[derive(Debug, Deserialize)]
-struct TomlRoot {
+struct RawRoot<B> {
     vendor: Option<String>,
     target: Option<String>,
     kind: Option<String>,
     align: Option<u8>,
     sign: Option<bool>,
-    blobs: Vec<TomlBlob>,
+    blobs: Vec<B>,
 }
 
 #[derive(Debug, Deserialize)]
-struct TomlBlob {
-    op_id: u32,
+struct RawBlob {
+    op_id: OpRef,
     path: String,
+    name: Option<String>,
     symbol: Option<String>,
     notes: Option<String>,
 }
 
+/// `op_id` as written in the manifest: a number, or a canonical op name from docs/op_ids.md.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum OpRef {
+    Id(u32),
+    Name(String),
+}
+
 /// One blob line from a pack manifest (path on disk + optional audit fields).
 #[derive(Debug, Clone)]
 pub struct PackManifestBlob {
     pub op_id: u32,
     pub path: PathBuf,
+    /// Entry name for the archive's name table (`PackOptions::entry_names`).
+    pub name: Option<String>,
     pub symbol: Option<String>,
     pub notes: Option<String>,
 }
@@ -45,31 +60,78 @@ pub struct PackManifestResolved {
     pub blobs: Vec<PackManifestBlob>,
 }
 
-/// Load and validate `pack.toml`-style manifest from disk.
+/// Load and validate a `pack.toml`-style (or `.json`) manifest from disk. Relative blob paths
+/// are resolved against the manifest's directory. Errors name the file and, for problems with
+/// one entry, its index and (TOML) line.
 pub fn load_pack_manifest(path: &Path) -> Result<PackManifestResolved, String> {
     let text = fs::read_to_string(path).map_err(|e| format!("read {}: {e}", path.display()))?;
-    let root: TomlRoot = toml::from_str(&text).map_err(|e| format!("TOML parse: {e}"))?;
+    let is_json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    if is_json {
+        let root: RawRoot<RawBlob> = serde_json::from_str(&text)
+            .map_err(|e| format!("{}: JSON parse: {e}", path.display()))?;
+        resolve(path, root, |blob| (blob, None))
+    } else {
+        let root: RawRoot<toml::Spanned<RawBlob>> =
+            toml::from_str(&text).map_err(|e| format!("{}: TOML parse: {e}", path.display()))?;
+        resolve(path, root, |blob| {
+            let line = text[..blob.span().start].matches('\n').count() + 1;
+            (blob.into_inner(), Some(line))
+        })
+    }
+}
+
+/// Validate a parsed manifest; `unwrap` yields each blob with the line it starts on, if known.
+fn resolve<B>(
+    path: &Path,
+    root: RawRoot<B>,
+    unwrap: impl Fn(B) -> (RawBlob, Option<usize>),
+) -> Result<PackManifestResolved, String> {
     if root.blobs.is_empty() {
-        return Err("manifest must contain at least one [[blobs]] entry".into());
+        return Err(format!(
+            "{}: manifest must contain at least one [[blobs]] entry",
+            path.display()
+        ));
     }
 
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
     let mut seen = std::collections::HashSet::new();
     let mut blobs = Vec::with_capacity(root.blobs.len());
-    for b in root.blobs {
-        if !seen.insert(b.op_id) {
-            return Err(format!("duplicate op_id {} in manifest", b.op_id));
+    for (i, blob) in root.blobs.into_iter().enumerate() {
+        let (b, line) = unwrap(blob);
+        let entry_err = |msg: String| match line {
+            Some(line) => format!("{}: blobs[{i}] (line {line}): {msg}", path.display()),
+            None => format!("{}: blobs[{i}]: {msg}", path.display()),
+        };
+        let op_id = match b.op_id {
+            OpRef::Id(id) => id,
+            OpRef::Name(name) => parse_op_id(&name).map_err(entry_err)?,
+        };
+        if !seen.insert(op_id) {
+            return Err(entry_err(format!("duplicate op_id {op_id}")));
+        }
+        if b.path.is_empty() {
+            return Err(entry_err("empty path".into()));
         }
-        let path = PathBuf::from(&b.path);
         blobs.push(PackManifestBlob {
-            op_id: b.op_id,
-            path,
+            op_id,
+            // `-` (stdin for the packer CLI) is not a file, so it is not resolved.
+            path: if b.path == "-" {
+                PathBuf::from("-")
+            } else {
+                base.join(&b.path)
+            },
+            name: b.name,
             symbol: b.symbol,
             notes: b.notes,
         });
     }
 
     let kind = match root.kind {
-        Some(ref s) => s.parse::<ClfKind>().map_err(|e| e.to_string())?,
+        Some(ref s) => s
+            .parse::<ClfKind>()
+            .map_err(|e| format!("{}: {e}", path.display()))?,
         None => ClfKind::Compute,
     };
 
@@ -82,3 +144,55 @@ pub fn load_pack_manifest(path: &Path) -> Result<PackManifestResolved, String> {
         blobs,
     })
 }
+
+/// Load an op_id map for `remap_op_ids` from a TOML (or `.json`) file of `old = new` pairs, e.g.
+/// `50 = 300` or `matmul = 300`. Either side may be a decimal op_id or a canonical op name. Two
+/// keys naming the same op_id are an error.
+pub fn load_remap_file(path: &Path) -> Result<BTreeMap<u32, u32>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("read {}: {e}", path.display()))?;
+    let is_json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let raw: BTreeMap<String, OpRef> = if is_json {
+        serde_json::from_str(&text).map_err(|e| format!("{}: JSON parse: {e}", path.display()))?
+    } else {
+        toml::from_str(&text).map_err(|e| format!("{}: TOML parse: {e}", path.display()))?
+    };
+    let mut map = BTreeMap::new();
+    for (key, value) in raw {
+        let pair_err = |msg: String| format!("{}: {key}: {msg}", path.display());
+        let from = parse_op_id(&key).map_err(pair_err)?;
+        let to = match value {
+            OpRef::Id(id) => id,
+            OpRef::Name(name) => parse_op_id(&name).map_err(pair_err)?,
+        };
+        if map.insert(from, to).is_some() {
+            return Err(pair_err(format!("op_id {from} is mapped more than once")));
+        }
+    }
+    Ok(map)
+}
+
+/// Load a list of required ops (e.g. what one model needs) for `ClfReader::coverage`: one
+/// op_id or op name per line. `#` starts a comment; blank lines are skipped. Every line naming
+/// an unknown op is reported, with the closest registered names, in one error.
+pub fn load_op_list(path: &Path) -> Result<Vec<u32>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("read {}: {e}", path.display()))?;
+    let mut op_ids = Vec::new();
+    let mut problems = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let token = line.split('#').next().unwrap_or_default().trim();
+        if token.is_empty() {
+            continue;
+        }
+        match parse_op_id(token) {
+            Ok(op_id) => op_ids.push(op_id),
+            Err(e) => problems.push(format!("{}:{}: {e}", path.display(), i + 1)),
+        }
+    }
+    if problems.is_empty() {
+        Ok(op_ids)
+    } else {
+        Err(problems.join("\n"))
+    }
+}
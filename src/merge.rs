@@ -0,0 +1,218 @@
+//! Merge several .clf archives into one (e.g. one archive per kernel team → one per target).
+//!
+//! Manifests are unioned and blobs are streamed from the inputs into a new archive. Input
+//! signatures are not carried over; sign the output with `append_signature` if needed, or use
+//! `merge_clf_to_path`, which signs as `MergeOptions::pack` requests.
+
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Seek, Write};
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::format::ClfKind;
+use crate::packer::{
+    pack_clf_streaming_variants, write_atomically, PackError, PackOptions, PackSummary,
+};
+use crate::reader::{ClfError, ClfReader};
+
+/// What to do when more than one input has an entry for the same op_id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictPolicy {
+    /// Fail with `MergeError::ConflictingOpId`.
+    #[default]
+    Error,
+    /// Keep the entry from the earliest input that has it.
+    PreferFirst,
+    /// Keep the entry from the latest input that has it.
+    PreferLast,
+}
+
+/// What to do when inputs disagree on a header string (vendor or target).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum MismatchPolicy {
+    /// Fail with `MergeError::VendorMismatch` / `MergeError::TargetMismatch`.
+    #[default]
+    Error,
+    /// Write this value to the output header instead.
+    Use(String),
+    /// Write this value to the output header whether or not the inputs agree.
+    Override(String),
+}
+
+/// Options for `merge_clf`.
+#[derive(Debug, Clone, Default)]
+pub struct MergeOptions {
+    /// Resolution for op_ids present in more than one input.
+    pub on_conflict: ConflictPolicy,
+    /// Resolution when input vendors differ (equal vendors are always kept).
+    pub on_vendor_mismatch: MismatchPolicy,
+    /// Resolution when input targets differ (equal targets are always kept).
+    pub on_target_mismatch: MismatchPolicy,
+    /// Output layout (alignment, version, compression, checksums, dedup). `vendor`, `target` and
+    /// `kind` are replaced by the merged header. The signature fields are honored by
+    /// `merge_clf_to_path` only; after `merge_clf`, sign `out` yourself.
+    pub pack: PackOptions,
+}
+
+/// Which input supplies each op_id of a merge, as decided by a `ConflictPolicy`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MergePlan {
+    /// op_id -> index of the input whose entries (all variants) are kept.
+    pub sources: BTreeMap<u32, usize>,
+    /// op_ids present in more than one input, ascending.
+    pub conflicts: Vec<u32>,
+}
+
+impl MergePlan {
+    /// Number of op_ids taken from input `index`.
+    #[must_use]
+    pub fn entries_from(&self, index: usize) -> usize {
+        self.sources.values().filter(|&&i| i == index).count()
+    }
+}
+
+/// Decide which input supplies each op_id without reading any blobs. Fails with
+/// `ConflictingOpId` on the first conflict when `on_conflict` is `ConflictPolicy::Error`.
+pub fn plan_merge(
+    readers: &[ClfReader],
+    on_conflict: ConflictPolicy,
+) -> Result<MergePlan, MergeError> {
+    let mut plan = MergePlan::default();
+    for (i, reader) in readers.iter().enumerate() {
+        for op_id in reader.op_ids() {
+            match plan.sources.entry(op_id) {
+                Entry::Vacant(slot) => {
+                    slot.insert(i);
+                }
+                Entry::Occupied(mut slot) => {
+                    if !plan.conflicts.contains(&op_id) {
+                        plan.conflicts.push(op_id);
+                    }
+                    match on_conflict {
+                        ConflictPolicy::Error => return Err(MergeError::ConflictingOpId(op_id)),
+                        ConflictPolicy::PreferFirst => {}
+                        ConflictPolicy::PreferLast => {
+                            slot.insert(i);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    plan.conflicts.sort_unstable();
+    Ok(plan)
+}
+
+/// Errors produced by `merge_clf`.
+#[derive(Debug, Error)]
+pub enum MergeError {
+    #[error("merge needs at least one input archive")]
+    NoInputs,
+    #[error("input kinds differ: expected {expected}, got {actual}")]
+    KindMismatch { expected: ClfKind, actual: ClfKind },
+    #[error("input vendors differ: {first:?} vs {other:?}")]
+    VendorMismatch { first: String, other: String },
+    #[error("input targets differ: {first:?} vs {other:?}")]
+    TargetMismatch { first: String, other: String },
+    #[error("op_id {0} is present in more than one input (policy: Error)")]
+    ConflictingOpId(u32),
+    #[error("reading input: {0}")]
+    Read(#[from] ClfError),
+    #[error("writing output: {0}")]
+    Pack(#[from] PackError),
+}
+
+/// Merge `readers` into a new archive written to `out`. All inputs must have the same kind.
+/// Output entries are ordered by op_id; blobs are streamed (decoded) from the inputs and
+/// re-packed with `options.pack` (see `pack_clf_streaming` for what `dedup_blobs` buffers).
+pub fn merge_clf<W: Write + Seek>(
+    readers: &[ClfReader],
+    options: &MergeOptions,
+    mut out: W,
+) -> Result<PackSummary, MergeError> {
+    let (first, rest) = readers.split_first().ok_or(MergeError::NoInputs)?;
+
+    let kind = first.header.kind;
+    if let Some(other) = rest.iter().find(|r| r.header.kind != kind) {
+        return Err(MergeError::KindMismatch {
+            expected: kind,
+            actual: other.header.kind,
+        });
+    }
+    let vendor = resolve_header_string(
+        readers.iter().map(|r| r.header.vendor.as_str()),
+        &options.on_vendor_mismatch,
+    )
+    .map_err(|(first, other)| MergeError::VendorMismatch { first, other })?;
+    let target = resolve_header_string(
+        readers.iter().map(|r| r.header.target.as_str()),
+        &options.on_target_mismatch,
+    )
+    .map_err(|(first, other)| MergeError::TargetMismatch { first, other })?;
+
+    let chosen = plan_merge(readers, options.on_conflict)?.sources;
+
+    // An op's variants all come from the input chosen for it.
+    let mut sources: Vec<(u32, u16, Box<dyn Read + '_>)> = Vec::with_capacity(chosen.len());
+    let mut entry_targets = HashMap::new();
+    let mut entry_names = HashMap::new();
+    for (&op_id, &i) in &chosen {
+        entry_targets.extend(readers[i].entry_target_tags(op_id));
+        if let Some(name) = readers[i].entry_name(op_id) {
+            entry_names.insert(op_id, name.to_string());
+        }
+        for variant in readers[i].variants(op_id) {
+            let blob = readers[i]
+                .blob_reader_variant(op_id, variant)?
+                .expect("variant listed by variants() is in the manifest");
+            sources.push((op_id, variant, Box::new(blob)));
+        }
+    }
+
+    entry_targets.extend(options.pack.entry_targets.clone());
+    entry_names.extend(options.pack.entry_names.clone());
+    let pack = PackOptions {
+        vendor,
+        target,
+        kind,
+        sign: false,
+        entry_targets,
+        entry_names,
+        ..options.pack.clone()
+    };
+    Ok(pack_clf_streaming_variants(&mut out, sources, &pack)?)
+}
+
+/// `merge_clf` into a temp file next to `path`, append the signature `options.pack` requests
+/// (`sign`, `hmac_key` or `sign_key`), then move it into place. An existing file at `path` is
+/// only replaced once the merge has succeeded, so `path` may also be one of the inputs.
+pub fn merge_clf_to_path<P: AsRef<Path>>(
+    readers: &[ClfReader],
+    options: &MergeOptions,
+    path: P,
+) -> Result<PackSummary, MergeError> {
+    write_atomically(path.as_ref(), &options.pack, |file| {
+        merge_clf(readers, options, file)
+    })
+}
+
+/// The common value of `values`, or the policy's replacement when they differ.
+/// On `MismatchPolicy::Error` returns the first two differing values.
+fn resolve_header_string<'a>(
+    mut values: impl Iterator<Item = &'a str>,
+    policy: &MismatchPolicy,
+) -> Result<String, (String, String)> {
+    if let MismatchPolicy::Override(value) = policy {
+        return Ok(value.clone());
+    }
+    let first = values.next().unwrap_or_default();
+    match values.find(|v| *v != first) {
+        None => Ok(first.to_string()),
+        Some(other) => match policy {
+            MismatchPolicy::Error => Err((first.to_string(), other.to_string())),
+            MismatchPolicy::Use(value) | MismatchPolicy::Override(value) => Ok(value.clone()),
+        },
+    }
+}
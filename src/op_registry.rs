@@ -1,208 +1,721 @@
 //! Canonical op_id registry and mapping from Coelanox OpType (or op name) to op_id.
 //!
 //! Single source of truth: see docs/op_ids.md for the full table. Op_ids are stable;
-//! new ops get new ids, old ones are not renumbered. Custom range **256–u32::MAX** is for
-//! producer-defined ops (no collision with canonical set).
+//! new ops get new ids, old ones are not renumbered.
+//!
+//! The op_id space is split into:
+//! - **0:** reserved / unknown.
+//! - **Canonical range 1–255** (`is_canonical_op_id`): assigned by this registry only. Ids
+//!   not yet assigned are reserved for future canonical ops; `PackOptions::strict_op_ids`
+//!   rejects them.
+//! - **Custom range 256–u32::MAX:** producer-defined ops (`OpType::Custom`), opaque to the
+//!   packager.
+//! - **Vendor range `VENDOR_OP_ID_MIN`–`VENDOR_OP_ID_MAX`** (`is_vendor_op_id`), the top of the
+//!   custom range: sanctioned for proprietary ops and guaranteed never to be assigned
+//!   canonically (`OpType::Vendor`). Always accepted when packing.
+//!
+//! The free functions cover the built-in table. `OpRegistry` holds the same table and also
+//! accepts vendor ops registered at runtime (name and category), so tooling can name them; the
+//! free name lookups go through `OpRegistry::builtin()`.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use thiserror::Error;
+
+use crate::format::MAX_ENTRY_NAME_LEN;
+
+/// Highest op_id of the canonical range (1–255) managed by this registry.
+pub const CANONICAL_OP_ID_MAX: u32 = 255;
+
+/// First op_id of the reserved vendor range.
+pub const VENDOR_OP_ID_MIN: u32 = 0xF000_0000;
+
+/// Last op_id of the reserved vendor range (`u32::MAX` itself stays reserved).
+pub const VENDOR_OP_ID_MAX: u32 = 0xFFFF_FFFE;
+
+/// True if `op_id` is in the canonical range (1–255), whether or not the registry assigns it
+/// yet.
+#[must_use]
+pub fn is_canonical_op_id(op_id: u32) -> bool {
+    (1..=CANONICAL_OP_ID_MAX).contains(&op_id)
+}
+
+/// True if `op_id` is in the reserved vendor range (`VENDOR_OP_ID_MIN..=VENDOR_OP_ID_MAX`).
+#[must_use]
+pub fn is_vendor_op_id(op_id: u32) -> bool {
+    (VENDOR_OP_ID_MIN..=VENDOR_OP_ID_MAX).contains(&op_id)
+}
+
+/// True if the registry assigns `op_id` to a named op.
+#[must_use]
+pub fn is_registered_op_id(op_id: u32) -> bool {
+    !matches!(
+        clf_id_to_op_type(op_id),
+        OpType::Unknown | OpType::Custom(_) | OpType::Vendor(_)
+    )
+}
 
-/// Coelanox OpType: symbolic type for IR nodes. Maps to canonical op_id in CLF.
+/// Defines `OpType`, `OpType::ALL`, both directions of the op_id map and `category` from one
+/// table, so they cannot drift: a variant without an id or category does not compile, and two
+/// variants with the same id trip the unreachable-pattern lint in `clf_id_to_op_type`.
+macro_rules! op_registry {
+    ($($(#[$meta:meta])* $variant:ident = $id:literal => $category:ident,)*) => {
+        /// Coelanox OpType: symbolic type for IR nodes. Maps to canonical op_id in CLF.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[non_exhaustive]
+        pub enum OpType {
+            /// Reserved / unknown (op_id 0).
+            Unknown,
+            $($(#[$meta])* $variant,)*
+            /// Custom or reserved; op_id 0 or 256+ per agreement.
+            Custom(u32),
+            /// Proprietary op in the reserved vendor range (`is_vendor_op_id`).
+            Vendor(u32),
+        }
+
+        impl OpType {
+            /// Every registered op (those with a canonical op_id), in op_id order. Excludes
+            /// `Unknown`, `Custom` and `Vendor`.
+            pub const ALL: &'static [OpType] = &[$(OpType::$variant,)*];
+
+            /// Canonical name (the variant name, as in docs/op_ids.md); `None` for `Unknown`,
+            /// `Custom` and `Vendor`.
+            #[must_use]
+            pub const fn name(&self) -> Option<&'static str> {
+                match self {
+                    OpType::Unknown | OpType::Custom(_) | OpType::Vendor(_) => None,
+                    $(OpType::$variant => Some(stringify!($variant)),)*
+                }
+            }
+        }
+
+        /// Maps Coelanox OpType to canonical CLF op_id (u32).
+        /// Used by the packager when generating code from a CLF backend.
+        #[must_use]
+        pub fn op_type_to_clf_id(op_type: OpType) -> u32 {
+            match op_type {
+                OpType::Unknown | OpType::Custom(0) => 0,
+                OpType::Custom(id) | OpType::Vendor(id) => id,
+                $(OpType::$variant => $id,)*
+            }
+        }
+
+        /// Reverse map: op_id → OpType (for tooling / diagnostics). Vendor-range op_ids map to
+        /// Vendor(id), other unassigned op_ids to Custom(id).
+        #[must_use]
+        pub fn clf_id_to_op_type(op_id: u32) -> OpType {
+            match op_id {
+                0 => OpType::Unknown,
+                $($id => OpType::$variant,)*
+                id if is_vendor_op_id(id) => OpType::Vendor(id),
+                id => OpType::Custom(id),
+            }
+        }
+
+        /// Scheduling category of `op_type`. Exhaustive over `OpType`: `Unknown`, `Custom` and
+        /// `Vendor` ops are `Other`.
+        #[must_use]
+        pub fn category(op_type: OpType) -> OpCategory {
+            match op_type {
+                OpType::Unknown | OpType::Custom(_) | OpType::Vendor(_) => OpCategory::Other,
+                $(OpType::$variant => OpCategory::$category,)*
+            }
+        }
+    };
+}
+
+/// How an op touches its data, for schedulers that treat op families differently.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[non_exhaustive]
-pub enum OpType {
-    // Reserved / unknown
-    Unknown,
+pub enum OpCategory {
+    /// One output element per input element(s) at the same index: arithmetic, math, comparison,
+    /// logical, min/max.
+    Elementwise,
+    /// Reduces along axes or windows: `Reduce*` and pooling.
+    Reduction,
+    /// Multiply-accumulate over a shared dimension: matmul, gemm, convolution.
+    Contraction,
+    /// Moves or reshapes data without arithmetic: reshape, transpose, gather, broadcast, ...
+    DataMovement,
+    /// Normalizes with computed statistics: batch and layer norm.
+    Normalization,
+    /// Activation functions, including softmax and log-softmax.
+    Activation,
+    /// Anything else, including dropout and ops outside the registry.
+    Other,
+}
+
+impl OpCategory {
+    /// Lowercase kebab-case name, as used in exports (`elementwise`, `data-movement`, ...).
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            OpCategory::Elementwise => "elementwise",
+            OpCategory::Reduction => "reduction",
+            OpCategory::Contraction => "contraction",
+            OpCategory::DataMovement => "data-movement",
+            OpCategory::Normalization => "normalization",
+            OpCategory::Activation => "activation",
+            OpCategory::Other => "other",
+        }
+    }
+
+    /// Every category, in declaration order.
+    pub const ALL: [OpCategory; 7] = [
+        OpCategory::Elementwise,
+        OpCategory::Reduction,
+        OpCategory::Contraction,
+        OpCategory::DataMovement,
+        OpCategory::Normalization,
+        OpCategory::Activation,
+        OpCategory::Other,
+    ];
+}
+
+op_registry! {
     // Arithmetic 1–9
-    Add,
-    Subtract,
-    Multiply,
-    Divide,
+    Add = 1 => Elementwise,
+    Subtract = 2 => Elementwise,
+    Multiply = 3 => Elementwise,
+    Divide = 4 => Elementwise,
     // Activations 10–19
-    Relu,
-    Sigmoid,
-    Tanh,
-    Softmax,
-    LogSoftmax,
-    Gelu,
-    Swish,
+    Relu = 10 => Activation,
+    Sigmoid = 11 => Activation,
+    Tanh = 12 => Activation,
+    Softmax = 13 => Activation,
+    LogSoftmax = 14 => Activation,
+    Gelu = 15 => Activation,
+    Swish = 16 => Activation,
     /// Element-wise |x| on f32 (canonical op_id **19**).
-    Abs,
+    Abs = 19 => Elementwise,
     // Math 20–29
-    Sqrt,
-    Pow,
-    Cos,
-    Sin,
-    Exp,
-    Log,
+    Sqrt = 20 => Elementwise,
+    Pow = 21 => Elementwise,
+    Cos = 22 => Elementwise,
+    Sin = 23 => Elementwise,
+    Exp = 24 => Elementwise,
+    Log = 25 => Elementwise,
     // Conv/pool/norm 30–39
-    Convolution,
-    MaxPool,
-    AvgPool,
-    GlobalMaxPool,
-    GlobalAvgPool,
-    BatchNorm,
-    LayerNorm,
-    Dropout,
+    Convolution = 30 => Contraction,
+    MaxPool = 31 => Reduction,
+    AvgPool = 32 => Reduction,
+    GlobalMaxPool = 33 => Reduction,
+    GlobalAvgPool = 34 => Reduction,
+    BatchNorm = 35 => Normalization,
+    LayerNorm = 36 => Normalization,
+    Dropout = 37 => Other,
     // Tensor manip 40–49
-    Reshape,
-    Transpose,
-    Permute,
-    Concatenate,
-    Split,
-    Slice,
-    Gather,
-    Scatter,
+    Reshape = 40 => DataMovement,
+    Transpose = 41 => DataMovement,
+    Permute = 42 => DataMovement,
+    Concatenate = 43 => DataMovement,
+    Split = 44 => DataMovement,
+    Slice = 45 => DataMovement,
+    Gather = 46 => DataMovement,
+    Scatter = 47 => DataMovement,
     // Linear 50–59
-    MatMul,
-    Gemm,
+    MatMul = 50 => Contraction,
+    Gemm = 51 => Contraction,
     // Reductions 60–69
-    ReduceSum,
-    ReduceMean,
-    ReduceMax,
-    ReduceMin,
-    ReduceProd,
+    ReduceSum = 60 => Reduction,
+    ReduceMean = 61 => Reduction,
+    ReduceMax = 62 => Reduction,
+    ReduceMin = 63 => Reduction,
+    ReduceProd = 64 => Reduction,
     // Broadcasting 70–71
-    Broadcast,
-    Expand,
+    Broadcast = 70 => DataMovement,
+    Expand = 71 => DataMovement,
     // Comparisons 80–89
-    Equal,
-    NotEqual,
-    Greater,
-    GreaterEqual,
-    Less,
-    LessEqual,
+    Equal = 80 => Elementwise,
+    NotEqual = 81 => Elementwise,
+    Greater = 82 => Elementwise,
+    GreaterEqual = 83 => Elementwise,
+    Less = 84 => Elementwise,
+    LessEqual = 85 => Elementwise,
     // Logical 90–99
-    And,
-    Or,
-    Not,
+    And = 90 => Elementwise,
+    Or = 91 => Elementwise,
+    Not = 92 => Elementwise,
     // Element-wise min/max (93–94; for Clip)
-    Min,
-    Max,
-    /// Custom or reserved; op_id 0 or 256+ per agreement.
-    Custom(u32),
+    Min = 93 => Elementwise,
+    Max = 94 => Elementwise,
+}
+
+impl OpType {
+    /// Other names accepted for this op, matched case-insensitively like the canonical name:
+    /// the spellings common in model graphs (ONNX-style `Conv`, `Concat`, ...).
+    #[must_use]
+    pub const fn aliases(&self) -> &'static [&'static str] {
+        match self {
+            OpType::Subtract => &["Sub"],
+            OpType::Multiply => &["Mul"],
+            OpType::Divide => &["Div"],
+            OpType::Swish => &["SiLU"],
+            OpType::Convolution => &["Conv"],
+            OpType::AvgPool => &["AveragePool"],
+            OpType::GlobalAvgPool => &["GlobalAveragePool"],
+            OpType::BatchNorm => &["BatchNormalization"],
+            OpType::LayerNorm => &["LayerNormalization"],
+            OpType::Concatenate => &["Concat"],
+            _ => &[],
+        }
+    }
+
+    /// Crate release that first assigned this op its op_id. Ops added later get their own arm.
+    #[must_use]
+    pub const fn since(&self) -> &'static str {
+        "0.1.0"
+    }
+}
+
+impl fmt::Display for OpCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl fmt::Display for OpType {
+    /// The canonical name; `Unknown` for `OpType::Unknown`; the decimal op_id for custom and
+    /// vendor ops.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.name(), self) {
+            (Some(name), _) => f.write_str(name),
+            (None, OpType::Unknown) => f.write_str("Unknown"),
+            (None, op) => write!(f, "{}", op_type_to_clf_id(*op)),
+        }
+    }
+}
+
+impl FromStr for OpType {
+    type Err = String;
+
+    /// Parse a canonical name or alias (case-insensitive), `Unknown`, or a decimal op_id (custom
+    /// and vendor ids included). The inverse of `Display`, except that `Custom(0)` reads back as
+    /// `Unknown`. An unknown name fails with the closest canonical names.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("unknown") {
+            return Ok(OpType::Unknown);
+        }
+        OpRegistry::builtin().parse_op_id(s).map(clf_id_to_op_type)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for OpType {
+    /// Serialized as its `Display` string.
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for OpType {
+    /// Accepts anything `FromStr` does, or a bare integer op_id.
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct OpTypeVisitor;
+
+        impl serde::de::Visitor<'_> for OpTypeVisitor {
+            type Value = OpType;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("an op name or op_id")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<OpType, E> {
+                v.parse().map_err(E::custom)
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<OpType, E> {
+                u32::try_from(v)
+                    .map(clf_id_to_op_type)
+                    .map_err(|_| E::custom(format!("op_id {v} does not fit in u32")))
+            }
+        }
+
+        deserializer.deserialize_any(OpTypeVisitor)
+    }
+}
+
+/// Like [`op_type_to_clf_id`], but returns `None` for ops with no registered CLF id
+/// (`OpType::Unknown` and `OpType::Custom(0)`), so callers can tell "not in the registry"
+/// apart from "not in this archive".
+#[must_use]
+pub fn try_op_type_to_clf_id(op_type: OpType) -> Option<u32> {
+    match op_type_to_clf_id(op_type) {
+        0 => None,
+        id => Some(id),
+    }
+}
+
+/// Canonical name of `op_id`, without building an `OpType` by hand. `None` for ids the
+/// registry does not assign (including custom and vendor ids).
+#[must_use]
+pub fn op_id_name(op_id: u32) -> Option<&'static str> {
+    clf_id_to_op_type(op_id).name()
+}
+
+/// `op_id` for display: its canonical name, `vendor op 0x...` for vendor-range ids, or
+/// `unknown op N` otherwise.
+#[must_use]
+pub fn describe_op_id(op_id: u32) -> String {
+    match op_id_name(op_id) {
+        Some(name) => name.to_string(),
+        None if is_vendor_op_id(op_id) => format!("vendor op {op_id:#010x}"),
+        None => format!("unknown op {op_id}"),
+    }
+}
+
+/// Every registered op, in op_id order (`OpType::ALL`).
+pub fn all_ops() -> impl Iterator<Item = OpType> {
+    OpType::ALL.iter().copied()
+}
+
+/// Registered ops in `category`, in op_id order.
+pub fn ops_in_category(category: OpCategory) -> impl Iterator<Item = OpType> {
+    all_ops().filter(move |&op| self::category(op) == category)
+}
+
+/// Every canonical op_id the registry assigns, ascending.
+pub fn all_op_ids() -> impl Iterator<Item = u32> {
+    all_ops().map(op_type_to_clf_id)
+}
+
+/// An op known to an `OpRegistry`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpInfo {
+    /// The op's op_id.
+    pub op_id: u32,
+    /// Registry op type; `OpType::Vendor(op_id)` for registered vendor ops.
+    pub op_type: OpType,
+    /// Display name: the `OpType` variant name for canonical ops, the registered name otherwise.
+    pub name: String,
+    /// Other accepted names (`OpType::aliases`); empty for vendor ops.
+    pub aliases: &'static [&'static str],
+    /// Scheduling category.
+    pub category: OpCategory,
+}
+
+/// Deprecated op_ids and their canonical replacements, as `(deprecated, canonical)` pairs. A
+/// deprecated op_id is never reassigned: archives packed with it stay readable through
+/// `canonical_op_id` and the readers' `get_blob_canonical`. Entries are append-only; none of the
+/// canonical op_ids has been deprecated yet.
+pub const DEPRECATED_OP_IDS: &[(u32, u32)] = &[];
+
+/// Errors produced by `OpRegistry::register_vendor_op` and `OpRegistry::deprecate_op_id`.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum RegistryError {
+    #[error(
+        "op_id {0:#x} is outside the vendor range {VENDOR_OP_ID_MIN:#x}-{VENDOR_OP_ID_MAX:#x}"
+    )]
+    NotVendorOpId(u32),
+    #[error("vendor op_id {0:#x} is already registered")]
+    DuplicateOpId(u32),
+    #[error("op name {0:?} is already registered")]
+    DuplicateName(String),
+    #[error("invalid op name {0:?}: names are 1-255 bytes and not all digits")]
+    InvalidName(String),
+    #[error("replacement op_id {0} is not registered")]
+    UnknownOpId(u32),
+    #[error("op_id {0} is a registered op and cannot be deprecated")]
+    OpIdInUse(u32),
+    #[error("op_id {0} is already deprecated")]
+    AlreadyDeprecated(u32),
+}
+
+/// Op registry: the built-in canonical table plus vendor ops registered at runtime. Names match
+/// case-insensitively and are unique across both. Deprecated op_ids (`DEPRECATED_OP_IDS` plus
+/// any added with `deprecate_op_id`) map to their canonical replacement.
+#[derive(Debug, Clone)]
+pub struct OpRegistry {
+    ops: BTreeMap<u32, OpInfo>,
+    /// Deprecated op_id -> canonical op_id.
+    deprecated: BTreeMap<u32, u32>,
+}
+
+impl Default for OpRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OpRegistry {
+    /// Registry holding the built-in canonical ops.
+    #[must_use]
+    pub fn new() -> Self {
+        let ops = all_ops()
+            .map(|op| {
+                let op_id = op_type_to_clf_id(op);
+                let info = OpInfo {
+                    op_id,
+                    op_type: op,
+                    name: op.to_string(),
+                    aliases: op.aliases(),
+                    category: category(op),
+                };
+                (op_id, info)
+            })
+            .collect();
+        Self {
+            ops,
+            deprecated: DEPRECATED_OP_IDS.iter().copied().collect(),
+        }
+    }
+
+    /// Shared built-in registry (no vendor ops), used by the free name lookups.
+    pub fn builtin() -> &'static OpRegistry {
+        static BUILTIN: OnceLock<OpRegistry> = OnceLock::new();
+        BUILTIN.get_or_init(OpRegistry::new)
+    }
+
+    /// Register a vendor op. `op_id` must be in the vendor range and not yet registered;
+    /// `name` must be 1–255 bytes, not all digits (those parse as op_ids), and not match an
+    /// existing name case-insensitively.
+    pub fn register_vendor_op(
+        &mut self,
+        op_id: u32,
+        name: impl Into<String>,
+        category: OpCategory,
+    ) -> Result<(), RegistryError> {
+        let name = name.into();
+        if !is_vendor_op_id(op_id) {
+            return Err(RegistryError::NotVendorOpId(op_id));
+        }
+        if name.is_empty()
+            || name.len() > MAX_ENTRY_NAME_LEN
+            || name.bytes().all(|b| b.is_ascii_digit())
+        {
+            return Err(RegistryError::InvalidName(name));
+        }
+        if self.ops.contains_key(&op_id) || self.deprecated.contains_key(&op_id) {
+            return Err(RegistryError::DuplicateOpId(op_id));
+        }
+        if self.lookup_name(&name).is_some() {
+            return Err(RegistryError::DuplicateName(name));
+        }
+        let info = OpInfo {
+            op_id,
+            op_type: OpType::Vendor(op_id),
+            name,
+            aliases: &[],
+            category,
+        };
+        self.ops.insert(op_id, info);
+        Ok(())
+    }
+
+    /// Record `deprecated` as a retired op_id now served by `canonical`. `canonical` must be
+    /// registered; `deprecated` must be neither a registered op nor already deprecated, so
+    /// aliases never chain.
+    pub fn deprecate_op_id(
+        &mut self,
+        deprecated: u32,
+        canonical: u32,
+    ) -> Result<(), RegistryError> {
+        if !self.ops.contains_key(&canonical) {
+            return Err(RegistryError::UnknownOpId(canonical));
+        }
+        if self.ops.contains_key(&deprecated) {
+            return Err(RegistryError::OpIdInUse(deprecated));
+        }
+        if self.deprecated.contains_key(&deprecated) {
+            return Err(RegistryError::AlreadyDeprecated(deprecated));
+        }
+        self.deprecated.insert(deprecated, canonical);
+        Ok(())
+    }
+
+    /// Canonical replacement for a deprecated `op_id`; any other id is returned unchanged.
+    #[must_use]
+    pub fn canonical_op_id(&self, op_id: u32) -> u32 {
+        self.deprecated.get(&op_id).copied().unwrap_or(op_id)
+    }
+
+    /// Whether `op_id` is a deprecated alias of another op_id.
+    #[must_use]
+    pub fn is_deprecated(&self, op_id: u32) -> bool {
+        self.deprecated.contains_key(&op_id)
+    }
+
+    /// Deprecated op_ids that map to `canonical`, ascending.
+    pub fn deprecated_aliases(&self, canonical: u32) -> impl Iterator<Item = u32> + '_ {
+        self.deprecated
+            .iter()
+            .filter(move |(_, &to)| to == canonical)
+            .map(|(&from, _)| from)
+    }
+
+    /// The op registered for `op_id`, if any.
+    #[must_use]
+    pub fn lookup_id(&self, op_id: u32) -> Option<&OpInfo> {
+        self.ops.get(&op_id)
+    }
+
+    /// The op named `name` (its name or an alias), matched case-insensitively.
+    #[must_use]
+    pub fn lookup_name(&self, name: &str) -> Option<&OpInfo> {
+        self.ops.values().find(|info| {
+            info.name.eq_ignore_ascii_case(name)
+                || info.aliases.iter().any(|a| a.eq_ignore_ascii_case(name))
+        })
+    }
+
+    /// Name of `op_id`, if registered.
+    #[must_use]
+    pub fn name(&self, op_id: u32) -> Option<&str> {
+        self.lookup_id(op_id).map(|info| info.name.as_str())
+    }
+
+    /// Every registered op, in op_id order (canonical ops first, then vendor ops).
+    pub fn iter(&self) -> impl Iterator<Item = &OpInfo> {
+        self.ops.values()
+    }
+
+    /// Up to three registered names close to `name` (case-insensitive edit distance of at most
+    /// 2, or containing it), best match first. For "did you mean" hints.
+    #[must_use]
+    pub fn similar_names(&self, name: &str) -> Vec<String> {
+        let wanted = name.to_ascii_lowercase();
+        let mut close: Vec<(usize, &str)> = self
+            .iter()
+            .filter_map(|info| {
+                let lower = info.name.to_ascii_lowercase();
+                let distance = edit_distance(&wanted, &lower);
+                (distance <= 2 || (wanted.len() >= 3 && lower.contains(&wanted)))
+                    .then_some((distance, info.name.as_str()))
+            })
+            .collect();
+        close.sort_by_key(|(distance, _)| *distance);
+        close
+            .into_iter()
+            .take(3)
+            .map(|(_, name)| name.to_string())
+            .collect()
+    }
+
+    /// Parse an op identifier as written on a command line or in a manifest: a decimal op_id
+    /// (any u32, including unnamed custom ids) or a registered name, case-insensitive. An
+    /// unknown name fails with the closest registered names.
+    pub fn parse_op_id(&self, token: &str) -> Result<u32, String> {
+        if !token.is_empty() && token.bytes().all(|b| b.is_ascii_digit()) {
+            return token
+                .parse()
+                .map_err(|_| format!("op_id {token} does not fit in u32"));
+        }
+        self.lookup_name(token)
+            .map(|info| info.op_id)
+            .ok_or_else(|| {
+                let similar = self.similar_names(token);
+                if similar.is_empty() {
+                    format!("unknown op name {token:?} (see docs/op_ids.md)")
+                } else {
+                    format!(
+                        "unknown op name {token:?} (did you mean {}?)",
+                        similar.join(", ")
+                    )
+                }
+            })
+    }
+}
+
+/// OpType for an op name from docs/op_ids.md (the `OpType` variant name, e.g. `"MatMul"`) or
+/// one of its aliases, matched case-insensitively. `None` for names outside the canonical set.
+#[must_use]
+pub fn op_type_from_name(name: &str) -> Option<OpType> {
+    OpRegistry::builtin()
+        .lookup_name(name)
+        .map(|info| info.op_type)
 }
 
-/// Maps Coelanox OpType to canonical CLF op_id (u32).
-/// Used by the packager when generating code from a CLF backend.
+/// Canonical op_id for an op name (see [`op_type_from_name`]). `None` for names outside the
+/// canonical set.
 #[must_use]
-pub fn op_type_to_clf_id(op_type: OpType) -> u32 {
-    match op_type {
-        OpType::Unknown | OpType::Custom(0) => 0,
-        OpType::Custom(id) => id,
-        OpType::Add => 1,
-        OpType::Subtract => 2,
-        OpType::Multiply => 3,
-        OpType::Divide => 4,
-        OpType::Relu => 10,
-        OpType::Sigmoid => 11,
-        OpType::Tanh => 12,
-        OpType::Softmax => 13,
-        OpType::LogSoftmax => 14,
-        OpType::Gelu => 15,
-        OpType::Swish => 16,
-        OpType::Abs => 19,
-        OpType::Sqrt => 20,
-        OpType::Pow => 21,
-        OpType::Cos => 22,
-        OpType::Sin => 23,
-        OpType::Exp => 24,
-        OpType::Log => 25,
-        OpType::Convolution => 30,
-        OpType::MaxPool => 31,
-        OpType::AvgPool => 32,
-        OpType::GlobalMaxPool => 33,
-        OpType::GlobalAvgPool => 34,
-        OpType::BatchNorm => 35,
-        OpType::LayerNorm => 36,
-        OpType::Dropout => 37,
-        OpType::Reshape => 40,
-        OpType::Transpose => 41,
-        OpType::Permute => 42,
-        OpType::Concatenate => 43,
-        OpType::Split => 44,
-        OpType::Slice => 45,
-        OpType::Gather => 46,
-        OpType::Scatter => 47,
-        OpType::MatMul => 50,
-        OpType::Gemm => 51,
-        OpType::ReduceSum => 60,
-        OpType::ReduceMean => 61,
-        OpType::ReduceMax => 62,
-        OpType::ReduceMin => 63,
-        OpType::ReduceProd => 64,
-        OpType::Broadcast => 70,
-        OpType::Expand => 71,
-        OpType::Equal => 80,
-        OpType::NotEqual => 81,
-        OpType::Greater => 82,
-        OpType::GreaterEqual => 83,
-        OpType::Less => 84,
-        OpType::LessEqual => 85,
-        OpType::And => 90,
-        OpType::Or => 91,
-        OpType::Not => 92,
-        OpType::Min => 93,
-        OpType::Max => 94,
-    }
-}
-
-/// Reverse map: op_id → OpType (for tooling / diagnostics). Unknown op_ids map to Custom(id).
+pub fn op_name_to_clf_id(name: &str) -> Option<u32> {
+    op_type_from_name(name).map(op_type_to_clf_id)
+}
+
+/// Canonical replacement for a deprecated op_id in `DEPRECATED_OP_IDS`; identity otherwise.
 #[must_use]
-pub fn clf_id_to_op_type(op_id: u32) -> OpType {
-    match op_id {
-        0 => OpType::Unknown,
-        1 => OpType::Add,
-        2 => OpType::Subtract,
-        3 => OpType::Multiply,
-        4 => OpType::Divide,
-        10 => OpType::Relu,
-        11 => OpType::Sigmoid,
-        12 => OpType::Tanh,
-        13 => OpType::Softmax,
-        14 => OpType::LogSoftmax,
-        15 => OpType::Gelu,
-        16 => OpType::Swish,
-        19 => OpType::Abs,
-        20 => OpType::Sqrt,
-        21 => OpType::Pow,
-        22 => OpType::Cos,
-        23 => OpType::Sin,
-        24 => OpType::Exp,
-        25 => OpType::Log,
-        30 => OpType::Convolution,
-        31 => OpType::MaxPool,
-        32 => OpType::AvgPool,
-        33 => OpType::GlobalMaxPool,
-        34 => OpType::GlobalAvgPool,
-        35 => OpType::BatchNorm,
-        36 => OpType::LayerNorm,
-        37 => OpType::Dropout,
-        40 => OpType::Reshape,
-        41 => OpType::Transpose,
-        42 => OpType::Permute,
-        43 => OpType::Concatenate,
-        44 => OpType::Split,
-        45 => OpType::Slice,
-        46 => OpType::Gather,
-        47 => OpType::Scatter,
-        50 => OpType::MatMul,
-        51 => OpType::Gemm,
-        60 => OpType::ReduceSum,
-        61 => OpType::ReduceMean,
-        62 => OpType::ReduceMax,
-        63 => OpType::ReduceMin,
-        64 => OpType::ReduceProd,
-        70 => OpType::Broadcast,
-        71 => OpType::Expand,
-        80 => OpType::Equal,
-        81 => OpType::NotEqual,
-        82 => OpType::Greater,
-        83 => OpType::GreaterEqual,
-        84 => OpType::Less,
-        85 => OpType::LessEqual,
-        90 => OpType::And,
-        91 => OpType::Or,
-        92 => OpType::Not,
-        93 => OpType::Min,
-        94 => OpType::Max,
-        id => OpType::Custom(id),
+pub fn canonical_op_id(op_id: u32) -> u32 {
+    OpRegistry::builtin().canonical_op_id(op_id)
+}
+
+/// Whether `op_id` is listed in `DEPRECATED_OP_IDS`.
+#[must_use]
+pub fn is_deprecated(op_id: u32) -> bool {
+    OpRegistry::builtin().is_deprecated(op_id)
+}
+
+/// Up to three canonical op names close to `name` (see `OpRegistry::similar_names`).
+#[must_use]
+pub fn similar_op_names(name: &str) -> Vec<String> {
+    OpRegistry::builtin().similar_names(name)
+}
+
+/// Parse an op identifier against the built-in registry (see `OpRegistry::parse_op_id`): a
+/// decimal op_id or a canonical op name. An unknown name fails with the closest canonical names.
+pub fn parse_op_id(token: &str) -> Result<u32, String> {
+    OpRegistry::builtin().parse_op_id(token)
+}
+
+/// The registry as pretty-printed JSON: the canonical and vendor ranges, then one object per
+/// registered op (`op_id`, `name`, `category`, `since`, `aliases`) in op_id order. Generated from
+/// the in-code table, so docs built from it cannot disagree with the code.
+#[must_use]
+pub fn export_json() -> String {
+    let ops: Vec<serde_json::Value> = all_ops()
+        .map(|op| {
+            serde_json::json!({
+                "op_id": op_type_to_clf_id(op),
+                "name": op.to_string(),
+                "category": category(op).name(),
+                "since": op.since(),
+                "aliases": op.aliases(),
+            })
+        })
+        .collect();
+    let doc = serde_json::json!({
+        "canonical_op_id_max": CANONICAL_OP_ID_MAX,
+        "vendor_op_id_min": VENDOR_OP_ID_MIN,
+        "vendor_op_id_max": VENDOR_OP_ID_MAX,
+        "ops": ops,
+    });
+    format!("{doc:#}")
+}
+
+/// The registry as a Markdown table (op_id, name, category, since, aliases), one row per
+/// registered op in op_id order. Same source as `export_json`.
+#[must_use]
+pub fn export_markdown() -> String {
+    let mut out = String::from(
+        "| op_id | Name | Category | Since | Aliases |\n|-------|------|----------|-------|---------|\n",
+    );
+    for op in all_ops() {
+        out.push_str(&format!(
+            "| {} | {op} | {} | {} | {} |\n",
+            op_type_to_clf_id(op),
+            category(op),
+            op.since(),
+            op.aliases().join(", ")
+        ));
+    }
+    out
+}
+
+/// Levenshtein distance between two strings, by chars.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitute = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitute.min(row[j] + 1).min(diagonal + 1);
+        }
     }
+    row[b.len()]
 }
@@ -2,12 +2,35 @@
 //!
 //! Used by the coelanox-packer binary. Writes header + manifest + blob store + optional signature.
 
-use std::io::{Read, Seek, Write};
+use std::borrow::{Borrow, Cow};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ffi::OsString;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
 
+use hmac::{Hmac, KeyInit, Mac};
 use sha2::{Digest, Sha256};
 use thiserror::Error;
 
-use crate::format::{ClfKind, CLF_MAGIC, CLF_VERSION, SIG_MAGIC};
+#[cfg(feature = "encryption")]
+use crate::encryption::EncryptionKey;
+#[cfg(feature = "encryption")]
+use crate::format::ENTRY_FLAG_AES256GCM;
+use crate::format::{
+    self, ClfHeader, ClfKind, ManifestEntry, SignatureBlock, VersionFeatures, CLF_VERSION,
+    ENTRY_FIELD_COMPRESSION, ENTRY_FIELD_CRC32, ENTRY_FIELD_TARGET, ENTRY_FIELD_VARIANT,
+    ENTRY_FLAG_ZSTD, MAX_ENTRY_NAME_LEN, MAX_EXTENSIONS_LEN, MAX_HEADER_TEXT_LEN, MAX_METADATA_LEN,
+    MAX_META_KEY_LEN, MAX_NAME_TABLE_LEN, SIG1_MIN_LEN, SIG_ALG_HMAC_SHA256, SIG_ALG_SHA256,
+    SIG_BLOCK_LEN, SIG_HASH_LEN, SIG_MAGIC,
+};
+#[cfg(feature = "ed25519")]
+use crate::format::{key_fingerprint, SIG_ALG_ED25519, SIG_KEY_FINGERPRINT_LEN};
+use crate::format::{read_header_with, ClfLimits, FieldReader, SignatureAlgorithm, SignatureScope};
+use crate::op_registry::{is_canonical_op_id, is_registered_op_id, parse_op_id};
+use crate::reader::{find_trailer, locate_signature, ClfError, Trailer};
+use crate::slice::read_manifest;
 
 /// Errors produced by the packer.
 #[derive(Debug, Error)]
@@ -16,13 +39,66 @@ pub enum PackError {
     Io(#[from] std::io::Error),
     #[error("duplicate op_id: {0}")]
     DuplicateOpId(u32),
-    #[error("vendor string too long (max u32::MAX bytes)")]
+    #[error("duplicate variant {variant} of op_id {op_id}")]
+    DuplicateVariant { op_id: u32, variant: u16 },
+    #[error("entry iterator reported {expected} entries but yielded {actual}")]
+    EntryCountMismatch { expected: usize, actual: usize },
+    #[error("vendor string too long (max 65536 bytes)")]
     VendorTooLong,
-    #[error("target string too long (max u32::MAX bytes)")]
+    #[error("target string too long (max 65536 bytes)")]
     TargetTooLong,
+    #[error("blob for op_id {0} is larger than u32::MAX bytes")]
+    BlobTooLarge(u32),
+    #[error("blob store would grow to {total} bytes at op_id {at_op_id}; offsets are limited to u32::MAX")]
+    BlobStoreTooLarge { at_op_id: u32, total: u64 },
+    #[error("archive would need {needed} bytes, over the size limit of {limit} bytes")]
+    SizeLimitExceeded { limit: u64, needed: u64 },
+    #[error("compression requested, but this build lacks the `compression` feature")]
+    CompressionUnsupported,
+    #[error("{feature} requires format version 3 or later (packing version {version})")]
+    RequiresV3 { feature: &'static str, version: u8 },
+    #[error("reading archive: {0}")]
+    Read(#[from] ClfError),
+    #[error("archive has a keyed (SIG1) signature; strip it and sign again with the key")]
+    KeyedSignaturePresent,
+    #[error("archive already ends in a signature block; use resign or strip_signature first")]
+    AlreadySigned,
+    #[error("pack_clf does not sign; use pack_and_sign when the options request a signature")]
+    SigningUnsupported,
+    #[error("expected {expected} bytes of archive data to sign, but the stream holds only {got}")]
+    TruncatedData { expected: u64, got: u64 },
+    #[error("expected {expected} bytes of archive data to sign, but the stream holds {got}; truncate it to the archive first")]
+    TrailingData { expected: u64, got: u64 },
+    #[error("metadata key {0:?} is longer than {MAX_META_KEY_LEN} bytes")]
+    MetadataKeyTooLong(String),
+    #[error("duplicate metadata key {0:?}")]
+    DuplicateMetadataKey(String),
+    #[error("metadata section exceeds {MAX_METADATA_LEN} bytes")]
+    MetadataTooLarge,
+    #[error("tool_version is longer than {} bytes", u16::MAX)]
+    ToolVersionTooLong,
+    #[error("name of op_id {0} is longer than {MAX_ENTRY_NAME_LEN} bytes")]
+    EntryNameTooLong(u32),
+    #[error("name table exceeds {MAX_NAME_TABLE_LEN} bytes")]
+    NameTableTooLarge,
+    #[error("op_id {0} is in the canonical range (1-255) but not in the registry (strict_op_ids)")]
+    UnregisteredOpId(u32),
+    #[error("duplicate header extension tag {0:#06x}")]
+    DuplicateExtension(u16),
+    #[error("header extension area exceeds {MAX_EXTENSIONS_LEN} bytes")]
+    ExtensionsTooLarge,
+    #[error("reading blob source for op_id {op_id}: {source}")]
+    Source { op_id: u32, source: std::io::Error },
+    #[error("blob source for op_id {op_id} declared {declared} bytes but produced {actual}")]
+    SourceLenMismatch {
+        op_id: u32,
+        declared: u64,
+        actual: u64,
+    },
 }
 
-/// Parse one CLI token `op_id:path` (first `:` separates id from path; path may be relative or absolute).
+/// Parse one CLI token `op:path` (first `:` separates op from path; path may be relative or
+/// absolute). `op` is a decimal op_id or a canonical op name (see `parse_op_id`).
 pub fn parse_op_blob_arg(arg: &str) -> Result<(u32, String), String> {
     let mut it = arg.splitn(2, ':');
     let id_str = it.next().unwrap_or("");
@@ -32,9 +108,7 @@ pub fn parse_op_blob_arg(arg: &str) -> Result<(u32, String), String> {
     if path.is_empty() {
         return Err(format!("empty path in {arg:?}"));
     }
-    let op_id: u32 = id_str
-        .parse()
-        .map_err(|_| format!("invalid op_id in {arg:?} (expected u32)"))?;
+    let op_id = parse_op_id(id_str).map_err(|e| format!("invalid op in {arg:?}: {e}"))?;
     Ok((op_id, path.to_string()))
 }
 
@@ -46,13 +120,94 @@ pub struct PackOptions {
     /// Target/architecture (e.g. "CPU", "GPU", "CDNA"); empty = not set. Packager uses this to match CLF to target.
     pub target: String,
     /// Blob alignment in bytes (0 = none). Each blob is padded to this alignment in the blob store (e.g. 16 for code).
+    /// The last blob is padded as well, so the blob store ends on an aligned file offset.
     pub blob_alignment: u8,
     /// File kind (Compute / MemoryMovement / MemoryProtection). Written to header (v2 format).
     pub kind: ClfKind,
-    /// Format version to write (default CLF_VERSION; packer writes v2 when kind is used).
+    /// Format version to write (default CLF_VERSION). v1 omits kind; v3 adds exact blob sizes to the manifest.
     pub version: u8,
-    /// If true, append SIG0 + SHA-256 of everything before the signature.
+    /// If true, append SIG0 + SHA-256 of everything before the signature. Honored by
+    /// `pack_and_sign` and the `*_to_path` functions; `pack_clf` rejects it with
+    /// `PackError::SigningUnsupported`.
     pub sign: bool,
+    /// If true, entries with byte-identical blobs share one copy in the blob store (their manifest
+    /// entries alias the same offset/size).
+    pub dedup_blobs: bool,
+    /// Compress each blob (v3; requires the `compression` feature). `get_blob` decompresses
+    /// transparently. `None` stores blobs as-is.
+    pub compression: Option<Compression>,
+    /// If true, record a CRC-32 of each stored blob in the manifest (v3); `get_blob` checks it.
+    pub per_blob_crc: bool,
+    /// Build metadata key/value pairs written to the v3 header (e.g. driver version, git SHA).
+    /// Keys are at most `MAX_META_KEY_LEN` bytes and must be unique.
+    pub metadata: Vec<(String, String)>,
+    /// Build time in Unix seconds, written to the v3 header. Omitted when `deterministic` is set.
+    pub created_at: Option<u64>,
+    /// Name/version of the producing tool, written to the v3 header. Omitted when `deterministic`
+    /// is set.
+    pub tool_version: Option<String>,
+    /// If true, leave out `created_at` and `tool_version` so identical inputs produce
+    /// byte-identical archives.
+    pub deterministic: bool,
+    /// Human-readable entry names by op_id, written as the v3 name table (informational only;
+    /// lookups stay keyed by op_id). Names are at most `MAX_ENTRY_NAME_LEN` bytes.
+    pub entry_names: HashMap<u32, String>,
+    /// Per-entry targets by (op_id, variant) for archives that mix targets (v3). Entries without
+    /// a tag, or tagged with `target` itself, inherit the header target; when every entry does,
+    /// no target table is written.
+    pub entry_targets: HashMap<(u32, u16), String>,
+    /// Extension records (tag, bytes) written to the v3 header's TLV area, in order. Tags must
+    /// be unique; set `EXTENSION_MUST_UNDERSTAND` in a tag to make readers that do not know it
+    /// reject the file.
+    pub extensions: Vec<(u16, Vec<u8>)>,
+    /// If true, reject op_ids in the canonical range (1–255) that the registry does not assign
+    /// (`PackError::UnregisteredOpId`). Custom and vendor-range op_ids are always accepted.
+    pub strict_op_ids: bool,
+    /// Sign with this Ed25519 key (SIG1 block) instead of the unkeyed SIG0 hash. When set, the
+    /// archive is signed even if `sign` is false.
+    #[cfg(feature = "ed25519")]
+    pub sign_key: Option<ed25519_dalek::SigningKey>,
+    /// MAC with this shared secret (HMAC-SHA256 SIG1 block) instead of the unkeyed SIG0 hash.
+    /// When set, the archive is signed even if `sign` is false. Ignored if `sign_key` is set.
+    pub hmac_key: Option<HmacKey>,
+    /// If true, the requested signature covers only the header and manifest, so verifying it
+    /// does not read the blob store. The unkeyed hash is then written as a SHA-256 SIG1 block,
+    /// since SIG0 always covers everything. Pair with `per_blob_crc` for per-blob checks.
+    pub sign_index_only: bool,
+    /// Encrypt each blob with this key (v3): AES-256-GCM under a random nonce per blob, after
+    /// compression. Readers need `set_decryption_key` to get the blobs back; header and manifest
+    /// stay readable without it. The nonces make the output differ between runs, even with
+    /// `deterministic`.
+    #[cfg(feature = "encryption")]
+    pub encrypt: Option<EncryptionKey>,
+    /// Largest archive to write, in bytes, counting the header, manifest, blob store and the
+    /// signature trailer requested here. The pack fails with `PackError::SizeLimitExceeded` before
+    /// writing the blob that would cross it. `None` means no limit.
+    pub max_total_size: Option<u64>,
+}
+
+/// Shared secret for HMAC-SHA256 signing. `Debug` does not print the key bytes.
+#[derive(Clone, PartialEq, Eq)]
+pub struct HmacKey(Vec<u8>);
+
+impl HmacKey {
+    /// Wrap the secret key bytes.
+    pub fn new(key: impl Into<Vec<u8>>) -> Self {
+        Self(key.into())
+    }
+}
+
+impl std::fmt::Debug for HmacKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("HmacKey(<redacted>)")
+    }
+}
+
+/// Per-blob compression applied by the packer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// zstd at the given level (1–22; 0 selects zstd's default).
+    Zstd(i32),
 }
 
 impl Default for PackOptions {
@@ -64,112 +219,1836 @@ impl Default for PackOptions {
             kind: ClfKind::Compute,
             version: CLF_VERSION,
             sign: false,
+            dedup_blobs: false,
+            compression: None,
+            per_blob_crc: false,
+            metadata: Vec::new(),
+            created_at: None,
+            tool_version: None,
+            deterministic: false,
+            entry_names: HashMap::new(),
+            entry_targets: HashMap::new(),
+            extensions: Vec::new(),
+            strict_op_ids: false,
+            #[cfg(feature = "ed25519")]
+            sign_key: None,
+            hmac_key: None,
+            sign_index_only: false,
+            #[cfg(feature = "encryption")]
+            encrypt: None,
+            max_total_size: None,
+        }
+    }
+}
+
+/// What a pack call produced.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackSummary {
+    /// Bytes written (header + manifest + blob store); pass to `append_signature`.
+    pub data_len: u64,
+    /// One record per manifest entry, in manifest order.
+    pub entries: Vec<PackedEntry>,
+    /// Blob bytes written to the blob store (stored, unpadded; aliased entries not counted).
+    pub total_blob_bytes: u64,
+    /// Zero fill written to the blob store: leading alignment fill plus per-blob padding.
+    pub padding_bytes: u64,
+    /// Stored bytes not written because an entry aliased an identical earlier blob
+    /// (`PackOptions::dedup_blobs`).
+    pub dedup_bytes_saved: u64,
+    /// Vendor written to the header.
+    pub vendor: String,
+    /// Kind written to the header.
+    pub kind: ClfKind,
+}
+
+/// One packed entry, as recorded in the manifest.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackedEntry {
+    pub op_id: u32,
+    /// Variant discriminator (0 = default).
+    pub variant: u16,
+    /// Offset within the blob store.
+    pub offset: u32,
+    /// Stored length including alignment padding.
+    pub size: u32,
+    /// Stored length without padding.
+    pub exact_size: u32,
+    /// Blob length before compression.
+    pub raw_size: u32,
+    /// True if the entry shares an earlier entry's stored bytes (`dedup_blobs`).
+    pub deduplicated: bool,
+}
+
+impl PackSummary {
+    /// Summarize a pack from its manifest; `aliased[i]` is set for entries that reuse an
+    /// earlier entry's bytes.
+    pub(crate) fn new(
+        data_len: u64,
+        lead: u32,
+        manifest: &[ManifestEntry],
+        aliased: &[bool],
+        options: &PackOptions,
+    ) -> Self {
+        let entries: Vec<PackedEntry> = manifest
+            .iter()
+            .zip(aliased)
+            .map(|(e, &deduplicated)| PackedEntry {
+                op_id: e.op_id,
+                variant: e.variant,
+                offset: e.offset,
+                size: e.size,
+                exact_size: e.exact_size,
+                raw_size: e.raw_size,
+                deduplicated,
+            })
+            .collect();
+        let written = || entries.iter().filter(|e| !e.deduplicated);
+        let total_blob_bytes = written().map(|e| u64::from(e.exact_size)).sum();
+        let padding_bytes = u64::from(lead)
+            + written()
+                .map(|e| u64::from(e.size - e.exact_size))
+                .sum::<u64>();
+        let dedup_bytes_saved = entries
+            .iter()
+            .filter(|e| e.deduplicated)
+            .map(|e| u64::from(e.size))
+            .sum();
+        Self {
+            data_len,
+            entries,
+            total_blob_bytes,
+            padding_bytes,
+            dedup_bytes_saved,
+            vendor: options.vendor.clone(),
+            kind: options.kind,
         }
     }
 }
 
 /// Build a .clf file from (op_id, blob) pairs. Entries must have unique op_ids.
-/// Writes to `out`: header + manifest + blob store. Returns the number of bytes written
-/// (caller may then call `append_signature` if options.sign is true).
-pub fn pack_clf<W: Write + Seek>(
+/// Writes to `out`: header + manifest + blob store. Returns the number of bytes written.
+/// The archive is unsigned: options requesting a signature (`sign`, `hmac_key`, `sign_key`)
+/// fail with `PackError::SigningUnsupported`; use `pack_and_sign` for those.
+///
+/// `entries` is any exact-size iterable (slices and `&Vec` work as before) and is consumed
+/// once, in order: a zeroed manifest is written first and patched after the blobs, which is
+/// why `out` must be seekable. Duplicate op_ids are caught as they arrive, and an iterator
+/// whose length does not match what it yields fails with `PackError::EntryCountMismatch`.
+/// Since the manifest layout is fixed before the first entry, the target table lists every
+/// variant-0 tag in `entry_targets` that differs from `target`, packed or not.
+pub fn pack_clf<W, I>(out: &mut W, entries: I, options: &PackOptions) -> Result<u64, PackError>
+where
+    W: Write + Seek,
+    I: IntoIterator,
+    I::IntoIter: ExactSizeIterator,
+    I::Item: Borrow<(u32, Vec<u8>)>,
+{
+    if Signer::requested_by(options).is_some() {
+        return Err(PackError::SigningUnsupported);
+    }
+    pack_clf_with_summary(out, entries, options).map(|summary| summary.data_len)
+}
+
+/// Like `pack_clf`, but returns a `PackSummary` describing what was written.
+pub fn pack_clf_with_summary<W, I>(
+    out: &mut W,
+    entries: I,
+    options: &PackOptions,
+) -> Result<PackSummary, PackError>
+where
+    W: Write + Seek,
+    I: IntoIterator,
+    I::IntoIter: ExactSizeIterator,
+    I::Item: Borrow<(u32, Vec<u8>)>,
+{
+    let layout = ManifestLayout::default_variants(options)?;
+    pack_entries(out, entries.into_iter(), &layout, options, |entry| {
+        let (op_id, blob) = entry.borrow();
+        (*op_id, 0, blob.as_slice())
+    })
+}
+
+/// Like `pack_clf_with_summary`, for `(op_id, variant, blob)` triples: several variants of one op
+/// (e.g. different tile sizes) in one archive. Each (op_id, variant) pair must be unique; readers
+/// return variant 0 from `get_blob` and others from `get_blob_variant`. Non-zero variants
+/// require format v3.
+pub fn pack_clf_variants<W: Write + Seek>(
+    out: &mut W,
+    entries: &[(u32, u16, Vec<u8>)],
+    options: &PackOptions,
+) -> Result<PackSummary, PackError> {
+    let layout = ManifestLayout::new(
+        options,
+        entries.iter().map(|(op_id, variant, _)| (*op_id, *variant)),
+    )?;
+    pack_entries(out, entries.iter(), &layout, options, |entry| {
+        let (op_id, variant, blob) = entry;
+        (*op_id, *variant, blob.as_slice())
+    })
+}
+
+/// Optional entry fields and target table of a manifest, fixed before any entry is written.
+struct ManifestLayout<'a> {
+    entry_fields: u32,
+    targets: Vec<&'a str>,
+}
+
+impl<'a> ManifestLayout<'a> {
+    /// Layout for packing the (op_id, variant) pairs `keys` with `options`.
+    fn new(
+        options: &'a PackOptions,
+        keys: impl Iterator<Item = (u32, u16)> + Clone,
+    ) -> Result<Self, PackError> {
+        let targets = target_table(options, keys.clone())?;
+        let entry_fields = entry_fields(options, keys.into_iter().any(|(_, v)| v != 0), &targets)?;
+        Ok(Self {
+            entry_fields,
+            targets,
+        })
+    }
+
+    /// Layout for `(op_id, blob)` entries that are not known up front: every entry is variant 0,
+    /// and the target table covers all variant-0 tags in `entry_targets`.
+    fn default_variants(options: &'a PackOptions) -> Result<Self, PackError> {
+        let tagged = options
+            .entry_targets
+            .keys()
+            .copied()
+            .filter(|&(_, variant)| variant == 0);
+        Self::new(options, tagged)
+    }
+}
+
+/// Assigns blob-store offsets to in-memory blobs in entry order, checking keys, encoding and
+/// deduplicating as `options` asks. The manifest grows as entries are placed.
+struct BlobPlacer<'a> {
+    options: &'a PackOptions,
+    targets: &'a [&'a str],
+    align: u32,
+    offset: u32,
+    size_limit: SizeLimit,
+    manifest: Vec<ManifestEntry>,
+    aliased: Vec<bool>,
+    keys: HashSet<(u32, u16)>,
+    // Content digest -> index of the first manifest entry with that content.
+    seen: HashMap<[u8; 32], usize>,
+}
+
+impl<'a> BlobPlacer<'a> {
+    /// Placer whose first blob goes at blob-store offset `lead`.
+    fn new(
+        options: &'a PackOptions,
+        layout: &'a ManifestLayout<'a>,
+        lead: u32,
+        num_entries: usize,
+        size_limit: SizeLimit,
+    ) -> Self {
+        Self {
+            options,
+            targets: &layout.targets,
+            align: effective_alignment(options),
+            offset: lead,
+            size_limit,
+            manifest: Vec::with_capacity(num_entries),
+            aliased: Vec::with_capacity(num_entries),
+            keys: HashSet::with_capacity(num_entries),
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Place a batch of entries in order. For each, returns the bytes to store at the next
+    /// offset (followed by padding to the alignment), or `None` when the entry aliases an earlier
+    /// identical blob.
+    #[cfg(not(feature = "parallel"))]
+    fn place_batch<'b>(
+        &mut self,
+        batch: &[(u32, u16, &'b [u8])],
+    ) -> Result<Vec<Option<Cow<'b, [u8]>>>, PackError> {
+        let options = self.options;
+        batch
+            .iter()
+            .map(|&(op_id, variant, blob)| {
+                let digest = options.dedup_blobs.then(|| Sha256::digest(blob).into());
+                self.place_hashed(op_id, variant, blob, digest, || stored_form(options, blob))
+            })
+            .collect()
+    }
+
+    /// Same as the serial `place_batch`, but digests, encoding and CRC-32s are computed for the
+    /// whole batch on the rayon pool first. Entries are still placed in order, so the result (and
+    /// the first error) is byte-identical to the serial path.
+    #[cfg(feature = "parallel")]
+    fn place_batch<'b>(
+        &mut self,
+        batch: &[(u32, u16, &'b [u8])],
+    ) -> Result<Vec<Option<Cow<'b, [u8]>>>, PackError> {
+        use rayon::prelude::*;
+
+        let options = self.options;
+        let hashed: Vec<_> = batch
+            .par_iter()
+            .map(|&(_, _, blob)| {
+                let digest: Option<[u8; 32]> =
+                    options.dedup_blobs.then(|| Sha256::digest(blob).into());
+                (digest, stored_form(options, blob))
+            })
+            .collect();
+        batch
+            .iter()
+            .zip(hashed)
+            .map(|(&(op_id, variant, blob), (digest, stored))| {
+                self.place_hashed(op_id, variant, blob, digest, || stored)
+            })
+            .collect()
+    }
+
+    /// Place one entry whose content digest (when deduplicating) is known; `stored` yields
+    /// its stored bytes, flags and CRC-32 and is only called for a blob that is not an alias.
+    fn place_hashed<'b>(
+        &mut self,
+        op_id: u32,
+        variant: u16,
+        blob: &'b [u8],
+        digest: Option<[u8; 32]>,
+        stored: impl FnOnce() -> Result<StoredForm<'b>, PackError>,
+    ) -> Result<Option<Cow<'b, [u8]>>, PackError> {
+        check_unique_entry(&mut self.keys, op_id, variant)?;
+        check_op_id(self.options, op_id)?;
+        let target_index = target_index(self.options, self.targets, op_id, variant);
+        if let Some(digest) = digest {
+            if let Some(&first) = self.seen.get(&digest) {
+                self.manifest.push(ManifestEntry {
+                    op_id,
+                    variant,
+                    target_index,
+                    ..self.manifest[first]
+                });
+                self.aliased.push(true);
+                return Ok(None);
+            }
+            self.seen.insert(digest, self.manifest.len());
+        }
+        let raw_size = u32::try_from(blob.len()).map_err(|_| PackError::BlobTooLarge(op_id))?;
+        let (bytes, flags, crc32) = stored()?;
+        let (padded_size, next_offset) =
+            place_blob(op_id, self.offset, bytes.len() as u64, self.align)?;
+        self.size_limit.check(u64::from(next_offset))?;
+        self.manifest.push(ManifestEntry {
+            op_id,
+            variant,
+            offset: self.offset,
+            size: padded_size,
+            exact_size: bytes.len() as u32,
+            flags,
+            raw_size,
+            crc32,
+            target_index,
+        });
+        self.aliased.push(false);
+        self.offset = next_offset;
+        Ok(Some(bytes))
+    }
+}
+
+/// A blob as stored: its (possibly compressed) bytes, entry flags and CRC-32 if requested.
+type StoredForm<'b> = (Cow<'b, [u8]>, u32, Option<u32>);
+
+/// Encode `blob` as `options` asks and checksum the result (see `encode_blob` and `seal`).
+fn stored_form<'b>(options: &PackOptions, blob: &'b [u8]) -> Result<StoredForm<'b>, PackError> {
+    let (bytes, flags) = encode_blob(blob, options.compression)?;
+    let (bytes, flags) = seal(options, bytes, flags)?;
+    let crc32 = options.per_blob_crc.then(|| crc32fast::hash(&bytes));
+    Ok((bytes, flags, crc32))
+}
+
+/// Entries handed to `BlobPlacer::place_batch` at once: enough to keep a thread pool busy with
+/// the `parallel` feature, one at a time without it so no entry is held longer than needed.
+const PLACE_BATCH: usize = if cfg!(feature = "parallel") { 64 } else { 1 };
+
+/// Borrows an entry's op_id, variant and blob (see `pack_entries`).
+type EntryParts<T> = fn(&T) -> (u32, u16, &[u8]);
+
+/// Pack `entries`, reading each once: `parts` borrows an entry's op_id, variant and blob. The
+/// manifest is written zeroed and patched once the blob offsets are known.
+fn pack_entries<W: Write + Seek, I: ExactSizeIterator>(
     out: &mut W,
-    entries: &[(u32, Vec<u8>)],
+    mut entries: I,
+    layout: &ManifestLayout<'_>,
     options: &PackOptions,
+    parts: EntryParts<I::Item>,
+) -> Result<PackSummary, PackError> {
+    let align = effective_alignment(options);
+    let entry_fields = layout.entry_fields;
+    check_metadata(options)?;
+    check_build_info(options)?;
+    check_entry_names(options)?;
+    check_extensions(options)?;
+
+    // --- Manifest: num_entries (4 B) [+ entry_fields (4 B) in v3] + entries (12 B each; 16 B or more
+    // in v3). Size = stored length in blob store (includes padding per SPEC); v3 also records the
+    // unpadded length so readers can trim the padding. ---
+    let num_entries = entries.len();
+    // Zero fill before the first blob so blobs start at aligned file offsets, not just
+    // aligned offsets within the blob store.
+    let lead = leading_padding(options, num_entries, entry_fields, &layout.targets, align);
+    let index_start = out.stream_position()?;
+    let (index, manifest_at) =
+        index_placeholder(options, num_entries, entry_fields, &layout.targets, lead)?;
+    let size_limit = SizeLimit::new(options, index.len() - lead as usize);
+    size_limit.check(u64::from(lead))?;
+    out.write_all(&index)?;
+    let manifest_start = index_start + manifest_at as u64;
+    let mut placer = BlobPlacer::new(options, layout, lead, num_entries, size_limit);
+    let mut batch: Vec<I::Item> = Vec::with_capacity(PLACE_BATCH);
+    loop {
+        batch.clear();
+        batch.extend(entries.by_ref().take(PLACE_BATCH));
+        if batch.is_empty() {
+            break;
+        }
+        if placer.manifest.len() + batch.len() > num_entries {
+            return Err(PackError::EntryCountMismatch {
+                expected: num_entries,
+                actual: placer.manifest.len() + batch.len() + entries.count(),
+            });
+        }
+        let parts: Vec<_> = batch.iter().map(parts).collect();
+        // --- Blob store: each blob padded to blob_alignment (or raw if 0). ---
+        for bytes in placer.place_batch(&parts)?.into_iter().flatten() {
+            out.write_all(&bytes)?;
+            write_padding(out, bytes.len() as u64, align)?;
+        }
+    }
+    if placer.manifest.len() != num_entries {
+        return Err(PackError::EntryCountMismatch {
+            expected: num_entries,
+            actual: placer.manifest.len(),
+        });
+    }
+
+    let data_len = out.stream_position()?;
+    out.seek(SeekFrom::Start(manifest_start))?;
+    out.write_all(&manifest_bytes(
+        &placer.manifest,
+        options.version,
+        entry_fields,
+    )?)?;
+    out.seek(SeekFrom::Start(data_len))?;
+    Ok(PackSummary::new(
+        data_len,
+        lead,
+        &placer.manifest,
+        &placer.aliased,
+        options,
+    ))
+}
+
+/// Pack and sign in one call: writes the same archive as `pack_clf_with_summary` followed by
+/// the signature `options` requests (`sign_key`, `hmac_key` or `sign`; see
+/// `append_requested_signature`), or no trailer when it requests none.
+///
+/// Entries are streamed as in `pack_clf_with_summary`, so memory use does not grow with the
+/// archive; the signature is then computed by reading back what was written (only the header
+/// and manifest with `sign_index_only`). `PackSummary::data_len` excludes the trailer.
+pub fn pack_and_sign<W, I>(
+    out: &mut W,
+    entries: I,
+    options: &PackOptions,
+) -> Result<PackSummary, PackError>
+where
+    W: Read + Write + Seek,
+    I: IntoIterator,
+    I::IntoIter: ExactSizeIterator,
+    I::Item: Borrow<(u32, Vec<u8>)>,
+{
+    let summary = pack_clf_with_summary(out, entries, options)?;
+    append_requested_signature(out, summary.data_len, options)?;
+    Ok(summary)
+}
+
+/// Running computation of the signature trailer `options` requests, fed the archive bytes in order.
+enum Signer {
+    /// SIG0: SHA-256 of the data (written as a SHA-256 SIG1 block when index-only).
+    Sig0(Sha256),
+    /// SHA-256 SIG1 block.
+    Sha256(Sha256),
+    /// Ed25519 SIG1: signature over the SHA-256 of the data.
+    #[cfg(feature = "ed25519")]
+    Ed25519(Sha256, ed25519_dalek::SigningKey),
+    /// HMAC-SHA256 SIG1 over the data.
+    Hmac(Hmac<Sha256>),
+}
+
+impl Signer {
+    /// The signer for the trailer `options` asks for (same precedence as
+    /// `append_requested_signature`), or `None` for an unsigned archive.
+    fn requested_by(options: &PackOptions) -> Option<Self> {
+        #[cfg(feature = "ed25519")]
+        if let Some(key) = &options.sign_key {
+            return Some(Signer::Ed25519(Sha256::new(), key.clone()));
+        }
+        if let Some(key) = &options.hmac_key {
+            return Some(Signer::hmac(&key.0));
+        }
+        options.sign.then(|| Signer::Sig0(Sha256::new()))
+    }
+
+    /// The signer for an explicit algorithm choice.
+    fn with(sign: SignWith<'_>) -> Self {
+        match sign {
+            SignWith::Sha256 => Signer::Sha256(Sha256::new()),
+            SignWith::HmacSha256(key) => Signer::hmac(key),
+            #[cfg(feature = "ed25519")]
+            SignWith::Ed25519(key) => Signer::Ed25519(Sha256::new(), key.clone()),
+        }
+    }
+
+    fn hmac(key: &[u8]) -> Self {
+        Signer::Hmac(Hmac::new_from_slice(key).expect("HMAC accepts keys of any length"))
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Signer::Sig0(hasher) | Signer::Sha256(hasher) => hasher.update(bytes),
+            #[cfg(feature = "ed25519")]
+            Signer::Ed25519(hasher, _) => hasher.update(bytes),
+            Signer::Hmac(mac) => Mac::update(mac, bytes),
+        }
+    }
+
+    /// Serialized trailer to append after the data; `index_only` marks a signature over the
+    /// header and manifest alone.
+    fn finish(self, index_only: bool) -> Result<Vec<u8>, ClfError> {
+        match self {
+            Signer::Sig0(hasher) if !index_only => {
+                Ok([SIG_MAGIC.as_slice(), &hasher.finalize()].concat())
+            }
+            Signer::Sig0(hasher) | Signer::Sha256(hasher) => SignatureBlock {
+                algorithm: SIG_ALG_SHA256,
+                index_only,
+                key_fingerprint: Vec::new(),
+                signature: hasher.finalize().to_vec(),
+            }
+            .to_bytes(),
+            #[cfg(feature = "ed25519")]
+            Signer::Ed25519(hasher, key) => {
+                use ed25519_dalek::Signer as _;
+
+                SignatureBlock {
+                    algorithm: SIG_ALG_ED25519,
+                    index_only,
+                    key_fingerprint: key_fingerprint(key.verifying_key().as_bytes()).to_vec(),
+                    signature: key.sign(&hasher.finalize()).to_bytes().to_vec(),
+                }
+                .to_bytes()
+            }
+            Signer::Hmac(mac) => SignatureBlock {
+                algorithm: SIG_ALG_HMAC_SHA256,
+                index_only,
+                key_fingerprint: Vec::new(),
+                signature: mac.finalize().into_bytes().to_vec(),
+            }
+            .to_bytes(),
+        }
+    }
+}
+
+/// Like `pack_clf_with_summary`, but copies each blob from a `Read` source into the blob store in
+/// chunks, so memory use does not grow with blob size. The byte count actually read is recorded in
+/// the manifest. A placeholder manifest is written first and patched afterwards, which is
+/// why `out` must be seekable.
+///
+/// With `dedup_blobs`, each encoded blob is buffered until its source's SHA-256 shows whether it
+/// duplicates an earlier one, so duplicates never reach `out` (memory then grows with the
+/// largest blob, one blob at a time).
+/// With `compression`, every source is stored compressed (there is no raw fallback for
+/// incompressible data, since the source cannot be re-read). The `BlobSource` packers fall back
+/// to storing a source raw, as `pack_clf` does, when it can be read again
+/// (`BlobSource::can_reread`); such sources are buffered one at a time as well, so `out` never
+/// holds the discarded compressed copy.
+pub fn pack_clf_streaming<'a, W: Write + Seek>(
+    out: &mut W,
+    entries: Vec<(u32, Box<dyn Read + 'a>)>,
+    options: &PackOptions,
+) -> Result<PackSummary, PackError> {
+    let entries = entries
+        .into_iter()
+        .map(|(op_id, source)| (op_id, 0, source))
+        .collect();
+    pack_clf_streaming_variants(out, entries, options)
+}
+
+/// Like `pack_clf_streaming`, for `(op_id, variant, source)` triples (see `pack_clf_variants`).
+pub fn pack_clf_streaming_variants<'a, W: Write + Seek>(
+    out: &mut W,
+    entries: Vec<(u32, u16, Box<dyn Read + 'a>)>,
+    options: &PackOptions,
+) -> Result<PackSummary, PackError> {
+    let entries = entries
+        .into_iter()
+        .map(|(op_id, variant, source)| {
+            (op_id, variant, Box::new(source) as Box<dyn BlobSource + 'a>)
+        })
+        .collect();
+    pack_clf_variants_from_sources(out, entries, options)
+}
+
+/// Like `pack_clf_streaming`, with each blob coming from a `BlobSource` (a byte slice, a file
+/// path, a boxed `Read`, or a source of your own). A read error fails with
+/// `PackError::Source` naming the op_id it was feeding.
+pub fn pack_clf_from_sources<'a, W: Write + Seek>(
+    out: &mut W,
+    entries: Vec<(u32, Box<dyn BlobSource + 'a>)>,
+    options: &PackOptions,
+) -> Result<PackSummary, PackError> {
+    let entries = entries
+        .into_iter()
+        .map(|(op_id, source)| (op_id, 0, source))
+        .collect();
+    pack_clf_variants_from_sources(out, entries, options)
+}
+
+/// Like `pack_clf_from_sources`, for `(op_id, variant, source)` triples.
+pub fn pack_clf_variants_from_sources<'a, W: Write + Seek>(
+    out: &mut W,
+    entries: Vec<(u32, u16, Box<dyn BlobSource + 'a>)>,
+    options: &PackOptions,
+) -> Result<PackSummary, PackError> {
+    let mut keys = HashSet::with_capacity(entries.len());
+    for (op_id, variant, _) in &entries {
+        check_unique_entry(&mut keys, *op_id, *variant)?;
+        check_op_id(options, *op_id)?;
+    }
+    let align = effective_alignment(options);
+    let ManifestLayout {
+        entry_fields,
+        targets,
+    } = ManifestLayout::new(
+        options,
+        entries.iter().map(|(op_id, variant, _)| (*op_id, *variant)),
+    )?;
+    check_metadata(options)?;
+    check_build_info(options)?;
+    check_entry_names(options)?;
+    check_extensions(options)?;
+
+    let lead = leading_padding(options, entries.len(), entry_fields, &targets, align);
+    let index_start = out.stream_position()?;
+    let (index, manifest_at) =
+        index_placeholder(options, entries.len(), entry_fields, &targets, lead)?;
+    let size_limit = SizeLimit::new(options, index.len() - lead as usize);
+    size_limit.check(u64::from(lead))?;
+    out.write_all(&index)?;
+    let manifest_start = index_start + manifest_at as u64;
+    // Tracked rather than queried, so a buffered `out` is not flushed for every entry.
+    let mut pos = index_start + index.len() as u64;
+    let mut manifest: Vec<ManifestEntry> = Vec::with_capacity(entries.len());
+    let mut seen: HashMap<[u8; 32], usize> = HashMap::new();
+    let mut aliased: Vec<bool> = Vec::with_capacity(entries.len());
+    let mut offset: u32 = lead;
+    for (op_id, variant, mut source) in entries {
+        let declared_len = source.len();
+        if declared_len.is_some_and(|len| len > u64::from(u32::MAX)) {
+            return Err(PackError::BlobTooLarge(op_id));
+        }
+        // A raw source is stored as is, so its size is checked before any of it is copied. Encoded
+        // or possibly duplicate sources are checked once their stored size is known.
+        if let Some(len) = declared_len.filter(|_| stores_as_is(options)) {
+            size_limit.check(u64::from(offset) + len.next_multiple_of(u64::from(align)))?;
+        }
+        let target_index = target_index(options, &targets, op_id, variant);
+        let blob_start = pos;
+        let mut hasher = options.dedup_blobs.then(Sha256::new);
+        // Checksums cover the stored bytes, so they are taken after compression.
+        let mut stored = Crc32Writer {
+            inner: &mut *out,
+            crc: options.per_blob_crc.then(crc32fast::Hasher::new),
+            written: 0,
+        };
+        // AES-GCM seals a whole blob at once, a duplicate is only known once its source is read,
+        // and a blob that compression did not shrink is read a second time and stored raw (if its
+        // source allows), so those blobs are buffered: `out` never receives bytes that are not
+        // kept.
+        let (copied, flags, buffered) = if buffers_blob(options, source.can_reread()) {
+            let mut encoded = Vec::new();
+            let (copied, mut flags) =
+                encode_source(op_id, &mut *source, &mut encoded, hasher.as_mut(), options)?;
+            if grew(flags, encoded.len() as u64, copied) && source.can_reread() {
+                encoded.clear();
+                reread_raw(op_id, &mut *source, &mut encoded, copied)?;
+                flags = 0;
+            }
+            let (sealed, flags) = seal(options, Cow::Owned(encoded), flags)?;
+            (copied, flags, Some(sealed))
+        } else {
+            let (copied, flags) =
+                encode_source(op_id, &mut *source, &mut stored, hasher.as_mut(), options)?;
+            (copied, flags, None)
+        };
+        if let Some(declared) = declared_len.filter(|&len| len != copied) {
+            return Err(PackError::SourceLenMismatch {
+                op_id,
+                declared,
+                actual: copied,
+            });
+        }
+        if let Some(hasher) = hasher {
+            let digest: [u8; 32] = hasher.finalize().into();
+            if let Some(&first) = seen.get(&digest) {
+                manifest.push(ManifestEntry {
+                    op_id,
+                    variant,
+                    target_index,
+                    ..manifest[first]
+                });
+                aliased.push(true);
+                continue;
+            }
+            seen.insert(digest, manifest.len());
+        }
+        if let Some(blob) = buffered {
+            stored.write_all(&blob)?;
+        }
+        let stored_len = stored.written;
+        let crc32 = stored.crc.map(crc32fast::Hasher::finalize);
+        let (padded_size, next_offset) = place_blob(op_id, offset, stored_len, align)?;
+        size_limit.check(u64::from(next_offset))?;
+        write_padding(out, stored_len, align)?;
+        pos = blob_start + u64::from(padded_size);
+        manifest.push(ManifestEntry {
+            op_id,
+            variant,
+            offset,
+            size: padded_size,
+            exact_size: stored_len as u32,
+            flags,
+            raw_size: copied as u32,
+            crc32,
+            target_index,
+        });
+        aliased.push(false);
+        offset = next_offset;
+    }
+
+    let data_len = pos;
+    out.seek(SeekFrom::Start(manifest_start))?;
+    out.write_all(&manifest_bytes(&manifest, options.version, entry_fields)?)?;
+    out.seek(SeekFrom::Start(data_len))?;
+    Ok(PackSummary::new(
+        data_len, lead, &manifest, &aliased, options,
+    ))
+}
+
+/// Whether a blob of `raw_len` bytes came out of compression (`flags`) at `stored_len` bytes or
+/// more, so storing it raw would be smaller.
+fn grew(flags: u32, stored_len: u64, raw_len: u64) -> bool {
+    flags & ENTRY_FLAG_ZSTD != 0 && stored_len >= raw_len
+}
+
+/// Feed `source` into `sink` a second time, unencoded. Fails with `SourceLenMismatch` if it
+/// does not give the `first_len` bytes of the first read.
+fn reread_raw<S: Write>(
+    op_id: u32,
+    source: &mut dyn BlobSource,
+    sink: &mut S,
+    first_len: u64,
+) -> Result<(), PackError> {
+    let copied = feed_source(op_id, source, sink, None)?;
+    if copied != first_len {
+        return Err(PackError::SourceLenMismatch {
+            op_id,
+            declared: first_len,
+            actual: copied,
+        });
+    }
+    Ok(())
+}
+
+/// Pass-through writer that CRC-32s everything written (when enabled) and counts it.
+struct Crc32Writer<W> {
+    inner: W,
+    crc: Option<crc32fast::Hasher>,
+    written: u64,
+}
+
+impl<W: Write> Write for Crc32Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        if let Some(crc) = &mut self.crc {
+            crc.update(&buf[..n]);
+        }
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Input of the streaming packers: a blob that writes itself into the blob store once, and
+/// may know its length up front. Implemented for byte slices, file paths and boxed readers;
+/// implement it to feed blobs from a cache or a compiler without staging them in files.
+// `len` is an optional hint, not a container length, so there is no `is_empty` to go with it.
+#[allow(clippy::len_without_is_empty)]
+pub trait BlobSource {
+    /// Byte count `read_into` will write, if known before reading. A source declaring more
+    /// than u32::MAX bytes fails with `PackError::BlobTooLarge` before it is read, and one
+    /// that writes a different count fails with `PackError::SourceLenMismatch`.
+    fn len(&self) -> Option<u64>;
+
+    /// Write the blob to `w`; returns the number of bytes written.
+    fn read_into(&mut self, w: &mut dyn Write) -> std::io::Result<u64>;
+
+    /// Whether `read_into` may be called again and write the same bytes. The packer then
+    /// stores a blob that compression would grow uncompressed, at the cost of a second read
+    /// and of buffering the compressed blob.
+    fn can_reread(&self) -> bool {
+        false
+    }
+}
+
+impl BlobSource for &[u8] {
+    fn len(&self) -> Option<u64> {
+        Some(<[u8]>::len(self) as u64)
+    }
+
+    fn read_into(&mut self, w: &mut dyn Write) -> std::io::Result<u64> {
+        w.write_all(self)?;
+        Ok(<[u8]>::len(self) as u64)
+    }
+
+    fn can_reread(&self) -> bool {
+        true
+    }
+}
+
+/// The file at the path; read errors name it.
+impl BlobSource for PathBuf {
+    fn len(&self) -> Option<u64> {
+        std::fs::metadata(self).ok().map(|m| m.len())
+    }
+
+    fn read_into(&mut self, w: &mut dyn Write) -> std::io::Result<u64> {
+        let named =
+            |e: std::io::Error| std::io::Error::new(e.kind(), format!("{}: {e}", self.display()));
+        let mut file = File::open(&*self).map_err(named)?;
+        copy_chunked(&mut file, w).map_err(named)
+    }
+
+    fn can_reread(&self) -> bool {
+        true
+    }
+}
+
+impl BlobSource for Box<dyn Read + '_> {
+    fn len(&self) -> Option<u64> {
+        None
+    }
+
+    fn read_into(&mut self, w: &mut dyn Write) -> std::io::Result<u64> {
+        copy_chunked(self, w)
+    }
+}
+
+/// Copy `reader` to `w` in `STREAM_CHUNK` pieces; returns the byte count.
+fn copy_chunked(reader: &mut dyn Read, w: &mut dyn Write) -> std::io::Result<u64> {
+    let mut buf = vec![0u8; STREAM_CHUNK];
+    let mut copied: u64 = 0;
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => return Ok(copied),
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        w.write_all(&buf[..n])?;
+        copied += n as u64;
+    }
+}
+
+/// Let `source` write its blob into `sink` (feeding `hasher` if given); returns the byte count.
+/// Failures are told apart by where they happened: writing `sink` is `Io`, going past
+/// u32::MAX bytes is `BlobTooLarge`, anything else is the source's and becomes `Source`.
+fn feed_source<S: Write>(
+    op_id: u32,
+    source: &mut dyn BlobSource,
+    sink: &mut S,
+    hasher: Option<&mut Sha256>,
 ) -> Result<u64, PackError> {
-    let vendor_bytes = options.vendor.as_bytes();
-    if vendor_bytes.len() > u32::MAX as usize {
-        return Err(PackError::VendorTooLong);
+    let mut feed = SourceSink {
+        sink,
+        hasher,
+        copied: 0,
+        too_large: false,
+        sink_error: None,
+    };
+    let result = source.read_into(&mut feed);
+    if feed.too_large {
+        return Err(PackError::BlobTooLarge(op_id));
     }
-    let target_bytes = options.target.as_bytes();
-    if target_bytes.len() > u32::MAX as usize {
-        return Err(PackError::TargetTooLong);
+    if let Some(e) = feed.sink_error {
+        return Err(e.into());
+    }
+    result.map_err(|source| PackError::Source { op_id, source })?;
+    Ok(feed.copied)
+}
+
+/// Writer handed to `BlobSource::read_into` by `feed_source`. Keeps its own failures aside so
+/// they are not reported as the source's.
+struct SourceSink<'h, S> {
+    sink: S,
+    hasher: Option<&'h mut Sha256>,
+    copied: u64,
+    too_large: bool,
+    sink_error: Option<std::io::Error>,
+}
+
+impl<S: Write> Write for SourceSink<'_, S> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.copied + buf.len() as u64 > u64::from(u32::MAX) {
+            self.too_large = true;
+            return Err(std::io::Error::other("blob is larger than u32::MAX bytes"));
+        }
+        let n = match self.sink.write(buf) {
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => return Err(e),
+            Err(e) => {
+                let kind = e.kind();
+                self.sink_error = Some(e);
+                return Err(std::io::Error::new(kind, "writing the blob store failed"));
+            }
+        };
+        if let Some(hasher) = self.hasher.as_deref_mut() {
+            hasher.update(&buf[..n]);
+        }
+        self.copied += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.sink.flush()
+    }
+}
+
+/// Copy `source` into `sink`, compressed if `options` asks for it; returns the bytes read from
+/// the source and the entry flags.
+fn encode_source<S: Write>(
+    op_id: u32,
+    source: &mut dyn BlobSource,
+    sink: &mut S,
+    hasher: Option<&mut Sha256>,
+    options: &PackOptions,
+) -> Result<(u64, u32), PackError> {
+    match options.compression {
+        None => Ok((feed_source(op_id, source, sink, hasher)?, 0)),
+        #[cfg(feature = "compression")]
+        Some(Compression::Zstd(level)) => {
+            let mut encoder = zstd::stream::write::Encoder::new(sink, level)?;
+            let copied = feed_source(op_id, source, &mut encoder, hasher)?;
+            encoder.finish()?;
+            Ok((copied, ENTRY_FLAG_ZSTD))
+        }
+        #[cfg(not(feature = "compression"))]
+        Some(_) => unreachable!("entry_fields rejects compression without the feature"),
+    }
+}
+
+/// Whether `options` asks for encrypted blobs.
+fn encrypts(options: &PackOptions) -> bool {
+    #[cfg(feature = "encryption")]
+    return options.encrypt.is_some();
+    #[cfg(not(feature = "encryption"))]
+    {
+        let _ = options;
+        false
+    }
+}
+
+/// Whether the streaming packers buffer each encoded blob before writing it: AES-GCM seals a
+/// whole blob at once, dedup must see a blob's digest before it knows whether to keep it, and a
+/// compressed blob from a source that `can_reread` may be replaced by its raw bytes.
+fn buffers_blob(options: &PackOptions, can_reread: bool) -> bool {
+    encrypts(options) || options.dedup_blobs || (options.compression.is_some() && can_reread)
+}
+
+/// True if sources are stored byte for byte: no compression, encryption or deduplication.
+fn stores_as_is(options: &PackOptions) -> bool {
+    options.compression.is_none() && !encrypts(options) && !options.dedup_blobs
+}
+
+/// Encrypt the (possibly compressed) `stored` bytes with `options.encrypt`, adding
+/// `ENTRY_FLAG_AES256GCM` to `flags`; both are returned unchanged when no key is set.
+fn seal<'b>(
+    options: &PackOptions,
+    stored: Cow<'b, [u8]>,
+    flags: u32,
+) -> Result<(Cow<'b, [u8]>, u32), PackError> {
+    #[cfg(feature = "encryption")]
+    if let Some(key) = &options.encrypt {
+        return Ok((Cow::Owned(key.seal(&stored)?), flags | ENTRY_FLAG_AES256GCM));
+    }
+    let _ = options;
+    Ok((stored, flags))
+}
+
+/// Compress `blob` if requested and worthwhile; returns the bytes to store and the entry flags.
+/// Blobs that do not shrink are stored as-is (flags 0).
+fn encode_blob(
+    blob: &[u8],
+    compression: Option<Compression>,
+) -> Result<(Cow<'_, [u8]>, u32), PackError> {
+    match compression {
+        None => Ok((Cow::Borrowed(blob), 0)),
+        #[cfg(feature = "compression")]
+        Some(Compression::Zstd(level)) => {
+            let compressed = zstd::bulk::compress(blob, level)?;
+            if compressed.len() < blob.len() {
+                Ok((Cow::Owned(compressed), ENTRY_FLAG_ZSTD))
+            } else {
+                Ok((Cow::Borrowed(blob), 0))
+            }
+        }
+        #[cfg(not(feature = "compression"))]
+        Some(_) => Err(PackError::CompressionUnsupported),
     }
+}
 
-    // Check for duplicate op_ids.
-    let mut seen = std::collections::HashSet::new();
-    for (op_id, _) in entries {
-        if !seen.insert(*op_id) {
-            return Err(PackError::DuplicateOpId(*op_id));
+/// Optional v3 entry fields implied by `options`, whether any entry has a non-zero variant, and
+/// the target table.
+fn entry_fields(
+    options: &PackOptions,
+    has_variants: bool,
+    targets: &[&str],
+) -> Result<u32, PackError> {
+    let mut fields = 0;
+    if options.compression.is_some() {
+        if cfg!(not(feature = "compression")) {
+            return Err(PackError::CompressionUnsupported);
         }
+        require_v3(options, "compression", |f| f.has_flags)?;
+        fields |= ENTRY_FIELD_COMPRESSION;
+    }
+    if encrypts(options) {
+        require_v3(options, "encryption", |f| f.has_flags)?;
+        fields |= ENTRY_FIELD_COMPRESSION;
+    }
+    if options.per_blob_crc {
+        require_v3(options, "per-blob CRC-32", |f| f.has_flags)?;
+        fields |= ENTRY_FIELD_CRC32;
+    }
+    if has_variants {
+        require_v3(options, "blob variants", |f| f.has_flags)?;
+        fields |= ENTRY_FIELD_VARIANT;
+    }
+    if !targets.is_empty() {
+        require_v3(options, "per-entry targets", |f| f.has_flags)?;
+        fields |= ENTRY_FIELD_TARGET;
+    }
+    Ok(fields)
+}
+
+/// Distinct targets of the packed entries `keys` other than the header target, sorted; empty for
+/// single-target archives.
+fn target_table(
+    options: &PackOptions,
+    keys: impl Iterator<Item = (u32, u16)>,
+) -> Result<Vec<&str>, PackError> {
+    let targets: BTreeSet<&str> = keys
+        .filter_map(|key| options.entry_targets.get(&key))
+        .map(String::as_str)
+        .filter(|&target| target != options.target)
+        .collect();
+    if targets
+        .iter()
+        .any(|target| target.len() > MAX_HEADER_TEXT_LEN)
+    {
+        return Err(PackError::TargetTooLong);
     }
+    Ok(targets.into_iter().collect())
+}
+
+/// Manifest target index of (op_id, variant): 0 for the header target, else 1 + its position in
+/// the target table.
+fn target_index(options: &PackOptions, targets: &[&str], op_id: u32, variant: u16) -> u32 {
+    options
+        .entry_targets
+        .get(&(op_id, variant))
+        .and_then(|target| targets.binary_search(&target.as_str()).ok())
+        .map_or(0, |i| i as u32 + 1)
+}
+
+/// Fail with `RequiresV3` unless `options.version` has the format feature `has` checks.
+fn require_v3(
+    options: &PackOptions,
+    feature: &'static str,
+    has: fn(&VersionFeatures) -> bool,
+) -> Result<(), PackError> {
+    if !has(&VersionFeatures::for_version(options.version)) {
+        return Err(PackError::RequiresV3 {
+            feature,
+            version: options.version,
+        });
+    }
+    Ok(())
+}
 
-    let align = if options.blob_alignment > 1 {
-        options.blob_alignment as u32
+/// Chunk size for streaming blob copies and hashing.
+const STREAM_CHUNK: usize = 64 * 1024;
+
+/// Record (op_id, variant) in `seen`, failing if it was packed already.
+fn check_unique_entry(
+    seen: &mut HashSet<(u32, u16)>,
+    op_id: u32,
+    variant: u16,
+) -> Result<(), PackError> {
+    if !seen.insert((op_id, variant)) {
+        return Err(if variant == 0 {
+            PackError::DuplicateOpId(op_id)
+        } else {
+            PackError::DuplicateVariant { op_id, variant }
+        });
+    }
+    Ok(())
+}
+
+/// With `strict_op_ids`, reject canonical-range op_ids the registry does not assign.
+fn check_op_id(options: &PackOptions, op_id: u32) -> Result<(), PackError> {
+    if options.strict_op_ids && is_canonical_op_id(op_id) && !is_registered_op_id(op_id) {
+        return Err(PackError::UnregisteredOpId(op_id));
+    }
+    Ok(())
+}
+
+/// Alignment as a divisor: 0 and 1 both mean "no padding".
+fn effective_alignment(options: &PackOptions) -> u32 {
+    if options.blob_alignment > 1 {
+        u32::from(options.blob_alignment)
     } else {
         1
-    };
+    }
+}
+
+/// Zero bytes between the end of the manifest and the first blob, chosen so that the blob
+/// store's first blob starts at a file offset that is a multiple of `align`.
+fn leading_padding(
+    options: &PackOptions,
+    num_entries: usize,
+    entry_fields: u32,
+    targets: &[&str],
+    align: u32,
+) -> u32 {
+    let header_len = header_len(options);
+    let manifest_len = ManifestEntry::manifest_prefix_len(options.version)
+        + target_table_len(targets)
+        + num_entries * ManifestEntry::entry_size(options.version, entry_fields);
+    let store_start = (header_len + manifest_len) as u64;
+    (store_start.next_multiple_of(u64::from(align)) - store_start) as u32
+}
+
+/// Serialized header length for `options` (see `write_header`).
+fn header_len(options: &PackOptions) -> usize {
+    format::header_len(&header_for(options))
+}
+
+/// Reject metadata that cannot be written: requires v3, bounded key length, unique keys,
+/// bounded section size.
+fn check_metadata(options: &PackOptions) -> Result<(), PackError> {
+    if options.metadata.is_empty() {
+        return Ok(());
+    }
+    require_v3(options, "header metadata", |f| f.has_header_sections)?;
+    let mut seen = HashSet::with_capacity(options.metadata.len());
+    for (key, _) in &options.metadata {
+        if key.len() > MAX_META_KEY_LEN {
+            return Err(PackError::MetadataKeyTooLong(key.clone()));
+        }
+        if !seen.insert(key.as_str()) {
+            return Err(PackError::DuplicateMetadataKey(key.clone()));
+        }
+    }
+    if format::metadata_section_len(&options.metadata) > MAX_METADATA_LEN {
+        return Err(PackError::MetadataTooLarge);
+    }
+    Ok(())
+}
+
+/// `created_at` / `tool_version` to write: both `None` for deterministic packs.
+fn build_info(options: &PackOptions) -> (Option<u64>, Option<&str>) {
+    if options.deterministic {
+        return (None, None);
+    }
+    (options.created_at, options.tool_version.as_deref())
+}
+
+/// Reject build info that cannot be written: requires v3, bounded `tool_version` length.
+fn check_build_info(options: &PackOptions) -> Result<(), PackError> {
+    let (created_at, tool_version) = build_info(options);
+    if created_at.is_none() && tool_version.is_none() {
+        return Ok(());
+    }
+    require_v3(options, "build info", |f| f.has_header_sections)?;
+    if tool_version.is_some_and(|v| v.len() > usize::from(u16::MAX)) {
+        return Err(PackError::ToolVersionTooLong);
+    }
+    Ok(())
+}
+
+/// Reject entry names that cannot be written: requires v3, bounded name length and table size.
+fn check_entry_names(options: &PackOptions) -> Result<(), PackError> {
+    if options.entry_names.is_empty() {
+        return Ok(());
+    }
+    require_v3(options, "entry names", |f| f.has_header_sections)?;
+    if let Some((&op_id, _)) = options
+        .entry_names
+        .iter()
+        .filter(|(_, name)| name.len() > MAX_ENTRY_NAME_LEN)
+        .min_by_key(|(&op_id, _)| op_id)
+    {
+        return Err(PackError::EntryNameTooLong(op_id));
+    }
+    if format::name_table_len(options.entry_names.values()) > MAX_NAME_TABLE_LEN {
+        return Err(PackError::NameTableTooLarge);
+    }
+    Ok(())
+}
+
+/// Reject extension records that cannot be written: requires v3, unique tags, bounded size.
+fn check_extensions(options: &PackOptions) -> Result<(), PackError> {
+    if options.extensions.is_empty() {
+        return Ok(());
+    }
+    require_v3(options, "header extensions", |f| f.has_header_sections)?;
+    let mut seen = HashSet::with_capacity(options.extensions.len());
+    for (tag, _) in &options.extensions {
+        if !seen.insert(*tag) {
+            return Err(PackError::DuplicateExtension(*tag));
+        }
+    }
+    if format::extensions_len(&options.extensions) > MAX_EXTENSIONS_LEN {
+        return Err(PackError::ExtensionsTooLarge);
+    }
+    Ok(())
+}
+
+/// The header `options` describes. Build info is left out of deterministic packs, and entry
+/// names are kept in op_id order so output does not depend on map order.
+fn header_for(options: &PackOptions) -> ClfHeader {
+    let (created_at, tool_version) = build_info(options);
+    ClfHeader {
+        version: options.version,
+        vendor: options.vendor.clone(),
+        target: options.target.clone(),
+        blob_alignment: options.blob_alignment,
+        kind: options.kind,
+        metadata: options.metadata.clone(),
+        created_at,
+        tool_version: tool_version.map(str::to_string),
+        entry_names: options
+            .entry_names
+            .iter()
+            .map(|(&op_id, name)| (op_id, name.clone()))
+            .collect(),
+        extensions: options.extensions.clone(),
+        header_end: 0,
+    }
+}
+
+/// Write the header `options` describes through the shared `format::write_header`. Vendor and
+/// target are checked here so an oversized one gets its own error.
+fn write_header<W: Write>(out: &mut W, options: &PackOptions) -> Result<(), PackError> {
+    if options.vendor.len() > MAX_HEADER_TEXT_LEN {
+        return Err(PackError::VendorTooLong);
+    }
+    if options.target.len() > MAX_HEADER_TEXT_LEN {
+        return Err(PackError::TargetTooLong);
+    }
+    format::write_header(out, &header_for(options))?;
+    Ok(())
+}
 
-    // --- Header ---
-    out.write_all(&CLF_MAGIC)?;
-    out.write_all(&[options.version])?;
-    let vendor_len = vendor_bytes.len() as u32;
-    out.write_all(&vendor_len.to_le_bytes())?;
-    out.write_all(vendor_bytes)?;
-    let target_len = target_bytes.len() as u32;
-    out.write_all(&target_len.to_le_bytes())?;
-    if !target_bytes.is_empty() {
-        out.write_all(target_bytes)?;
-    }
-    out.write_all(&[options.blob_alignment])?;
-    // v2: kind byte (0 = Compute, 1 = MemoryMovement, 2 = MemoryProtection). v1: omitted.
-    if options.version >= 2 {
-        out.write_all(&[options.kind as u8])?;
-    }
-
-    // --- Manifest: num_entries (4 B) + entries (12 B each). Size = stored length in blob store (includes padding per SPEC). ---
-    let num_entries = entries.len() as u32;
+/// Serialized target table length: count, then per target 4 + its bytes. Zero (no table) when
+/// `targets` is empty.
+fn target_table_len(targets: &[&str]) -> usize {
+    if targets.is_empty() {
+        return 0;
+    }
+    4 + targets.iter().map(|target| 4 + target.len()).sum::<usize>()
+}
+
+/// Write the manifest prefix: entry count, plus the `entry_fields` mask for v3+ and the target
+/// table when there is one.
+fn write_manifest_prefix<W: Write>(
+    out: &mut W,
+    num_entries: u32,
+    version: u8,
+    entry_fields: u32,
+    targets: &[&str],
+) -> std::io::Result<()> {
     out.write_all(&num_entries.to_le_bytes())?;
+    if VersionFeatures::for_version(version).has_flags {
+        out.write_all(&entry_fields.to_le_bytes())?;
+    }
+    if !targets.is_empty() {
+        out.write_all(&(targets.len() as u32).to_le_bytes())?;
+        for target in targets {
+            out.write_all(&(target.len() as u32).to_le_bytes())?;
+            out.write_all(target.as_bytes())?;
+        }
+    }
+    Ok(())
+}
 
-    let mut offset: u32 = 0;
-    for (op_id, blob) in entries {
-        let unpadded = blob.len() as u32;
-        // Next multiple of `align` in the blob store (not `unpadded.div_ceil(align)`, which is ceil(unpadded/align)).
-        let padded_size = unpadded.next_multiple_of(align);
-        out.write_all(&op_id.to_le_bytes())?;
-        out.write_all(&offset.to_le_bytes())?;
-        out.write_all(&padded_size.to_le_bytes())?;
-        offset = offset.saturating_add(padded_size);
-    }
-
-    // --- Blob store: each blob padded to blob_alignment (or raw if 0). ---
-    for (_, blob) in entries {
-        out.write_all(blob)?;
-        if options.blob_alignment > 1 {
-            let remainder = blob.len() % (options.blob_alignment as usize);
-            if remainder != 0 {
-                let pad = (options.blob_alignment as usize) - remainder;
-                out.write_all(&vec![0u8; pad])?;
-            }
+/// Write one manifest entry in the layout of `version` (exact size only for v3+) and `entry_fields`.
+fn write_manifest_entry<W: Write>(
+    out: &mut W,
+    entry: &ManifestEntry,
+    version: u8,
+    entry_fields: u32,
+) -> std::io::Result<()> {
+    out.write_all(&entry.op_id.to_le_bytes())?;
+    out.write_all(&entry.offset.to_le_bytes())?;
+    out.write_all(&entry.size.to_le_bytes())?;
+    if VersionFeatures::for_version(version).has_exact_size {
+        out.write_all(&entry.exact_size.to_le_bytes())?;
+    }
+    if entry_fields & ENTRY_FIELD_COMPRESSION != 0 {
+        out.write_all(&entry.flags.to_le_bytes())?;
+        out.write_all(&entry.raw_size.to_le_bytes())?;
+    }
+    if entry_fields & ENTRY_FIELD_CRC32 != 0 {
+        out.write_all(&entry.crc32.unwrap_or(0).to_le_bytes())?;
+    }
+    if entry_fields & ENTRY_FIELD_VARIANT != 0 {
+        out.write_all(&entry.variant.to_le_bytes())?;
+        out.write_all(&[0, 0])?;
+    }
+    if entry_fields & ENTRY_FIELD_TARGET != 0 {
+        out.write_all(&entry.target_index.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Header, manifest prefix, zeroed manifest entries and `lead` bytes of alignment fill, built in
+/// memory so they reach the output in one write instead of a few per entry. Returns the bytes
+/// and where the manifest entries start in them (for patching once blob offsets are known).
+fn index_placeholder(
+    options: &PackOptions,
+    num_entries: usize,
+    entry_fields: u32,
+    targets: &[&str],
+    lead: u32,
+) -> Result<(Vec<u8>, usize), PackError> {
+    let mut index = Vec::new();
+    write_header(&mut index, options)?;
+    write_manifest_prefix(
+        &mut index,
+        num_entries as u32,
+        options.version,
+        entry_fields,
+        targets,
+    )?;
+    let manifest_at = index.len();
+    let manifest_len = num_entries * ManifestEntry::entry_size(options.version, entry_fields);
+    index.resize(manifest_at + manifest_len + lead as usize, 0);
+    Ok((index, manifest_at))
+}
+
+/// Serialized manifest entries, so the manifest is patched with a single write.
+pub(crate) fn manifest_bytes(
+    entries: &[ManifestEntry],
+    version: u8,
+    entry_fields: u32,
+) -> std::io::Result<Vec<u8>> {
+    let mut bytes =
+        Vec::with_capacity(entries.len() * ManifestEntry::entry_size(version, entry_fields));
+    for entry in entries {
+        write_manifest_entry(&mut bytes, entry, version, entry_fields)?;
+    }
+    Ok(bytes)
+}
+
+/// Place a blob of `stored_len` bytes at blob-store `offset`: returns its padded size and the
+/// offset of the next blob. Errors if the blob or the blob store outgrows the u32 manifest fields.
+fn place_blob(
+    op_id: u32,
+    offset: u32,
+    stored_len: u64,
+    align: u32,
+) -> Result<(u32, u32), PackError> {
+    if stored_len > u64::from(u32::MAX) {
+        return Err(PackError::BlobTooLarge(op_id));
+    }
+    // Next multiple of `align` in the blob store (not `div_ceil`, which is ceil(len/align)).
+    let padded = stored_len.next_multiple_of(u64::from(align));
+    let total = u64::from(offset) + padded;
+    let next_offset = u32::try_from(total).map_err(|_| PackError::BlobStoreTooLarge {
+        at_op_id: op_id,
+        total,
+    })?;
+    Ok((padded as u32, next_offset))
+}
+
+/// `PackOptions::max_total_size` for one pack: the bytes outside the blob store (header,
+/// manifest and the requested trailer) are fixed, so only the blob store length varies.
+#[derive(Debug, Clone, Copy)]
+struct SizeLimit {
+    limit: Option<u64>,
+    fixed: u64,
+}
+
+impl SizeLimit {
+    /// Limit for an archive whose header and manifest take `index_len` bytes.
+    fn new(options: &PackOptions, index_len: usize) -> Self {
+        Self {
+            limit: options.max_total_size,
+            fixed: index_len as u64 + trailer_len(options),
         }
     }
 
-    let data_len = out.stream_position()?;
-    Ok(data_len)
+    /// `SizeLimitExceeded` if a blob store of `blob_store_len` bytes takes the archive over the
+    /// limit.
+    fn check(&self, blob_store_len: u64) -> Result<(), PackError> {
+        let needed = self.fixed + blob_store_len;
+        match self.limit {
+            Some(limit) if needed > limit => Err(PackError::SizeLimitExceeded { limit, needed }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Length of the signature trailer `options` requests (see `Signer::requested_by`), 0 if none.
+fn trailer_len(options: &PackOptions) -> u64 {
+    let sig1 = |fingerprint_len: usize, signature_len: usize| {
+        (SIG1_MIN_LEN + fingerprint_len + signature_len) as u64
+    };
+    #[cfg(feature = "ed25519")]
+    if options.sign_key.is_some() {
+        return sig1(SIG_KEY_FINGERPRINT_LEN, ed25519_dalek::SIGNATURE_LENGTH);
+    }
+    if options.hmac_key.is_some() || (options.sign && options.sign_index_only) {
+        sig1(0, SIG_HASH_LEN)
+    } else if options.sign {
+        SIG_BLOCK_LEN as u64
+    } else {
+        0
+    }
+}
+
+/// Zero-fill after a blob of `len` bytes up to the next multiple of `align`.
+fn write_padding<W: Write>(out: &mut W, len: u64, align: u32) -> std::io::Result<()> {
+    let pad = (len.next_multiple_of(u64::from(align)) - len) as usize;
+    if pad != 0 {
+        out.write_all(&vec![0u8; pad])?;
+    }
+    Ok(())
+}
+
+/// Pack to `path` atomically: write a sibling temp file, append the signature requested by `options`,
+/// fsync, then rename over `path`. On any error the temp file is removed and an existing file at
+/// `path` is left untouched.
+pub fn pack_clf_to_path<P, I>(
+    path: P,
+    entries: I,
+    options: &PackOptions,
+) -> Result<PackSummary, PackError>
+where
+    P: AsRef<Path>,
+    I: IntoIterator,
+    I::IntoIter: ExactSizeIterator,
+    I::Item: Borrow<(u32, Vec<u8>)>,
+{
+    write_atomically(path.as_ref(), options, |file| {
+        pack_clf_with_summary(file, entries, options)
+    })
+}
+
+/// Streaming variant of `pack_clf_to_path` (see `pack_clf_streaming`).
+pub fn pack_clf_streaming_to_path<'a, P: AsRef<Path>>(
+    path: P,
+    entries: Vec<(u32, Box<dyn Read + 'a>)>,
+    options: &PackOptions,
+) -> Result<PackSummary, PackError> {
+    write_atomically(path.as_ref(), options, |file| {
+        pack_clf_streaming(file, entries, options)
+    })
+}
+
+/// `pack_clf_to_path` with blobs from `BlobSource`s (see `pack_clf_from_sources`).
+pub fn pack_clf_from_sources_to_path<'a, P: AsRef<Path>>(
+    path: P,
+    entries: Vec<(u32, Box<dyn BlobSource + 'a>)>,
+    options: &PackOptions,
+) -> Result<PackSummary, PackError> {
+    write_atomically(path.as_ref(), options, |file| {
+        pack_clf_from_sources(file, entries, options)
+    })
+}
+
+/// Run `pack` against a fresh temp file next to `path`, then move it into place. Generic over
+/// the error so callers with their own error type (edit, merge) can run fallible reads in `pack`.
+/// `pack` writes through a `BufWriter`, flushed before the signature re-reads the file.
+pub(crate) fn write_atomically<E: From<PackError>>(
+    path: &Path,
+    options: &PackOptions,
+    pack: impl FnOnce(&mut BufWriter<File>) -> Result<PackSummary, E>,
+) -> Result<PackSummary, E> {
+    write_atomically_verified(path, options, pack, |_| Ok(()))
+}
+
+/// `write_atomically`, with `verify` run on the finished temp file (signature included) before
+/// it replaces `path`.
+pub(crate) fn write_atomically_verified<E: From<PackError>>(
+    path: &Path,
+    options: &PackOptions,
+    pack: impl FnOnce(&mut BufWriter<File>) -> Result<PackSummary, E>,
+    verify: impl FnOnce(&Path) -> Result<(), E>,
+) -> Result<PackSummary, E> {
+    let (tmp_path, file) = create_temp_sibling(path).map_err(PackError::from)?;
+    let result = (|| {
+        let mut out = BufWriter::new(file);
+        let summary = pack(&mut out)?;
+        let mut file = out
+            .into_inner()
+            .map_err(|e| PackError::from(e.into_error()))?;
+        // The temp file is fresh, but `pack` may have written past the archive it reports.
+        file.set_len(summary.data_len).map_err(PackError::from)?;
+        append_requested_signature(&mut file, summary.data_len, options)?;
+        file.sync_all().map_err(PackError::from)?;
+        drop(file);
+        verify(&tmp_path)?;
+        replace_file(&tmp_path, path).map_err(PackError::from)?;
+        Ok(summary)
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+/// Create `.<name>.<pid>.<n>.tmp` in the directory of `path` (same filesystem, so rename is atomic).
+fn create_temp_sibling(path: &Path) -> std::io::Result<(PathBuf, File)> {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+    let name = path.file_name().ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "output path has no file name",
+        )
+    })?;
+    let dir = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    loop {
+        let mut tmp_name = OsString::from(".");
+        tmp_name.push(name);
+        tmp_name.push(format!(
+            ".{}.{}.tmp",
+            process_id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let tmp_path = dir.join(tmp_name);
+        match OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&tmp_path)
+        {
+            Ok(file) => return Ok((tmp_path, file)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Id of this process for temporary file names. Wasm has no process ids (`std::process::id`
+/// panics there), and path APIs fail anyway, so any constant does.
+fn process_id() -> u32 {
+    if cfg!(target_family = "wasm") {
+        0
+    } else {
+        std::process::id()
+    }
+}
+
+/// Rename `from` over `to`. `std::fs::rename` replaces an existing file on both Unix and Windows
+/// (MoveFileExW with MOVEFILE_REPLACE_EXISTING); on Windows it can still fail with access denied
+/// when the destination is read-only or being replaced concurrently, so retry once after removing it.
+fn replace_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    match std::fs::rename(from, to) {
+        Ok(()) => {}
+        #[cfg(windows)]
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied && to.exists() => {
+            std::fs::remove_file(to)?;
+            std::fs::rename(from, to)?;
+        }
+        Err(e) => return Err(e),
+    }
+    // Persist the rename itself (directory entry) where the platform allows it.
+    #[cfg(unix)]
+    if let Some(dir) = to.parent().filter(|p| !p.as_os_str().is_empty()) {
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Remove a trailing signature block (SIG0 or SIG1) from the archive at `path`, e.g. after
+/// modifying it. Returns whether anything was removed; unsigned files are left untouched.
+///
+/// The trailer is only stripped when the bytes before it end exactly where the manifest says the
+/// blob store ends, so an unsigned file whose last blob happens to end in a signature magic is
+/// never truncated.
+pub fn strip_signature<P: AsRef<Path>>(path: P) -> Result<bool, PackError> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let file_len = file.metadata()?.len();
+    let (data_len, trailer) = locate_signature(&mut file, file_len)?;
+    if trailer == Trailer::None {
+        return Ok(false);
+    }
+    file.set_len(data_len)?;
+    file.sync_all()?;
+    Ok(true)
+}
+
+/// Re-sign a modified archive in one call: drop any existing SIG0 block, hash everything before
+/// it, and write a fresh SIG0 block. Safe to call repeatedly. An unkeyed SHA-256 SIG1 block is
+/// replaced in kind (same scope, same length). The stream cannot be truncated, so an archive
+/// carrying a keyed SIG1 block (HMAC or Ed25519) is rejected with
+/// `PackError::KeyedSignaturePresent` (strip it with `strip_signature` and sign again with the key).
+pub fn resign<F: Read + Write + Seek>(file: &mut F) -> Result<(), PackError> {
+    let file_len = file.seek(SeekFrom::End(0))?;
+    let (data_len, trailer) = locate_signature(file, file_len)?;
+    if let Trailer::Sig1(len) = trailer {
+        let mut block = vec![0u8; len as usize];
+        file.seek(SeekFrom::Start(data_len))?;
+        file.read_exact(&mut block)?;
+        let block = SignatureBlock::parse(&block).ok_or(ClfError::SignatureInvalid)?;
+        if block.algorithm != SIG_ALG_SHA256 {
+            return Err(PackError::KeyedSignaturePresent);
+        }
+        let covered = if block.index_only {
+            index_len(file, data_len)?
+        } else {
+            data_len
+        };
+        let mut hasher = Sha256::new();
+        for_each_prefix_chunk(file, covered, |chunk| hasher.update(chunk))?;
+        let fresh = Signer::Sha256(hasher).finish(block.index_only)?;
+        // Only a block this crate would not write (e.g. with a fingerprint) differs in length.
+        if fresh.len() as u64 != len {
+            return Err(ClfError::SignatureInvalid.into());
+        }
+        file.seek(SeekFrom::Start(data_len))?;
+        file.write_all(&fresh)?;
+        return Ok(());
+    }
+    let hash = sha256_prefix(file, data_len)?;
+    file.seek(SeekFrom::Start(data_len))?;
+    file.write_all(&SIG_MAGIC)?;
+    file.write_all(&hash)?;
+    Ok(())
+}
+
+/// Append the signature `options` asks for: an Ed25519 SIG1 block when `sign_key` is set, else
+/// an HMAC-SHA256 SIG1 block when `hmac_key` is set, else SIG0 when `sign` is true, else nothing.
+pub(crate) fn append_requested_signature<W: Read + Write + Seek>(
+    out: &mut W,
+    data_len: u64,
+    options: &PackOptions,
+) -> Result<(), PackError> {
+    match Signer::requested_by(options) {
+        Some(signer) => append_trailer(out, data_len, signer, options.sign_index_only),
+        None => Ok(()),
+    }
+}
+
+/// Feed the first `data_len` bytes of `out` (only its header and manifest with `index_only`) to
+/// `signer`, then append its trailer at the end. Refuses to stack a second trailer on one that
+/// already follows the data, and to sign a stream holding other bytes past `data_len`.
+fn append_trailer<W: Read + Write + Seek>(
+    out: &mut W,
+    data_len: u64,
+    mut signer: Signer,
+    index_only: bool,
+) -> Result<(), PackError> {
+    let file_len = out.seek(SeekFrom::End(0))?;
+    let existing = find_trailer(file_len, data_len, |buf, offset| {
+        out.seek(SeekFrom::Start(offset))?;
+        out.read_exact(buf)
+    });
+    if existing != Trailer::None {
+        return Err(PackError::AlreadySigned);
+    }
+    if file_len > data_len {
+        return Err(PackError::TrailingData {
+            expected: data_len,
+            got: file_len,
+        });
+    }
+    let covered = if index_only {
+        index_len(out, data_len)?
+    } else {
+        data_len
+    };
+    for_each_prefix_chunk(out, covered, |chunk| signer.update(chunk))?;
+    out.seek(SeekFrom::Start(data_len))?;
+    out.write_all(&signer.finish(index_only)?)?;
+    Ok(())
+}
+
+/// Length of the header and manifest of the archive in `out` (where its blob store starts).
+fn index_len<R: Read + Seek>(out: &mut R, data_len: u64) -> Result<u64, PackError> {
+    out.seek(SeekFrom::Start(0))?;
+    let header = read_header_with(out, true, &ClfLimits::FORMAT_MAX)?;
+    let (_, _, manifest_len) = read_manifest(
+        &mut FieldReader::at(out, header.header_end),
+        header.version,
+        data_len.saturating_sub(header.header_end),
+        &ClfLimits::FORMAT_MAX,
+    )?;
+    Ok(header.header_end + manifest_len)
 }
 
 /// Append signature block (SIG0 + SHA-256) to the end of an open file. Call after pack_clf when options.sign is true.
-/// `data_len` must be the number of bytes written so far (header + manifest + blob store).
+/// `data_len` must be the number of bytes written so far (header + manifest + blob store); a
+/// shorter stream fails with `PackError::TruncatedData`, one whose data is already followed by
+/// a signature block with `PackError::AlreadySigned` (use `resign` to replace it), and one with
+/// other bytes past `data_len` with `PackError::TrailingData`.
 /// The file must support Read, Write, and Seek.
 pub fn append_signature<W: Read + Write + Seek>(
     out: &mut W,
     data_len: u64,
 ) -> Result<(), PackError> {
-    out.seek(std::io::SeekFrom::Start(0))?;
+    append_trailer(out, data_len, Signer::Sig0(Sha256::new()), false)
+}
+
+/// Algorithm and key for `append_signature_with`. `Debug` does not print key material.
+#[derive(Clone, Copy)]
+pub enum SignWith<'k> {
+    /// Unkeyed SHA-256 digest in a SIG1 block (integrity only).
+    Sha256,
+    /// HMAC-SHA256 under this shared secret.
+    HmacSha256(&'k [u8]),
+    /// Ed25519 signature with this key.
+    #[cfg(feature = "ed25519")]
+    Ed25519(&'k ed25519_dalek::SigningKey),
+}
+
+impl SignWith<'_> {
+    /// The algorithm id the block records.
+    #[must_use]
+    pub fn algorithm(&self) -> SignatureAlgorithm {
+        match self {
+            SignWith::Sha256 => SignatureAlgorithm::Sha256,
+            SignWith::HmacSha256(_) => SignatureAlgorithm::HmacSha256,
+            #[cfg(feature = "ed25519")]
+            SignWith::Ed25519(_) => SignatureAlgorithm::Ed25519,
+        }
+    }
+}
+
+impl std::fmt::Debug for SignWith<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SignWith({})", self.algorithm())
+    }
+}
+
+/// Append a SIG1 block using the chosen algorithm: the general form of `append_signature_hmac`
+/// and `append_signature_ed25519`, which also writes unkeyed SHA-256 blocks. `scope` picks the
+/// covered bytes as in `append_signature_scoped`; `SignatureScope::None` appends nothing.
+pub fn append_signature_with<W: Read + Write + Seek>(
+    out: &mut W,
+    data_len: u64,
+    sign: SignWith<'_>,
+    scope: SignatureScope,
+) -> Result<(), PackError> {
+    match scope {
+        SignatureScope::None => Ok(()),
+        SignatureScope::IndexOnly => append_trailer(out, data_len, Signer::with(sign), true),
+        SignatureScope::Full => append_trailer(out, data_len, Signer::with(sign), false),
+    }
+}
+
+/// Like `append_signature`, choosing what the hash covers: `Full` appends SIG0 over everything
+/// before the block, `IndexOnly` a SHA-256 SIG1 block over the header and manifest only (see
+/// `PackOptions::sign_index_only`), and `None` appends nothing.
+pub fn append_signature_scoped<W: Read + Write + Seek>(
+    out: &mut W,
+    data_len: u64,
+    scope: SignatureScope,
+) -> Result<(), PackError> {
+    match scope {
+        SignatureScope::None => Ok(()),
+        SignatureScope::IndexOnly => {
+            append_trailer(out, data_len, Signer::Sha256(Sha256::new()), true)
+        }
+        SignatureScope::Full => append_signature(out, data_len),
+    }
+}
+
+/// Append an Ed25519 SIG1 block to the end of an open file: the signature covers the SHA-256 of
+/// the first `data_len` bytes and the block records the key fingerprint (see SPEC.md §3.4).
+/// Verify with `ClfReader::verify_signature_with`.
+#[cfg(feature = "ed25519")]
+pub fn append_signature_ed25519<W: Read + Write + Seek>(
+    out: &mut W,
+    data_len: u64,
+    key: &ed25519_dalek::SigningKey,
+) -> Result<(), PackError> {
+    append_trailer(
+        out,
+        data_len,
+        Signer::Ed25519(Sha256::new(), key.clone()),
+        false,
+    )
+}
+
+/// Append an HMAC-SHA256 SIG1 block to the end of an open file: the MAC covers the first
+/// `data_len` bytes under the shared secret `key` (see SPEC.md §3.4). The block carries no key
+/// fingerprint. Verify with `ClfReader::verify_signature_hmac`.
+pub fn append_signature_hmac<W: Read + Write + Seek>(
+    out: &mut W,
+    data_len: u64,
+    key: &[u8],
+) -> Result<(), PackError> {
+    append_trailer(out, data_len, Signer::hmac(key), false)
+}
+
+/// SHA-256 of the first `data_len` bytes of `out`.
+fn sha256_prefix<R: Read + Seek>(out: &mut R, data_len: u64) -> Result<Vec<u8>, PackError> {
     let mut hasher = Sha256::new();
-    let mut to_read = data_len as usize;
+    for_each_prefix_chunk(out, data_len, |chunk| hasher.update(chunk))?;
+    Ok(hasher.finalize().to_vec())
+}
+
+/// Feed the first `data_len` bytes of `out` to `f` in chunks. Fails with
+/// `PackError::TruncatedData` if `out` ends first.
+fn for_each_prefix_chunk<R: Read + Seek>(
+    out: &mut R,
+    data_len: u64,
+    mut f: impl FnMut(&[u8]),
+) -> Result<(), PackError> {
+    out.seek(std::io::SeekFrom::Start(0))?;
+    let mut done = 0u64;
     let mut buf = [0u8; 4096];
-    while to_read > 0 {
-        let n = to_read.min(buf.len());
-        let got = out.read(&mut buf[..n])?;
-        if got == 0 {
-            break;
-        }
-        hasher.update(&buf[..got]);
-        to_read -= got;
+    while done < data_len {
+        let n = (data_len - done).min(buf.len() as u64) as usize;
+        let got = match out.read(&mut buf[..n]) {
+            Ok(0) => {
+                return Err(PackError::TruncatedData {
+                    expected: data_len,
+                    got: done,
+                })
+            }
+            Ok(got) => got,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        f(&buf[..got]);
+        done += got as u64;
     }
-    let hash = hasher.finalize();
-    out.seek(std::io::SeekFrom::End(0))?;
-    out.write_all(&SIG_MAGIC)?;
-    out.write_all(&hash)?;
     Ok(())
 }
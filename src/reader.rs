@@ -2,22 +2,36 @@
 //!
 //! Does not interpret blob contents. Optional signature verification before use.
 //! When building a code section from a list of op_ids, use `build_code_section` with
-//! a `MissingOpIdPolicy`: **Fail** (default) if any op_id is missing, **Skip** to allow partial code.
+//! a `MissingOpIdPolicy`: **Fail** (default) if any op_id is missing, **Skip** to allow partial code,
+//! **Collect** to fail with every missing op_id at once; `build_code_section_report` builds what
+//! is present and returns the missing list alongside.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashSet};
 use std::fs::File;
-use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
-use std::path::Path;
+use std::io::{BufReader, Cursor, Read, Seek};
+use std::ops::{ControlFlow, Range};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock, PoisonError};
 
+use hmac::{Hmac, KeyInit, Mac};
 use sha2::{Digest, Sha256};
-use thiserror::Error;
 
+#[cfg(feature = "encryption")]
+use crate::encryption::EncryptionKey;
+pub use crate::error::ClfError;
 use crate::format::{
-    ClfHeader, ClfKind, ManifestEntry, CLF_MAGIC, CLF_VERSION, SIG_BLOCK_LEN, SIG_HASH_LEN,
-    SIG_MAGIC,
+    constant_time_eq, read_header_with, ClfHeader, ClfKind, ClfLimits, FieldReader, ManifestEntry,
+    SignatureAlgorithm, SignatureBlock, SignatureScope, TargetMatcher, ENTRY_FLAG_AES256GCM,
+    ENTRY_FLAG_ZSTD, SIG1_MAGIC, SIG1_MIN_LEN, SIG_ALG_HMAC_SHA256, SIG_ALG_SHA256, SIG_BLOCK_LEN,
+    SIG_HASH_LEN, SIG_MAGIC,
 };
-
-const MAX_HEADER_TEXT_LEN: usize = 64 * 1024;
+#[cfg(feature = "ed25519")]
+use crate::format::{key_fingerprint, SIG_ALG_ED25519};
+use crate::op_registry::{try_op_type_to_clf_id, OpRegistry, OpType};
+use crate::slice::{declared_store_len, read_manifest, validate_manifest, Manifest};
+#[cfg(feature = "ed25519")]
+use crate::trust::{TrustedKeys, VerifiedBy};
+use crate::view::ManifestView;
 
 /// Policy when an op_id required by the model is not present in the CLF.
 /// The packager can choose: fail (strict), skip (partial code), or eventually fall back to another backend.
@@ -27,6 +41,199 @@ pub enum MissingOpIdPolicy {
     Fail,
     /// If an op_id is missing, skip it (append nothing). Use for partial code or stubs.
     Skip,
+    /// If any op_id is missing, return `ClfError::MissingOpIds` listing all of them (request
+    /// order, duplicates removed) instead of only the first. No blob is read in that case. Use
+    /// to fix coverage gaps in one pass; `build_code_section_report` also returns the section.
+    Collect,
+}
+
+/// Progress of `ClfReader::build_code_section_with_progress`, reported once per op_id.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProgressEvent {
+    /// Op_ids handled so far (appended or skipped).
+    pub ops_completed: usize,
+    /// Number of op_ids requested.
+    pub ops_total: usize,
+    /// Bytes in the code section so far.
+    pub bytes_written: u64,
+}
+
+/// What `ClfReader::build_code_section` will produce, worked out from the manifest alone: the
+/// entry chosen for each requested op_id and the section length, so callers can size downstream
+/// buffers before any blob is read.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeSectionPlan {
+    /// Requested op_ids in order, each with the entry whose blob is appended (`None` when the op
+    /// is skipped under `MissingOpIdPolicy::Skip`).
+    pub ops: Vec<(u32, Option<ManifestEntry>)>,
+    /// Length of the built section: the blob sizes of the chosen entries (`raw_size` when
+    /// encoded, `exact_size` otherwise).
+    pub total_len: u64,
+}
+
+/// Where a blob's bytes lie in the archive file (`ClfReader::blob_location`), for callers that
+/// map the file themselves and hand out (file, offset, length) ranges instead of copying.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlobLocation {
+    /// Offset of the blob's first byte from the start of the file.
+    pub file_offset: u64,
+    /// Length of the blob, without alignment padding (`exact_size`).
+    pub size: u32,
+    /// Whether `file_offset` is a multiple of the header's `blob_alignment` (always true when
+    /// the archive is unaligned).
+    pub aligned: bool,
+}
+
+/// Code section built by `ClfReader::build_code_section_report`: the blobs of the present ops,
+/// where each one landed, and every requested op_id the archive lacks.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeSectionResult {
+    /// Blobs of the present op_ids, concatenated in request order.
+    pub bytes: Vec<u8>,
+    /// Requested op_ids without an entry, in request order with duplicates removed. Empty when
+    /// the archive covers the request.
+    pub missing: Vec<u32>,
+    /// Each appended op_id (in request order, repeats included) with the range of its blob in
+    /// `bytes`.
+    pub layout: Vec<(u32, Range<usize>)>,
+}
+
+/// Code section built by `ClfReader::build_unique_code_section`: each distinct op's blob once,
+/// and where it is.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UniqueCodeSection {
+    /// Blob of each distinct present op_id, in order of first request.
+    pub bytes: Vec<u8>,
+    /// Each op_id in `bytes` with the range of its single copy; `start` is the offset every call
+    /// site of that op points at. Op_ids skipped as missing are absent.
+    pub blobs: BTreeMap<u32, Range<usize>>,
+}
+
+/// How `ClfReader::build_code_section_with_order` reads the blobs of a code section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReadOrder {
+    /// One read per op, in the requested order.
+    Requested,
+    /// Read the needed blobs in ascending file offset, merging ranges less than 64 KiB apart
+    /// into one read of up to 4 MiB, then place each blob at its position in the section. Kinder
+    /// to spinning disks and network mounts when the requested order jumps around the file.
+    FileOrder,
+    /// `FileOrder` when reading in the requested order would seek backwards more than 1 MiB in
+    /// total, `Requested` otherwise.
+    #[default]
+    Auto,
+}
+
+/// Ranges at most this far apart are read together by `ReadOrder::FileOrder`.
+const COALESCE_GAP: u64 = 64 * 1024;
+/// Longest single read `ReadOrder::FileOrder` makes when merging ranges (one larger blob is
+/// still read whole).
+const MAX_COALESCED_READ: u64 = 4 * 1024 * 1024;
+/// Backward seeking above which `ReadOrder::Auto` reads in file order.
+const AUTO_FILE_ORDER_SEEK_BACK: u64 = 1024 * 1024;
+
+impl ReadOrder {
+    /// Whether to read `plan` in file order.
+    fn file_order_for(self, plan: &CodeSectionPlan) -> bool {
+        match self {
+            ReadOrder::Requested => false,
+            ReadOrder::FileOrder => true,
+            ReadOrder::Auto => {
+                let mut seek_back = 0;
+                let mut pos: u64 = 0;
+                for entry in plan.ops.iter().filter_map(|(_, entry)| *entry) {
+                    seek_back += pos.saturating_sub(u64::from(entry.offset));
+                    pos = u64::from(entry.offset) + u64::from(entry.exact_size);
+                }
+                seek_back > AUTO_FILE_ORDER_SEEK_BACK
+            }
+        }
+    }
+}
+
+/// Pick the entry for each of `op_ids` (the variant `select` names), missing ones as `None`.
+fn code_section_plan(
+    manifest: &Manifest,
+    op_ids: &[u32],
+    mut select: impl FnMut(u32, &[u16]) -> u16,
+) -> CodeSectionPlan {
+    let mut variants: Vec<u16> = Vec::new();
+    let ops: Vec<(u32, Option<ManifestEntry>)> = op_ids
+        .iter()
+        .map(|&op_id| {
+            variants.clear();
+            variants.extend(variants_of(manifest, op_id).map(|e| e.variant));
+            let variant = select(op_id, &variants);
+            (op_id, manifest.get(&(op_id, variant)).copied())
+        })
+        .collect();
+    let total_len = ops
+        .iter()
+        .filter_map(|(_, entry)| entry.map(|e| u64::from(blob_len(&e))))
+        .sum();
+    CodeSectionPlan { ops, total_len }
+}
+
+/// Length of the blob `get_blob` returns for `entry`: decoded size if encoded, else exact size.
+fn blob_len(entry: &ManifestEntry) -> u32 {
+    if entry.flags == 0 {
+        entry.exact_size
+    } else {
+        entry.raw_size
+    }
+}
+
+/// Each op of `plan` with an entry, with the range its blob takes in the built section.
+fn layout_of(plan: &CodeSectionPlan) -> Vec<(u32, Range<usize>)> {
+    let mut layout = Vec::with_capacity(plan.ops.len());
+    let mut at = 0;
+    for &(op_id, entry) in &plan.ops {
+        if let Some(entry) = entry {
+            let len = blob_len(&entry) as usize;
+            layout.push((op_id, at..at + len));
+            at += len;
+        }
+    }
+    layout
+}
+
+/// Op_ids of `plan` without an entry, in request order with duplicates removed.
+fn missing_in(plan: &CodeSectionPlan) -> Vec<u32> {
+    let mut missing: Vec<u32> = Vec::new();
+    for &(op_id, entry) in &plan.ops {
+        if entry.is_none() && !missing.contains(&op_id) {
+            missing.push(op_id);
+        }
+    }
+    missing
+}
+
+/// `Err(MissingOpId)` for the first op of `plan` without an entry under `MissingOpIdPolicy::Fail`,
+/// `Err(MissingOpIds)` with all of them under `MissingOpIdPolicy::Collect`.
+fn check_plan(
+    plan: CodeSectionPlan,
+    policy: MissingOpIdPolicy,
+) -> Result<CodeSectionPlan, ClfError> {
+    match policy {
+        MissingOpIdPolicy::Fail => {
+            if let Some(&(op_id, _)) = plan.ops.iter().find(|(_, entry)| entry.is_none()) {
+                return Err(ClfError::MissingOpId(op_id));
+            }
+        }
+        MissingOpIdPolicy::Collect => {
+            let missing = missing_in(&plan);
+            if !missing.is_empty() {
+                return Err(ClfError::MissingOpIds(missing));
+            }
+        }
+        MissingOpIdPolicy::Skip => {}
+    }
+    Ok(plan)
 }
 
 /// Verification policy scaffold for future authenticated-signature support.
@@ -39,77 +246,696 @@ pub enum VerificationPolicy {
     RequireAuthenticity,
 }
 
-/// Errors produced by the CLF reader.
-#[derive(Debug, Error)]
-pub enum ClfError {
-    #[error("IO error: {0}")]
-    Io(#[from] std::io::Error),
-    #[error("invalid magic: expected CLF1")]
-    InvalidMagic,
-    #[error("unsupported version: {0} (supported: {1})")]
-    UnsupportedVersion(u8, u8),
-    #[error("invalid vendor: UTF-8 error")]
-    InvalidVendorUtf8,
-    #[error("invalid target: UTF-8 error")]
-    InvalidTargetUtf8,
-    #[error("invalid kind byte in v2+ header: {0}")]
-    InvalidKindByte(u8),
-    #[error("verification policy requires authenticity, but current CLF format only supports integrity")]
-    AuthenticityVerificationUnsupported,
-    #[error("signature missing or invalid")]
-    SignatureInvalid,
-    #[error("missing op_id {0} in CLF (policy: Fail)")]
-    MissingOpId(u32),
-    #[error("CLF kind mismatch: expected {expected:?}, got {actual:?}")]
-    KindMismatch { expected: ClfKind, actual: ClfKind },
-}
-
-fn read_len_prefixed_utf8<R: Read>(
+/// Which required op_ids a CLF provides; see `ClfReader::coverage`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoverageReport {
+    /// Required op_ids present in the manifest (first-occurrence order, duplicates removed).
+    pub present: Vec<u32>,
+    /// Required op_ids absent from the manifest (first-occurrence order, duplicates removed).
+    pub missing: Vec<u32>,
+    /// `present.len() / (present.len() + missing.len())`; 1.0 when nothing is required.
+    pub fraction_covered: f64,
+    /// Sum of the blob sizes `get_blob` returns for the present ops: decoded sizes for
+    /// compressed or encrypted entries, exact sizes otherwise.
+    pub present_bytes: u64,
+}
+
+impl CoverageReport {
+    /// True when every required op_id is present.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+/// Entries for every variant of `op_id`, in variant order.
+fn variants_of(manifest: &Manifest, op_id: u32) -> impl Iterator<Item = &ManifestEntry> {
+    manifest.variants(op_id).iter()
+}
+
+/// Op_id serving `op_id` (default variant): its canonical id, else a deprecated alias of it.
+fn resolve_in(manifest: &Manifest, op_id: u32, registry: &OpRegistry) -> Option<u32> {
+    let canonical = registry.canonical_op_id(op_id);
+    std::iter::once(canonical)
+        .chain(registry.deprecated_aliases(canonical))
+        .find(|id| manifest.get(&(*id, 0)).is_some())
+}
+
+/// Distinct op_ids in ascending order.
+fn op_ids_of(manifest: &Manifest) -> Vec<u32> {
+    let mut ids: Vec<u32> = manifest.values().map(|e| e.op_id).collect();
+    ids.dedup();
+    ids
+}
+
+/// Target of `entry`: its tag from the manifest target table, else the header target.
+fn target_of<'a>(header: &'a ClfHeader, targets: &'a [String], entry: &ManifestEntry) -> &'a str {
+    match entry.target_index {
+        0 => &header.target,
+        n => &targets[n as usize - 1],
+    }
+}
+
+/// Whether the header target or any entry's target is `target`: by `matcher` if given, else
+/// exactly.
+fn matches_target(
+    header: &ClfHeader,
+    targets: &[String],
+    manifest: &Manifest,
+    target: &str,
+    matcher: Option<&TargetMatcher>,
+) -> bool {
+    let fits = |t: &str| matcher.map_or(t == target, |m| m.matches(t, target));
+    fits(&header.target)
+        || manifest
+            .values()
+            .any(|entry| fits(target_of(header, targets, entry)))
+}
+
+/// File location of an unencoded (op_id, variant) blob; see `ClfReader::blob_location`.
+fn location_of(
+    header: &ClfHeader,
+    blob_store_offset: u64,
+    manifest: &Manifest,
+    op_id: u32,
+    variant: u16,
+) -> Option<BlobLocation> {
+    let entry = manifest.get(&(op_id, variant)).filter(|e| e.flags == 0)?;
+    let file_offset = blob_store_offset + u64::from(entry.offset);
+    Some(BlobLocation {
+        file_offset,
+        size: entry.exact_size,
+        aligned: file_offset % u64::from(header.blob_alignment.max(1)) == 0,
+    })
+}
+
+/// Distinct op_ids with at least one entry for `target`, in ascending order.
+fn op_ids_for_target_of(
+    header: &ClfHeader,
+    targets: &[String],
+    manifest: &Manifest,
+    target: &str,
+) -> Vec<u32> {
+    let mut ids: Vec<u32> = manifest
+        .values()
+        .filter(|entry| target_of(header, targets, entry) == target)
+        .map(|entry| entry.op_id)
+        .collect();
+    ids.dedup();
+    ids
+}
+
+/// First variant of `op_id` whose target is `target`.
+fn variant_for_target(
+    header: &ClfHeader,
+    targets: &[String],
+    manifest: &Manifest,
+    op_id: u32,
+    target: &str,
+) -> Option<u16> {
+    variants_of(manifest, op_id)
+        .find(|entry| target_of(header, targets, entry) == target)
+        .map(|entry| entry.variant)
+}
+
+/// Shared by both readers: coverage is pure manifest work.
+fn coverage_of(manifest: &Manifest, required: &[u32]) -> CoverageReport {
+    let mut seen = HashSet::with_capacity(required.len());
+    let mut present = Vec::new();
+    let mut missing = Vec::new();
+    let mut present_bytes = 0u64;
+    for &op_id in required {
+        if !seen.insert(op_id) {
+            continue;
+        }
+        // The default variant if there is one, else the lowest-numbered variant.
+        match variants_of(manifest, op_id).next() {
+            Some(entry) => {
+                present.push(op_id);
+                present_bytes += u64::from(blob_len(entry));
+            }
+            None => missing.push(op_id),
+        }
+    }
+    let total = present.len() + missing.len();
+    let fraction_covered = if total == 0 {
+        1.0
+    } else {
+        present.len() as f64 / total as f64
+    };
+    CoverageReport {
+        present,
+        missing,
+        fraction_covered,
+        present_bytes,
+    }
+}
+
+/// Options for opening a CLF (`ClfReader::open_with_options`).
+#[derive(Debug, Clone, Default)]
+pub struct ClfReaderOptions {
+    /// Reject the file unless the header kind matches.
+    pub expected_kind: Option<ClfKind>,
+    /// Map unknown v2+ kind bytes to Compute (`ClfKind::from_byte`) instead of failing with
+    /// `ClfError::InvalidKindByte`. Off by default: an unknown kind may be something a newer
+    /// packer wrote that must not be treated as compute kernels.
+    pub lenient_kind: bool,
+    /// Verify the unkeyed trailer (SIG0 or SHA-256 SIG1) while opening, hashing the bytes as
+    /// they are parsed so the file is read exactly once; an index-only block is checked without
+    /// reading the blob store at all. Fails with `ClfError::SignatureInvalid` when the trailer
+    /// is missing or does not match; on success `signature_scope()` reports what was covered.
+    pub verify_signature: bool,
+    /// Reject the file unless the header target or the target of at least one entry matches,
+    /// so a multi-target archive opens for any target it carries; otherwise fail with
+    /// `ClfError::TargetMismatch`. The comparison is exact unless `target_matcher` is set: an
+    /// empty header target only matches an empty expected target, unless
+    /// `empty_target_matches_any` is set.
+    pub expected_target: Option<String>,
+    /// With `expected_target`: compare targets with this matcher (case-insensitive, target
+    /// families) instead of exactly.
+    pub target_matcher: Option<TargetMatcher>,
+    /// With `expected_target`: accept an archive whose header target is empty (packed without
+    /// `--target`) for any expected target. Off by default, since such an archive says nothing
+    /// about where its kernels run.
+    pub empty_target_matches_any: bool,
+    /// Reject the file unless its path extension matches the header kind
+    /// (`ClfKind::matches_path`; legacy `.clf` counts as Compute) with
+    /// `ClfError::ExtensionMismatch`. Ignored by `ClfReaderFromBytes`, which has no path.
+    pub check_extension: bool,
+    /// Largest decoded size (`raw_size`) a compressed or encrypted entry may claim; decoding a
+    /// larger one fails with `ClfError::LimitExceeded` before anything is allocated for it.
+    /// `None` means `ClfLimits::default().max_blob_len` (256 MiB).
+    pub max_blob_len: Option<u32>,
+}
+
+impl ClfReaderOptions {
+    /// `max_blob_len`, or its default.
+    fn max_blob_len(&self) -> u32 {
+        self.max_blob_len
+            .unwrap_or_else(|| ClfLimits::default().max_blob_len)
+    }
+}
+
+fn check_expected_kind(header: &ClfHeader, expected_kind: Option<ClfKind>) -> Result<(), ClfError> {
+    if let Some(expected) = expected_kind {
+        if header.kind != expected {
+            return Err(ClfError::KindMismatch {
+                expected,
+                actual: header.kind,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn check_expected_target(
+    header: &ClfHeader,
+    targets: &[String],
+    manifest: &Manifest,
+    options: &ClfReaderOptions,
+) -> Result<(), ClfError> {
+    if let Some(expected) = options.expected_target.as_deref() {
+        let wildcard = options.empty_target_matches_any && header.target.is_empty();
+        let matcher = options.target_matcher.as_ref();
+        if !wildcard && !matches_target(header, targets, manifest, expected, matcher) {
+            return Err(ClfError::TargetMismatch {
+                expected: expected.to_string(),
+                actual: header.target.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// `Err(BlobChecksumMismatch)` if the entry has a CRC-32 and `stored` does not match it.
+pub(crate) fn check_crc(entry: &ManifestEntry, stored: &[u8]) -> Result<(), ClfError> {
+    if let Some(expected) = entry.crc32 {
+        let actual = crc32fast::hash(stored);
+        if actual != expected {
+            return Err(ClfError::BlobChecksumMismatch {
+                op_id: entry.op_id,
+                expected,
+                actual,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Run `check` on every manifest entry that has a CRC-32 and return the first failure in
+/// manifest order. `check` gets `scratch` to read into.
+#[cfg(not(feature = "parallel"))]
+fn check_crc_entries(
+    manifest: &Manifest,
+    scratch: &mut Vec<u8>,
+    check: impl Fn(&mut Vec<u8>, &ManifestEntry) -> Result<(), ClfError>,
+) -> Result<(), ClfError> {
+    manifest
+        .values()
+        .filter(|entry| entry.crc32.is_some())
+        .try_for_each(|entry| check(scratch, entry))
+}
+
+/// Same as the serial `check_crc_entries`, running `check` on the rayon pool. Each worker gets
+/// its own scratch buffer instead of `scratch`. The reported failure is still the first in
+/// manifest order.
+#[cfg(feature = "parallel")]
+fn check_crc_entries(
+    manifest: &Manifest,
+    _scratch: &mut Vec<u8>,
+    check: impl Fn(&mut Vec<u8>, &ManifestEntry) -> Result<(), ClfError> + Sync,
+) -> Result<(), ClfError> {
+    use rayon::prelude::*;
+
+    let entries: Vec<&ManifestEntry> = manifest.values().filter(|e| e.crc32.is_some()).collect();
+    entries
+        .par_iter()
+        .map_init(Vec::new, |scratch, entry| check(scratch, entry))
+        .find_first(Result::is_err)
+        .unwrap_or(Ok(()))
+}
+
+/// Decryption key the decode helpers are given: the one set on the reader, if any.
+#[cfg(feature = "encryption")]
+pub(crate) type BlobKey<'k> = Option<&'k EncryptionKey>;
+
+/// Without the `encryption` feature no key can be set.
+#[cfg(not(feature = "encryption"))]
+pub(crate) type BlobKey<'k> = Option<&'k std::convert::Infallible>;
+
+/// Plaintext of an encrypted entry's `stored` bytes, or `Encrypted` if there is no `key`.
+#[cfg(feature = "encryption")]
+fn decrypt(entry: &ManifestEntry, stored: &[u8], key: BlobKey<'_>) -> Result<Vec<u8>, ClfError> {
+    key.ok_or(ClfError::Encrypted(entry.op_id))?
+        .open(entry.op_id, stored)
+}
+
+/// Plaintext of an encrypted entry's `stored` bytes, or `Encrypted` if there is no `key`.
+#[cfg(not(feature = "encryption"))]
+fn decrypt(entry: &ManifestEntry, _stored: &[u8], _key: BlobKey<'_>) -> Result<Vec<u8>, ClfError> {
+    Err(ClfError::Encrypted(entry.op_id))
+}
+
+/// `LimitExceeded` if decoding `entry` needs a buffer over `max_blob_len` bytes. Its `raw_size`
+/// comes from the manifest, so it is checked before anything is allocated for it.
+pub(crate) fn check_decoded_len(entry: &ManifestEntry, max_blob_len: u32) -> Result<(), ClfError> {
+    if entry.flags != 0 && entry.raw_size > max_blob_len {
+        return Err(ClfError::LimitExceeded {
+            what: "decoded blob length",
+            value: u64::from(entry.raw_size),
+            limit: u64::from(max_blob_len),
+        });
+    }
+    Ok(())
+}
+
+/// Check the entry's CRC-32 (if any), then undo its encoding (if any). `stored` is the first
+/// `exact_size` bytes of the entry; `key` decrypts encrypted entries.
+pub(crate) fn decode_blob(
+    entry: &ManifestEntry,
+    stored: Vec<u8>,
+    key: BlobKey<'_>,
+) -> Result<Vec<u8>, ClfError> {
+    if entry.flags == 0 {
+        check_crc(entry, &stored)?;
+        return Ok(stored);
+    }
+    let mut raw = Vec::new();
+    decode_into(entry, &stored, &mut raw, key)?;
+    Ok(raw)
+}
+
+/// Same as `decode_blob` for an encoded entry, appending the decoded blob to `out` (left as it
+/// was on error) instead of allocating it.
+fn decode_into(
+    entry: &ManifestEntry,
+    stored: &[u8],
+    out: &mut Vec<u8>,
+    key: BlobKey<'_>,
+) -> Result<(), ClfError> {
+    let start = out.len();
+    out.resize(start + entry.raw_size as usize, 0);
+    let decoded = decode_to_slice(entry, stored, &mut out[start..], key);
+    if decoded.is_err() {
+        out.truncate(start);
+    }
+    decoded
+}
+
+/// Check the CRC-32 of an entry's `stored` bytes and write its blob to `dst`, which is
+/// `blob_len` bytes long: copied if unencoded, decrypted and/or decompressed otherwise. The
+/// CRC covers the stored bytes, so corruption is reported as such even without the key.
+fn decode_to_slice(
+    entry: &ManifestEntry,
+    stored: &[u8],
+    dst: &mut [u8],
+    key: BlobKey<'_>,
+) -> Result<(), ClfError> {
+    check_crc(entry, stored)?;
+    if entry.flags == 0 {
+        dst.copy_from_slice(stored);
+        return Ok(());
+    }
+    let unsupported = ClfError::CompressionUnsupported {
+        op_id: entry.op_id,
+        flags: entry.flags,
+    };
+    if entry.flags & !(ENTRY_FLAG_ZSTD | ENTRY_FLAG_AES256GCM) != 0 {
+        return Err(unsupported);
+    }
+    let plain;
+    let stored = if entry.flags & ENTRY_FLAG_AES256GCM != 0 {
+        plain = decrypt(entry, stored, key)?;
+        plain.as_slice()
+    } else {
+        stored
+    };
+    if entry.flags & ENTRY_FLAG_ZSTD == 0 {
+        // The manifest parser checked that the plaintext is `raw_size` bytes long.
+        dst.copy_from_slice(stored);
+        return Ok(());
+    }
+    #[cfg(feature = "compression")]
+    {
+        let decompress_err = |source| ClfError::Decompress {
+            op_id: entry.op_id,
+            source,
+        };
+        match zstd::bulk::decompress_to_buffer(stored, dst) {
+            Ok(n) if n == dst.len() => Ok(()),
+            Ok(_) => Err(decompress_err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "decompressed length does not match raw size",
+            ))),
+            Err(e) => Err(decompress_err(e)),
+        }
+    }
+    #[cfg(not(feature = "compression"))]
+    {
+        let _ = dst;
+        Err(unsupported)
+    }
+}
+
+/// Reader adapter that feeds every byte read into a SHA-256 hasher (when enabled).
+struct HashingReader<R> {
+    inner: R,
+    hasher: Option<Sha256>,
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if let Some(hasher) = &mut self.hasher {
+            hasher.update(&buf[..n]);
+        }
+        Ok(n)
+    }
+}
+
+/// Read exactly `buf.len()` bytes at `offset` without relying on the file cursor.
+fn read_exact_at(file: &File, buf: &mut [u8], offset: u64) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileExt;
+        file.read_exact_at(buf, offset)
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::FileExt;
+        let (mut buf, mut offset) = (buf, offset);
+        while !buf.is_empty() {
+            match file.seek_read(buf, offset) {
+                Ok(0) => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => {
+                    let rest = buf;
+                    buf = &mut rest[n..];
+                    offset += n as u64;
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let mut file = file;
+        file.seek(std::io::SeekFrom::Start(offset))?;
+        file.read_exact(buf)
+    }
+}
+
+/// Locate the signature trailer of a complete archive `reader` of `file_len` bytes, returning
+/// where the signed data ends and the trailer kind. A trailer is only reported when the data
+/// before it ends exactly where the manifest says the blob store ends (or, with no entries, at
+/// the store start or its alignment fill), so a last blob that happens to end in a signature
+/// magic is not mistaken for one.
+pub(crate) fn locate_signature<R: Read + Seek>(
     reader: &mut R,
-    field_name: &'static str,
-) -> Result<String, ClfError> {
-    let mut len_buf = [0u8; 4];
-    reader.read_exact(&mut len_buf)?;
-    let len = u32::from_le_bytes(len_buf) as usize;
-    if len > MAX_HEADER_TEXT_LEN {
-        return Err(ClfError::Io(std::io::Error::new(
-            std::io::ErrorKind::InvalidData,
-            format!("{field_name} too large: {len} bytes"),
-        )));
-    }
-
-    let mut bytes = vec![0u8; len];
-    if len > 0 {
-        reader.read_exact(&mut bytes)?;
-    }
-
-    String::from_utf8(bytes).map_err(|_| match field_name {
-        "vendor" => ClfError::InvalidVendorUtf8,
-        "target" => ClfError::InvalidTargetUtf8,
-        _ => ClfError::Io(std::io::Error::new(
-            std::io::ErrorKind::InvalidData,
-            "invalid UTF-8 field",
-        )),
+    file_len: u64,
+) -> Result<(u64, Trailer), ClfError> {
+    reader.seek(std::io::SeekFrom::Start(0))?;
+    let header = read_header_with(reader, true, &ClfLimits::FORMAT_MAX)?;
+    let (manifest, _, manifest_len) = read_manifest(
+        &mut FieldReader::at(reader, header.header_end),
+        header.version,
+        file_len.saturating_sub(header.header_end),
+        &ClfLimits::FORMAT_MAX,
+    )?;
+    let store_start = header.header_end + manifest_len;
+    let trailer = find_trailer(file_len, store_start, |buf, offset| {
+        reader.seek(std::io::SeekFrom::Start(offset))?;
+        reader.read_exact(buf)
+    });
+    let data_end = file_len - trailer.len();
+    let blobs_end = manifest
+        .values()
+        .map(|e| u64::from(e.offset) + u64::from(e.size))
+        .max();
+    let consistent = match blobs_end {
+        Some(blobs_end) => data_end == store_start + blobs_end,
+        None => {
+            let align = u64::from(header.blob_alignment.max(1));
+            data_end == store_start || data_end == store_start.next_multiple_of(align)
+        }
+    };
+    Ok(if consistent {
+        (data_end, trailer)
+    } else {
+        (file_len, Trailer::None)
     })
 }
 
+/// Bytes read per step when hashing a file for signature verification.
+pub const HASH_CHUNK_LEN: usize = 256 * 1024;
+
+/// Progress callback that never cancels.
+fn no_progress(_done: u64, _total: u64) -> ControlFlow<()> {
+    ControlFlow::Continue(())
+}
+
+/// Length the archive should have: where the manifest says the blob store ends, plus the
+/// signature block that starts there, if any. With no entries the store may end at its start or
+/// at its alignment fill. Anything past this length was appended after the archive.
+/// `store_end` is where the blob store ends going by the trailer at end of file; a manifest that
+/// reaches past it is a bounds error rather than trailing data, so `file_len` is returned.
+/// `read_at` reads at an absolute offset.
+pub(crate) fn expected_file_len(
+    manifest: &Manifest,
+    store_start: u64,
+    store_end: u64,
+    blob_alignment: u8,
+    file_len: u64,
+    mut read_at: impl FnMut(&mut [u8], u64) -> std::io::Result<()>,
+) -> u64 {
+    let mut with_trailer = |data_end: u64| data_end + trailer_len_at(data_end, &mut read_at);
+    let blobs_end = manifest
+        .values()
+        .map(|e| u64::from(e.offset) + u64::from(e.size))
+        .max();
+    match blobs_end {
+        Some(blobs_end) if store_start + blobs_end > store_end => file_len,
+        Some(blobs_end) => with_trailer(store_start + blobs_end),
+        None => {
+            let unaligned = with_trailer(store_start);
+            if unaligned == file_len {
+                unaligned
+            } else {
+                with_trailer(store_start.next_multiple_of(u64::from(blob_alignment.max(1))))
+            }
+        }
+    }
+}
+
+/// Length of the SIG0 or SIG1 block starting at `pos`, from its leading fields; 0 if none does.
+fn trailer_len_at(
+    pos: u64,
+    read_at: &mut impl FnMut(&mut [u8], u64) -> std::io::Result<()>,
+) -> u64 {
+    let mut head = [0u8; 6];
+    if read_at(&mut head, pos).is_err() {
+        return 0;
+    }
+    if head[..4] == SIG_MAGIC {
+        return SIG_BLOCK_LEN as u64;
+    }
+    if head[..4] != SIG1_MAGIC {
+        return 0;
+    }
+    let fingerprint_len = u64::from(head[5]);
+    let mut signature_len = [0u8; 2];
+    if read_at(&mut signature_len, pos + 6 + fingerprint_len).is_err() {
+        return 0;
+    }
+    SIG1_MIN_LEN as u64 + fingerprint_len + u64::from(u16::from_le_bytes(signature_len))
+}
+
+/// `Err(TrailingGarbage)` if the file is longer than the archive it holds.
+pub(crate) fn check_trailing_data(expected_len: u64, actual_len: u64) -> Result<(), ClfError> {
+    if actual_len > expected_len {
+        return Err(ClfError::TrailingGarbage {
+            expected_len,
+            actual_len,
+        });
+    }
+    Ok(())
+}
+
+/// What lies at the end of a file: its signature trailer and the archive length the manifest
+/// and that trailer imply (see `expected_file_len`).
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct EofLayout {
+    pub(crate) trailer: Trailer,
+    pub(crate) expected_len: u64,
+}
+
+/// Read the end of a file of `file_len` bytes whose blob store starts at `store_start`.
+/// `read_at` reads at an absolute offset.
+pub(crate) fn eof_layout(
+    manifest: &Manifest,
+    store_start: u64,
+    blob_alignment: u8,
+    file_len: u64,
+    mut read_at: impl FnMut(&mut [u8], u64) -> std::io::Result<()>,
+) -> EofLayout {
+    let trailer = find_trailer(file_len, store_start, &mut read_at);
+    let expected_len = expected_file_len(
+        manifest,
+        store_start,
+        file_len - trailer.len(),
+        blob_alignment,
+        file_len,
+        read_at,
+    );
+    EofLayout {
+        trailer,
+        expected_len,
+    }
+}
+
+/// `read_at` over an in-memory archive: reads past the end fail with `UnexpectedEof`.
+pub(crate) fn slice_read_at(data: &[u8]) -> impl FnMut(&mut [u8], u64) -> std::io::Result<()> + '_ {
+    move |buf, offset| {
+        let start = usize::try_from(offset).unwrap_or(usize::MAX);
+        let bytes = data
+            .get(start..)
+            .and_then(|rest| rest.get(..buf.len()))
+            .ok_or(std::io::ErrorKind::UnexpectedEof)?;
+        buf.copy_from_slice(bytes);
+        Ok(())
+    }
+}
+
+/// Signature trailer found at the end of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Trailer {
+    None,
+    /// Fixed-size SIG0 block (magic + SHA-256).
+    Sig0,
+    /// SIG1 block of the given total length.
+    Sig1(u64),
+}
+
+impl Trailer {
+    /// Bytes the trailer occupies at the end of the file.
+    pub(crate) fn len(self) -> u64 {
+        match self {
+            Trailer::None => 0,
+            Trailer::Sig0 => SIG_BLOCK_LEN as u64,
+            Trailer::Sig1(len) => len,
+        }
+    }
+}
+
+/// Locate the signature trailer (SIG1 first, then SIG0) at the end of a file of `file_len`
+/// bytes. The trailer may not reach back before `data_start`. `read_at` reads at an absolute offset.
+pub(crate) fn find_trailer(
+    file_len: u64,
+    data_start: u64,
+    mut read_at: impl FnMut(&mut [u8], u64) -> std::io::Result<()>,
+) -> Trailer {
+    let fits = |len: u64| file_len.saturating_sub(data_start) >= len;
+    if fits(SIG1_MIN_LEN as u64) {
+        let mut footer = [0u8; 8];
+        if read_at(&mut footer, file_len - 8).is_ok() && footer[4..] == SIG1_MAGIC {
+            let block_len = u64::from(u32::from_le_bytes([
+                footer[0], footer[1], footer[2], footer[3],
+            ]));
+            let mut magic = [0u8; 4];
+            if block_len >= SIG1_MIN_LEN as u64
+                && fits(block_len)
+                && read_at(&mut magic, file_len - block_len).is_ok()
+                && magic == SIG1_MAGIC
+            {
+                return Trailer::Sig1(block_len);
+            }
+        }
+    }
+    let mut magic = [0u8; 4];
+    if fits(SIG_BLOCK_LEN as u64)
+        && read_at(&mut magic, file_len - SIG_BLOCK_LEN as u64).is_ok()
+        && magic == SIG_MAGIC
+    {
+        return Trailer::Sig0;
+    }
+    Trailer::None
+}
+
 /// CLF reader: parses header and manifest, provides get_blob(op_id).
 #[derive(Debug)]
 pub struct ClfReader {
     /// Parsed header (vendor, version).
     pub header: ClfHeader,
-    /// Manifest: op_id → (offset, size) relative to blob store start.
-    manifest: HashMap<u32, ManifestEntry>,
-    /// File handle; blob store starts at blob_store_offset.
-    reader: BufReader<File>,
+    /// Manifest: (op_id, variant) → (offset, size) relative to blob store start.
+    manifest: Manifest,
+    /// Manifest target table; entries with `target_index` n > 0 target `targets[n - 1]`.
+    targets: Vec<String>,
+    /// File handle; blob store starts at blob_store_offset. Read with positional IO only
+    /// (`read_exact_at`), so handles duplicated by `try_clone` never disturb each other.
+    file: File,
+    /// Path the file was opened from; errors from reading it carry this path.
+    path: PathBuf,
     /// Byte offset in file where blob store starts.
     blob_store_offset: u64,
-    /// Total length of blob store (so we can bounds-check reads).
-    blob_store_len: u64,
-    /// True if a SIG0 block is present at end of file (not verified until `verify_signature`).
-    signature_block_present: bool,
-    /// If true, file has a valid signature block at end (verified by verify_signature).
-    signature_verified: bool,
+    /// Blob store length according to the manifest (see `declared_blob_store_len`).
+    declared_blob_store_len: u64,
+    /// What the last successful `verify_signature*` call (or open-time check) covered.
+    signature_scope: SignatureScope,
+    /// File length at open.
+    file_len: u64,
+    /// Trailer and expected archive length. Read from the end of the file only when first
+    /// needed (signature, validation), so a plain open reads front to back and never there.
+    eof: OnceLock<EofLayout>,
+    /// Reused by internal read paths (`build_code_section*`, `verify_blobs`) for stored bytes
+    /// that are not returned as is. Cleared between uses, never shrunk.
+    scratch: Mutex<Vec<u8>>,
+    /// Largest decoded blob (`ClfReaderOptions::max_blob_len`).
+    max_blob_len: u32,
+    /// Key for encrypted blobs (`set_decryption_key`).
+    #[cfg(feature = "encryption")]
+    decryption_key: Option<EncryptionKey>,
 }
 
 impl ClfReader {
@@ -121,208 +947,803 @@ impl ClfReader {
 
     /// Open a .clf file and parse header + manifest. When `expected_kind` is `Some(k)`,
     /// rejects the file if the header kind does not match (e.g. opening a .clfmm when
-    /// expecting MemoryMovement).
+    /// expecting MemoryMovement). To also cross-check the path extension against the header
+    /// kind, use `open_with_options` with `check_extension`.
     pub fn open_with_expected_kind<P: AsRef<Path>>(
         path: P,
         expected_kind: Option<ClfKind>,
     ) -> Result<Self, ClfError> {
+        Self::open_with_options(
+            path,
+            &ClfReaderOptions {
+                expected_kind,
+                ..ClfReaderOptions::default()
+            },
+        )
+    }
+
+    /// Open a .clf file and parse header + manifest, rejecting it with
+    /// `ClfError::TargetMismatch` unless its header target, or the target of one of its
+    /// entries, is `expected` (e.g. a gfx942 archive on a CPU-only host). An empty header
+    /// target does not match; see `ClfReaderOptions::empty_target_matches_any`.
+    pub fn open_with_expected_target<P: AsRef<Path>>(
+        path: P,
+        expected: &str,
+    ) -> Result<Self, ClfError> {
+        Self::open_with_options(
+            path,
+            &ClfReaderOptions {
+                expected_target: Some(expected.to_string()),
+                ..ClfReaderOptions::default()
+            },
+        )
+    }
+
+    /// Open a .clf file with explicit options (expected kind and target, lenient kind parsing).
+    /// Errors come wrapped in `ClfError::WithPath` naming `path`.
+    pub fn open_with_options<P: AsRef<Path>>(
+        path: P,
+        options: &ClfReaderOptions,
+    ) -> Result<Self, ClfError> {
+        let path = path.as_ref();
+        Self::open_impl(path, options).map_err(|e| e.with_path(path))
+    }
+
+    fn open_impl(path: &Path, options: &ClfReaderOptions) -> Result<Self, ClfError> {
         let file = File::open(path)?;
-        let mut reader = BufReader::new(file);
+        let file_len = file.metadata()?.len();
+        // With `verify_signature`, every byte before the trailer passes through the hasher
+        // exactly once while we parse, so the file is read a single time.
+        let mut reader = HashingReader {
+            inner: BufReader::new(file),
+            hasher: options.verify_signature.then(Sha256::new),
+        };
 
         // --- Header ---
-        let mut magic = [0u8; 4];
-        reader.read_exact(&mut magic)?;
-        if magic != CLF_MAGIC {
-            return Err(ClfError::InvalidMagic);
+        let header = read_header_with(&mut reader, options.lenient_kind, &ClfLimits::FORMAT_MAX)?;
+        check_expected_kind(&header, options.expected_kind)?;
+        if options.check_extension && !header.kind.matches_path(path) {
+            return Err(ClfError::ExtensionMismatch {
+                extension: path
+                    .extension()
+                    .map(|e| e.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                kind: header.kind,
+            });
         }
 
-        let mut version_byte = [0u8; 1];
-        reader.read_exact(&mut version_byte)?;
-        let version = version_byte[0];
-        if version > CLF_VERSION {
-            return Err(ClfError::UnsupportedVersion(version, CLF_VERSION));
-        }
+        // --- Manifest ---
+        let (manifest, targets, manifest_len) = read_manifest(
+            &mut FieldReader::at(&mut reader, header.header_end),
+            header.version,
+            file_len.saturating_sub(header.header_end),
+            &ClfLimits::FORMAT_MAX,
+        )?;
+        check_expected_target(&header, &targets, &manifest, options)?;
+        let blob_store_offset = header.header_end + manifest_len;
+        let eof = OnceLock::new();
 
-        let vendor = read_len_prefixed_utf8(&mut reader, "vendor")?;
+        let (signature_scope, file) = match reader.hasher.take() {
+            Some(mut hasher) => {
+                let EofLayout {
+                    trailer,
+                    expected_len,
+                } = *eof.get_or_init(|| {
+                    eof_layout(
+                        &manifest,
+                        blob_store_offset,
+                        header.blob_alignment,
+                        file_len,
+                        |buf, offset| read_exact_at(reader.inner.get_ref(), buf, offset),
+                    )
+                });
+                check_trailing_data(expected_len, file_len)?;
+                // The hasher has seen the header and manifest. Unless the trailer covers only
+                // those, stream the rest of the data region, then compare against the trailer.
+                let mut inner = reader.inner;
+                let data_len = file_len - trailer.len();
+                let (scope, stored_hash) = match trailer {
+                    Trailer::None => return Err(ClfError::SignatureInvalid),
+                    Trailer::Sig0 => {
+                        let mut sig_block = [0u8; SIG_BLOCK_LEN];
+                        read_exact_at(inner.get_ref(), &mut sig_block, data_len)?;
+                        (SignatureScope::Full, sig_block[4..].to_vec())
+                    }
+                    Trailer::Sig1(len) => {
+                        let mut block = vec![0u8; len as usize];
+                        read_exact_at(inner.get_ref(), &mut block, data_len)?;
+                        let block = SignatureBlock::parse(&block)
+                            .filter(|b| b.algorithm == SIG_ALG_SHA256)
+                            .ok_or(ClfError::SignatureInvalid)?;
+                        (block.scope(), block.signature)
+                    }
+                };
+                if scope == SignatureScope::Full {
+                    let mut buf = vec![0u8; HASH_CHUNK_LEN];
+                    let mut pos = blob_store_offset;
+                    while pos < data_len {
+                        let n = (data_len - pos).min(buf.len() as u64) as usize;
+                        inner.read_exact(&mut buf[..n])?;
+                        hasher.update(&buf[..n]);
+                        pos += n as u64;
+                    }
+                }
+                if !constant_time_eq(&hasher.finalize(), &stored_hash) {
+                    return Err(ClfError::SignatureInvalid);
+                }
+                (scope, inner.into_inner())
+            }
+            None => (SignatureScope::None, reader.inner.into_inner()),
+        };
 
-        // Target length (4 B LE), target (M bytes), blob alignment (1 B).
-        let target = read_len_prefixed_utf8(&mut reader, "target")?;
-        let mut blob_align_byte = [0u8; 1];
-        reader.read_exact(&mut blob_align_byte)?;
-        let blob_alignment = blob_align_byte[0];
+        Ok(ClfReader {
+            header,
+            declared_blob_store_len: declared_store_len(&manifest),
+            manifest,
+            targets,
+            file,
+            path: path.to_path_buf(),
+            blob_store_offset,
+            signature_scope,
+            file_len,
+            eof,
+            scratch: Mutex::default(),
+            max_blob_len: options.max_blob_len(),
+            #[cfg(feature = "encryption")]
+            decryption_key: None,
+        })
+    }
 
-        // v2: read kind byte; v1: default to Compute (backwards compatibility).
-        let kind = if version >= 2 {
-            let mut kind_byte = [0u8; 1];
-            reader.read_exact(&mut kind_byte)?;
-            ClfKind::try_from_byte(kind_byte[0]).ok_or(ClfError::InvalidKindByte(kind_byte[0]))?
-        } else {
-            ClfKind::default_for_v1()
-        };
+    /// Create an independent handle to the same file without re-parsing header and manifest.
+    /// The underlying `File` is duplicated; blob reads are positional, so the clone and the
+    /// original can be used (also from different threads) without affecting each other.
+    /// `signature_scope` carries over since it describes the file contents.
+    pub fn try_clone(&self) -> Result<ClfReader, ClfError> {
+        Ok(ClfReader {
+            header: self.header.clone(),
+            manifest: self.manifest.clone(),
+            targets: self.targets.clone(),
+            file: self.file.try_clone().map_err(|e| self.io_err(e))?,
+            path: self.path.clone(),
+            blob_store_offset: self.blob_store_offset,
+            declared_blob_store_len: self.declared_blob_store_len,
+            signature_scope: self.signature_scope,
+            file_len: self.file_len,
+            eof: self.eof.clone(),
+            scratch: Mutex::default(),
+            max_blob_len: self.max_blob_len,
+            #[cfg(feature = "encryption")]
+            decryption_key: self.decryption_key.clone(),
+        })
+    }
+
+    /// Trailer and expected archive length, read from the end of the file on first use.
+    fn eof(&self) -> EofLayout {
+        *self.eof.get_or_init(|| {
+            eof_layout(
+                &self.manifest,
+                self.blob_store_offset,
+                self.header.blob_alignment,
+                self.file_len,
+                |buf, offset| read_exact_at(&self.file, buf, offset),
+            )
+        })
+    }
+
+    /// Length of the serialized header in bytes (the manifest starts here).
+    #[must_use]
+    pub fn header_len(&self) -> u64 {
+        self.header.header_end
+    }
 
-        let header_end = reader.stream_position()?;
-        let header = ClfHeader {
-            version,
-            vendor,
+    /// Value of header metadata `key` (v3 metadata section), if present.
+    #[must_use]
+    pub fn get_meta(&self, key: &str) -> Option<&str> {
+        self.header.get_meta(key)
+    }
+
+    /// Human-readable name of `op_id` from the v3 name table, if the packer recorded one.
+    /// Informational only: lookups stay keyed by op_id.
+    #[must_use]
+    pub fn entry_name(&self, op_id: u32) -> Option<&str> {
+        self.header.entry_name(op_id)
+    }
+
+    /// Name to show for `op_id`: the file's name table entry, else the name `registry` knows
+    /// (canonical or registered vendor op).
+    #[must_use]
+    pub fn entry_label<'a>(&'a self, op_id: u32, registry: &'a OpRegistry) -> Option<&'a str> {
+        self.entry_name(op_id).or_else(|| registry.name(op_id))
+    }
+
+    /// Target `entry` was built for: its per-entry tag, else the header target.
+    #[must_use]
+    pub fn entry_target(&self, entry: &ManifestEntry) -> &str {
+        target_of(&self.header, &self.targets, entry)
+    }
+
+    /// Whether the header target or the target of any entry is `target`.
+    #[must_use]
+    pub fn matches_target(&self, target: &str) -> bool {
+        matches_target(&self.header, &self.targets, &self.manifest, target, None)
+    }
+
+    /// Like `matches_target`, comparing with `matcher`, so an archive for `gfx90a` suits a
+    /// caller asking for `CDNA2` and `CPU` matches `cpu`. For picking archives by target.
+    #[must_use]
+    pub fn matches_target_with(&self, matcher: &TargetMatcher, target: &str) -> bool {
+        matches_target(
+            &self.header,
+            &self.targets,
+            &self.manifest,
             target,
-            blob_alignment,
-            kind,
-            header_end,
-        };
+            Some(matcher),
+        )
+    }
 
-        if let Some(expected) = expected_kind {
-            if header.kind != expected {
-                return Err(ClfError::KindMismatch {
-                    expected,
-                    actual: header.kind,
-                });
+    /// Per-entry target tags of `op_id`'s variants, in the form `PackOptions::entry_targets`
+    /// takes, so rewriting tools carry them over.
+    pub(crate) fn entry_target_tags(&self, op_id: u32) -> Vec<((u32, u16), String)> {
+        variants_of(&self.manifest, op_id)
+            .filter(|entry| entry.target_index != 0)
+            .map(|entry| {
+                let target = target_of(&self.header, &self.targets, entry);
+                ((entry.op_id, entry.variant), target.to_string())
+            })
+            .collect()
+    }
+
+    /// Op_ids with at least one entry built for `target` (untagged entries count as the
+    /// header target), each once in ascending order.
+    #[must_use]
+    pub fn op_ids_for_target(&self, target: &str) -> Vec<u32> {
+        op_ids_for_target_of(&self.header, &self.targets, &self.manifest, target)
+    }
+
+    /// Return the blob of the first variant of `op_id` built for `target`, if any (same
+    /// decoding as `get_blob`).
+    pub fn get_blob_for_target(
+        &mut self,
+        op_id: u32,
+        target: &str,
+    ) -> Result<Option<Vec<u8>>, ClfError> {
+        match variant_for_target(&self.header, &self.targets, &self.manifest, op_id, target) {
+            Some(variant) => self.get_blob_variant(op_id, variant),
+            None => Ok(None),
+        }
+    }
+
+    /// Exact serialized header bytes `[0, header_len())`, e.g. for external attestation.
+    pub fn raw_header(&self) -> Result<Vec<u8>, ClfError> {
+        let mut bytes = vec![0u8; self.header.header_end as usize];
+        self.read_at(&mut bytes, 0)?;
+        Ok(bytes)
+    }
+
+    /// Exact serialized manifest bytes (entry count + entries), `[header_len(), blob_store_offset())`.
+    pub fn raw_manifest(&self) -> Result<Vec<u8>, ClfError> {
+        let mut bytes = vec![0u8; (self.blob_store_offset - self.header.header_end) as usize];
+        self.read_at(&mut bytes, self.header.header_end)?;
+        Ok(bytes)
+    }
+
+    /// Path the archive was opened from.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Decrypt encrypted blobs with `key` from now on. Without a key, reading one fails with
+    /// `ClfError::Encrypted`; with the wrong key, with `ClfError::DecryptionFailed`. `try_clone`
+    /// copies the key.
+    #[cfg(feature = "encryption")]
+    pub fn set_decryption_key(&mut self, key: EncryptionKey) {
+        self.decryption_key = Some(key);
+    }
+
+    /// The key set by `set_decryption_key`, as the decode helpers take it.
+    fn blob_key(&self) -> BlobKey<'_> {
+        #[cfg(feature = "encryption")]
+        return self.decryption_key.as_ref();
+        #[cfg(not(feature = "encryption"))]
+        None
+    }
+
+    /// Byte offset in the file where the blob store begins.
+    #[must_use]
+    pub fn blob_store_offset(&self) -> u64 {
+        self.blob_store_offset
+    }
+
+    /// Length of the blob store region; same as `declared_blob_store_len`.
+    #[must_use]
+    pub fn blob_store_len(&self) -> u64 {
+        self.declared_blob_store_len
+    }
+
+    /// Blob store length according to the manifest: the end of its furthest entry, padding
+    /// included. Known from the header and manifest alone, without reading the end of the file.
+    #[must_use]
+    pub fn declared_blob_store_len(&self) -> u64 {
+        self.declared_blob_store_len
+    }
+
+    /// Blob store length according to the file: everything between the manifest and the
+    /// signature trailer (or end of file). Differs from `declared_blob_store_len` for a
+    /// truncated file, an empty store followed by alignment fill, or data appended after it.
+    /// Reads the end of the file on first use.
+    #[must_use]
+    pub fn file_blob_store_len(&self) -> u64 {
+        self.file_len
+            .saturating_sub(self.eof().trailer.len())
+            .saturating_sub(self.blob_store_offset)
+    }
+
+    /// Whether a signature block (SIG0 hash or SIG1) is present at the end of the file (not
+    /// verified). Reads the end of the file on first use.
+    #[must_use]
+    pub fn signature_block_present(&self) -> bool {
+        self.eof().trailer != Trailer::None
+    }
+
+    /// Snapshot of the header and manifest as plain data (see `ManifestView`), to record what
+    /// the archive held and compare it later without the file. Reads the end of the file on
+    /// first use, to see whether a signature block is present.
+    #[must_use]
+    pub fn manifest_view(&self) -> ManifestView {
+        let entries = self
+            .manifest
+            .values()
+            .map(|entry| (entry, target_of(&self.header, &self.targets, entry)));
+        ManifestView::new(&self.header, entries, self.signature_block_present())
+    }
+
+    /// Manifest entries sorted by `op_id`, then variant (stable order for display and tooling).
+    #[must_use]
+    pub fn manifest_entries(&self) -> Vec<ManifestEntry> {
+        self.manifest.values().copied().collect()
+    }
+
+    /// Return the blob for the given op_id if present. No interpretation of blob contents.
+    /// For v3+ files the alignment padding is trimmed, so this is exactly the bytes that were packed.
+    /// With several variants this is the default variant 0 (see `get_blob_variant`).
+    pub fn get_blob(&mut self, op_id: u32) -> Result<Option<Vec<u8>>, ClfError> {
+        self.get_blob_variant(op_id, 0)
+    }
+
+    /// Op_id in this manifest that serves `op_id` under `registry`: its canonical id if packed,
+    /// else the first packed deprecated alias of it. `None` if neither is present.
+    #[must_use]
+    pub fn resolve_op_id(&self, op_id: u32, registry: &OpRegistry) -> Option<u32> {
+        resolve_in(&self.manifest, op_id, registry)
+    }
+
+    /// Like `get_blob`, but also serves archives packed under a deprecated alias of `op_id`
+    /// (see `resolve_op_id`), so callers can ask with the canonical id.
+    pub fn get_blob_canonical(
+        &mut self,
+        op_id: u32,
+        registry: &OpRegistry,
+    ) -> Result<Option<Vec<u8>>, ClfError> {
+        match self.resolve_op_id(op_id, registry) {
+            Some(id) => self.get_blob(id),
+            None => Ok(None),
+        }
+    }
+
+    /// Return the blob for one variant of `op_id`, if present (same decoding as `get_blob`).
+    pub fn get_blob_variant(
+        &mut self,
+        op_id: u32,
+        variant: u16,
+    ) -> Result<Option<Vec<u8>>, ClfError> {
+        match self.manifest.get(&(op_id, variant)).copied() {
+            Some(entry) => {
+                check_decoded_len(&entry, self.max_blob_len)?;
+                let stored = self.read_blob(&entry, entry.exact_size)?;
+                decode_blob(&entry, stored, self.blob_key()).map(Some)
             }
+            None => Ok(None),
         }
+    }
 
-        // --- Manifest ---
-        let mut num_entries_buf = [0u8; 4];
-        reader.read_exact(&mut num_entries_buf)?;
-        let num_entries = u32::from_le_bytes(num_entries_buf) as usize;
-        let manifest_start = reader.stream_position()?;
-        let file_len = reader.seek(SeekFrom::End(0))?;
-        let required_manifest_bytes = (num_entries as u64) * (ManifestEntry::ENTRY_SIZE as u64);
-        if required_manifest_bytes > file_len.saturating_sub(manifest_start) {
-            return Err(ClfError::Io(std::io::Error::new(
+    /// Location of `op_id`'s blob (default variant) in the file this reader opened, so it can
+    /// be used in place, e.g. from a memory map, without `get_blob` copying it. The offsets
+    /// mean nothing for any other file, including a rewritten copy of this one. `None` if the
+    /// op is absent or its blob is compressed or encrypted, as the stored bytes are then not the
+    /// blob. Nothing is read: the range is neither CRC-checked (see `verify_blobs`) nor
+    /// bounds-checked (see `validate`).
+    #[must_use]
+    pub fn blob_location(&self, op_id: u32) -> Option<BlobLocation> {
+        self.blob_location_variant(op_id, 0)
+    }
+
+    /// Like `blob_location`, for one variant of `op_id`.
+    #[must_use]
+    pub fn blob_location_variant(&self, op_id: u32, variant: u16) -> Option<BlobLocation> {
+        location_of(
+            &self.header,
+            self.blob_store_offset,
+            &self.manifest,
+            op_id,
+            variant,
+        )
+    }
+
+    /// Variants packed for `op_id`, ascending; empty if the op is absent.
+    #[must_use]
+    pub fn variants(&self, op_id: u32) -> Vec<u16> {
+        variants_of(&self.manifest, op_id)
+            .map(|e| e.variant)
+            .collect()
+    }
+
+    /// Return the blob (default variant) as stored in the blob store, including any alignment padding.
+    pub fn get_blob_padded(&mut self, op_id: u32) -> Result<Option<Vec<u8>>, ClfError> {
+        match self.manifest.get(&(op_id, 0)).copied() {
+            Some(entry) => self.read_blob(&entry, entry.size).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Read the first `len` bytes of `entry`'s stored range, after bounds-checking the whole range.
+    fn read_blob(&self, entry: &ManifestEntry, len: u32) -> Result<Vec<u8>, ClfError> {
+        let start = self.checked_blob_start(entry)?;
+        let mut blob = vec![0u8; len as usize];
+        self.read_at(&mut blob, start)?;
+        Ok(blob)
+    }
+
+    /// File offset of `entry`'s stored range, after bounds-checking the whole range.
+    fn checked_blob_start(&self, entry: &ManifestEntry) -> Result<u64, ClfError> {
+        let start = self.blob_store_offset + u64::from(entry.offset);
+        let end = start + u64::from(entry.size);
+        if end > self.file_len {
+            return Err(self.io_err(std::io::Error::new(
                 std::io::ErrorKind::InvalidData,
-                "manifest entry count exceeds available file data",
+                "manifest entry extends past blob store",
             )));
         }
-        reader.seek(SeekFrom::Start(manifest_start))?;
+        Ok(start)
+    }
 
-        let mut manifest = HashMap::with_capacity(num_entries);
-        for _ in 0..num_entries {
-            let mut entry_buf = [0u8; ManifestEntry::ENTRY_SIZE];
-            reader.read_exact(&mut entry_buf)?;
-            let op_id = u32::from_le_bytes(entry_buf[0..4].try_into().unwrap());
-            let offset = u32::from_le_bytes(entry_buf[4..8].try_into().unwrap());
-            let size = u32::from_le_bytes(entry_buf[8..12].try_into().unwrap());
-            let entry = ManifestEntry {
-                op_id,
-                offset,
-                size,
-            };
-            manifest.insert(op_id, entry);
+    /// Fill `buf` from `offset` in the file; a failure is an `Io` error naming the file.
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<(), ClfError> {
+        read_exact_at(&self.file, buf, offset).map_err(|e| self.io_err(e))
+    }
+
+    /// `e` as a `ClfError::Io` wrapped with the archive's path.
+    fn io_err(&self, e: std::io::Error) -> ClfError {
+        ClfError::Io(e).with_path(&self.path)
+    }
+
+    /// Read `entry`'s first `exact_size` stored bytes into `buf`, replacing its contents.
+    fn read_stored_into(&self, entry: &ManifestEntry, buf: &mut Vec<u8>) -> Result<(), ClfError> {
+        let start = self.checked_blob_start(entry)?;
+        buf.clear();
+        buf.resize(entry.exact_size as usize, 0);
+        self.read_at(buf, start)?;
+        Ok(())
+    }
+
+    /// Append `entry`'s blob (same bytes as `get_blob`) to `out`. Unencoded blobs are read
+    /// straight into `out`; encoded ones go through `scratch` and are decoded from there.
+    fn append_blob(
+        &self,
+        entry: &ManifestEntry,
+        out: &mut Vec<u8>,
+        scratch: &mut Vec<u8>,
+    ) -> Result<(), ClfError> {
+        if entry.flags != 0 {
+            check_decoded_len(entry, self.max_blob_len)?;
+            self.read_stored_into(entry, scratch)?;
+            return decode_into(entry, scratch, out, self.blob_key());
+        }
+        let start = self.checked_blob_start(entry)?;
+        let at = out.len();
+        out.resize(at + entry.exact_size as usize, 0);
+        let read = self
+            .read_at(&mut out[at..], start)
+            .and_then(|()| check_crc(entry, &out[at..]));
+        if read.is_err() {
+            out.truncate(at);
         }
+        read
+    }
 
-        let blob_store_offset = reader.stream_position()?;
+    /// Stream the blob for `op_id` (same bytes as `get_blob`) without buffering it, e.g. to copy
+    /// it into another archive. Blobs are read in chunks straight from the file, through a zstd
+    /// decoder if compressed, and their CRC-32 (if any) is checked at end of stream. Encrypted
+    /// blobs are decrypted up front, since the tag covers the whole blob.
+    pub fn blob_reader(&self, op_id: u32) -> Result<Option<BlobReader<'_>>, ClfError> {
+        self.blob_reader_variant(op_id, 0)
+    }
 
-        // Compute blob store length: either from file size minus optional signature, or from max(offset+size).
-        let has_sig = file_len >= (SIG_BLOCK_LEN as u64) && {
-            reader.seek(SeekFrom::End(-(SIG_BLOCK_LEN as i64)))?;
-            let mut sig_magic = [0u8; 4];
-            reader.read_exact(&mut sig_magic).is_ok() && sig_magic == SIG_MAGIC
-        };
-        let blob_store_len = if has_sig {
-            file_len
-                .saturating_sub(SIG_BLOCK_LEN as u64)
-                .saturating_sub(blob_store_offset)
-        } else {
-            file_len.saturating_sub(blob_store_offset)
+    /// Same as `blob_reader`, for one variant of `op_id`.
+    pub fn blob_reader_variant(
+        &self,
+        op_id: u32,
+        variant: u16,
+    ) -> Result<Option<BlobReader<'_>>, ClfError> {
+        let Some(entry) = self.manifest.get(&(op_id, variant)).copied() else {
+            return Ok(None);
         };
+        #[cfg(feature = "compression")]
+        if entry.flags == ENTRY_FLAG_ZSTD {
+            let stored = self.stored_reader(&entry)?;
+            let decoder = zstd::stream::read::Decoder::new(stored).map_err(|source| {
+                ClfError::Decompress {
+                    op_id: entry.op_id,
+                    source,
+                }
+            })?;
+            return Ok(Some(BlobReader(BlobSource::Zstd {
+                decoder: Box::new(decoder),
+                op_id: entry.op_id,
+                remaining: u64::from(entry.raw_size),
+            })));
+        }
+        if entry.flags != 0 {
+            check_decoded_len(&entry, self.max_blob_len)?;
+            let stored = self.read_blob(&entry, entry.exact_size)?;
+            let decoded = decode_blob(&entry, stored, self.blob_key())?;
+            return Ok(Some(BlobReader(BlobSource::Decoded(Cursor::new(decoded)))));
+        }
+        self.stored_reader(&entry).map(Some)
+    }
 
-        // Re-seek to start of blob store for future get_blob reads.
-        reader.seek(SeekFrom::Start(blob_store_offset))?;
+    /// Streaming reader over the stored bytes of `entry`, checking its CRC-32 at end of stream.
+    fn stored_reader(&self, entry: &ManifestEntry) -> Result<BlobReader<'_>, ClfError> {
+        let pos = self.checked_blob_start(entry)?;
+        Ok(BlobReader(BlobSource::Range {
+            file: &self.file,
+            path: &self.path,
+            pos,
+            end: pos + u64::from(entry.exact_size),
+            crc: entry
+                .crc32
+                .map(|expected| (crc32fast::Hasher::new(), expected, entry.op_id)),
+        }))
+    }
 
-        Ok(ClfReader {
-            header,
-            manifest,
-            reader,
-            blob_store_offset,
-            blob_store_len,
-            signature_block_present: has_sig,
-            signature_verified: false,
+    /// Manifest entry for (op_id, variant), if present.
+    pub(crate) fn entry(&self, op_id: u32, variant: u16) -> Option<ManifestEntry> {
+        self.manifest.get(&(op_id, variant)).copied()
+    }
+
+    /// Streaming reader over the whole blob store as the manifest declares it
+    /// (`declared_blob_store_len` bytes, padding included), for copying it unchanged.
+    pub(crate) fn blob_store_reader(&self) -> BlobReader<'_> {
+        BlobReader(BlobSource::Range {
+            file: &self.file,
+            path: &self.path,
+            pos: self.blob_store_offset,
+            end: self.blob_store_offset + self.declared_blob_store_len,
+            crc: None,
         })
     }
 
-    /// Byte offset in the file where the blob store begins.
+    /// Return the blob for the given `OpType`, mapping it through the op registry.
+    /// Returns `Err(ClfError::UnregisteredOpType)` if the op has no canonical op_id.
+    pub fn get_blob_for(&mut self, op: OpType) -> Result<Option<Vec<u8>>, ClfError> {
+        let op_id = try_op_type_to_clf_id(op).ok_or(ClfError::UnregisteredOpType(op))?;
+        self.get_blob(op_id)
+    }
+
+    /// Whether the manifest has an entry (any variant) for `op` (false if the op has no
+    /// registered op_id).
     #[must_use]
-    pub fn blob_store_offset(&self) -> u64 {
-        self.blob_store_offset
+    pub fn contains(&self, op: OpType) -> bool {
+        try_op_type_to_clf_id(op).is_some_and(|id| variants_of(&self.manifest, id).next().is_some())
+    }
+
+    /// Verify the optional unkeyed signature at end of file: SIG0 (SHA-256 of everything before
+    /// it) or a SHA-256 SIG1 block. Call after open() if the consumer requires a valid signature
+    /// before use. `Ok(true)` only when the signature covers the whole file: an index-only one
+    /// leaves the blob store unchecked, so it gives `Ok(false)` like an unsigned file. Use
+    /// `verify_signature_scope` to tell the two apart.
+    pub fn verify_signature(&mut self) -> Result<bool, ClfError> {
+        self.verify_signature_scope()
+            .map(|scope| scope == SignatureScope::Full)
+    }
+
+    /// Like `verify_signature`, reporting what the signature covers: `Full`, `IndexOnly` (header
+    /// and manifest; the blob store is not read), or `None` when there is no unkeyed signature
+    /// (keyed SIG1 blocks verify with `verify_signature_with` / `verify_signature_hmac`).
+    pub fn verify_signature_scope(&mut self) -> Result<SignatureScope, ClfError> {
+        self.verify_signature_with_progress(no_progress)
     }
 
-    /// Length of the blob store region (excluding an optional trailing signature block).
-    #[must_use]
-    pub fn blob_store_len(&self) -> u64 {
-        self.blob_store_len
+    /// Same as `verify_signature_scope`, calling `progress(bytes_done, bytes_total)` after each
+    /// hashed chunk (`HASH_CHUNK_LEN`, 256 KiB) for feedback on large files. Returning
+    /// `ControlFlow::Break` aborts with `ClfError::Cancelled`.
+    pub fn verify_signature_with_progress<F>(
+        &mut self,
+        mut progress: F,
+    ) -> Result<SignatureScope, ClfError>
+    where
+        F: FnMut(u64, u64) -> ControlFlow<()>,
+    {
+        check_trailing_data(self.eof().expected_len, self.file_len)?;
+        if let Some((data_len, block)) = self.sig1_block()? {
+            if block.algorithm != SIG_ALG_SHA256 {
+                return Ok(SignatureScope::None);
+            }
+            let computed = self.sha256_prefix(self.covered_len(&block, data_len), &mut progress)?;
+            if !constant_time_eq(&computed, &block.signature) {
+                return Err(ClfError::SignatureInvalid);
+            }
+            self.signature_scope = block.scope();
+            return Ok(self.signature_scope);
+        }
+
+        if self.eof().trailer != Trailer::Sig0 {
+            return Ok(SignatureScope::None);
+        }
+        let data_len = self.file_len - SIG_BLOCK_LEN as u64;
+        let mut stored_hash = [0u8; SIG_HASH_LEN];
+        self.read_at(&mut stored_hash, data_len + SIG_MAGIC.len() as u64)?;
+        let computed = self.sha256_prefix(data_len, &mut progress)?;
+        if !constant_time_eq(&computed, &stored_hash) {
+            return Err(ClfError::SignatureInvalid);
+        }
+
+        self.signature_scope = SignatureScope::Full;
+        Ok(self.signature_scope)
     }
 
-    /// Whether a SIG0 + hash block is present at the end of the file (hash not verified).
-    #[must_use]
-    pub fn signature_block_present(&self) -> bool {
-        self.signature_block_present
+    /// Verify an Ed25519 SIG1 block against `key`. Returns `Ok(false)` if the file has no SIG1
+    /// block; `Err(SignatureInvalid)` if the block is for another algorithm or key, or the
+    /// signature does not match the file contents. Legacy SIG0 files still verify with
+    /// `verify_signature`.
+    #[cfg(feature = "ed25519")]
+    pub fn verify_signature_with(
+        &mut self,
+        key: &ed25519_dalek::VerifyingKey,
+    ) -> Result<bool, ClfError> {
+        let Some((data_len, block)) = self.sig1_block()? else {
+            return Ok(false);
+        };
+        if block.algorithm != SIG_ALG_ED25519
+            || block.key_fingerprint != key_fingerprint(key.as_bytes())
+        {
+            return Err(ClfError::SignatureInvalid);
+        }
+        let signature = ed25519_dalek::Signature::from_slice(&block.signature)
+            .map_err(|_| ClfError::SignatureInvalid)?;
+        let digest = self.sha256_prefix(self.covered_len(&block, data_len), no_progress)?;
+        key.verify_strict(&digest, &signature)
+            .map_err(|_| ClfError::SignatureInvalid)?;
+
+        self.signature_scope = block.scope();
+        Ok(true)
     }
 
-    /// Manifest entries sorted by `op_id` (stable order for display and tooling).
-    #[must_use]
-    pub fn manifest_entries(&self) -> Vec<ManifestEntry> {
-        let mut v: Vec<ManifestEntry> = self.manifest.values().copied().collect();
-        v.sort_unstable_by_key(|e| e.op_id);
-        v
+    /// Verify an Ed25519 SIG1 block against a set of trusted keys and report which one matched.
+    /// Fails with `ClfError::UntrustedKey` when the block's key fingerprint is not in `trusted`,
+    /// even if the signature itself is valid, and with `SignatureInvalid` when the file has no
+    /// Ed25519 block or the signature does not match the contents.
+    #[cfg(feature = "ed25519")]
+    pub fn verify_against(&mut self, trusted: &TrustedKeys) -> Result<VerifiedBy, ClfError> {
+        let (_, block) = self.sig1_block()?.ok_or(ClfError::SignatureInvalid)?;
+        if block.algorithm != SIG_ALG_ED25519 {
+            return Err(ClfError::SignatureInvalid);
+        }
+        let mut candidates = trusted.matching(&block.key_fingerprint).peekable();
+        if candidates.peek().is_none() {
+            return Err(ClfError::UntrustedKey {
+                fingerprint: block.key_fingerprint,
+            });
+        }
+        for candidate in candidates {
+            match self.verify_signature_with(&candidate.key) {
+                Ok(_) => {
+                    return Ok(VerifiedBy {
+                        key: candidate.clone(),
+                        scope: self.signature_scope,
+                    })
+                }
+                Err(ClfError::SignatureInvalid) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Err(ClfError::SignatureInvalid)
     }
 
-    /// Return the blob for the given op_id if present. No interpretation of blob contents.
-    pub fn get_blob(&mut self, op_id: u32) -> Result<Option<Vec<u8>>, ClfError> {
-        let entry = match self.manifest.get(&op_id) {
-            Some(e) => e,
-            None => return Ok(None),
+    /// Verify an HMAC-SHA256 SIG1 block with the shared secret `key`. Returns `Ok(false)` if the
+    /// file has no SIG1 block (a SIG0 hash is not a MAC and never passes this check);
+    /// `Err(SignatureInvalid)` if the block uses another algorithm or the MAC does not match.
+    pub fn verify_signature_hmac(&mut self, key: &[u8]) -> Result<bool, ClfError> {
+        let Some((data_len, block)) = self.sig1_block()? else {
+            return Ok(false);
         };
-
-        let start = self.blob_store_offset + u64::from(entry.offset);
-        let end = start + u64::from(entry.size);
-        if end > self.blob_store_offset + self.blob_store_len {
-            return Err(ClfError::Io(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "manifest entry extends past blob store",
-            )));
+        if block.algorithm != SIG_ALG_HMAC_SHA256 {
+            return Err(ClfError::SignatureInvalid);
         }
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+        self.for_each_prefix_chunk(
+            self.covered_len(&block, data_len),
+            |chunk| Mac::update(&mut mac, chunk),
+            no_progress,
+        )?;
+        mac.verify_slice(&block.signature)
+            .map_err(|_| ClfError::SignatureInvalid)?;
 
-        self.reader.seek(SeekFrom::Start(start))?;
-        let mut blob = vec![0u8; entry.size as usize];
-        self.reader.read_exact(&mut blob)?;
-        Ok(Some(blob))
+        self.signature_scope = block.scope();
+        Ok(true)
     }
 
-    /// Verify the optional signature at end of file (SIG0 + SHA-256 of everything before it).
-    /// Call after open() if the consumer requires a valid signature before use.
-    pub fn verify_signature(&mut self) -> Result<bool, ClfError> {
-        let file_len = self.reader.seek(SeekFrom::End(0))?;
-        if file_len < SIG_BLOCK_LEN as u64 {
-            return Ok(false);
+    /// Algorithm of the signature trailer (not verified): `Sha256` for a legacy SIG0 block, the
+    /// block's algorithm for SIG1, `None` for an unsigned file. Fails with
+    /// `ClfError::UnknownSignatureAlgorithm` for a SIG1 id this reader does not know.
+    pub fn signature_algorithm(&self) -> Result<Option<SignatureAlgorithm>, ClfError> {
+        if let Some((_, block)) = self.sig1_block()? {
+            return block
+                .signature_algorithm()
+                .map(Some)
+                .ok_or(ClfError::UnknownSignatureAlgorithm(block.algorithm));
         }
+        Ok(self
+            .signature_block_present()
+            .then_some(SignatureAlgorithm::Sha256))
+    }
 
-        self.reader.seek(SeekFrom::End(-(SIG_BLOCK_LEN as i64)))?;
-        let mut sig_block = [0u8; SIG_BLOCK_LEN];
-        self.reader.read_exact(&mut sig_block)?;
-        let (sig_magic, stored_hash) = sig_block.split_at(4);
-        if sig_magic != SIG_MAGIC {
-            return Ok(false);
+    /// The SIG1 block at the end of the file with the length of the data it covers, if any.
+    /// Fails with `TrailingGarbage` if data was appended after the archive, so a signature is
+    /// never checked at anything but the manifest-consistent position.
+    fn sig1_block(&self) -> Result<Option<(u64, SignatureBlock)>, ClfError> {
+        let eof = self.eof();
+        check_trailing_data(eof.expected_len, self.file_len)?;
+        let Trailer::Sig1(trailer) = eof.trailer else {
+            return Ok(None);
+        };
+        let mut block = vec![0u8; trailer as usize];
+        self.read_at(&mut block, self.file_len - trailer)?;
+        let block = SignatureBlock::parse(&block).ok_or(ClfError::SignatureInvalid)?;
+        Ok(Some((self.file_len - trailer, block)))
+    }
+
+    /// Bytes `block` signs: the header and manifest if it is index-only, else all `data_len`
+    /// bytes before it.
+    fn covered_len(&self, block: &SignatureBlock, data_len: u64) -> u64 {
+        if block.index_only {
+            self.blob_store_offset
+        } else {
+            data_len
         }
+    }
 
-        let data_len = file_len - SIG_BLOCK_LEN as u64;
-        self.reader.seek(SeekFrom::Start(0))?;
+    /// SHA-256 of the first `len` bytes of the file.
+    fn sha256_prefix(
+        &self,
+        len: u64,
+        progress: impl FnMut(u64, u64) -> ControlFlow<()>,
+    ) -> Result<Vec<u8>, ClfError> {
         let mut hasher = Sha256::new();
-        let mut to_read = data_len as usize;
-        let mut buf = [0u8; 4096];
-        while to_read > 0 {
-            let n = to_read.min(buf.len());
-            self.reader.read_exact(&mut buf[..n])?;
-            hasher.update(&buf[..n]);
-            to_read -= n;
-        }
-        let computed = hasher.finalize();
-        if computed.as_slice() != stored_hash {
-            return Err(ClfError::SignatureInvalid);
-        }
+        self.for_each_prefix_chunk(len, |chunk| hasher.update(chunk), progress)?;
+        Ok(hasher.finalize().to_vec())
+    }
 
-        self.signature_verified = true;
-        // Re-seek to blob store for subsequent get_blob.
-        self.reader.seek(SeekFrom::Start(self.blob_store_offset))?;
-        Ok(true)
+    /// Feed the first `len` bytes of the file to `f` in chunks, reporting `(done, len)` to
+    /// `progress` after each; `ControlFlow::Break` stops with `ClfError::Cancelled`.
+    fn for_each_prefix_chunk(
+        &self,
+        len: u64,
+        mut f: impl FnMut(&[u8]),
+        mut progress: impl FnMut(u64, u64) -> ControlFlow<()>,
+    ) -> Result<(), ClfError> {
+        let mut pos = 0u64;
+        let mut buf = vec![0u8; HASH_CHUNK_LEN.min(len as usize)];
+        while pos < len {
+            let n = (len - pos).min(buf.len() as u64) as usize;
+            self.read_at(&mut buf[..n], pos)?;
+            f(&buf[..n]);
+            pos += n as u64;
+            if progress(pos, len).is_break() {
+                return Err(ClfError::Cancelled);
+            }
+        }
+        Ok(())
     }
 
     /// Verify according to policy. This is intentionally forward-compatible so callers
@@ -336,31 +1757,230 @@ impl ClfReader {
         }
     }
 
-    /// Whether verify_signature() was called and succeeded.
+    /// Whether a `verify_signature*` call (or the open-time check) succeeded, whatever its scope.
     #[must_use]
     pub fn signature_verified(&self) -> bool {
-        self.signature_verified
+        self.signature_scope != SignatureScope::None
+    }
+
+    /// What the verified signature covers; `SignatureScope::None` until a verification succeeds.
+    #[must_use]
+    pub fn signature_scope(&self) -> SignatureScope {
+        self.signature_scope
+    }
+
+    /// Length of the prefix of the file the signature trailer covers: everything before it, or
+    /// only the header and manifest for an index-only SIG1 block. `None` for an unsigned
+    /// archive. Nothing is verified.
+    pub fn signed_len(&self) -> Result<Option<u64>, ClfError> {
+        match self.eof().trailer {
+            Trailer::None => Ok(None),
+            Trailer::Sig0 => Ok(Some(self.file_len - SIG_BLOCK_LEN as u64)),
+            Trailer::Sig1(_) => Ok(self
+                .sig1_block()?
+                .map(|(data_len, block)| self.covered_len(&block, data_len))),
+        }
     }
 
-    /// List all op_ids present in the manifest.
+    /// List all op_ids present in the manifest (each once, whatever its variants).
     #[must_use]
     pub fn op_ids(&self) -> Vec<u32> {
-        let mut ids: Vec<u32> = self.manifest.keys().copied().collect();
-        ids.sort_unstable();
-        ids
+        op_ids_of(&self.manifest)
+    }
+
+    /// Report which of `required` are present, which are missing, and the total size of the
+    /// present blobs. Manifest-only: no blob bytes are read.
+    #[must_use]
+    pub fn coverage(&self, required: &[u32]) -> CoverageReport {
+        coverage_of(&self.manifest, required)
+    }
+
+    /// Check manifest consistency without reading blob bytes: every entry must lie inside the
+    /// blob store, and entries may share bytes only as exact aliases (same offset and size).
+    /// Also fails with `ClfError::TrailingGarbage` when bytes follow the manifest-derived end of
+    /// the archive (its last blob, or the signature block after it). This compares
+    /// `declared_blob_store_len` against `file_blob_store_len`, so it reads the end of the file.
+    pub fn validate(&self) -> Result<(), ClfError> {
+        validate_manifest(&self.manifest, self.file_blob_store_len())?;
+        check_trailing_data(self.eof().expected_len, self.file_len)
+    }
+
+    /// Bytes past the manifest-derived end of the archive; 0 for a well-formed file.
+    #[must_use]
+    pub fn trailing_data_len(&self) -> u64 {
+        self.file_len.saturating_sub(self.eof().expected_len)
+    }
+
+    /// Check the CRC-32 of every entry that has one (see `PackOptions::per_blob_crc`) with
+    /// positioned reads of its stored bytes; entries without one are skipped. Fails with the
+    /// error `get_blob` would give for the first bad entry in manifest order. With the
+    /// `parallel` feature the blobs are read and checked concurrently.
+    pub fn verify_blobs(&self) -> Result<(), ClfError> {
+        let mut scratch = self.scratch.lock().unwrap_or_else(PoisonError::into_inner);
+        check_crc_entries(&self.manifest, &mut scratch, |buf, entry| {
+            self.read_stored_into(entry, buf)?;
+            check_crc(entry, buf)
+        })
     }
 
     /// Build the code section by concatenating blobs for the given op_ids in order.
-    /// If an op_id is missing: **Fail** returns `Err(ClfError::MissingOpId(id))`, **Skip** appends nothing for that op.
+    /// If an op_id is missing: **Fail** returns `Err(ClfError::MissingOpId(id))`, **Skip** appends nothing for that op,
+    /// **Collect** returns `Err(ClfError::MissingOpIds(ids))` with every missing op_id.
+    /// Blobs are read in file order when the requested order jumps around (`ReadOrder::Auto`).
     pub fn build_code_section(
         &mut self,
         op_ids: &[u32],
         policy: MissingOpIdPolicy,
     ) -> Result<Vec<u8>, ClfError> {
+        self.build_code_section_with_order(op_ids, policy, ReadOrder::Auto)
+    }
+
+    /// Same as `build_code_section`, reading blobs as `order` says. The section is the same
+    /// whatever the order; with a missing op and another failure, which error is reported may
+    /// differ.
+    pub fn build_code_section_with_order(
+        &mut self,
+        op_ids: &[u32],
+        policy: MissingOpIdPolicy,
+        order: ReadOrder,
+    ) -> Result<Vec<u8>, ClfError> {
+        self.build_code_section_impl(op_ids, policy, |_, _| 0, order, |_| {})
+    }
+
+    /// Same as `build_code_section`, calling `progress` once after each op_id is handled
+    /// (appended or skipped). The callback only sees counters, never the output buffer. Blobs
+    /// are read in the requested order (`ReadOrder::Requested`).
+    pub fn build_code_section_with_progress<F: FnMut(ProgressEvent)>(
+        &mut self,
+        op_ids: &[u32],
+        policy: MissingOpIdPolicy,
+        mut progress: F,
+    ) -> Result<Vec<u8>, ClfError> {
+        self.build_code_section_impl(
+            op_ids,
+            policy,
+            |_, _| 0,
+            ReadOrder::Requested,
+            &mut progress,
+        )
+    }
+
+    /// Same as `build_code_section`, but `select` picks the variant of each op: it is called
+    /// with the op_id and its packed variants (ascending, empty if the op is absent). A selected
+    /// variant that is not packed counts as a missing op.
+    pub fn build_code_section_with_variants<S: FnMut(u32, &[u16]) -> u16>(
+        &mut self,
+        op_ids: &[u32],
+        policy: MissingOpIdPolicy,
+        select: S,
+    ) -> Result<Vec<u8>, ClfError> {
+        self.build_code_section_impl(op_ids, policy, select, ReadOrder::Auto, |_| {})
+    }
+
+    /// Build the code section from the op_ids the archive has, skipping the others, and report
+    /// every missing op_id along with where each blob landed. Never fails for a missing op;
+    /// whether `missing` may be non-empty is the caller's call. Blobs are read as
+    /// `build_code_section` reads them (`ReadOrder::Auto`).
+    pub fn build_code_section_report(
+        &mut self,
+        op_ids: &[u32],
+    ) -> Result<CodeSectionResult, ClfError> {
+        let plan = code_section_plan(&self.manifest, op_ids, |_, _| 0);
+        let missing = missing_in(&plan);
+        let layout = layout_of(&plan);
+        let bytes = self.build_from_plan(plan, MissingOpIdPolicy::Skip, ReadOrder::Auto, |_| {})?;
+        Ok(CodeSectionResult {
+            bytes,
+            missing,
+            layout,
+        })
+    }
+
+    /// Same as `build_code_section`, but each distinct op_id's blob is emitted once, at its first
+    /// request, and `blobs` says where; call sites of a repeated op all point at that copy.
+    /// Missing op_ids are handled as `policy` says. `build_code_section` keeps emitting one copy
+    /// per request for consumers that rely on that positional layout.
+    pub fn build_unique_code_section(
+        &mut self,
+        op_ids: &[u32],
+        policy: MissingOpIdPolicy,
+    ) -> Result<UniqueCodeSection, ClfError> {
+        let mut seen = HashSet::with_capacity(op_ids.len());
+        let unique: Vec<u32> = op_ids
+            .iter()
+            .copied()
+            .filter(|&id| seen.insert(id))
+            .collect();
+        let plan = code_section_plan(&self.manifest, &unique, |_, _| 0);
+        let blobs = layout_of(&plan).into_iter().collect();
+        let bytes = self.build_from_plan(plan, policy, ReadOrder::Auto, |_| {})?;
+        Ok(UniqueCodeSection { bytes, blobs })
+    }
+
+    /// What `build_code_section(op_ids, policy)` would build, without reading any blob. Fails
+    /// with `ClfError::MissingOpId` like the build does under `MissingOpIdPolicy::Fail`, and
+    /// with `ClfError::MissingOpIds` under `MissingOpIdPolicy::Collect`.
+    pub fn plan_code_section(
+        &self,
+        op_ids: &[u32],
+        policy: MissingOpIdPolicy,
+    ) -> Result<CodeSectionPlan, ClfError> {
+        check_plan(code_section_plan(&self.manifest, op_ids, |_, _| 0), policy)
+    }
+
+    /// Same as `plan_code_section`, choosing variants as `build_code_section_with_variants` does.
+    pub fn plan_code_section_with_variants<S: FnMut(u32, &[u16]) -> u16>(
+        &self,
+        op_ids: &[u32],
+        policy: MissingOpIdPolicy,
+        select: S,
+    ) -> Result<CodeSectionPlan, ClfError> {
+        check_plan(code_section_plan(&self.manifest, op_ids, select), policy)
+    }
+
+    fn build_code_section_impl(
+        &mut self,
+        op_ids: &[u32],
+        policy: MissingOpIdPolicy,
+        select: impl FnMut(u32, &[u16]) -> u16,
+        order: ReadOrder,
+        progress: impl FnMut(ProgressEvent),
+    ) -> Result<Vec<u8>, ClfError> {
+        let plan = code_section_plan(&self.manifest, op_ids, select);
+        self.build_from_plan(plan, policy, order, progress)
+    }
+
+    /// Build the section `plan` describes, handling its missing ops as `policy` says.
+    fn build_from_plan(
+        &mut self,
+        plan: CodeSectionPlan,
+        policy: MissingOpIdPolicy,
+        order: ReadOrder,
+        mut progress: impl FnMut(ProgressEvent),
+    ) -> Result<Vec<u8>, ClfError> {
+        let plan = if policy == MissingOpIdPolicy::Collect {
+            check_plan(plan, policy)?
+        } else {
+            plan
+        };
+        for entry in plan.ops.iter().filter_map(|(_, entry)| entry.as_ref()) {
+            check_decoded_len(entry, self.max_blob_len)?;
+        }
+        let ops_total = plan.ops.len();
         let mut out = Vec::new();
-        for &op_id in op_ids {
-            match self.get_blob(op_id)? {
-                Some(blob) => out.extend_from_slice(&blob),
+        // The sizes come from the file, so a failed reservation just falls back to growing.
+        if let Ok(total_len) = usize::try_from(plan.total_len) {
+            let _ = out.try_reserve_exact(total_len);
+        }
+        let mut scratch = self.scratch.lock().unwrap_or_else(PoisonError::into_inner);
+        if order.file_order_for(&plan) {
+            let plan = check_plan(plan, policy)?;
+            self.fill_in_file_order(&plan, &mut out, &mut scratch)?;
+            return Ok(out);
+        }
+        for (i, &(op_id, entry)) in plan.ops.iter().enumerate() {
+            match entry {
+                Some(entry) => self.append_blob(&entry, &mut out, &mut scratch)?,
                 None => {
                     if policy == MissingOpIdPolicy::Fail {
                         return Err(ClfError::MissingOpId(op_id));
@@ -368,10 +1988,149 @@ impl ClfReader {
                     // Skip: append nothing.
                 }
             }
+            progress(ProgressEvent {
+                ops_completed: i + 1,
+                ops_total,
+                bytes_written: out.len() as u64,
+            });
         }
         Ok(out)
     }
 
+    /// Build `plan`'s section into `out` with `ReadOrder::FileOrder`: each blob is written
+    /// (decoded if needed) at its position in the section as `read_in_file_order` reads it.
+    fn fill_in_file_order(
+        &self,
+        plan: &CodeSectionPlan,
+        out: &mut Vec<u8>,
+        scratch: &mut Vec<u8>,
+    ) -> Result<(), ClfError> {
+        // Each appended blob with its position in the section.
+        let mut entries = Vec::with_capacity(plan.ops.len());
+        let mut positions = Vec::with_capacity(plan.ops.len());
+        let mut section_len = 0;
+        for entry in plan.ops.iter().filter_map(|(_, entry)| *entry) {
+            entries.push(entry);
+            positions.push(section_len);
+            section_len += blob_len(&entry) as usize;
+        }
+        out.resize(section_len, 0);
+        self.read_in_file_order(&entries, scratch, |i, stored| {
+            let entry = &entries[i];
+            let dst = &mut out[positions[i]..positions[i] + blob_len(entry) as usize];
+            decode_to_slice(entry, stored, dst, self.blob_key())
+        })
+    }
+
+    /// Read the stored bytes of each of `entries` in ascending file offset, merging ranges less
+    /// than 64 KiB apart into one read of up to 4 MiB through `scratch`, and pass them to
+    /// `place` with the entry's index in `entries`.
+    fn read_in_file_order(
+        &self,
+        entries: &[ManifestEntry],
+        scratch: &mut Vec<u8>,
+        mut place: impl FnMut(usize, &[u8]) -> Result<(), ClfError>,
+    ) -> Result<(), ClfError> {
+        let mut order: Vec<usize> = (0..entries.len()).collect();
+        order.sort_by_key(|&i| entries[i].offset);
+
+        let mut rest = order.as_slice();
+        while let Some(&first) = rest.first() {
+            let start = u64::from(entries[first].offset);
+            let mut end = start;
+            let mut n = 0;
+            for entry in rest.iter().map(|&i| &entries[i]) {
+                let entry_start = u64::from(entry.offset);
+                let entry_end = (entry_start + u64::from(entry.exact_size)).max(end);
+                if n > 0
+                    && (entry_start > end + COALESCE_GAP || entry_end - start > MAX_COALESCED_READ)
+                {
+                    break;
+                }
+                self.checked_blob_start(entry)?;
+                end = entry_end;
+                n += 1;
+            }
+            let (group, tail) = rest.split_at(n);
+            rest = tail;
+            scratch.clear();
+            scratch.resize((end - start) as usize, 0);
+            self.read_at(scratch, self.blob_store_offset + start)?;
+            for &i in group {
+                let entry = &entries[i];
+                let from = (u64::from(entry.offset) - start) as usize;
+                place(i, &scratch[from..from + entry.exact_size as usize])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Blobs for each of `op_ids` (default variant, decoded as by `get_blob`), in the order
+    /// given: `None` where the archive lacks the op. Each distinct blob is read once, in file
+    /// order with nearby blobs merged into one read (as `ReadOrder::FileOrder`) through the
+    /// reader's scratch buffer, instead of one seek and read per op. An op_id listed more than
+    /// once gets a copy at each position.
+    pub fn get_blobs(&mut self, op_ids: &[u32]) -> Result<Vec<Option<Vec<u8>>>, ClfError> {
+        let mut distinct: Vec<ManifestEntry> = Vec::new();
+        let mut slot_of: BTreeMap<u32, usize> = BTreeMap::new();
+        let slots: Vec<Option<usize>> = op_ids
+            .iter()
+            .map(|&op_id| {
+                let entry = self.manifest.get(&(op_id, 0))?;
+                Some(*slot_of.entry(op_id).or_insert_with(|| {
+                    distinct.push(*entry);
+                    distinct.len() - 1
+                }))
+            })
+            .collect();
+        for entry in &distinct {
+            check_decoded_len(entry, self.max_blob_len)?;
+        }
+
+        let mut blobs: Vec<Vec<u8>> = vec![Vec::new(); distinct.len()];
+        let mut scratch = self.scratch.lock().unwrap_or_else(PoisonError::into_inner);
+        self.read_in_file_order(&distinct, &mut scratch, |i, stored| {
+            let entry = &distinct[i];
+            let mut blob = vec![0; blob_len(entry) as usize];
+            decode_to_slice(entry, stored, &mut blob, self.blob_key())?;
+            blobs[i] = blob;
+            Ok(())
+        })?;
+
+        // The last request for a blob takes it; earlier ones get copies.
+        let mut remaining = vec![0usize; distinct.len()];
+        for &i in slots.iter().flatten() {
+            remaining[i] += 1;
+        }
+        Ok(slots
+            .into_iter()
+            .map(|slot| {
+                slot.map(|i| {
+                    remaining[i] -= 1;
+                    if remaining[i] == 0 {
+                        std::mem::take(&mut blobs[i])
+                    } else {
+                        blobs[i].clone()
+                    }
+                })
+            })
+            .collect())
+    }
+
+    /// Same as `build_code_section`, for model graphs expressed as `OpType`s.
+    /// Fails with `ClfError::UnregisteredOpType` (regardless of policy) if an op has no registered op_id.
+    pub fn build_code_section_for_ops(
+        &mut self,
+        ops: &[OpType],
+        policy: MissingOpIdPolicy,
+    ) -> Result<Vec<u8>, ClfError> {
+        let op_ids = ops
+            .iter()
+            .map(|&op| try_op_type_to_clf_id(op).ok_or(ClfError::UnregisteredOpType(op)))
+            .collect::<Result<Vec<u32>, ClfError>>()?;
+        self.build_code_section(&op_ids, policy)
+    }
+
     /// Iterate blobs in manifest order (sorted by `op_id`).
     pub fn blobs_iter(&mut self) -> BlobIter<'_> {
         let entries = self.manifest_entries().into_iter();
@@ -382,6 +2141,106 @@ impl ClfReader {
     }
 }
 
+/// Streaming reader over one blob (see `ClfReader::blob_reader`).
+pub struct BlobReader<'a>(BlobSource<'a>);
+
+enum BlobSource<'a> {
+    /// Unencoded blob read positionally from the archive; `crc` holds the running checksum,
+    /// the expected value and the op_id while a CRC-32 check is pending.
+    Range {
+        file: &'a File,
+        path: &'a Path,
+        pos: u64,
+        end: u64,
+        crc: Option<(crc32fast::Hasher, u32, u32)>,
+    },
+    /// zstd blob decoded as it is read from a `Range` over its stored bytes; `remaining` counts
+    /// down the raw size still expected.
+    #[cfg(feature = "compression")]
+    Zstd {
+        decoder: Box<zstd::stream::read::Decoder<'static, std::io::BufReader<BlobReader<'a>>>>,
+        op_id: u32,
+        remaining: u64,
+    },
+    /// Encoded blob, decoded in memory.
+    Decoded(Cursor<Vec<u8>>),
+}
+
+impl Read for BlobReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match &mut self.0 {
+            BlobSource::Decoded(cursor) => cursor.read(buf),
+            #[cfg(feature = "compression")]
+            BlobSource::Zstd {
+                decoder,
+                op_id,
+                remaining,
+            } => {
+                if buf.is_empty() {
+                    return Ok(0);
+                }
+                let op_id = *op_id;
+                let decompress_err = |source: std::io::Error| {
+                    // Errors from the stored bytes (I/O, CRC-32) pass through as they are.
+                    if source.get_ref().is_some_and(|e| e.is::<ClfError>()) {
+                        return source;
+                    }
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        ClfError::Decompress { op_id, source },
+                    )
+                };
+                let n = decoder.read(buf).map_err(decompress_err)?;
+                if n as u64 > *remaining || (n == 0 && *remaining != 0) {
+                    return Err(decompress_err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "decompressed length does not match raw size",
+                    )));
+                }
+                *remaining -= n as u64;
+                if n == 0 {
+                    // Run the stored bytes to their end so the CRC-32 is checked.
+                    std::io::copy(decoder.get_mut(), &mut std::io::sink())
+                        .map_err(decompress_err)?;
+                }
+                Ok(n)
+            }
+            BlobSource::Range {
+                file,
+                path,
+                pos,
+                end,
+                crc,
+            } => {
+                if *pos == *end {
+                    if let Some((hasher, expected, op_id)) = crc.take() {
+                        let actual = hasher.finalize();
+                        if actual != expected {
+                            return Err(std::io::Error::new(
+                                std::io::ErrorKind::InvalidData,
+                                ClfError::BlobChecksumMismatch {
+                                    op_id,
+                                    expected,
+                                    actual,
+                                },
+                            ));
+                        }
+                    }
+                    return Ok(0);
+                }
+                let n = ((*end - *pos) as usize).min(buf.len());
+                read_exact_at(file, &mut buf[..n], *pos)
+                    .map_err(|e| std::io::Error::new(e.kind(), ClfError::Io(e).with_path(path)))?;
+                if let Some((hasher, _, _)) = crc {
+                    hasher.update(&buf[..n]);
+                }
+                *pos += n as u64;
+                Ok(n)
+            }
+        }
+    }
+}
+
 /// Iterator over `(op_id, blob bytes)` in manifest order (by `op_id`).
 pub struct BlobIter<'a> {
     reader: &'a mut ClfReader,
@@ -393,7 +2252,7 @@ impl<'a> Iterator for BlobIter<'a> {
 
     fn next(&mut self) -> Option<Self::Item> {
         let e = self.entries.next()?;
-        Some(match self.reader.get_blob(e.op_id) {
+        Some(match self.reader.get_blob_variant(e.op_id, e.variant) {
             Ok(Some(b)) => Ok((e.op_id, b)),
             Ok(None) => Err(ClfError::Io(std::io::Error::new(
                 std::io::ErrorKind::InvalidData,
@@ -408,114 +2267,168 @@ impl<'a> Iterator for BlobIter<'a> {
 #[derive(Debug)]
 pub struct ClfReaderFromBytes {
     pub header: ClfHeader,
-    manifest: HashMap<u32, ManifestEntry>,
+    manifest: Manifest,
+    targets: Vec<String>,
     data: Vec<u8>,
     blob_store_offset: u64,
-    blob_store_len: u64,
+    declared_blob_store_len: u64,
+    file_blob_store_len: u64,
     signature_block_present: bool,
+    expected_len: u64,
+    max_blob_len: u32,
+    #[cfg(feature = "encryption")]
+    decryption_key: Option<EncryptionKey>,
 }
 
 impl ClfReaderFromBytes {
     /// Open CLF from bytes. When `expected_kind` is `Some(k)`, rejects if header kind does not match.
     pub fn open(data: &[u8], expected_kind: Option<ClfKind>) -> Result<Self, ClfError> {
+        Self::open_with_options(
+            data,
+            &ClfReaderOptions {
+                expected_kind,
+                ..ClfReaderOptions::default()
+            },
+        )
+    }
+
+    /// Open CLF from bytes with explicit options (see `ClfReaderOptions`).
+    pub fn open_with_options(data: &[u8], options: &ClfReaderOptions) -> Result<Self, ClfError> {
         let mut cursor = Cursor::new(data);
-        let mut magic = [0u8; 4];
-        cursor.read_exact(&mut magic)?;
-        if magic != CLF_MAGIC {
-            return Err(ClfError::InvalidMagic);
-        }
-        let mut version_byte = [0u8; 1];
-        cursor.read_exact(&mut version_byte)?;
-        let version = version_byte[0];
-        if version > CLF_VERSION {
-            return Err(ClfError::UnsupportedVersion(version, CLF_VERSION));
-        }
-        let vendor = read_len_prefixed_utf8(&mut cursor, "vendor")?;
-        let target = read_len_prefixed_utf8(&mut cursor, "target")?;
-        let mut blob_align_byte = [0u8; 1];
-        cursor.read_exact(&mut blob_align_byte)?;
-        let blob_alignment = blob_align_byte[0];
-        let kind = if version >= 2 {
-            let mut kind_byte = [0u8; 1];
-            cursor.read_exact(&mut kind_byte)?;
-            ClfKind::try_from_byte(kind_byte[0]).ok_or(ClfError::InvalidKindByte(kind_byte[0]))?
-        } else {
-            ClfKind::default_for_v1()
-        };
-        let header_end = cursor.stream_position()?;
-        let header = ClfHeader {
-            version,
-            vendor,
-            target,
-            blob_alignment,
-            kind,
-            header_end,
-        };
-        if let Some(expected) = expected_kind {
-            if header.kind != expected {
-                return Err(ClfError::KindMismatch {
-                    expected,
-                    actual: header.kind,
-                });
-            }
-        }
-        let mut num_entries_buf = [0u8; 4];
-        cursor.read_exact(&mut num_entries_buf)?;
-        let num_entries = u32::from_le_bytes(num_entries_buf) as usize;
-        let manifest_start = cursor.stream_position()?;
-        let required_manifest_bytes = (num_entries as u64) * (ManifestEntry::ENTRY_SIZE as u64);
-        if required_manifest_bytes > (data.len() as u64).saturating_sub(manifest_start) {
-            return Err(ClfError::Io(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "manifest entry count exceeds available CLF bytes",
-            )));
-        }
-        let mut manifest = HashMap::with_capacity(num_entries);
-        for _ in 0..num_entries {
-            let mut entry_buf = [0u8; ManifestEntry::ENTRY_SIZE];
-            cursor.read_exact(&mut entry_buf)?;
-            let op_id = u32::from_le_bytes(entry_buf[0..4].try_into().unwrap());
-            let offset = u32::from_le_bytes(entry_buf[4..8].try_into().unwrap());
-            let size = u32::from_le_bytes(entry_buf[8..12].try_into().unwrap());
-            manifest.insert(
-                op_id,
-                ManifestEntry {
-                    op_id,
-                    offset,
-                    size,
-                },
-            );
-        }
+        let header = read_header_with(&mut cursor, options.lenient_kind, &ClfLimits::FORMAT_MAX)?;
+        check_expected_kind(&header, options.expected_kind)?;
+        let (manifest, targets, _) = read_manifest(
+            &mut FieldReader::at(&mut cursor, header.header_end),
+            header.version,
+            (data.len() as u64).saturating_sub(header.header_end),
+            &ClfLimits::FORMAT_MAX,
+        )?;
+        check_expected_target(&header, &targets, &manifest, options)?;
         let blob_store_offset = cursor.stream_position()?;
         let data_len = data.len() as u64;
-        let has_sig = data_len >= (SIG_BLOCK_LEN as u64)
-            && data.len() >= SIG_BLOCK_LEN
-            && data[data.len() - SIG_BLOCK_LEN..data.len() - SIG_HASH_LEN] == SIG_MAGIC;
-        let blob_store_len = if has_sig {
-            data_len
-                .saturating_sub(SIG_BLOCK_LEN as u64)
-                .saturating_sub(blob_store_offset)
-        } else {
-            data_len.saturating_sub(blob_store_offset)
-        };
+        let EofLayout {
+            trailer,
+            expected_len,
+        } = eof_layout(
+            &manifest,
+            blob_store_offset,
+            header.blob_alignment,
+            data_len,
+            slice_read_at(data),
+        );
         Ok(Self {
             header,
+            declared_blob_store_len: declared_store_len(&manifest),
             manifest,
+            targets,
             data: data.to_vec(),
             blob_store_offset,
-            blob_store_len,
-            signature_block_present: has_sig,
+            file_blob_store_len: data_len
+                .saturating_sub(trailer.len())
+                .saturating_sub(blob_store_offset),
+            signature_block_present: trailer != Trailer::None,
+            expected_len,
+            max_blob_len: options.max_blob_len(),
+            #[cfg(feature = "encryption")]
+            decryption_key: None,
         })
     }
 
+    /// Same as `ClfReader::set_decryption_key`.
+    #[cfg(feature = "encryption")]
+    pub fn set_decryption_key(&mut self, key: EncryptionKey) {
+        self.decryption_key = Some(key);
+    }
+
+    /// Same as `ClfReader::blob_key`.
+    fn blob_key(&self) -> BlobKey<'_> {
+        #[cfg(feature = "encryption")]
+        return self.decryption_key.as_ref();
+        #[cfg(not(feature = "encryption"))]
+        None
+    }
+
     #[must_use]
     pub fn blob_store_offset(&self) -> u64 {
         self.blob_store_offset
     }
 
+    /// Same as `ClfReader::blob_store_len`.
     #[must_use]
     pub fn blob_store_len(&self) -> u64 {
-        self.blob_store_len
+        self.declared_blob_store_len
+    }
+
+    /// Same as `ClfReader::declared_blob_store_len`.
+    #[must_use]
+    pub fn declared_blob_store_len(&self) -> u64 {
+        self.declared_blob_store_len
+    }
+
+    /// Same as `ClfReader::file_blob_store_len`.
+    #[must_use]
+    pub fn file_blob_store_len(&self) -> u64 {
+        self.file_blob_store_len
+    }
+
+    /// Same as `ClfReader::get_meta`.
+    #[must_use]
+    pub fn get_meta(&self, key: &str) -> Option<&str> {
+        self.header.get_meta(key)
+    }
+
+    /// Same as `ClfReader::entry_name`.
+    #[must_use]
+    pub fn entry_name(&self, op_id: u32) -> Option<&str> {
+        self.header.entry_name(op_id)
+    }
+
+    /// Same as `ClfReader::entry_label`.
+    #[must_use]
+    pub fn entry_label<'b>(&'b self, op_id: u32, registry: &'b OpRegistry) -> Option<&'b str> {
+        self.entry_name(op_id).or_else(|| registry.name(op_id))
+    }
+
+    /// Same as `ClfReader::entry_target`.
+    #[must_use]
+    pub fn entry_target(&self, entry: &ManifestEntry) -> &str {
+        target_of(&self.header, &self.targets, entry)
+    }
+
+    /// Same as `ClfReader::matches_target`.
+    #[must_use]
+    pub fn matches_target(&self, target: &str) -> bool {
+        matches_target(&self.header, &self.targets, &self.manifest, target, None)
+    }
+
+    /// Same as `ClfReader::matches_target_with`.
+    #[must_use]
+    pub fn matches_target_with(&self, matcher: &TargetMatcher, target: &str) -> bool {
+        matches_target(
+            &self.header,
+            &self.targets,
+            &self.manifest,
+            target,
+            Some(matcher),
+        )
+    }
+
+    /// Same as `ClfReader::op_ids_for_target`.
+    #[must_use]
+    pub fn op_ids_for_target(&self, target: &str) -> Vec<u32> {
+        op_ids_for_target_of(&self.header, &self.targets, &self.manifest, target)
+    }
+
+    /// Same as `ClfReader::get_blob_for_target`.
+    pub fn get_blob_for_target(
+        &self,
+        op_id: u32,
+        target: &str,
+    ) -> Result<Option<Vec<u8>>, ClfError> {
+        match variant_for_target(&self.header, &self.targets, &self.manifest, op_id, target) {
+            Some(variant) => self.get_blob_variant(op_id, variant),
+            None => Ok(None),
+        }
     }
 
     #[must_use]
@@ -523,29 +2436,132 @@ impl ClfReaderFromBytes {
         self.signature_block_present
     }
 
+    /// Same as `ClfReader::manifest_view`.
+    #[must_use]
+    pub fn manifest_view(&self) -> ManifestView {
+        let entries = self
+            .manifest
+            .values()
+            .map(|entry| (entry, target_of(&self.header, &self.targets, entry)));
+        ManifestView::new(&self.header, entries, self.signature_block_present)
+    }
+
     #[must_use]
     pub fn manifest_entries(&self) -> Vec<ManifestEntry> {
-        let mut v: Vec<ManifestEntry> = self.manifest.values().copied().collect();
-        v.sort_unstable_by_key(|e| e.op_id);
-        v
+        self.manifest.values().copied().collect()
+    }
+
+    /// Same as `ClfReader::coverage`.
+    #[must_use]
+    pub fn coverage(&self, required: &[u32]) -> CoverageReport {
+        coverage_of(&self.manifest, required)
+    }
+
+    /// Same as `ClfReader::validate`.
+    pub fn validate(&self) -> Result<(), ClfError> {
+        validate_manifest(&self.manifest, self.file_blob_store_len)?;
+        check_trailing_data(self.expected_len, self.data.len() as u64)
+    }
+
+    /// Same as `ClfReader::trailing_data_len`.
+    #[must_use]
+    pub fn trailing_data_len(&self) -> u64 {
+        (self.data.len() as u64).saturating_sub(self.expected_len)
+    }
+
+    /// Same as `ClfReader::verify_blobs`.
+    pub fn verify_blobs(&self) -> Result<(), ClfError> {
+        check_crc_entries(&self.manifest, &mut Vec::new(), |_, entry| {
+            check_crc(entry, self.blob_slice(entry, entry.exact_size)?)
+        })
     }
 
-    /// Get blob for op_id.
+    /// Get blob for op_id, default variant (alignment padding trimmed for v3+ files).
     pub fn get_blob(&self, op_id: u32) -> Result<Option<Vec<u8>>, ClfError> {
-        let entry = match self.manifest.get(&op_id) {
-            Some(e) => e,
-            None => return Ok(None),
-        };
+        self.get_blob_variant(op_id, 0)
+    }
+
+    /// Same as `ClfReader::resolve_op_id`.
+    #[must_use]
+    pub fn resolve_op_id(&self, op_id: u32, registry: &OpRegistry) -> Option<u32> {
+        resolve_in(&self.manifest, op_id, registry)
+    }
+
+    /// Same as `ClfReader::get_blob_canonical`.
+    pub fn get_blob_canonical(
+        &self,
+        op_id: u32,
+        registry: &OpRegistry,
+    ) -> Result<Option<Vec<u8>>, ClfError> {
+        match self.resolve_op_id(op_id, registry) {
+            Some(id) => self.get_blob(id),
+            None => Ok(None),
+        }
+    }
+
+    /// Same as `ClfReader::get_blob_variant`.
+    pub fn get_blob_variant(&self, op_id: u32, variant: u16) -> Result<Option<Vec<u8>>, ClfError> {
+        match self.manifest.get(&(op_id, variant)) {
+            Some(entry) => {
+                check_decoded_len(entry, self.max_blob_len)?;
+                let stored = self.blob_slice(entry, entry.exact_size)?.to_vec();
+                decode_blob(entry, stored, self.blob_key()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Same as `ClfReader::blob_location`, with offsets into the bytes this reader opened.
+    #[must_use]
+    pub fn blob_location(&self, op_id: u32) -> Option<BlobLocation> {
+        self.blob_location_variant(op_id, 0)
+    }
+
+    /// Same as `ClfReader::blob_location_variant`.
+    #[must_use]
+    pub fn blob_location_variant(&self, op_id: u32, variant: u16) -> Option<BlobLocation> {
+        location_of(
+            &self.header,
+            self.blob_store_offset,
+            &self.manifest,
+            op_id,
+            variant,
+        )
+    }
+
+    /// Same as `ClfReader::get_blobs`; the data is already in memory, so each blob is simply
+    /// decoded in turn.
+    pub fn get_blobs(&self, op_ids: &[u32]) -> Result<Vec<Option<Vec<u8>>>, ClfError> {
+        op_ids.iter().map(|&op_id| self.get_blob(op_id)).collect()
+    }
+
+    /// Same as `ClfReader::variants`.
+    #[must_use]
+    pub fn variants(&self, op_id: u32) -> Vec<u16> {
+        variants_of(&self.manifest, op_id)
+            .map(|e| e.variant)
+            .collect()
+    }
+
+    /// Get blob for op_id (default variant) as stored, including alignment padding.
+    pub fn get_blob_padded(&self, op_id: u32) -> Result<Option<Vec<u8>>, ClfError> {
+        match self.manifest.get(&(op_id, 0)) {
+            Some(entry) => self.blob_slice(entry, entry.size).map(|b| Some(b.to_vec())),
+            None => Ok(None),
+        }
+    }
+
+    fn blob_slice(&self, entry: &ManifestEntry, len: u32) -> Result<&[u8], ClfError> {
         let start = (self.blob_store_offset + u64::from(entry.offset)) as usize;
         let end = start + entry.size as usize;
-        let blob_store_end = self.blob_store_offset as usize + self.blob_store_len as usize;
+        let blob_store_end = self.blob_store_offset as usize + self.file_blob_store_len as usize;
         if end > blob_store_end || end > self.data.len() {
             return Err(ClfError::Io(std::io::Error::new(
                 std::io::ErrorKind::InvalidData,
                 "manifest entry extends past blob store",
             )));
         }
-        Ok(Some(self.data[start..end].to_vec()))
+        Ok(&self.data[start..start + len as usize])
     }
 
     /// Iterate blobs in manifest order (sorted by `op_id`).
@@ -568,7 +2584,7 @@ impl<'a> Iterator for BlobIterFromBytes<'a> {
 
     fn next(&mut self) -> Option<Self::Item> {
         let e = self.entries.next()?;
-        Some(match self.reader.get_blob(e.op_id) {
+        Some(match self.reader.get_blob_variant(e.op_id, e.variant) {
             Ok(Some(b)) => Ok((e.op_id, b)),
             Ok(None) => Err(ClfError::Io(std::io::Error::new(
                 std::io::ErrorKind::InvalidData,
@@ -0,0 +1,254 @@
+//! Repack a subset of an existing .clf (e.g. strip a large library down to the ops one model needs).
+//!
+//! Blobs are streamed from the source archive into a new one with fresh offsets; nothing is
+//! buffered beyond one chunk per blob (compressed source blobs are decoded in memory, and with
+//! `dedup_blobs` or compression each output blob is held in memory until it is written).
+//! `convert_version_to_path` rewrites a whole archive in another format version, e.g. legacy v1
+//! files as v3.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Seek, Write};
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::format::{ClfKind, VersionFeatures};
+use crate::packer::{
+    append_requested_signature, pack_clf_variants_from_sources, write_atomically_verified,
+    BlobSource, PackError, PackOptions, PackSummary,
+};
+use crate::reader::{ClfError, ClfReader, MissingOpIdPolicy};
+
+/// Options for `repack`.
+#[derive(Debug, Clone)]
+pub struct RepackOptions {
+    /// What to do when a requested op_id is not in the source: **Fail** returns
+    /// `ClfError::MissingOpId`, **Skip** leaves it out, **Collect** returns
+    /// `ClfError::MissingOpIds` with every missing op_id.
+    pub on_missing: MissingOpIdPolicy,
+    /// Output vendor; `None` keeps the source header's vendor.
+    pub vendor: Option<String>,
+    /// Output target; `None` keeps the source header's target.
+    pub target: Option<String>,
+    /// Output kind; `None` keeps the source header's kind.
+    pub kind: Option<ClfKind>,
+    /// Output layout (alignment, version, compression, checksums, dedup) and signing.
+    /// Its `vendor`, `target` and `kind` are ignored in favour of the fields above.
+    pub pack: PackOptions,
+}
+
+impl Default for RepackOptions {
+    fn default() -> Self {
+        Self {
+            on_missing: MissingOpIdPolicy::Fail,
+            vendor: None,
+            target: None,
+            kind: None,
+            pack: PackOptions::default(),
+        }
+    }
+}
+
+/// Errors produced by `repack`.
+#[derive(Debug, Error)]
+pub enum RepackError {
+    #[error("reading source: {0}")]
+    Read(#[from] ClfError),
+    #[error("writing output: {0}")]
+    Pack(#[from] PackError),
+    #[error("format v{version} cannot express the {feature} this archive uses")]
+    Downgrade { version: u8, feature: &'static str },
+    #[error("converted archive returns different bytes for op_id {op_id} (variant {variant})")]
+    RoundTripMismatch { op_id: u32, variant: u16 },
+}
+
+/// Write a new archive to `out` holding only the `keep` entries of `reader`, in `keep` order
+/// (repeated op_ids are written once, each with all its variants). Appends the signature
+/// `options.pack` requests (`sign`, `hmac_key` or `sign_key`, covering only the header and
+/// manifest with `sign_index_only`). `out` should start empty: signing a stream that holds more
+/// than the new archive fails with `PackError::TrailingData`.
+pub fn repack<W: Read + Write + Seek>(
+    reader: &mut ClfReader,
+    keep: &[u32],
+    options: &RepackOptions,
+    mut out: W,
+) -> Result<PackSummary, RepackError> {
+    let summary = repack_unsigned(reader, keep, options, &mut out)?;
+    append_requested_signature(&mut out, summary.data_len, &options.pack)?;
+    Ok(summary)
+}
+
+/// `repack` without the signature.
+fn repack_unsigned<W: Write + Seek>(
+    reader: &ClfReader,
+    keep: &[u32],
+    options: &RepackOptions,
+    out: &mut W,
+) -> Result<PackSummary, RepackError> {
+    if options.on_missing == MissingOpIdPolicy::Collect {
+        let coverage = reader.coverage(keep);
+        if !coverage.missing.is_empty() {
+            return Err(ClfError::MissingOpIds(coverage.missing).into());
+        }
+    }
+    let mut seen = HashSet::with_capacity(keep.len());
+    let mut sources: Vec<(u32, u16, Box<dyn BlobSource + '_>)> = Vec::with_capacity(keep.len());
+    let mut entry_targets = HashMap::new();
+    let mut entry_names = HashMap::new();
+    for &op_id in keep {
+        if !seen.insert(op_id) {
+            continue;
+        }
+        entry_targets.extend(reader.entry_target_tags(op_id));
+        if let Some(name) = reader.entry_name(op_id) {
+            entry_names.insert(op_id, name.to_string());
+        }
+        let variants = reader.variants(op_id);
+        if variants.is_empty() && options.on_missing == MissingOpIdPolicy::Fail {
+            return Err(ClfError::MissingOpId(op_id).into());
+        }
+        for variant in variants {
+            let source = ArchiveBlob {
+                reader,
+                op_id,
+                variant,
+            };
+            sources.push((op_id, variant, Box::new(source)));
+        }
+    }
+
+    let header = &reader.header;
+    let pack = PackOptions {
+        vendor: options
+            .vendor
+            .clone()
+            .unwrap_or_else(|| header.vendor.clone()),
+        target: options
+            .target
+            .clone()
+            .unwrap_or_else(|| header.target.clone()),
+        kind: options.kind.unwrap_or(header.kind),
+        ..options.pack.clone()
+    };
+    // Tags and names set in `options.pack` win over the source's.
+    entry_targets.extend(pack.entry_targets);
+    entry_names.extend(pack.entry_names);
+    let pack = PackOptions {
+        entry_targets,
+        entry_names,
+        ..pack
+    };
+    Ok(pack_clf_variants_from_sources(out, sources, &pack)?)
+}
+
+/// One entry of a source archive as a packer input, streamed (decoded) from the archive each
+/// time it is read, so the packer can read it twice.
+struct ArchiveBlob<'r> {
+    reader: &'r ClfReader,
+    op_id: u32,
+    variant: u16,
+}
+
+impl BlobSource for ArchiveBlob<'_> {
+    fn len(&self) -> Option<u64> {
+        self.reader
+            .entry(self.op_id, self.variant)
+            .map(|e| u64::from(e.raw_size))
+    }
+
+    fn read_into(&mut self, w: &mut dyn Write) -> std::io::Result<u64> {
+        let mut blob = self
+            .reader
+            .blob_reader_variant(self.op_id, self.variant)
+            .map_err(std::io::Error::other)?
+            .expect("listed variant is in the manifest");
+        std::io::copy(&mut blob, w)
+    }
+
+    fn can_reread(&self) -> bool {
+        true
+    }
+}
+
+/// Rewrite every entry of `reader` as format `options.pack.version` at `path`, keeping the
+/// header metadata, entry names, extensions, variants and per-entry targets; `options` supplies
+/// the rest of the layout, header overrides and signature (`on_missing` is unused). The result is
+/// written to a temp file, and replaces `path` only once every blob reads back byte-identical
+/// (`RepackError::RoundTripMismatch` otherwise). At the source's own version this re-encodes the
+/// blobs as `options.pack` asks, e.g. to compress or decompress them; a blob that zstd would grow
+/// is stored raw.
+///
+/// Converting to an older version fails with `RepackError::Downgrade` when the output would need
+/// a feature that version lacks: a kind other than Compute in v1, or blob sizes that are not a
+/// multiple of the alignment before v3 (readers would return them padded). Header sections,
+/// variants, targets, checksums and compression fail with `PackError::RequiresV3`.
+pub fn convert_version_to_path<P: AsRef<Path>>(
+    reader: &mut ClfReader,
+    options: &RepackOptions,
+    path: P,
+) -> Result<PackSummary, RepackError> {
+    let header = &reader.header;
+    let features = VersionFeatures::for_version(options.pack.version);
+    if !features.has_kind && options.kind.unwrap_or(header.kind) != ClfKind::Compute {
+        return Err(RepackError::Downgrade {
+            version: options.pack.version,
+            feature: "kind byte",
+        });
+    }
+    let align = u32::from(options.pack.blob_alignment).max(1);
+    if !features.has_exact_size
+        && reader
+            .manifest_entries()
+            .iter()
+            .any(|e| e.raw_size % align != 0)
+    {
+        return Err(RepackError::Downgrade {
+            version: options.pack.version,
+            feature: "exact blob sizes",
+        });
+    }
+    let options = RepackOptions {
+        pack: PackOptions {
+            metadata: header.metadata.clone(),
+            entry_names: header
+                .entry_names
+                .iter()
+                .map(|(&op_id, name)| (op_id, name.clone()))
+                .collect(),
+            extensions: header.extensions.clone(),
+            ..options.pack.clone()
+        },
+        ..options.clone()
+    };
+    let keep = reader.op_ids();
+    let mut source = reader.try_clone()?;
+    write_atomically_verified(
+        path.as_ref(),
+        &options.pack,
+        |file| repack_unsigned(reader, &keep, &options, file),
+        |written| verify_round_trip(&mut source, written, &options.pack),
+    )
+}
+
+/// `RoundTripMismatch` for the first entry of `source` whose blob differs in the archive at
+/// `converted`, which was packed with `options` (and so is read with its encryption key).
+fn verify_round_trip(
+    source: &mut ClfReader,
+    converted: &Path,
+    options: &PackOptions,
+) -> Result<(), RepackError> {
+    let mut converted = ClfReader::open(converted)?;
+    #[cfg(feature = "encryption")]
+    if let Some(key) = &options.encrypt {
+        converted.set_decryption_key(key.clone());
+    }
+    #[cfg(not(feature = "encryption"))]
+    let _ = options;
+    for entry in source.manifest_entries() {
+        let (op_id, variant) = (entry.op_id, entry.variant);
+        if source.get_blob_variant(op_id, variant)? != converted.get_blob_variant(op_id, variant)? {
+            return Err(RepackError::RoundTripMismatch { op_id, variant });
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,396 @@
+//! In-memory archive view that builds without `std`: `ClfSlice` parses the header and manifest of
+//! a CLF already mapped into memory and lends blobs straight out of it. The manifest parser and
+//! bounds checks here are the ones `ClfReader` and `ClfReaderFromBytes` use as well.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::error::ClfError;
+use crate::format::{
+    read_header_with, read_len_prefixed_utf8, ByteCursor, ClfHeader, ClfKind, ClfLimits,
+    FieldReader, ManifestEntry, Source, VersionFeatures, AES_GCM_NONCE_LEN, AES_GCM_TAG_LEN,
+    ENTRY_FIELD_COMPRESSION, ENTRY_FIELD_CRC32, ENTRY_FIELD_TARGET, ENTRY_FIELD_VARIANT,
+    ENTRY_FLAG_AES256GCM, ENTRY_FLAG_ZSTD, KNOWN_ENTRY_FIELDS,
+};
+
+/// A CLF archive borrowed from memory (e.g. a mapped flash region): header and manifest are
+/// parsed once, blobs are returned as subslices of the data without copying.
+///
+/// `open` checks that every entry lies inside `data` and that stored ranges do not partially
+/// overlap, so `get_blob` never reads out of bounds. A signature trailer is neither located nor
+/// checked; verify the archive before trusting its contents.
+#[derive(Debug, Clone)]
+pub struct ClfSlice<'a> {
+    pub header: ClfHeader,
+    manifest: Manifest,
+    targets: Vec<String>,
+    data: &'a [u8],
+    blob_store_offset: usize,
+}
+
+impl<'a> ClfSlice<'a> {
+    /// Parse `data` (a whole archive) with strict kind parsing. When `expected_kind` is
+    /// `Some(k)`, the header kind must be `k`.
+    pub fn open(data: &'a [u8], expected_kind: Option<ClfKind>) -> Result<Self, ClfError> {
+        let mut rest = data;
+        let header = read_header_with(&mut rest, false, &ClfLimits::FORMAT_MAX)?;
+        if let Some(expected) = expected_kind {
+            if header.kind != expected {
+                return Err(ClfError::KindMismatch {
+                    expected,
+                    actual: header.kind,
+                });
+            }
+        }
+        let available = rest.len() as u64;
+        let (manifest, targets, _) = read_manifest(
+            &mut FieldReader::at(&mut rest, header.header_end),
+            header.version,
+            available,
+            &ClfLimits::FORMAT_MAX,
+        )?;
+        let blob_store_offset = data.len() - rest.len();
+        validate_manifest(&manifest, rest.len() as u64)?;
+        Ok(Self {
+            header,
+            manifest,
+            targets,
+            data,
+            blob_store_offset,
+        })
+    }
+
+    /// Byte offset of the blob store in the data.
+    #[must_use]
+    pub fn blob_store_offset(&self) -> usize {
+        self.blob_store_offset
+    }
+
+    /// Blob store length according to the manifest: the end of its furthest entry, padding
+    /// included.
+    #[must_use]
+    pub fn declared_blob_store_len(&self) -> u64 {
+        declared_store_len(&self.manifest)
+    }
+
+    /// Manifest entries sorted by `op_id`, then variant.
+    #[must_use]
+    pub fn manifest_entries(&self) -> &[ManifestEntry] {
+        self.manifest.as_slice()
+    }
+
+    /// Distinct op_ids in ascending order.
+    pub fn op_ids(&self) -> impl Iterator<Item = u32> + '_ {
+        let entries = self.manifest.as_slice();
+        entries
+            .iter()
+            .enumerate()
+            .filter(move |&(i, e)| i == 0 || entries[i - 1].op_id != e.op_id)
+            .map(|(_, e)| e.op_id)
+    }
+
+    /// Manifest entry for `op_id` (default variant), if present.
+    #[must_use]
+    pub fn entry(&self, op_id: u32) -> Option<&ManifestEntry> {
+        self.manifest.get(&(op_id, 0))
+    }
+
+    /// Variants packed for `op_id`, ascending; empty if the op is absent.
+    #[must_use]
+    pub fn variants(&self, op_id: u32) -> Vec<u16> {
+        self.manifest
+            .variants(op_id)
+            .iter()
+            .map(|e| e.variant)
+            .collect()
+    }
+
+    /// Target of `entry`: its tag from the manifest target table, else the header target.
+    #[must_use]
+    pub fn entry_target(&self, entry: &ManifestEntry) -> &str {
+        match entry.target_index {
+            0 => &self.header.target,
+            n => &self.targets[n as usize - 1],
+        }
+    }
+
+    /// Blob for `op_id` (default variant), without alignment padding. `None` if the op_id is
+    /// missing or its blob is encoded (compressed blobs cannot be lent; see `entry`).
+    #[must_use]
+    pub fn get_blob(&self, op_id: u32) -> Option<&'a [u8]> {
+        self.get_blob_variant(op_id, 0)
+    }
+
+    /// Blob for one variant of `op_id`; `None` as for `get_blob`.
+    #[must_use]
+    pub fn get_blob_variant(&self, op_id: u32, variant: u16) -> Option<&'a [u8]> {
+        let entry = self.manifest.get(&(op_id, variant))?;
+        if entry.flags != 0 {
+            return None;
+        }
+        let start = self.blob_store_offset + entry.offset as usize;
+        self.data.get(start..start + entry.exact_size as usize)
+    }
+}
+
+/// Manifest entries sorted by (op_id, variant), so an op's variants are adjacent. A flat slice
+/// rather than a map: one allocation of exactly the entries, lookups by binary search.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Manifest(Box<[ManifestEntry]>);
+
+impl Manifest {
+    /// Sort `entries` by (op_id, variant), rejecting the first duplicate key in that order.
+    pub(crate) fn from_entries(mut entries: Vec<ManifestEntry>) -> Result<Self, ClfError> {
+        entries.sort_unstable_by_key(|e| (e.op_id, e.variant));
+        if let Some(pair) = entries
+            .windows(2)
+            .find(|w| (w[0].op_id, w[0].variant) == (w[1].op_id, w[1].variant))
+        {
+            return Err(ClfError::DuplicateEntry {
+                op_id: pair[0].op_id,
+                variant: pair[0].variant,
+            });
+        }
+        Ok(Self(entries.into_boxed_slice()))
+    }
+
+    /// Entry for (op_id, variant), by binary search.
+    pub(crate) fn get(&self, &(op_id, variant): &(u32, u16)) -> Option<&ManifestEntry> {
+        self.0
+            .binary_search_by_key(&(op_id, variant), |e| (e.op_id, e.variant))
+            .ok()
+            .map(|i| &self.0[i])
+    }
+
+    /// Entries in (op_id, variant) order.
+    pub(crate) fn values(&self) -> core::slice::Iter<'_, ManifestEntry> {
+        self.0.iter()
+    }
+
+    /// Entries for every variant of `op_id`, in variant order.
+    pub(crate) fn variants(&self, op_id: u32) -> &[ManifestEntry] {
+        let start = self.0.partition_point(|e| e.op_id < op_id);
+        let len = self.0[start..].partition_point(|e| e.op_id == op_id);
+        &self.0[start..start + len]
+    }
+
+    pub(crate) fn as_slice(&self) -> &[ManifestEntry] {
+        &self.0
+    }
+
+    pub(crate) fn into_values(self) -> Vec<ManifestEntry> {
+        self.0.into_vec()
+    }
+}
+
+/// Shared by both readers and `ClfSlice`: every entry lies inside the blob store, and stored ranges either do
+/// not overlap or are exact aliases (same offset and size, as written by a deduplicating packer).
+pub(crate) fn validate_manifest(manifest: &Manifest, blob_store_len: u64) -> Result<(), ClfError> {
+    let mut entries: Vec<&ManifestEntry> = manifest.values().filter(|e| e.size > 0).collect();
+    entries.sort_unstable_by_key(|e| (e.offset, e.size, e.op_id));
+    // Entry with the furthest end seen so far.
+    let mut furthest: Option<&ManifestEntry> = None;
+    for entry in entries {
+        let end = u64::from(entry.offset) + u64::from(entry.size);
+        if end > blob_store_len {
+            return Err(ClfError::BlobOutOfBounds { op_id: entry.op_id });
+        }
+        if let Some(prev) = furthest {
+            let prev_end = u64::from(prev.offset) + u64::from(prev.size);
+            let alias = prev.offset == entry.offset && prev.size == entry.size;
+            if !alias && u64::from(entry.offset) < prev_end {
+                return Err(ClfError::OverlappingBlobs {
+                    first: prev.op_id,
+                    second: entry.op_id,
+                });
+            }
+            if end <= prev_end {
+                continue;
+            }
+        }
+        furthest = Some(entry);
+    }
+    Ok(())
+}
+
+/// Blob store length the manifest declares: the end of its furthest entry (stored sizes
+/// include alignment padding). 0 with no entries, even if an alignment fill follows.
+pub(crate) fn declared_store_len(manifest: &Manifest) -> u64 {
+    manifest
+        .values()
+        .map(|e| u64::from(e.offset) + u64::from(e.size))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Parse the manifest that follows the header: entry count, the v3 `entry_fields` mask, the
+/// target table (with `ENTRY_FIELD_TARGET`), then the entries. `available` is the number of bytes
+/// after the header, used to reject impossible counts before allocating; counts over `limits`
+/// are rejected as well. Returns the manifest, the target table and the manifest's serialized
+/// length. Shared by both readers, `ClfSlice`, `format::parse_manifest` and `parse_untrusted`.
+pub(crate) fn read_manifest<R: Source + ?Sized>(
+    reader: &mut FieldReader<'_, R>,
+    version: u8,
+    available: u64,
+    limits: &ClfLimits,
+) -> Result<(Manifest, Vec<String>, u64), ClfError> {
+    let start = reader.pos();
+    let num_entries = reader.u32("manifest entry count")?;
+    if num_entries > limits.max_entries {
+        return Err(ClfError::LimitExceeded {
+            what: "manifest entry count",
+            value: u64::from(num_entries),
+            limit: u64::from(limits.max_entries),
+        });
+    }
+    let num_entries = num_entries as usize;
+    let features = VersionFeatures::for_version(version);
+    let entry_fields = if features.has_flags {
+        reader.u32("manifest entry fields")?
+    } else {
+        0
+    };
+    if entry_fields & !KNOWN_ENTRY_FIELDS != 0 {
+        return Err(ClfError::UnsupportedEntryFields(entry_fields));
+    }
+
+    let mut prefix_len = ManifestEntry::manifest_prefix_len(version) as u64;
+    let mut targets = Vec::new();
+    if entry_fields & ENTRY_FIELD_TARGET != 0 {
+        let count_at = reader.pos();
+        let num_targets = reader.u32("manifest target count")?;
+        prefix_len += 4;
+        if num_targets > limits.max_targets {
+            return Err(ClfError::LimitExceeded {
+                what: "manifest target count",
+                value: u64::from(num_targets),
+                limit: u64::from(limits.max_targets),
+            });
+        }
+        // Each target costs at least its 4-byte length.
+        if u64::from(num_targets) * 4 > available.saturating_sub(prefix_len) {
+            return Err(ClfError::Malformed {
+                at_offset: count_at,
+                what: "manifest target count exceeds available file data",
+            });
+        }
+        for _ in 0..num_targets {
+            let target = read_len_prefixed_utf8(reader, "target", limits.max_text_len)?;
+            prefix_len += 4 + target.len() as u64;
+            targets.push(target);
+        }
+    }
+    let entries_len =
+        (num_entries as u64) * (ManifestEntry::entry_size(version, entry_fields) as u64);
+    if entries_len > available.saturating_sub(prefix_len) {
+        return Err(ClfError::Malformed {
+            at_offset: start,
+            what: "manifest entry count exceeds available file data",
+        });
+    }
+
+    let mut entries = Vec::with_capacity(num_entries);
+    for _ in 0..num_entries {
+        let at_offset = reader.pos();
+        let entry = read_manifest_entry(reader, version, entry_fields)?;
+        if entry.target_index as usize > targets.len() {
+            return Err(ClfError::Malformed {
+                at_offset,
+                what: "manifest entry target index out of range",
+            });
+        }
+        entries.push(entry);
+    }
+    let manifest = Manifest::from_entries(entries)?;
+    Ok((manifest, targets, prefix_len + entries_len))
+}
+
+/// Parse one manifest entry in the layout of the given format version and entry fields.
+fn read_manifest_entry<R: Source + ?Sized>(
+    reader: &mut FieldReader<'_, R>,
+    version: u8,
+    entry_fields: u32,
+) -> Result<ManifestEntry, ClfError> {
+    let at_offset = reader.pos();
+    let mut entry_buf = [0u8; ManifestEntry::MAX_ENTRY_SIZE];
+    let entry_buf = &mut entry_buf[..ManifestEntry::entry_size(version, entry_fields)];
+    reader.read(entry_buf, "manifest entry")?;
+    // `entry_buf` holds exactly the fields read below, so `field` never sees `None`.
+    fn field<T>(value: Option<T>, at_offset: u64) -> Result<T, ClfError> {
+        value.ok_or(ClfError::Malformed {
+            at_offset,
+            what: "truncated manifest entry",
+        })
+    }
+    let mut fields = ByteCursor::new(entry_buf);
+    let op_id = field(fields.u32(), at_offset)?;
+    let offset = field(fields.u32(), at_offset)?;
+    let size = field(fields.u32(), at_offset)?;
+    // v1/v2 entries only know the stored (padded) length.
+    let exact_size = if VersionFeatures::for_version(version).has_exact_size {
+        field(fields.u32(), at_offset)?
+    } else {
+        size
+    };
+    if exact_size > size {
+        return Err(ClfError::Malformed {
+            at_offset,
+            what: "manifest entry exact size exceeds stored size",
+        });
+    }
+    // Optional fields follow the fixed part in entry-field bit order.
+    let (flags, raw_size) = if entry_fields & ENTRY_FIELD_COMPRESSION != 0 {
+        (
+            field(fields.u32(), at_offset)?,
+            field(fields.u32(), at_offset)?,
+        )
+    } else {
+        (0, exact_size)
+    };
+    if flags & ENTRY_FLAG_AES256GCM != 0 {
+        let overhead = (AES_GCM_NONCE_LEN + AES_GCM_TAG_LEN) as u32;
+        if exact_size < overhead {
+            return Err(ClfError::Malformed {
+                at_offset,
+                what: "encrypted blob shorter than its nonce and tag",
+            });
+        }
+        // Decryption yields exactly the ciphertext length, which only zstd then changes.
+        if flags & ENTRY_FLAG_ZSTD == 0 && raw_size != exact_size - overhead {
+            return Err(ClfError::Malformed {
+                at_offset,
+                what: "raw size of encrypted blob does not match its stored size",
+            });
+        }
+    }
+    let crc32 = if entry_fields & ENTRY_FIELD_CRC32 != 0 {
+        Some(field(fields.u32(), at_offset)?)
+    } else {
+        None
+    };
+    // Variant (2 B) is followed by 2 reserved bytes, ignored on read.
+    let variant = if entry_fields & ENTRY_FIELD_VARIANT != 0 {
+        let variant = field(fields.u16(), at_offset)?;
+        fields.take(2);
+        variant
+    } else {
+        0
+    };
+    let target_index = if entry_fields & ENTRY_FIELD_TARGET != 0 {
+        field(fields.u32(), at_offset)?
+    } else {
+        0
+    };
+    Ok(ManifestEntry {
+        op_id,
+        offset,
+        size,
+        exact_size,
+        flags,
+        raw_size,
+        crc32,
+        variant,
+        target_index,
+    })
+}
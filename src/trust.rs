@@ -0,0 +1,106 @@
+//! Trusted signing keys (feature `ed25519`): the consumer-side policy "accept archives signed by
+//! any of these keys".
+//!
+//! `ClfReader::verify_against` checks an archive's Ed25519 SIG1 block against a `TrustedKeys`
+//! set and reports which key matched. Expired or revoked keys are simply left out of the set.
+
+use ed25519_dalek::VerifyingKey;
+
+use crate::format::{key_fingerprint, SignatureScope, SIG_KEY_FINGERPRINT_LEN};
+
+/// A verifying key accepted by a consumer, with an optional display name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrustedKey {
+    /// Display name (e.g. the vendor), if one was given.
+    pub name: Option<String>,
+    /// The Ed25519 public key.
+    pub key: VerifyingKey,
+}
+
+impl TrustedKey {
+    /// Fingerprint SIG1 blocks record for this key (see `format::key_fingerprint`).
+    #[must_use]
+    pub fn fingerprint(&self) -> [u8; SIG_KEY_FINGERPRINT_LEN] {
+        key_fingerprint(self.key.as_bytes())
+    }
+}
+
+/// Set of trusted verifying keys, looked up by the fingerprint stored in the signature block.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedKeys {
+    keys: Vec<TrustedKey>,
+}
+
+impl TrustedKeys {
+    /// Empty set (trusts nothing).
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trust `key`. Returns false if it was already in the set.
+    pub fn add(&mut self, key: VerifyingKey) -> bool {
+        self.insert(TrustedKey { name: None, key })
+    }
+
+    /// Trust `key` under a display name. Returns false if the key was already in the set (its
+    /// name is left unchanged).
+    pub fn add_named(&mut self, name: impl Into<String>, key: VerifyingKey) -> bool {
+        self.insert(TrustedKey {
+            name: Some(name.into()),
+            key,
+        })
+    }
+
+    fn insert(&mut self, trusted: TrustedKey) -> bool {
+        if self.keys.iter().any(|k| k.key == trusted.key) {
+            return false;
+        }
+        self.keys.push(trusted);
+        true
+    }
+
+    /// Number of trusted keys.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Whether the set is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Trusted keys in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = &TrustedKey> {
+        self.keys.iter()
+    }
+
+    /// Trusted keys whose fingerprint is `fingerprint` (normally at most one).
+    pub fn matching(&self, fingerprint: &[u8]) -> impl Iterator<Item = &TrustedKey> + '_ {
+        let fingerprint = fingerprint.to_vec();
+        self.keys
+            .iter()
+            .filter(move |k| k.fingerprint().as_slice() == fingerprint)
+    }
+}
+
+impl FromIterator<VerifyingKey> for TrustedKeys {
+    fn from_iter<I: IntoIterator<Item = VerifyingKey>>(iter: I) -> Self {
+        let mut keys = Self::new();
+        for key in iter {
+            keys.add(key);
+        }
+        keys
+    }
+}
+
+/// The trusted key an archive verified against; returned by `ClfReader::verify_against`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifiedBy {
+    /// The matching entry of the `TrustedKeys` set.
+    pub key: TrustedKey,
+    /// What the signature covers (`Full` or `IndexOnly`).
+    pub scope: SignatureScope,
+}
@@ -0,0 +1,137 @@
+//! `parse_untrusted`: one entry point that parses a whole archive held in memory with every
+//! allocation bounded by `ClfLimits`. Meant for input from anywhere (and for fuzzing): any panic,
+//! or any allocation larger than the limits and the input allow, reachable from here is a bug.
+
+use crate::format::{
+    read_header_with, ClfHeader, ClfLimits, FieldReader, ManifestEntry, ParsedManifest,
+    SignatureAlgorithm, SignatureBlock,
+};
+use crate::reader::{
+    check_crc, check_decoded_len, check_trailing_data, decode_blob, eof_layout, slice_read_at,
+    ClfError, EofLayout, Trailer,
+};
+use crate::slice::{read_manifest, validate_manifest};
+
+/// Archive checked by `parse_untrusted`: the header, the manifest and the signature trailer are
+/// parsed, every entry lies inside the blob store, and every stored CRC-32 matches. The trailer
+/// is located but its signature is not verified.
+#[derive(Debug, Clone)]
+pub struct ParsedClf<'a> {
+    pub header: ClfHeader,
+    /// Manifest entries ordered by (op_id, variant), and the target table.
+    pub manifest: ParsedManifest,
+    /// Byte offset of the blob store in the input.
+    pub blob_store_offset: usize,
+    /// Algorithm of the signature trailer (`Sha256` for SIG0), or `None` if the archive is
+    /// unsigned.
+    pub signature_algorithm: Option<SignatureAlgorithm>,
+    data: &'a [u8],
+    max_blob_len: u32,
+}
+
+/// Parse the archive in `bytes` without trusting any of it: header, manifest, trailer position,
+/// entry bounds and overlaps, trailing data and per-blob CRC-32s, in that order. Strict kind
+/// parsing. Lengths and counts over `limits` fail with `ClfError::LimitExceeded` before anything
+/// is allocated for them, and none may exceed the input either; so the parse allocates at most a
+/// small multiple of `bytes.len()`. Compressed blobs are not decoded here but by
+/// `ParsedClf::get_blob`, into buffers of at most `limits.max_blob_len` bytes. Never panics.
+pub fn parse_untrusted<'a>(bytes: &'a [u8], limits: &ClfLimits) -> Result<ParsedClf<'a>, ClfError> {
+    // No string or header section of a well-formed archive is longer than the archive itself.
+    let parse_limits = ClfLimits {
+        max_text_len: limits.max_text_len.min(bytes.len()),
+        max_section_len: limits.max_section_len.min(bytes.len()),
+        ..*limits
+    };
+    let mut rest = bytes;
+    let header = read_header_with(&mut rest, false, &parse_limits)?;
+    let available = rest.len() as u64;
+    let (manifest, targets, _) = read_manifest(
+        &mut FieldReader::at(&mut rest, header.header_end),
+        header.version,
+        available,
+        &parse_limits,
+    )?;
+    let blob_store_offset = bytes.len() - rest.len();
+
+    let file_len = bytes.len() as u64;
+    let EofLayout {
+        trailer,
+        expected_len,
+    } = eof_layout(
+        &manifest,
+        blob_store_offset as u64,
+        header.blob_alignment,
+        file_len,
+        slice_read_at(bytes),
+    );
+    check_trailing_data(expected_len, file_len)?;
+    let data_end = bytes.len() - trailer.len() as usize;
+    validate_manifest(&manifest, (data_end - blob_store_offset) as u64)?;
+
+    let signature_algorithm = match trailer {
+        Trailer::None => None,
+        Trailer::Sig0 => Some(SignatureAlgorithm::Sha256),
+        Trailer::Sig1(_) => {
+            let block =
+                SignatureBlock::parse(&bytes[data_end..]).ok_or(ClfError::SignatureInvalid)?;
+            Some(
+                block
+                    .signature_algorithm()
+                    .ok_or(ClfError::UnknownSignatureAlgorithm(block.algorithm))?,
+            )
+        }
+    };
+
+    let parsed = ParsedClf {
+        header,
+        manifest: ParsedManifest {
+            entries: manifest.into_values(),
+            targets,
+        },
+        blob_store_offset,
+        signature_algorithm,
+        data: &bytes[..data_end],
+        max_blob_len: limits.max_blob_len,
+    };
+    for entry in &parsed.manifest.entries {
+        check_crc(entry, parsed.stored(entry)?)?;
+        check_decoded_len(entry, parsed.max_blob_len)?;
+    }
+    Ok(parsed)
+}
+
+impl<'a> ParsedClf<'a> {
+    /// Manifest entry for (op_id, variant), if present.
+    #[must_use]
+    pub fn entry(&self, op_id: u32, variant: u16) -> Option<&ManifestEntry> {
+        let entries = &self.manifest.entries;
+        entries
+            .binary_search_by_key(&(op_id, variant), |e| (e.op_id, e.variant))
+            .ok()
+            .map(|i| &entries[i])
+    }
+
+    /// Blob for `op_id` (default variant), decoded and without alignment padding.
+    pub fn get_blob(&self, op_id: u32) -> Result<Option<Vec<u8>>, ClfError> {
+        self.get_blob_variant(op_id, 0)
+    }
+
+    /// Blob for one variant of `op_id`; see `get_blob`. A compressed blob is decoded into a
+    /// buffer of its declared raw size, which `parse_untrusted` has checked against the limits.
+    pub fn get_blob_variant(&self, op_id: u32, variant: u16) -> Result<Option<Vec<u8>>, ClfError> {
+        let Some(entry) = self.entry(op_id, variant) else {
+            return Ok(None);
+        };
+        check_decoded_len(entry, self.max_blob_len)?;
+        decode_blob(entry, self.stored(entry)?.to_vec(), None).map(Some)
+    }
+
+    /// Stored bytes of `entry` (the first `exact_size` bytes of its range).
+    fn stored(&self, entry: &ManifestEntry) -> Result<&'a [u8], ClfError> {
+        let start = self.blob_store_offset + entry.offset as usize;
+        self.data
+            .get(start..)
+            .and_then(|rest| rest.get(..entry.exact_size as usize))
+            .ok_or(ClfError::BlobOutOfBounds { op_id: entry.op_id })
+    }
+}
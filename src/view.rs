@@ -0,0 +1,172 @@
+//! `ManifestView`: a plain-data snapshot of an archive's header and manifest that outlives the
+//! file (serializable with the `serde` feature), and `ManifestView::diff` to compare two.
+
+use std::collections::BTreeMap;
+
+use crate::format::{ClfHeader, ClfKind, ManifestEntry};
+
+/// What an archive held when the view was taken (`ClfReader::manifest_view`): header fields,
+/// whether a signature block was present, and every manifest entry.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestView {
+    pub version: u8,
+    pub kind: ClfKind,
+    pub vendor: String,
+    pub target: String,
+    pub blob_alignment: u8,
+    /// A SIG0 or SIG1 block was present (not verified).
+    pub signature_present: bool,
+    /// Entries sorted by (op_id, variant).
+    pub entries: Vec<ManifestViewEntry>,
+}
+
+/// One manifest entry of a `ManifestView`, with its target and name resolved.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestViewEntry {
+    pub op_id: u32,
+    pub variant: u16,
+    /// Offset in the blob store.
+    pub offset: u32,
+    /// Stored size including alignment padding.
+    pub size: u32,
+    /// Stored size without padding.
+    pub exact_size: u32,
+    /// Decoded size (`exact_size` for unencoded blobs).
+    pub raw_size: u32,
+    /// Encoding flags (`ENTRY_FLAG_*`).
+    pub flags: u32,
+    /// CRC-32 of the stored bytes, when the archive records one (`--crc`).
+    pub crc32: Option<u32>,
+    /// Target the entry was built for: its per-entry tag, else the header target.
+    pub target: String,
+    /// Name from the archive's name table, if any.
+    pub name: Option<String>,
+}
+
+impl ManifestViewEntry {
+    /// Whether `other` describes the same blob: offsets and padding may differ, everything else
+    /// must match, and CRC-32s are compared when both sides have one. Without CRC-32s an edit
+    /// that keeps the blob's length is not visible.
+    fn same_contents(&self, other: &Self) -> bool {
+        self.exact_size == other.exact_size
+            && self.raw_size == other.raw_size
+            && self.flags == other.flags
+            && self.target == other.target
+            && self.name == other.name
+            && match (self.crc32, other.crc32) {
+                (Some(a), Some(b)) => a == b,
+                _ => true,
+            }
+    }
+}
+
+/// Differences between two `ManifestView`s (`ManifestView::diff`), from the older to the newer.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ManifestDiff {
+    /// Header fields that differ, as (field, old value, new value).
+    pub header: Vec<(String, String, String)>,
+    /// Entries only the newer view has.
+    pub added: Vec<ManifestViewEntry>,
+    /// Entries only the older view has.
+    pub removed: Vec<ManifestViewEntry>,
+    /// Entries with the same (op_id, variant) in both whose blob differs, as (old, new).
+    pub changed: Vec<(ManifestViewEntry, ManifestViewEntry)>,
+}
+
+impl ManifestDiff {
+    /// Whether the two views describe the same archive contents.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.header.is_empty()
+            && self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+    }
+}
+
+impl ManifestView {
+    /// View of `header` and `entries` (sorted by (op_id, variant), each with its target).
+    pub(crate) fn new<'a>(
+        header: &ClfHeader,
+        entries: impl Iterator<Item = (&'a ManifestEntry, &'a str)>,
+        signature_present: bool,
+    ) -> Self {
+        Self {
+            version: header.version,
+            kind: header.kind,
+            vendor: header.vendor.clone(),
+            target: header.target.clone(),
+            blob_alignment: header.blob_alignment,
+            signature_present,
+            entries: entries
+                .map(|(entry, target)| ManifestViewEntry {
+                    op_id: entry.op_id,
+                    variant: entry.variant,
+                    offset: entry.offset,
+                    size: entry.size,
+                    exact_size: entry.exact_size,
+                    raw_size: entry.raw_size,
+                    flags: entry.flags,
+                    crc32: entry.crc32,
+                    target: target.to_string(),
+                    name: header.entry_name(entry.op_id).map(str::to_string),
+                })
+                .collect(),
+        }
+    }
+
+    /// What changed from `self` to `newer`: header fields, then entries added, removed, or
+    /// kept with a different blob (see `ManifestDiff::changed`), each list in (op_id, variant)
+    /// order. Entries that only moved are not reported.
+    #[must_use]
+    pub fn diff(&self, newer: &ManifestView) -> ManifestDiff {
+        let mut diff = ManifestDiff::default();
+        let mut field = |name: &str, old: String, new: String| {
+            if old != new {
+                diff.header.push((name.to_string(), old, new));
+            }
+        };
+        field(
+            "version",
+            self.version.to_string(),
+            newer.version.to_string(),
+        );
+        field("kind", self.kind.to_string(), newer.kind.to_string());
+        field("vendor", self.vendor.clone(), newer.vendor.clone());
+        field("target", self.target.clone(), newer.target.clone());
+        field(
+            "blob_alignment",
+            self.blob_alignment.to_string(),
+            newer.blob_alignment.to_string(),
+        );
+        field(
+            "signature_present",
+            self.signature_present.to_string(),
+            newer.signature_present.to_string(),
+        );
+
+        let key = |e: &ManifestViewEntry| (e.op_id, e.variant);
+        let old: BTreeMap<(u32, u16), &ManifestViewEntry> =
+            self.entries.iter().map(|e| (key(e), e)).collect();
+        let new: BTreeMap<(u32, u16), &ManifestViewEntry> =
+            newer.entries.iter().map(|e| (key(e), e)).collect();
+        for (k, &entry) in &old {
+            match new.get(k) {
+                None => diff.removed.push(entry.clone()),
+                Some(&other) if !entry.same_contents(other) => {
+                    diff.changed.push((entry.clone(), other.clone()));
+                }
+                Some(_) => {}
+            }
+        }
+        diff.added = new
+            .iter()
+            .filter(|(k, _)| !old.contains_key(k))
+            .map(|(_, &e)| e.clone())
+            .collect();
+        diff
+    }
+}
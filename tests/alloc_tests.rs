@@ -0,0 +1,76 @@
+//! Allocation tests: this binary installs a counting global allocator, so it holds only tests
+//! that measure heap allocations on their own thread.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::io::{Cursor, Write};
+
+use clf::{pack_clf, ClfReader, MissingOpIdPolicy, PackOptions};
+
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+// SAFETY: every call is forwarded to `System`; the counter is a const-initialized thread local
+// without a destructor, so touching it never allocates.
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|n| n.set(n.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|n| n.set(n.get() + 1));
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Heap allocations made by `f` on the current thread.
+fn allocations_in<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let value = f();
+    (value, ALLOCATIONS.with(Cell::get) - before)
+}
+
+/// Building a code section and checking CRCs over 10k small blobs costs a handful of
+/// allocations in total, not one or more per blob.
+#[test]
+fn code_section_and_verify_blobs_do_not_allocate_per_blob() {
+    const COUNT: u32 = 10_000;
+    let entries: Vec<(u32, Vec<u8>)> = (1..=COUNT)
+        .map(|op_id| (op_id, op_id.to_le_bytes().to_vec()))
+        .collect();
+    let options = PackOptions {
+        per_blob_crc: true,
+        ..PackOptions::default()
+    };
+    let mut buf = Cursor::new(Vec::new());
+    pack_clf(&mut buf, &entries, &options).unwrap();
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(buf.get_ref()).unwrap();
+    file.flush().unwrap();
+    let mut reader = ClfReader::open(file.path()).unwrap();
+    let op_ids: Vec<u32> = (1..=COUNT).collect();
+
+    // Warm up: size the scratch buffer.
+    reader.verify_blobs().unwrap();
+    let (code, allocations) = allocations_in(|| {
+        reader
+            .build_code_section(&op_ids, MissingOpIdPolicy::Fail)
+            .unwrap()
+    });
+    assert_eq!(code.len(), COUNT as usize * 4);
+    assert!(allocations < 16, "{allocations} allocations");
+
+    let ((), allocations) = allocations_in(|| reader.verify_blobs().unwrap());
+    assert!(allocations < 64, "{allocations} allocations");
+}
@@ -0,0 +1,143 @@
+//! C API tests: drive the `extern "C"` reader functions from Rust as a C caller would.
+#![cfg(feature = "capi")]
+
+use std::ffi::{CStr, CString};
+use std::io::{Cursor, Write};
+use std::ptr;
+
+use clf::capi::*;
+use clf::{pack_and_sign, pack_clf, HmacKey, PackOptions};
+
+/// `entries` packed with default options into a temporary file.
+fn packed_file(entries: &[(u32, Vec<u8>)]) -> tempfile::NamedTempFile {
+    let mut buf = Cursor::new(Vec::new());
+    pack_clf(&mut buf, entries, &PackOptions::default()).unwrap();
+    temp_file(buf.get_ref())
+}
+
+fn temp_file(bytes: &[u8]) -> tempfile::NamedTempFile {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(bytes).unwrap();
+    file.flush().unwrap();
+    file
+}
+
+/// `clf_reader_verify_signature` on `bytes` written to a temporary file.
+fn verify_bytes(bytes: &[u8]) -> i32 {
+    let file = temp_file(bytes);
+    let path = CString::new(file.path().to_str().unwrap()).unwrap();
+    unsafe {
+        let handle = clf_reader_open(path.as_ptr());
+        assert!(!handle.is_null());
+        let result = clf_reader_verify_signature(handle);
+        clf_reader_close(handle);
+        result
+    }
+}
+
+/// Open, enumerate op_ids, fetch and free blobs, check the (absent) signature, then close.
+#[test]
+fn capi_open_list_and_get_blobs() {
+    let file = packed_file(&[(1, b"add".to_vec()), (50, b"matmul".to_vec())]);
+    let path = CString::new(file.path().to_str().unwrap()).unwrap();
+    unsafe {
+        let handle = clf_reader_open(path.as_ptr());
+        assert!(!handle.is_null());
+        assert_eq!(clf_reader_op_count(handle), 2);
+
+        let mut blobs = Vec::new();
+        for index in 0..clf_reader_op_count(handle) {
+            let mut op_id = 0;
+            assert_eq!(clf_reader_op_id_at(handle, index, &mut op_id), CLF_OK);
+            let (mut ptr, mut len) = (ptr::null_mut(), 0);
+            assert_eq!(
+                clf_reader_get_blob(handle, op_id, &mut ptr, &mut len),
+                CLF_OK
+            );
+            blobs.push((op_id, std::slice::from_raw_parts(ptr, len).to_vec()));
+            clf_blob_free(ptr, len);
+        }
+        assert_eq!(blobs, [(1, b"add".to_vec()), (50, b"matmul".to_vec())]);
+        assert_eq!(clf_reader_verify_signature(handle), CLF_SIGNATURE_NONE);
+        assert!(clf_last_error_message(handle).is_null());
+        clf_reader_close(handle);
+    }
+}
+
+/// Failures return their codes and leave a message behind; NULL arguments are rejected.
+#[test]
+fn capi_errors_are_codes_and_messages() {
+    let file = packed_file(&[(7, b"blob".to_vec())]);
+    let path = CString::new(file.path().to_str().unwrap()).unwrap();
+    let missing = CString::new("/nonexistent/archive.clf").unwrap();
+    unsafe {
+        assert!(clf_reader_open(missing.as_ptr()).is_null());
+        assert!(!clf_last_error_message(ptr::null()).is_null());
+        assert!(clf_reader_open(ptr::null()).is_null());
+
+        let handle = clf_reader_open(path.as_ptr());
+        assert!(!handle.is_null());
+        assert!(clf_last_error_message(ptr::null()).is_null());
+
+        let (mut ptr, mut len) = (ptr::null_mut(), 0);
+        assert_eq!(
+            clf_reader_get_blob(handle, 8, &mut ptr, &mut len),
+            CLF_ERR_NOT_FOUND
+        );
+        assert!(ptr.is_null());
+        let message = CStr::from_ptr(clf_last_error_message(handle));
+        assert!(message.to_str().unwrap().contains('8'), "{message:?}");
+
+        let mut op_id = 0;
+        assert_eq!(
+            clf_reader_op_id_at(handle, 1, &mut op_id),
+            CLF_ERR_INVALID_ARGUMENT
+        );
+        assert_eq!(
+            clf_reader_op_id_at(handle, 0, ptr::null_mut()),
+            CLF_ERR_INVALID_ARGUMENT
+        );
+        assert_eq!(
+            clf_reader_get_blob(ptr::null_mut(), 7, &mut ptr, &mut len),
+            CLF_ERR_INVALID_ARGUMENT
+        );
+        assert_eq!(clf_reader_op_count(ptr::null()), 0);
+        clf_reader_close(handle);
+        clf_reader_close(ptr::null_mut());
+        clf_blob_free(ptr::null_mut(), 0);
+    }
+}
+
+/// `clf_reader_verify_signature` reports the scope: an index-only signature is not reported as
+/// full even when the blob store is intact, and a keyed block is reported unchecked.
+#[test]
+fn capi_verify_signature_reports_scope() {
+    let entries = [(1, b"add".to_vec()), (2, b"mul".to_vec())];
+    let signed = |options: PackOptions| {
+        let mut bytes = Cursor::new(Vec::new());
+        pack_and_sign(&mut bytes, &entries, &options).unwrap();
+        bytes.into_inner()
+    };
+    let full = signed(PackOptions {
+        sign: true,
+        ..PackOptions::default()
+    });
+    let index_only = signed(PackOptions {
+        sign: true,
+        sign_index_only: true,
+        ..PackOptions::default()
+    });
+    let keyed = signed(PackOptions {
+        hmac_key: Some(HmacKey::new(b"secret".to_vec())),
+        ..PackOptions::default()
+    });
+
+    assert_eq!(verify_bytes(&full), CLF_SIGNATURE_FULL);
+    assert_eq!(verify_bytes(&index_only), CLF_SIGNATURE_INDEX_ONLY);
+    assert_eq!(verify_bytes(&keyed), CLF_SIGNATURE_KEYED);
+
+    let mut tampered = full.clone();
+    let last_data_byte = tampered.len() - clf::SIG_BLOCK_LEN - 1;
+    tampered[last_data_byte] ^= 0xFF;
+    assert_eq!(verify_bytes(&tampered), CLF_ERR_SIGNATURE);
+}
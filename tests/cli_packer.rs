@@ -87,8 +87,9 @@ fn coelanox_packer_inspect_json_stdout() {
     assert!(json["manifest"].is_array());
 }
 
+/// An unsigned file passes `--verify` on structure alone and fails with `--require-signature`.
 #[test]
-fn coelanox_packer_verify_unsigned_fails() {
+fn coelanox_packer_verify_unsigned_fails_with_require_signature() {
     let bin = env!("CARGO_BIN_EXE_coelanox-packer");
     let mut tmp = NamedTempFile::new().expect("temp");
     tmp.write_all(&[0xc3]).expect("write");
@@ -109,7 +110,556 @@ fn coelanox_packer_verify_unsigned_fails() {
         .args(["--verify", clf_path.to_str().expect("utf8")])
         .status()
         .expect("verify");
-    assert!(!st.success(), "unsigned clf should fail verify");
+    assert!(st.success(), "unsigned clf should pass structural verify");
+
+    let out = Command::new(bin)
+        .args([
+            "--verify",
+            clf_path.to_str().expect("utf8"),
+            "--require-signature",
+        ])
+        .output()
+        .expect("verify");
+    assert!(!out.status.success(), "unsigned clf should fail verify");
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("no signature block"), "{stderr}");
+}
+
+/// `--verify` reports each failure on stderr: a tampered signed file fails both the bounds check
+/// and the signature check.
+#[test]
+fn coelanox_packer_verify_reports_each_failure() {
+    let bin = env!("CARGO_BIN_EXE_coelanox-packer");
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("add.bin"), b"add kernel").expect("write");
+    let clf_path = dir.path().join("out.clfc");
+    assert!(Command::new(bin)
+        .args(["--sign", "-o"])
+        .arg(&clf_path)
+        .arg(format!("1:{}", dir.path().join("add.bin").display()))
+        .status()
+        .expect("pack")
+        .success());
+    let verify = |path: &std::path::Path| {
+        Command::new(bin)
+            .arg("--verify")
+            .arg(path)
+            .arg("--require-signature")
+            .output()
+            .expect("verify")
+    };
+    assert!(verify(&clf_path).status.success());
+
+    // Drop blob bytes and keep the SIG0 block: the entry now runs into the trailer and the hash
+    // no longer matches.
+    let bytes = std::fs::read(&clf_path).expect("read");
+    let sig_start = bytes.len() - clf::SIG_BLOCK_LEN;
+    let mut tampered = bytes[..sig_start - 4].to_vec();
+    tampered.extend_from_slice(&bytes[sig_start..]);
+    std::fs::write(&clf_path, &tampered).expect("write");
+    let out = verify(&clf_path);
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        stderr.contains("blob store") && stderr.contains("2 problem(s)"),
+        "unexpected stderr: {stderr}"
+    );
+}
+
+/// `--sign-archive` signs an unsigned archive, refuses to double-sign without `--force`, and
+/// `--strip` removes the block again; a file that fails structural parsing is never signed.
+#[test]
+fn coelanox_packer_signs_and_strips_existing_archive() {
+    let bin = env!("CARGO_BIN_EXE_coelanox-packer");
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("add.bin"), b"add kernel").expect("write");
+    let clf_path = dir.path().join("out.clfc");
+    assert!(Command::new(bin)
+        .arg("-o")
+        .arg(&clf_path)
+        .arg(format!("1:{}", dir.path().join("add.bin").display()))
+        .status()
+        .expect("pack")
+        .success());
+    let unsigned = std::fs::read(&clf_path).expect("read");
+    let sign = |extra: &[&str]| {
+        Command::new(bin)
+            .arg("--sign-archive")
+            .arg(&clf_path)
+            .args(extra)
+            .output()
+            .expect("sign")
+    };
+
+    assert!(sign(&[]).status.success());
+    let signed = std::fs::read(&clf_path).expect("read");
+    assert_eq!(signed.len(), unsigned.len() + clf::SIG_BLOCK_LEN);
+    let mut reader = clf::ClfReader::open(&clf_path).expect("open");
+    assert!(reader.verify_signature().expect("verify"));
+
+    let again = sign(&[]);
+    assert!(!again.status.success());
+    assert!(String::from_utf8_lossy(&again.stderr).contains("--force"));
+    assert!(sign(&["--force"]).status.success());
+    assert_eq!(std::fs::read(&clf_path).expect("read"), signed);
+
+    assert!(sign(&["--strip"]).status.success());
+    assert_eq!(std::fs::read(&clf_path).expect("read"), unsigned);
+
+    std::fs::write(&clf_path, &unsigned[..unsigned.len() - 4]).expect("write");
+    let broken = sign(&[]);
+    assert!(!broken.status.success());
+    assert!(String::from_utf8_lossy(&broken.stderr).contains("refusing to sign"));
+}
+
+/// A kind/extension mismatch warns by default, fails with `--enforce-extension`, and is fixed
+/// by `--auto-extension`.
+#[test]
+fn coelanox_packer_checks_output_extension_against_kind() {
+    let bin = env!("CARGO_BIN_EXE_coelanox-packer");
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("mm.bin"), b"dma").expect("write");
+    let entry = format!("1:{}", dir.path().join("mm.bin").display());
+    let pack = |out: &str, extra: &[&str]| {
+        Command::new(bin)
+            .args(["--kind", "memory-movement", "-o"])
+            .arg(dir.path().join(out))
+            .args(extra)
+            .arg(&entry)
+            .output()
+            .expect("pack")
+    };
+
+    let warned = pack("warned.clfc", &[]);
+    assert!(warned.status.success());
+    assert!(String::from_utf8_lossy(&warned.stderr).contains("warning: output"));
+    assert!(dir.path().join("warned.clfc").exists());
+
+    let enforced = pack("enforced.clfc", &["--enforce-extension"]);
+    assert!(!enforced.status.success());
+    assert!(!dir.path().join("enforced.clfc").exists());
+
+    let clean = pack("clean.clfmm", &["--enforce-extension"]);
+    assert!(clean.status.success());
+    assert!(!String::from_utf8_lossy(&clean.stderr).contains("warning"));
+
+    assert!(pack("fixed.clfc", &["--auto-extension"]).status.success());
+    assert!(dir.path().join("fixed.clfmm").exists());
+    assert!(!dir.path().join("fixed.clfc").exists());
+    assert!(pack("kernels.bin", &["--auto-extension"]).status.success());
+    assert!(dir.path().join("kernels.bin.clfmm").exists());
+}
+
+/// `--max-size` takes a size with a unit and fails the pack, leaving no output, when the archive
+/// would be larger.
+#[test]
+fn coelanox_packer_enforces_max_size() {
+    let bin = env!("CARGO_BIN_EXE_coelanox-packer");
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("big.bin"), vec![7u8; 2048]).expect("write");
+    let entry = format!("1:{}", dir.path().join("big.bin").display());
+    let pack = |out: &str, size: &str| {
+        Command::new(bin)
+            .args(["--sign", "--max-size", size, "-o"])
+            .arg(dir.path().join(out))
+            .arg(&entry)
+            .output()
+            .expect("pack")
+    };
+
+    assert!(pack("fits.clfc", "1MiB").status.success());
+    assert!(dir.path().join("fits.clfc").exists());
+
+    let over = pack("over.clfc", "2KiB");
+    assert!(!over.status.success());
+    let stderr = String::from_utf8_lossy(&over.stderr);
+    assert!(
+        stderr.contains("over the size limit of 2048 bytes"),
+        "{stderr}"
+    );
+    assert!(!dir.path().join("over.clfc").exists());
+
+    let bad = pack("bad.clfc", "2 parsecs");
+    assert!(!bad.status.success());
+    assert!(String::from_utf8_lossy(&bad.stderr).contains("unknown size unit"));
+}
+
+/// `--upgrade` rewrites a v1 archive as the newest version with the requested kind and a
+/// signature, and refuses a downgrade that would lose the kind.
+#[test]
+fn coelanox_packer_upgrades_legacy_archive() {
+    let bin = env!("CARGO_BIN_EXE_coelanox-packer");
+    let dir = tempfile::tempdir().expect("tempdir");
+    let legacy = dir.path().join("legacy.clf");
+    let options = clf::PackOptions {
+        vendor: "old".to_string(),
+        version: 1,
+        ..clf::PackOptions::default()
+    };
+    clf::pack_clf_to_path(
+        &legacy,
+        &[(1, b"add".to_vec()), (50, b"matmul".to_vec())],
+        &options,
+    )
+    .expect("pack v1");
+
+    let upgraded = dir.path().join("legacy.clfc");
+    let out = Command::new(bin)
+        .arg("--upgrade")
+        .arg(&legacy)
+        .arg("-o")
+        .arg(&upgraded)
+        .args(["--kind", "compute", "--sign"])
+        .output()
+        .expect("upgrade");
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(String::from_utf8_lossy(&out.stderr).contains("(v1) to"));
+    let mut reader = clf::ClfReader::open(&upgraded).expect("open");
+    assert_eq!(reader.header.version, CLF_VERSION);
+    assert_eq!(reader.header.vendor, "old");
+    assert!(reader.verify_signature().expect("verify"));
+    assert_eq!(
+        reader.get_blob(50).expect("read").as_deref(),
+        Some(&b"matmul"[..])
+    );
+
+    let refused = dir.path().join("refused.clf");
+    let out = Command::new(bin)
+        .arg("--upgrade")
+        .arg(&upgraded)
+        .arg("-o")
+        .arg(&refused)
+        .args(["--kind", "executor", "--to-version", "1"])
+        .output()
+        .expect("downgrade");
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("cannot express the kind byte"));
+    assert!(!refused.exists());
+}
+
+/// `--recompress --zstd` shrinks an archive, keeping its header and signing it with `--sign`;
+/// `--decompress` turns it back into the original bytes. Either codec flag is required.
+#[cfg(feature = "compression")]
+#[test]
+fn coelanox_packer_recompresses_archive() {
+    let bin = env!("CARGO_BIN_EXE_coelanox-packer");
+    let dir = tempfile::tempdir().expect("tempdir");
+    let plain = dir.path().join("plain.clfc");
+    let kernel = b"vmovaps ymm0, [rdi]; ".repeat(500);
+    let options = clf::PackOptions {
+        vendor: "acme".to_string(),
+        deterministic: true,
+        ..clf::PackOptions::default()
+    };
+    clf::pack_clf_to_path(
+        &plain,
+        &[(1, b"add".to_vec()), (50, kernel.clone())],
+        &options,
+    )
+    .expect("pack");
+
+    let small = dir.path().join("small.clfc");
+    let out = Command::new(bin)
+        .arg("--recompress")
+        .arg(&plain)
+        .arg("-o")
+        .arg(&small)
+        .args(["--zstd", "9", "--sign"])
+        .output()
+        .expect("recompress");
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(
+        stdout.contains("1 of 2 blobs zstd-compressed, signed"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("1 blob(s) stored raw"), "{stdout}");
+    assert!(std::fs::metadata(&small).unwrap().len() < std::fs::metadata(&plain).unwrap().len());
+    let mut reader = clf::ClfReader::open(&small).expect("open");
+    assert_eq!(reader.header.vendor, "acme");
+    assert!(reader.verify_signature().expect("verify"));
+    assert_eq!(reader.get_blob(50).expect("read"), Some(kernel));
+
+    let restored = dir.path().join("restored.clfc");
+    let out = Command::new(bin)
+        .arg("--recompress")
+        .arg(&small)
+        .arg("-o")
+        .arg(&restored)
+        .arg("--decompress")
+        .output()
+        .expect("decompress");
+    assert!(out.status.success());
+    assert_eq!(
+        std::fs::read(&restored).unwrap(),
+        std::fs::read(&plain).unwrap()
+    );
+
+    let out = Command::new(bin)
+        .arg("--recompress")
+        .arg(&plain)
+        .arg("-o")
+        .arg(dir.path().join("none.clfc"))
+        .output()
+        .expect("no codec");
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("--zstd LEVEL or --decompress"));
+}
+
+/// `--remap` renumbers op_ids from a map file (ids or names), reports what it left alone,
+/// signs with `--sign`, and fails on a map that would give two entries the same op_id.
+#[test]
+fn coelanox_packer_remaps_op_ids() {
+    let bin = env!("CARGO_BIN_EXE_coelanox-packer");
+    let dir = tempfile::tempdir().expect("tempdir");
+    let source = dir.path().join("old.clfc");
+    clf::pack_clf_to_path(
+        &source,
+        &[
+            (1, b"add".to_vec()),
+            (2, b"sub".to_vec()),
+            (50, b"matmul".to_vec()),
+        ],
+        &clf::PackOptions::default(),
+    )
+    .expect("pack");
+    let map = dir.path().join("remap.toml");
+    std::fs::write(&map, "matmul = 300\n1 = 3\n").expect("write map");
+    let remapped = dir.path().join("new.clfc");
+    let remap = || {
+        Command::new(bin)
+            .arg("--remap")
+            .arg(&source)
+            .arg("--map")
+            .arg(&map)
+            .arg("-o")
+            .arg(&remapped)
+            .arg("--sign")
+            .output()
+            .expect("remap")
+    };
+
+    let out = remap();
+    assert!(out.status.success(), "{out:?}");
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.contains("remapped 2 op_ids"), "{stdout}");
+    assert!(stdout.contains("50 -> 300"), "{stdout}");
+    assert!(stdout.contains("left as-is: 2"), "{stdout}");
+    let mut reader = clf::ClfReader::open(&remapped).expect("open");
+    assert!(reader.verify_signature().expect("verify"));
+    assert_eq!(reader.op_ids(), vec![2, 3, 300]);
+    assert_eq!(
+        reader.get_blob(300).expect("read").as_deref(),
+        Some(&b"matmul"[..])
+    );
+
+    std::fs::write(&map, "1 = 2\n").expect("write map");
+    let out = remap();
+    assert!(!out.status.success());
+    assert!(
+        String::from_utf8_lossy(&out.stderr).contains("would both become op_id 2"),
+        "{out:?}"
+    );
+}
+
+/// `--coverage` lists present and missing ops from an ops file with comments and names, exits 1
+/// while ops are missing unless `--allow-missing` covers them, and rejects unknown names with
+/// suggestions.
+#[test]
+fn coelanox_packer_reports_coverage() {
+    let bin = env!("CARGO_BIN_EXE_coelanox-packer");
+    let dir = tempfile::tempdir().expect("tempdir");
+    let archive = dir.path().join("cpu.clfc");
+    clf::pack_clf_to_path(
+        &archive,
+        &[(1, b"add".to_vec()), (50, b"matmul".to_vec())],
+        &clf::PackOptions::default(),
+    )
+    .expect("pack");
+    let ops = dir.path().join("ops.txt");
+    std::fs::write(&ops, "# model X\nadd\n50  # gemm\n\nrelu\nAdd\n").expect("write ops");
+    let coverage = |extra: &[&str]| {
+        Command::new(bin)
+            .arg("--coverage")
+            .arg(&archive)
+            .arg("--ops")
+            .arg(&ops)
+            .args(extra)
+            .output()
+            .expect("coverage")
+    };
+
+    let out = coverage(&[]);
+    assert!(!out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.contains("2 of 3 ops present (66.7%)"), "{stdout}");
+    let relu = stdout
+        .lines()
+        .find(|l| l.contains("Relu"))
+        .expect("relu row");
+    assert!(relu.starts_with("missing"), "{stdout}");
+    assert!(String::from_utf8_lossy(&out.stderr).contains("missing 1 op(s)"));
+
+    let out = coverage(&["--allow-missing", "1", "--json"]);
+    assert!(out.status.success(), "{out:?}");
+    let doc: serde_json::Value = serde_json::from_slice(&out.stdout).expect("JSON");
+    assert_eq!(doc["ok"], true, "{doc}");
+    assert_eq!(doc["required"], 3, "{doc}");
+    assert_eq!(doc["missing"], serde_json::json!([10]), "{doc}");
+    assert_eq!(doc["present_bytes"], 9, "{doc}");
+
+    std::fs::write(&ops, "add\nmatmull\n").expect("write ops");
+    let out = coverage(&[]);
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        stderr.contains(":2: unknown op name \"matmull\""),
+        "{stderr}"
+    );
+    assert!(stderr.contains("did you mean MatMul"), "{stderr}");
+}
+
+/// `--hash` lists the SHA-256 of each blob and of the file and its signed region; `--op`
+/// narrows it to one op, and `--json` reports the same digests.
+#[test]
+fn coelanox_packer_hashes_blobs() {
+    const ADD_SHA256: &str = "7e9e5ac30f2216fd0fd6f5faed316f2d5983361a4203c3330cfa46ef65bb4767";
+    let bin = env!("CARGO_BIN_EXE_coelanox-packer");
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("add.bin"), b"add").expect("write");
+    std::fs::write(dir.path().join("mm.bin"), b"matmul").expect("write");
+    let clf_path = dir.path().join("out.clfc");
+    assert!(Command::new(bin)
+        .current_dir(dir.path())
+        .args(["--sign", "-o", "out.clfc", "add:add.bin", "matmul:mm.bin"])
+        .status()
+        .expect("pack")
+        .success());
+    let hash = |extra: &[&str]| {
+        Command::new(bin)
+            .arg("--hash")
+            .arg(&clf_path)
+            .args(extra)
+            .output()
+            .expect("hash")
+    };
+
+    let out = hash(&[]);
+    assert!(out.status.success(), "{out:?}");
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let add = stdout.lines().find(|l| l.contains("Add")).expect("add row");
+    assert!(add.contains(ADD_SHA256) && add.contains(" 3 "), "{stdout}");
+    assert!(stdout.contains("MatMul"), "{stdout}");
+    let file_len = std::fs::metadata(&clf_path).expect("stat").len();
+    assert!(stdout.contains(&format!("({file_len} bytes)")), "{stdout}");
+    let signed_len = file_len - clf::SIG_BLOCK_LEN as u64;
+    assert!(
+        stdout.contains(&format!("({signed_len} bytes)")),
+        "{stdout}"
+    );
+
+    let out = hash(&["--op", "add", "--json"]);
+    assert!(out.status.success(), "{out:?}");
+    let doc: serde_json::Value = serde_json::from_slice(&out.stdout).expect("JSON");
+    assert_eq!(doc["entries"].as_array().map(Vec::len), Some(1), "{doc}");
+    assert_eq!(doc["entries"][0]["sha256"], ADD_SHA256, "{doc}");
+    assert_eq!(doc["signed_len"], signed_len, "{doc}");
+    assert_eq!(doc["file_size"], file_len, "{doc}");
+
+    assert!(!hash(&["--op", "999"]).status.success());
+}
+
+/// `--dump-op-ids` prints the registry as a Markdown table, or as JSON with `--json`.
+#[test]
+fn coelanox_packer_dumps_op_ids() {
+    let bin = env!("CARGO_BIN_EXE_coelanox-packer");
+    let out = Command::new(bin)
+        .args(["--dump-op-ids", "--json"])
+        .output()
+        .expect("run");
+    assert!(out.status.success());
+    let doc: serde_json::Value = serde_json::from_slice(&out.stdout).expect("json");
+    assert_eq!(doc["ops"].as_array().unwrap().len(), clf::OpType::ALL.len());
+
+    let out = Command::new(bin)
+        .arg("--dump-op-ids")
+        .output()
+        .expect("run");
+    assert!(out.status.success());
+    let text = String::from_utf8(out.stdout).unwrap();
+    assert!(text.starts_with("| op_id | Name |"), "{text}");
+    assert!(text.contains("| 50 | MatMul | contraction |"), "{text}");
+}
+
+/// `--stats` accounts for every byte of the file, lists entries largest first (limited by
+/// `--top`) and groups entries with identical contents.
+#[test]
+fn coelanox_packer_stats_reports_sizes_and_duplicates() {
+    let bin = env!("CARGO_BIN_EXE_coelanox-packer");
+    let dir = tempfile::tempdir().expect("tempdir");
+    for (name, bytes) in [
+        ("a.bin", &b"same"[..]),
+        ("b.bin", b"same"),
+        ("c.bin", b"largest blob"),
+    ] {
+        std::fs::write(dir.path().join(name), bytes).expect("write");
+    }
+    let clf_path = dir.path().join("out.clfc");
+    assert!(Command::new(bin)
+        .args(["--align", "16", "--sign", "-o"])
+        .arg(&clf_path)
+        .arg(format!("add:{}", dir.path().join("a.bin").display()))
+        .arg(format!("relu:{}", dir.path().join("b.bin").display()))
+        .arg(format!("300:{}", dir.path().join("c.bin").display()))
+        .status()
+        .expect("pack")
+        .success());
+
+    let out = Command::new(bin)
+        .arg("--stats")
+        .arg(&clf_path)
+        .args(["--top", "1", "--json"])
+        .output()
+        .expect("stats");
+    assert!(out.status.success());
+    let doc: serde_json::Value = serde_json::from_slice(&out.stdout).expect("json");
+    let field = |name: &str| doc[name].as_u64().unwrap();
+    assert_eq!(
+        field("header_bytes")
+            + field("manifest_bytes")
+            + field("blob_store_bytes")
+            + field("signature_bytes"),
+        field("file_size")
+    );
+    assert_eq!(field("blob_bytes"), 4 + 4 + 12);
+    assert_eq!(field("padding_bytes"), field("blob_store_bytes") - 20);
+    assert_eq!(field("entries"), 3);
+    let ops = doc["ops"].as_array().unwrap();
+    assert_eq!(ops.len(), 1);
+    assert_eq!(ops[0]["op_id"], 300);
+    let duplicates = doc["duplicates"].as_array().unwrap();
+    assert_eq!(duplicates.len(), 1);
+    assert_eq!(duplicates[0]["entries"].as_array().unwrap().len(), 2);
+    assert_eq!(duplicates[0]["wasted_bytes"], 4);
+
+    let text = Command::new(bin)
+        .arg("--stats")
+        .arg(&clf_path)
+        .output()
+        .expect("stats");
+    let stdout = String::from_utf8_lossy(&text.stdout);
+    assert!(
+        stdout.contains("2 ops have identical bytes") && stdout.contains("Relu"),
+        "unexpected stdout: {stdout}"
+    );
 }
 
 #[test]
@@ -140,7 +690,10 @@ fn coelanox_packer_verify_require_authenticity_fails_until_supported() {
         ])
         .output()
         .expect("verify");
-    assert!(!out.status.success(), "auth policy should fail until supported");
+    assert!(
+        !out.status.success(),
+        "auth policy should fail until supported"
+    );
     let stderr = String::from_utf8_lossy(&out.stderr);
     assert!(
         stderr.contains("requires authenticity"),
@@ -172,3 +725,622 @@ fn coelanox_packer_verify_policy_requires_verification_mode() {
         "unexpected stderr: {stderr}"
     );
 }
+
+/// `--manifest` packs the listed entries: op_ids may be canonical names, relative paths resolve
+/// against the manifest's directory (not the working directory), and names reach the archive.
+#[test]
+fn coelanox_packer_packs_from_manifest() {
+    let bin = env!("CARGO_BIN_EXE_coelanox-packer");
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::create_dir(dir.path().join("build")).expect("mkdir");
+    std::fs::write(dir.path().join("build/add.bin"), b"add").expect("write");
+    std::fs::write(dir.path().join("build/mm.bin"), b"matmul").expect("write");
+    std::fs::write(
+        dir.path().join("pack.toml"),
+        "vendor = \"acme\"\ntarget = \"CPU\"\nalign = 8\n\n\
+         [[blobs]]\nop_id = 1\npath = \"build/add.bin\"\nname = \"add_f32\"\n\n\
+         [[blobs]]\nop_id = \"matmul\"\npath = \"build/mm.bin\"\n",
+    )
+    .expect("write manifest");
+    std::fs::write(
+        dir.path().join("pack.json"),
+        r#"{"target": "GPU", "blobs": [{"op_id": "Relu", "path": "build/add.bin"}]}"#,
+    )
+    .expect("write manifest");
+
+    let out = dir.path().join("out.clf");
+    let pack = Command::new(bin)
+        .current_dir(std::env::temp_dir())
+        .arg("--manifest")
+        .arg(dir.path().join("pack.toml"))
+        .args(["--vendor", "override", "-o"])
+        .arg(&out)
+        .output()
+        .expect("pack");
+    assert!(pack.status.success(), "{pack:?}");
+    let mut reader = clf::ClfReader::open(&out).expect("open");
+    assert_eq!(reader.header.vendor, "override");
+    assert_eq!(reader.header.target, "CPU");
+    assert_eq!(reader.header.blob_alignment, 8);
+    assert_eq!(reader.get_blob(50).unwrap().unwrap(), b"matmul");
+    assert_eq!(reader.entry_name(1), Some("add_f32"));
+
+    let pack = Command::new(bin)
+        .arg("--manifest")
+        .arg(dir.path().join("pack.json"))
+        .arg("-o")
+        .arg(&out)
+        .output()
+        .expect("pack");
+    assert!(pack.status.success(), "{pack:?}");
+    let mut reader = clf::ClfReader::open(&out).expect("open");
+    assert_eq!(reader.header.target, "GPU");
+    assert_eq!(reader.get_blob(10).unwrap().unwrap(), b"add");
+}
+
+/// A manifest entry with a bad op_id fails with an error naming the entry and its line.
+#[test]
+fn coelanox_packer_manifest_errors_name_entry_and_line() {
+    let bin = env!("CARGO_BIN_EXE_coelanox-packer");
+    let dir = tempfile::tempdir().expect("tempdir");
+    let manifest = dir.path().join("pack.toml");
+    std::fs::write(
+        &manifest,
+        "[[blobs]]\nop_id = 1\npath = \"a.bin\"\n\n[[blobs]]\nop_id = \"frobnicate\"\npath = \"b.bin\"\n",
+    )
+    .expect("write manifest");
+    let out = Command::new(bin)
+        .arg("--manifest")
+        .arg(&manifest)
+        .arg("-o")
+        .arg(dir.path().join("out.clf"))
+        .output()
+        .expect("run");
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        stderr.contains("blobs[1] (line 5)") && stderr.contains("\"frobnicate\""),
+        "unexpected stderr: {stderr}"
+    );
+}
+
+/// `--from-dir` packs op_id-named files sorted by op_id, skips other files with a warning and
+/// lets an explicit OP_ID:PATH entry replace a directory file.
+#[test]
+fn coelanox_packer_packs_from_dir() {
+    let bin = env!("CARGO_BIN_EXE_coelanox-packer");
+    let dir = tempfile::tempdir().expect("tempdir");
+    let kernels = dir.path().join("kernels");
+    std::fs::create_dir(&kernels).expect("mkdir");
+    std::fs::write(kernels.join("0050_matmul.bin"), b"matmul").expect("write");
+    std::fs::write(kernels.join("1.bin"), b"add").expect("write");
+    std::fs::write(kernels.join("10_relu"), b"relu").expect("write");
+    std::fs::write(kernels.join("README.md"), b"docs").expect("write");
+    std::fs::write(dir.path().join("tuned.bin"), b"tuned").expect("write");
+
+    let out = dir.path().join("out.clfc");
+    let pack = Command::new(bin)
+        .arg("--from-dir")
+        .arg(&kernels)
+        .args(["--target", "CPU", "-o"])
+        .arg(&out)
+        .arg(format!("10:{}", dir.path().join("tuned.bin").display()))
+        .output()
+        .expect("pack");
+    assert!(pack.status.success(), "{pack:?}");
+    let stderr = String::from_utf8_lossy(&pack.stderr);
+    assert!(stderr.contains("README.md"), "unexpected stderr: {stderr}");
+    let mut reader = clf::ClfReader::open(&out).expect("open");
+    assert_eq!(reader.header.target, "CPU");
+    assert_eq!(reader.op_ids(), vec![1, 10, 50]);
+    assert_eq!(reader.get_blob(10).unwrap().unwrap(), b"tuned");
+    assert_eq!(reader.get_blob(50).unwrap().unwrap(), b"matmul");
+}
+
+/// Two files in the `--from-dir` directory with the same op_id are an error naming both.
+#[test]
+fn coelanox_packer_from_dir_rejects_duplicate_op_ids() {
+    let bin = env!("CARGO_BIN_EXE_coelanox-packer");
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("7_a.bin"), b"a").expect("write");
+    std::fs::write(dir.path().join("007_b.bin"), b"b").expect("write");
+    let out = Command::new(bin)
+        .arg("--from-dir")
+        .arg(dir.path())
+        .arg("-o")
+        .arg(dir.path().join("out.clfc"))
+        .output()
+        .expect("run");
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        stderr.contains("7_a.bin") && stderr.contains("007_b.bin"),
+        "unexpected stderr: {stderr}"
+    );
+}
+
+/// Inspect names known ops, flags an entry running past the blob store without failing, and
+/// exits non-zero on a file it cannot parse.
+#[test]
+fn coelanox_packer_inspect_flags_problems_and_names_ops() {
+    let bin = env!("CARGO_BIN_EXE_coelanox-packer");
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("add.bin"), b"add kernel").expect("write");
+    let clf_path = dir.path().join("out.clfc");
+    let pack = Command::new(bin)
+        .arg("-o")
+        .arg(&clf_path)
+        .arg(format!("1:{}", dir.path().join("add.bin").display()))
+        .status()
+        .expect("pack");
+    assert!(pack.success(), "pack failed");
+    let mut bytes = std::fs::read(&clf_path).expect("read");
+    bytes.truncate(bytes.len() - 3);
+    std::fs::write(&clf_path, &bytes).expect("write");
+
+    let inspect = Command::new(bin)
+        .arg("-i")
+        .arg(&clf_path)
+        .output()
+        .expect("inspect");
+    assert!(inspect.status.success(), "{inspect:?}");
+    let s = String::from_utf8_lossy(&inspect.stdout);
+    assert!(s.contains("Add"), "expected op name: {s}");
+    assert!(
+        s.contains("Problems (1)") && s.contains("op_id 1"),
+        "expected problem report: {s}"
+    );
+
+    let garbage = dir.path().join("garbage.clf");
+    std::fs::write(&garbage, b"not a clf file").expect("write");
+    let inspect = Command::new(bin)
+        .arg("-i")
+        .arg(&garbage)
+        .output()
+        .expect("inspect");
+    assert!(!inspect.status.success());
+}
+
+/// `--extract --op` writes the exact packed bytes (no alignment padding) and a missing op_id
+/// fails with the list of available ids.
+#[test]
+fn coelanox_packer_extracts_one_blob() {
+    let bin = env!("CARGO_BIN_EXE_coelanox-packer");
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("add.bin"), b"add").expect("write");
+    std::fs::write(dir.path().join("mm.bin"), b"matmul").expect("write");
+    let clf_path = dir.path().join("out.clfc");
+    assert!(Command::new(bin)
+        .args(["--align", "16", "-o"])
+        .arg(&clf_path)
+        .arg(format!("1:{}", dir.path().join("add.bin").display()))
+        .arg(format!("50:{}", dir.path().join("mm.bin").display()))
+        .status()
+        .expect("pack")
+        .success());
+
+    let out = dir.path().join("matmul.bin");
+    let extract = Command::new(bin)
+        .arg("--extract")
+        .arg(&clf_path)
+        .args(["--op", "50", "--verify-signature", "-o"])
+        .arg(&out)
+        .output()
+        .expect("extract");
+    assert!(
+        !extract.status.success(),
+        "--verify-signature on an unsigned file should fail"
+    );
+
+    let extract = Command::new(bin)
+        .arg("--extract")
+        .arg(&clf_path)
+        .args(["--op", "50", "-o"])
+        .arg(&out)
+        .output()
+        .expect("extract");
+    assert!(extract.status.success(), "{extract:?}");
+    assert_eq!(std::fs::read(&out).expect("read"), b"matmul");
+
+    let missing = Command::new(bin)
+        .arg("--extract")
+        .arg(&clf_path)
+        .args(["--op", "51", "-o"])
+        .arg(dir.path().join("missing.bin"))
+        .output()
+        .expect("extract");
+    assert!(!missing.status.success());
+    let stderr = String::from_utf8_lossy(&missing.stderr);
+    assert!(stderr.contains("available: 1, 50"), "{stderr}");
+}
+
+/// `--extract --all` names files after op_id and registry name, refuses to overwrite them
+/// without `--force`, and its output packs back with `--from-dir`.
+#[test]
+fn coelanox_packer_extracts_all_blobs() {
+    let bin = env!("CARGO_BIN_EXE_coelanox-packer");
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("add.bin"), b"add").expect("write");
+    std::fs::write(dir.path().join("custom.bin"), b"custom").expect("write");
+    let clf_path = dir.path().join("out.clfc");
+    assert!(Command::new(bin)
+        .arg("-o")
+        .arg(&clf_path)
+        .arg(format!("1:{}", dir.path().join("add.bin").display()))
+        .arg(format!("9000:{}", dir.path().join("custom.bin").display()))
+        .status()
+        .expect("pack")
+        .success());
+
+    let outdir = dir.path().join("blobs");
+    let extract_all = |force: bool| {
+        let mut cmd = Command::new(bin);
+        cmd.arg("--extract").arg(&clf_path).args(["--all", "-o"]);
+        cmd.arg(&outdir);
+        if force {
+            cmd.arg("--force");
+        }
+        cmd.output().expect("extract")
+    };
+    let first = extract_all(false);
+    assert!(first.status.success(), "{first:?}");
+    assert_eq!(std::fs::read(outdir.join("1_add.bin")).unwrap(), b"add");
+    assert_eq!(std::fs::read(outdir.join("9000.bin")).unwrap(), b"custom");
+
+    let again = extract_all(false);
+    assert!(!again.status.success());
+    assert!(String::from_utf8_lossy(&again.stderr).contains("--force"));
+    assert!(extract_all(true).status.success());
+
+    let repacked = dir.path().join("repacked.clfc");
+    assert!(Command::new(bin)
+        .arg("--from-dir")
+        .arg(&outdir)
+        .arg("-o")
+        .arg(&repacked)
+        .status()
+        .expect("pack")
+        .success());
+    assert_eq!(
+        clf::ClfReader::open(&repacked).unwrap().op_ids(),
+        vec![1, 9000]
+    );
+}
+
+/// `--merge` combines two archives with an overlapping op per `--on-conflict`, reports the
+/// conflict, and refuses inputs of different kinds.
+#[test]
+fn coelanox_packer_merges_archives() {
+    let bin = env!("CARGO_BIN_EXE_coelanox-packer");
+    let dir = tempfile::tempdir().expect("tempdir");
+    for (name, bytes) in [
+        ("a1", "a-add"),
+        ("a50", "a-mm"),
+        ("b50", "b-mm"),
+        ("b10", "b-relu"),
+    ] {
+        std::fs::write(dir.path().join(name), bytes).expect("write");
+    }
+    let pack = |out: &str, kind: &str, entries: &[(u32, &str)]| {
+        let mut cmd = Command::new(bin);
+        cmd.args(["--vendor", "team", "--kind", kind, "-o"])
+            .arg(dir.path().join(out));
+        for (op_id, name) in entries {
+            cmd.arg(format!("{op_id}:{}", dir.path().join(name).display()));
+        }
+        assert!(cmd.status().expect("pack").success());
+        dir.path().join(out)
+    };
+    let a = pack("a.clfc", "compute", &[(1, "a1"), (50, "a50")]);
+    let b = pack("b.clfc", "compute", &[(50, "b50"), (10, "b10")]);
+    let mm = pack("c.clfmm", "memory-movement", &[(2, "a1")]);
+    let out = dir.path().join("combined.clfc");
+    let merge = |inputs: &[&std::path::Path], extra: &[&str]| {
+        Command::new(bin)
+            .arg("--merge")
+            .args(inputs)
+            .args(extra)
+            .arg("-o")
+            .arg(&out)
+            .output()
+            .expect("merge")
+    };
+
+    let refused = merge(&[&a, &b], &[]);
+    assert!(!refused.status.success());
+    assert!(String::from_utf8_lossy(&refused.stderr).contains("op_id 50"));
+
+    let merged = merge(
+        &[&a, &b],
+        &[
+            "--on-conflict",
+            "prefer-second",
+            "--target",
+            "CPU",
+            "--sign",
+        ],
+    );
+    assert!(merged.status.success(), "{merged:?}");
+    let stderr = String::from_utf8_lossy(&merged.stderr);
+    assert!(
+        stderr.contains("a.clfc: 1 op_ids")
+            && stderr.contains("b.clfc: 2 op_ids")
+            && stderr.contains("conflicts: 50"),
+        "unexpected summary: {stderr}"
+    );
+    let mut reader = clf::ClfReader::open(&out).expect("open");
+    assert!(reader.verify_signature().unwrap());
+    assert_eq!(reader.header.vendor, "team");
+    assert_eq!(reader.header.target, "CPU");
+    assert_eq!(reader.op_ids(), vec![1, 10, 50]);
+    assert_eq!(reader.get_blob(50).unwrap().unwrap(), b"b-mm");
+
+    let kinds = merge(&[&a, &mm], &[]);
+    assert!(!kinds.status.success());
+    assert!(String::from_utf8_lossy(&kinds.stderr).contains("kinds differ"));
+}
+
+/// `--diff` lists header changes and added, removed and changed entries; `--exit-code` fails
+/// only when the archives differ.
+#[test]
+fn coelanox_packer_diffs_archives() {
+    let bin = env!("CARGO_BIN_EXE_coelanox-packer");
+    let dir = tempfile::tempdir().expect("tempdir");
+    for (name, bytes) in [
+        ("add", "add"),
+        ("sub", "sub"),
+        ("sub2", "sub v2"),
+        ("mm", "mm"),
+    ] {
+        std::fs::write(dir.path().join(name), bytes).expect("write");
+    }
+    let pack = |out: &str, vendor: &str, entries: &[(u32, &str)]| {
+        let mut cmd = Command::new(bin);
+        cmd.args(["--vendor", vendor, "-o"])
+            .arg(dir.path().join(out));
+        for (op_id, name) in entries {
+            cmd.arg(format!("{op_id}:{}", dir.path().join(name).display()));
+        }
+        assert!(cmd.status().expect("pack").success());
+        dir.path().join(out)
+    };
+    let old = pack("old.clfc", "acme", &[(1, "add"), (2, "sub"), (3, "mm")]);
+    let new = pack("new.clfc", "acme-2", &[(1, "add"), (2, "sub2"), (50, "mm")]);
+    let diff = |a: &std::path::Path, b: &std::path::Path| {
+        Command::new(bin)
+            .arg("--diff")
+            .arg(a)
+            .arg(b)
+            .arg("--exit-code")
+            .output()
+            .expect("diff")
+    };
+
+    let out = diff(&old, &new);
+    assert!(!out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(
+        stdout.contains("header vendor: \"acme\" -> \"acme-2\""),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("Subtract  changed (3 -> 6 bytes)"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("Multiply  removed"), "{stdout}");
+    assert!(stdout.contains("MatMul  added"), "{stdout}");
+    assert!(!stdout.contains("Add "), "unchanged op listed: {stdout}");
+
+    let same = diff(&old, &old);
+    assert!(same.status.success(), "{same:?}");
+    assert!(String::from_utf8_lossy(&same.stdout).contains("no differences"));
+}
+
+/// Positional entries and `--extract --op` take op names as well as numeric ids.
+#[test]
+fn coelanox_packer_accepts_op_names() {
+    let bin = env!("CARGO_BIN_EXE_coelanox-packer");
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("mm.bin"), b"matmul").expect("write");
+    let clf_path = dir.path().join("out.clfc");
+    let pack = Command::new(bin)
+        .arg("-o")
+        .arg(&clf_path)
+        .arg(format!("MatMul:{}", dir.path().join("mm.bin").display()))
+        .arg(format!("300:{}", dir.path().join("mm.bin").display()))
+        .output()
+        .expect("pack");
+    assert!(pack.status.success(), "{pack:?}");
+    assert_eq!(
+        clf::ClfReader::open(&clf_path).unwrap().op_ids(),
+        vec![50, 300]
+    );
+
+    let out = dir.path().join("extracted.bin");
+    let extract = Command::new(bin)
+        .arg("-x")
+        .arg(&clf_path)
+        .args(["--op", "matmul", "-o"])
+        .arg(&out)
+        .output()
+        .expect("extract");
+    assert!(extract.status.success(), "{extract:?}");
+    assert_eq!(std::fs::read(&out).unwrap(), b"matmul");
+
+    let typo = Command::new(bin)
+        .arg("-o")
+        .arg(&clf_path)
+        .arg(format!("relux:{}", dir.path().join("mm.bin").display()))
+        .output()
+        .expect("pack");
+    assert!(!typo.status.success());
+    let stderr = String::from_utf8_lossy(&typo.stderr);
+    assert!(stderr.contains("did you mean Relu"), "{stderr}");
+}
+
+/// The `--inspect --json` document matches the snapshot in tests/fixtures/inspect.json, and
+/// `--verify --json` reports failures and errors as JSON with exit status 1.
+#[test]
+fn coelanox_packer_json_schema_snapshot() {
+    let bin = env!("CARGO_BIN_EXE_coelanox-packer");
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("add.bin"), b"add").expect("write");
+    std::fs::write(dir.path().join("c.bin"), b"custom kernel").expect("write");
+    let clf_path = dir.path().join("snap.clfc");
+    assert!(Command::new(bin)
+        .current_dir(dir.path())
+        .args(["--deterministic", "--vendor", "acme", "--target", "CPU"])
+        .args(["--align", "8", "--crc", "--sign", "-o", "snap.clfc"])
+        .args(["add:add.bin", "300:c.bin"])
+        .status()
+        .expect("pack")
+        .success());
+
+    let inspect = Command::new(bin)
+        .arg("-i")
+        .arg(&clf_path)
+        .args(["--json", "--verify-signature"])
+        .output()
+        .expect("inspect");
+    assert!(inspect.status.success(), "{inspect:?}");
+    let mut actual: serde_json::Value = serde_json::from_slice(&inspect.stdout).expect("JSON");
+    actual["file"] = "<FILE>".into();
+    let expected: serde_json::Value =
+        serde_json::from_str(include_str!("fixtures/inspect.json")).expect("snapshot JSON");
+    assert_eq!(
+        actual,
+        expected,
+        "inspect JSON changed; update tests/fixtures/inspect.json if intended:\n{}",
+        serde_json::to_string_pretty(&actual).unwrap()
+    );
+
+    let verify = |path: &std::path::Path| {
+        let out = Command::new(bin)
+            .arg("--verify")
+            .arg(path)
+            .args(["--json", "--require-signature"])
+            .output()
+            .expect("verify");
+        let doc: serde_json::Value = serde_json::from_slice(&out.stdout).expect("JSON");
+        (out.status.success(), doc)
+    };
+    let (ok, doc) = verify(&clf_path);
+    assert!(
+        ok && doc["ok"] == true && doc["signature_verified"] == true,
+        "{doc}"
+    );
+
+    let mut bytes = std::fs::read(&clf_path).expect("read");
+    bytes[90] ^= 0xff;
+    std::fs::write(&clf_path, &bytes).expect("write");
+    let (ok, doc) = verify(&clf_path);
+    assert!(!ok);
+    assert_eq!(doc["ok"], false, "{doc}");
+    assert_eq!(doc["signature_verified"], false, "{doc}");
+    assert_eq!(doc["failures"].as_array().map(Vec::len), Some(1), "{doc}");
+
+    let (ok, doc) = verify(&dir.path().join("missing.clfc"));
+    assert!(!ok);
+    assert!(doc["error"]["message"].is_string(), "{doc}");
+}
+
+/// A blob path of `-` reads that blob from stdin, from the command line or a manifest (with the
+/// sidecar hash taken while streaming); a second stdin entry is an error.
+#[test]
+fn coelanox_packer_reads_blob_from_stdin() {
+    use std::process::Stdio;
+
+    let bin = env!("CARGO_BIN_EXE_coelanox-packer");
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("add.bin"), b"add").expect("write");
+    let clf_path = dir.path().join("out.clfc");
+    let run_with_stdin = |cmd: &mut Command, input: &[u8]| {
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("spawn");
+        // A run that fails before reading stdin closes the pipe; the exit status is what the
+        // test checks, so a broken pipe here is not an error.
+        let _ = child.stdin.take().expect("stdin").write_all(input);
+        child.wait_with_output().expect("wait")
+    };
+
+    let out = run_with_stdin(
+        Command::new(bin)
+            .arg("-o")
+            .arg(&clf_path)
+            .arg(format!("1:{}", dir.path().join("add.bin").display()))
+            .arg("matmul:-"),
+        b"piped matmul",
+    );
+    assert!(out.status.success(), "{out:?}");
+    let mut reader = clf::ClfReader::open(&clf_path).expect("open");
+    assert_eq!(reader.get_blob(50).unwrap().unwrap(), b"piped matmul");
+    assert_eq!(reader.get_blob(1).unwrap().unwrap(), b"add");
+
+    let twice = run_with_stdin(
+        Command::new(bin)
+            .arg("-o")
+            .arg(&clf_path)
+            .args(["1:-", "2:-"]),
+        b"x",
+    );
+    assert!(!twice.status.success());
+    assert!(String::from_utf8_lossy(&twice.stderr).contains("only one entry"));
+
+    std::fs::write(
+        dir.path().join("pack.toml"),
+        "[[blobs]]\nop_id = 10\npath = \"-\"\n",
+    )
+    .expect("write manifest");
+    let out = run_with_stdin(
+        Command::new(bin)
+            .arg("--manifest")
+            .arg(dir.path().join("pack.toml"))
+            .arg("--write-sidecar")
+            .arg("-o")
+            .arg(&clf_path),
+        b"relu",
+    );
+    assert!(out.status.success(), "{out:?}");
+    let mut reader = clf::ClfReader::open(&clf_path).expect("open");
+    assert_eq!(reader.get_blob(10).unwrap().unwrap(), b"relu");
+    let sidecar: serde_json::Value = serde_json::from_slice(
+        &std::fs::read(dir.path().join("out.clfc.meta.json")).expect("sidecar"),
+    )
+    .expect("JSON");
+    assert_eq!(
+        sidecar["blobs"][0]["sha256_hex"],
+        "99e2995dbca8ca48ab1e5e0b7946ea783a9f242fb8ea1482dd8c8aca7e2805b8"
+    );
+}
+
+/// `--input-format elf` packs only the selected section of an object file and fails on a section
+/// that still needs relocating.
+#[cfg(feature = "elf")]
+#[test]
+fn coelanox_packer_packs_elf_sections() {
+    let bin = env!("CARGO_BIN_EXE_coelanox-packer");
+    let dir = tempfile::tempdir().expect("tempdir");
+    let object = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/kernel.o");
+    let clf_path = dir.path().join("out.clfc");
+    let pack = |section: &str| {
+        Command::new(bin)
+            .args(["--input-format", "elf", "--section", section, "-o"])
+            .arg(&clf_path)
+            .arg(format!("1:{object}"))
+            .output()
+            .expect("pack")
+    };
+
+    let out = pack(".text.mykernel");
+    assert!(out.status.success(), "{out:?}");
+    let mut reader = clf::ClfReader::open(&clf_path).expect("open");
+    assert_eq!(reader.get_blob(1).unwrap().unwrap(), [0x90, 0x90, 0xc3]);
+
+    let out = pack(".text.reloc");
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("relocation"));
+}
@@ -0,0 +1,299 @@
+//! Editor tests: replace, remove and add entries of a packed file with ClfEditor, append
+//! entries in place with append_entries, and renumber op_ids with remap_op_ids.
+
+use std::io::Write;
+
+use std::collections::{BTreeMap, HashMap};
+
+use clf::{
+    append_entries, pack_and_sign, pack_clf, pack_clf_variants, plan_remap, remap_op_ids,
+    remap_op_ids_to_path, AppendOptions, ClfEditor, ClfError, ClfReader, ClfReaderFromBytes,
+    EditError, PackError, PackOptions,
+};
+use tempfile::NamedTempFile;
+
+/// Pack `entries` (signed) into a temp file.
+fn signed_archive(entries: &[(u32, Vec<u8>)]) -> NamedTempFile {
+    let mut file = NamedTempFile::new().unwrap();
+    let options = PackOptions {
+        vendor: "Acme".to_string(),
+        blob_alignment: 16,
+        sign: true,
+        ..PackOptions::default()
+    };
+    pack_and_sign(&mut file, entries, &options).unwrap();
+    file.flush().unwrap();
+    file
+}
+
+/// Replacing with a larger blob, removing and adding rewrite the file; the signature is
+/// dropped without `resign` and the header is kept.
+#[test]
+fn editor_replace_remove_add() {
+    let file = signed_archive(&[(1, vec![1; 4]), (2, vec![2; 4]), (3, vec![3; 4])]);
+
+    let mut editor = ClfEditor::open(file.path()).unwrap();
+    editor.replace_blob(1, &[9; 100]).unwrap();
+    editor.remove(2).unwrap();
+    editor.add(7, b"new kernel").unwrap();
+    assert!(matches!(
+        editor.add(3, b"x"),
+        Err(EditError::DuplicateOpId(3))
+    ));
+    assert!(matches!(editor.remove(2), Err(EditError::UnknownOpId(2))));
+    assert_eq!(editor.op_ids(), vec![1, 3, 7]);
+    editor.save(false).unwrap();
+
+    let mut reader = ClfReader::open(file.path()).unwrap();
+    reader.validate().unwrap();
+    assert_eq!(reader.header.vendor, "Acme");
+    assert_eq!(reader.header.blob_alignment, 16);
+    assert!(!reader.signature_block_present());
+    assert_eq!(reader.op_ids(), vec![1, 3, 7]);
+    assert_eq!(reader.get_blob(1).unwrap().unwrap(), vec![9; 100]);
+    assert_eq!(reader.get_blob(3).unwrap().unwrap(), vec![3; 4]);
+    assert_eq!(reader.get_blob(7).unwrap().unwrap(), b"new kernel");
+}
+
+/// Removing the only entry leaves an empty archive; `resign` writes a valid signature.
+#[test]
+fn editor_remove_last_entry_and_resign() {
+    let file = signed_archive(&[(5, vec![5; 8])]);
+
+    let mut editor = ClfEditor::open(file.path()).unwrap();
+    editor.remove(5).unwrap();
+    editor.save(true).unwrap();
+
+    let mut reader = ClfReader::open(file.path()).unwrap();
+    assert!(reader.op_ids().is_empty());
+    assert!(reader.signature_block_present());
+    assert!(reader.verify_signature().unwrap());
+}
+
+/// A file whose manifest fails validation is not opened for editing and is left untouched.
+#[test]
+fn editor_rejects_invalid_manifest() {
+    let entries: Vec<(u32, Vec<u8>)> = vec![(1, vec![1; 8]), (2, vec![2; 8])];
+    let mut file = NamedTempFile::new().unwrap();
+    pack_clf(&mut file, &entries, &PackOptions::default()).unwrap();
+    let mut bytes = std::fs::read(file.path()).unwrap();
+    // Point the second manifest entry's offset into the first blob (partial overlap).
+    let blob_store = ClfReaderFromBytes::open(&bytes, None)
+        .unwrap()
+        .blob_store_offset() as usize;
+    let second_offset_at = blob_store - 16 + 4;
+    bytes[second_offset_at..second_offset_at + 4].copy_from_slice(&4u32.to_le_bytes());
+    std::fs::write(file.path(), &bytes).unwrap();
+
+    let err = ClfEditor::open(file.path()).unwrap_err();
+    assert!(
+        matches!(err, EditError::Read(ClfError::OverlappingBlobs { .. })),
+        "{err}"
+    );
+    assert_eq!(std::fs::read(file.path()).unwrap(), bytes);
+}
+
+/// Unsigned archive of `entries` with CRCs, 16-byte alignment and some metadata, in a temp file.
+fn unsigned_archive(entries: &[(u32, Vec<u8>)]) -> NamedTempFile {
+    let mut file = NamedTempFile::new().unwrap();
+    let options = PackOptions {
+        vendor: "Acme".to_string(),
+        blob_alignment: 16,
+        per_blob_crc: true,
+        metadata: vec![("driver".to_string(), "1.0".to_string())],
+        ..PackOptions::default()
+    };
+    pack_clf(&mut file, entries, &options).unwrap();
+    file.flush().unwrap();
+    file
+}
+
+/// Appending more entries than fit in front of the first blob moves the blob store (keeping
+/// the requested reserve); a later append that fits in the reserve leaves it where it is. Every
+/// blob reads back, CRCs included, and the header is unchanged.
+#[test]
+fn append_moves_blob_store_only_when_manifest_outgrows_it() {
+    let original: Vec<(u32, Vec<u8>)> = (1..=3)
+        .map(|op_id| (op_id, vec![op_id as u8; 20]))
+        .collect();
+    let file = unsigned_archive(&original);
+    let old_len = std::fs::metadata(file.path()).unwrap().len();
+
+    let batch: Vec<(u32, Vec<u8>)> = (100..140)
+        .map(|op_id| (op_id, vec![op_id as u8; 7]))
+        .collect();
+    let options = AppendOptions {
+        reserve_entries: 4,
+        ..AppendOptions::default()
+    };
+    let summary = append_entries(file.path(), &batch, &options).unwrap();
+    assert!(summary.store_shift > 0);
+    assert_eq!(summary.store_shift % 16, 0);
+    assert!(summary.spare_entries >= 4);
+    assert!(summary.data_len > old_len);
+
+    let more: Vec<(u32, Vec<u8>)> = (200..203)
+        .map(|op_id| (op_id, vec![op_id as u8; 33]))
+        .collect();
+    let second = append_entries(file.path(), &more, &options).unwrap();
+    assert_eq!(second.store_shift, 0);
+    assert_eq!(second.spare_entries, summary.spare_entries - 3);
+    assert_eq!(
+        std::fs::metadata(file.path()).unwrap().len(),
+        second.data_len
+    );
+
+    let mut reader = ClfReader::open(file.path()).unwrap();
+    reader.validate().unwrap();
+    assert_eq!(reader.header.vendor, "Acme");
+    assert_eq!(
+        reader.header.metadata,
+        vec![("driver".to_string(), "1.0".to_string())]
+    );
+    assert_eq!(reader.op_ids().len(), 46);
+    for (op_id, blob) in original.iter().chain(&batch).chain(&more) {
+        assert_eq!(
+            reader.get_blob(*op_id).unwrap().as_ref(),
+            Some(blob),
+            "op_id {op_id}"
+        );
+    }
+    assert!(reader.manifest_entries().iter().all(|e| e.crc32.is_some()));
+}
+
+/// An op_id the archive has fails without touching the file unless `replace` is set, which
+/// swaps the blob; `sign` signs the result, and a signed archive is refused.
+#[test]
+fn append_duplicate_replace_and_sign() {
+    let file = unsigned_archive(&[(1, vec![1; 4]), (2, vec![2; 4])]);
+    let before = std::fs::read(file.path()).unwrap();
+
+    let err = append_entries(
+        file.path(),
+        [(9, vec![9]), (2, vec![0; 4])],
+        &AppendOptions::default(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, EditError::DuplicateOpId(2)), "{err}");
+    assert_eq!(std::fs::read(file.path()).unwrap(), before);
+
+    let options = AppendOptions {
+        replace: true,
+        sign: true,
+        ..AppendOptions::default()
+    };
+    append_entries(file.path(), [(2, b"replacement".to_vec())], &options).unwrap();
+    let mut reader = ClfReader::open(file.path()).unwrap();
+    reader.validate().unwrap();
+    assert!(reader.verify_signature().unwrap());
+    assert_eq!(reader.op_ids(), vec![1, 2]);
+    assert_eq!(reader.get_blob(1).unwrap().unwrap(), vec![1; 4]);
+    assert_eq!(reader.get_blob(2).unwrap().unwrap(), b"replacement");
+
+    let err = append_entries(file.path(), [(3, vec![3])], &AppendOptions::default()).unwrap_err();
+    assert!(
+        matches!(err, EditError::Pack(PackError::AlreadySigned)),
+        "{err}"
+    );
+}
+
+/// Remapping swaps two op_ids and moves a third, with their variants and names; the blob store
+/// is copied byte for byte and the signature dropped. `remap_op_ids_to_path` may overwrite its
+/// input and signs as asked.
+#[test]
+fn remap_renumbers_entries_and_copies_blobs() {
+    let entries: Vec<(u32, u16, Vec<u8>)> = vec![
+        (1, 0, b"add".to_vec()),
+        (2, 0, b"sub".to_vec()),
+        (50, 0, b"matmul".to_vec()),
+        (50, 2, b"matmul_avx2".to_vec()),
+        (60, 0, b"relu".to_vec()),
+    ];
+    let options = PackOptions {
+        blob_alignment: 16,
+        per_blob_crc: true,
+        entry_names: HashMap::from([(50, "gemm".to_string()), (60, "relu".to_string())]),
+        ..PackOptions::default()
+    };
+    let mut file = NamedTempFile::new().unwrap();
+    pack_clf_variants(&mut file, &entries, &options).unwrap();
+    clf::resign(file.as_file_mut()).unwrap();
+    let source = ClfReader::open(file.path()).unwrap();
+    let map = BTreeMap::from([(1, 2), (2, 1), (50, 300), (7, 8)]);
+
+    let plan = plan_remap(&source, &map).unwrap();
+    assert_eq!(plan.remapped, vec![(1, 2), (2, 1), (50, 300)]);
+    assert_eq!(plan.unmapped, vec![60]);
+    assert_eq!(plan.absent, vec![7]);
+
+    let mut remapped = NamedTempFile::new().unwrap();
+    let summary = remap_op_ids(&source, &map, remapped.as_file_mut()).unwrap();
+    let out = std::fs::read(remapped.path()).unwrap();
+    assert_eq!(summary.data_len, out.len() as u64);
+    let mut reader = ClfReader::open(remapped.path()).unwrap();
+    reader.validate().unwrap();
+    assert!(!reader.signature_block_present());
+    assert_eq!(reader.op_ids(), vec![1, 2, 60, 300]);
+    assert_eq!(reader.variants(300), vec![0, 2]);
+    for (op_id, variant, blob) in &entries {
+        let to = map.get(op_id).copied().unwrap_or(*op_id);
+        assert_eq!(
+            reader.get_blob_variant(to, *variant).unwrap().as_ref(),
+            Some(blob)
+        );
+    }
+    assert_eq!(reader.header.entry_names.get(&300).unwrap(), "gemm");
+    assert_eq!(reader.header.entry_names.get(&60).unwrap(), "relu");
+    let original = std::fs::read(file.path()).unwrap();
+    let store = |bytes: &[u8], at: u64| {
+        bytes[at as usize..][..source.declared_blob_store_len() as usize].to_vec()
+    };
+    assert_eq!(
+        store(&out, reader.blob_store_offset()),
+        store(&original, source.blob_store_offset())
+    );
+
+    let sign = PackOptions {
+        sign: true,
+        ..PackOptions::default()
+    };
+    remap_op_ids_to_path(&source, &map, &sign, file.path()).unwrap();
+    let mut reader = ClfReader::open(file.path()).unwrap();
+    assert!(reader.verify_signature().unwrap());
+    assert_eq!(reader.get_blob(2).unwrap().unwrap(), b"add");
+}
+
+/// Two op_ids that would share a new id fail with `RemapCollision`, whether both are remapped
+/// or one is an op_id the map leaves alone, and nothing is written.
+#[test]
+fn remap_rejects_collisions() {
+    let file = unsigned_archive(&[(1, vec![1]), (2, vec![2]), (3, vec![3])]);
+    let reader = ClfReader::open(file.path()).unwrap();
+
+    let err = plan_remap(&reader, &BTreeMap::from([(1, 3)])).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            EditError::RemapCollision {
+                to: 3,
+                first: 1,
+                second: 3
+            }
+        ),
+        "{err}"
+    );
+    let mut out = Vec::new();
+    let err = remap_op_ids(&reader, &BTreeMap::from([(1, 9), (2, 9)]), &mut out).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            EditError::RemapCollision {
+                to: 9,
+                first: 1,
+                second: 2
+            }
+        ),
+        "{err}"
+    );
+    assert!(out.is_empty());
+}
@@ -0,0 +1,41 @@
+//! ELF input tests: extract sections from tests/fixtures/kernel.o (built from kernel.s).
+#![cfg(feature = "elf")]
+
+use clf::elf::{section_bytes, DEFAULT_SECTION};
+use clf::ElfError;
+
+const KERNEL_O: &[u8] = include_bytes!("fixtures/kernel.o");
+
+/// `.text` and a named section come back as just their bytes, without ELF headers.
+#[test]
+fn elf_section_bytes_returns_section_contents() {
+    assert_eq!(section_bytes(KERNEL_O, DEFAULT_SECTION).unwrap(), [0xc3]);
+    assert_eq!(
+        section_bytes(KERNEL_O, ".text.mykernel").unwrap(),
+        [0x90, 0x90, 0xc3]
+    );
+}
+
+/// Missing sections, sections without file data, sections with relocations and non-ELF input
+/// are errors.
+#[test]
+fn elf_section_bytes_rejects_unusable_input() {
+    let err = section_bytes(KERNEL_O, ".text.nope").unwrap_err();
+    assert!(matches!(err, ElfError::MissingSection(_)), "{err}");
+    let err = section_bytes(KERNEL_O, ".bss").unwrap_err();
+    assert!(matches!(err, ElfError::NoData { .. }), "{err}");
+    let err = section_bytes(KERNEL_O, ".text.reloc").unwrap_err();
+    assert!(
+        matches!(
+            err,
+            ElfError::Relocations {
+                count: 1,
+                first_offset: 1,
+                ..
+            }
+        ),
+        "{err}"
+    );
+    let err = section_bytes(b"not an object file", DEFAULT_SECTION).unwrap_err();
+    assert!(matches!(err, ElfError::Parse(_)), "{err}");
+}
@@ -0,0 +1,19 @@
+# Source of kernel.o, the ELF fixture for --input-format elf.
+# Rebuild with: as --64 -o kernel.o kernel.s
+    .text
+    .globl add_kernel
+add_kernel:
+    ret
+
+    .section .text.mykernel,"ax",@progbits
+    .globl my_kernel
+my_kernel:
+    nop
+    nop
+    ret
+
+    .section .text.reloc,"ax",@progbits
+    .globl needs_link
+needs_link:
+    call external_helper
+    ret
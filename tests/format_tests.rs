@@ -0,0 +1,422 @@
+//! Format types: `ClfKind` names, aliases, kind bytes and parsing; version features; the header
+//! codec; slice parsers for the header and manifest; digest comparison; target matching.
+
+use clf::{ClfError, ClfKind, TargetMatcher};
+
+/// Every kind parses back from its `Display` name and from each alias, in any case.
+#[test]
+fn kind_display_and_aliases_round_trip() {
+    for kind in ClfKind::ALL {
+        let name = kind.to_string();
+        assert_eq!(name, kind.name());
+        assert_eq!(name.parse::<ClfKind>().unwrap(), kind);
+        assert_eq!(name.to_uppercase().parse::<ClfKind>().unwrap(), kind);
+        for alias in kind.aliases() {
+            assert_eq!(alias.parse::<ClfKind>().unwrap(), kind, "alias {alias}");
+        }
+        assert_eq!(kind.extension().parse::<ClfKind>().unwrap(), kind);
+        assert_eq!(ClfKind::try_from_byte(kind.as_byte()), Some(kind));
+    }
+    assert_eq!("MM".parse::<ClfKind>().unwrap(), ClfKind::MemoryMovement);
+}
+
+/// `TryFrom<u8>` accepts exactly the defined kind bytes and mirrors `as_byte`; `from_byte`
+/// stays lenient.
+#[test]
+fn kind_try_from_byte_is_strict() {
+    for kind in ClfKind::ALL {
+        assert_eq!(ClfKind::try_from(kind.as_byte()).unwrap(), kind);
+    }
+    assert_eq!(ClfKind::try_from(3).unwrap(), ClfKind::Executor);
+    for bad in [4u8, 7, 200, 255] {
+        assert!(matches!(
+            ClfKind::try_from(bad),
+            Err(ClfError::InvalidKindByte(b)) if b == bad
+        ));
+        assert_eq!(ClfKind::from_byte(bad), ClfKind::Compute);
+    }
+}
+
+/// Junk input fails with a message listing the canonical names.
+#[test]
+fn kind_from_str_rejects_junk() {
+    for junk in ["", "vector", "compute ", "memory_movement", "clf"] {
+        let err = junk.parse::<ClfKind>().unwrap_err();
+        assert!(
+            err.contains("compute, memory-movement, memory-protection, executor"),
+            "{junk:?}: {err}"
+        );
+    }
+}
+
+/// Pack a v3 archive exercising every header section and optional entry field.
+fn full_v3_archive() -> Vec<u8> {
+    let options = clf::PackOptions {
+        vendor: "acme".to_string(),
+        target: "CPU".to_string(),
+        blob_alignment: 8,
+        per_blob_crc: true,
+        metadata: vec![("git".to_string(), "abc123".to_string())],
+        created_at: Some(1_700_000_000),
+        tool_version: Some("clf test".to_string()),
+        entry_names: [(1, "add_f32".to_string())].into_iter().collect(),
+        entry_targets: [((50, 1), "GPU".to_string())].into_iter().collect(),
+        extensions: vec![(0x0042, b"ext".to_vec())],
+        ..clf::PackOptions::default()
+    };
+    let entries = vec![
+        (1, 0, b"add".to_vec()),
+        (50, 0, b"matmul".to_vec()),
+        (50, 1, b"matmul-gpu".to_vec()),
+    ];
+    let mut bytes = Vec::new();
+    clf::pack_clf_variants(&mut std::io::Cursor::new(&mut bytes), &entries, &options).unwrap();
+    bytes
+}
+
+/// `parse_header` and `parse_manifest` agree with the file reader on a full v3 archive and
+/// report where each region ends.
+#[test]
+fn parse_header_and_manifest_from_slice() {
+    let bytes = full_v3_archive();
+    let reader = clf::ClfReaderFromBytes::open(&bytes, None).unwrap();
+
+    let (header, header_len) = clf::parse_header(&bytes).unwrap();
+    assert_eq!(header_len as u64, header.header_end);
+    assert_eq!(header.vendor, "acme");
+    assert_eq!(header.get_meta("git"), Some("abc123"));
+    assert_eq!(header.entry_name(1), Some("add_f32"));
+    assert_eq!(header.extension(0x0042), Some(&b"ext"[..]));
+
+    let (manifest, manifest_len) =
+        clf::parse_manifest(&bytes[header_len..], header.version).unwrap();
+    assert_eq!(
+        (header_len + manifest_len) as u64,
+        reader.blob_store_offset()
+    );
+    assert_eq!(manifest.entries, reader.manifest_entries());
+    assert_eq!(manifest.targets, ["GPU"]);
+    let gpu = manifest.entries.iter().find(|e| e.variant == 1).unwrap();
+    assert_eq!(manifest.targets[gpu.target_index as usize - 1], "GPU");
+
+    // v1 and v2 layouts parse too.
+    for version in [1u8, 2] {
+        let options = clf::PackOptions {
+            version,
+            ..clf::PackOptions::default()
+        };
+        let mut old = Vec::new();
+        clf::pack_clf(
+            &mut std::io::Cursor::new(&mut old),
+            &[(7, b"x".to_vec())],
+            &options,
+        )
+        .unwrap();
+        let (header, len) = clf::parse_header(&old).unwrap();
+        assert_eq!(header.version, version);
+        let (manifest, _) = clf::parse_manifest(&old[len..], version).unwrap();
+        assert_eq!(manifest.entries.len(), 1);
+    }
+}
+
+/// Every truncation of the header or manifest is an error, and arbitrary bytes never panic.
+#[test]
+fn parse_header_and_manifest_reject_truncation() {
+    let bytes = full_v3_archive();
+    let (header, header_len) = clf::parse_header(&bytes).unwrap();
+    for cut in 0..header_len {
+        assert!(
+            clf::parse_header(&bytes[..cut]).is_err(),
+            "header cut at {cut}"
+        );
+    }
+    let rest = &bytes[header_len..];
+    let (_, manifest_len) = clf::parse_manifest(rest, header.version).unwrap();
+    for cut in 0..manifest_len {
+        assert!(
+            clf::parse_manifest(&rest[..cut], header.version).is_err(),
+            "manifest cut at {cut}"
+        );
+    }
+
+    // Flip bytes of a valid archive (deterministic xorshift) and parse the result.
+    let mut state = 0x9E37_79B9_7F4A_7C15u64;
+    for _ in 0..2000 {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let mut mutated = bytes.clone();
+        let at = (state as usize) % (header_len + manifest_len);
+        mutated[at] = (state >> 32) as u8;
+        if let Ok((header, len)) = clf::parse_header(&mutated) {
+            let _ = clf::parse_manifest(&mutated[len..], header.version);
+        }
+        let _ = clf::parse_manifest(&mutated, (state >> 40) as u8);
+    }
+}
+
+/// Deterministic xorshift generator for the randomized round-trip tests.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// Up to `max_chars` characters mixing ASCII, multi-byte UTF-8 and NUL.
+    fn text(&mut self, max_chars: usize) -> String {
+        const CHARS: [char; 8] = ['a', 'Z', '0', '-', ' ', '\0', 'é', '核'];
+        (0..self.below(max_chars + 1))
+            .map(|_| CHARS[self.below(CHARS.len())])
+            .collect()
+    }
+}
+
+/// A random header for `version` and `kind`; v3-only fields are filled only for v3.
+fn random_header(rng: &mut XorShift, version: u8, kind: ClfKind) -> clf::ClfHeader {
+    let v3 = version >= 3;
+    clf::ClfHeader {
+        version,
+        vendor: rng.text(40),
+        target: rng.text(12),
+        blob_alignment: rng.next() as u8,
+        kind,
+        metadata: if v3 {
+            (0..rng.below(4))
+                .map(|i| (format!("k{i}{}", rng.text(8)), rng.text(30)))
+                .collect()
+        } else {
+            Vec::new()
+        },
+        created_at: (v3 && rng.below(2) == 0).then(|| rng.next()),
+        tool_version: (v3 && rng.below(2) == 0).then(|| rng.text(20)),
+        entry_names: if v3 {
+            (0..rng.below(4))
+                .map(|_| (rng.next() as u32, rng.text(30)))
+                .collect()
+        } else {
+            Default::default()
+        },
+        extensions: if v3 {
+            (0..rng.below(3) as u16)
+                .map(|tag| (tag, rng.text(10).into_bytes()))
+                .collect()
+        } else {
+            Vec::new()
+        },
+        header_end: 0,
+    }
+}
+
+/// Randomized headers of every version and kind survive `write_header` then `read_header`, and
+/// the packer writes byte-for-byte the same header for the same fields.
+#[test]
+fn header_codec_round_trips_all_versions_and_kinds() {
+    let mut rng = XorShift(0x2545_F491_4F6C_DD1D);
+    for _ in 0..200 {
+        for version in 1..=clf::CLF_VERSION {
+            for kind in ClfKind::ALL {
+                let header = random_header(&mut rng, version, kind);
+                let mut bytes = Vec::new();
+                clf::write_header(&mut bytes, &header).unwrap();
+                assert_eq!(bytes.len(), clf::format::header_len(&header));
+
+                let read = clf::read_header(&mut bytes.as_slice()).unwrap();
+                assert_eq!(read.header_end, bytes.len() as u64);
+                assert_eq!(read.version, version);
+                assert_eq!(read.vendor, header.vendor);
+                assert_eq!(read.target, header.target);
+                assert_eq!(read.blob_alignment, header.blob_alignment);
+                let expected_kind = if version >= 2 { kind } else { ClfKind::Compute };
+                assert_eq!(read.kind, expected_kind);
+                assert_eq!(read.metadata, header.metadata);
+                assert_eq!(read.created_at, header.created_at);
+                assert_eq!(read.tool_version, header.tool_version);
+                assert_eq!(read.entry_names, header.entry_names);
+                assert_eq!(read.extensions, header.extensions);
+
+                let options = clf::PackOptions {
+                    vendor: header.vendor.clone(),
+                    target: header.target.clone(),
+                    blob_alignment: header.blob_alignment,
+                    kind,
+                    version,
+                    metadata: header.metadata.clone(),
+                    created_at: header.created_at,
+                    tool_version: header.tool_version.clone(),
+                    entry_names: header.entry_names.clone().into_iter().collect(),
+                    extensions: header.extensions.clone(),
+                    ..clf::PackOptions::default()
+                };
+                let mut packed = Vec::new();
+                clf::pack_clf(
+                    &mut std::io::Cursor::new(&mut packed),
+                    Vec::<(u32, Vec<u8>)>::new(),
+                    &options,
+                )
+                .unwrap();
+                assert_eq!(packed[..bytes.len()], bytes[..]);
+            }
+        }
+    }
+}
+
+/// `write_header` refuses headers the reader would reject, and writes nothing for them.
+#[test]
+fn write_header_rejects_unreadable_headers() {
+    let base = clf::ClfHeader {
+        version: 2,
+        vendor: String::new(),
+        target: String::new(),
+        blob_alignment: 0,
+        kind: ClfKind::Compute,
+        metadata: Vec::new(),
+        created_at: None,
+        tool_version: None,
+        entry_names: Default::default(),
+        extensions: Vec::new(),
+        header_end: 0,
+    };
+    let bad = [
+        clf::ClfHeader {
+            metadata: vec![("k".into(), "v".into())],
+            ..base.clone()
+        },
+        clf::ClfHeader {
+            created_at: Some(1),
+            ..base.clone()
+        },
+        clf::ClfHeader {
+            vendor: "v".repeat(clf::format::MAX_HEADER_TEXT_LEN + 1),
+            ..base.clone()
+        },
+        clf::ClfHeader {
+            version: clf::CLF_VERSION + 1,
+            ..base.clone()
+        },
+        clf::ClfHeader {
+            version: 3,
+            entry_names: [(1, "n".repeat(256))].into_iter().collect(),
+            ..base.clone()
+        },
+    ];
+    for header in bad {
+        let mut out = Vec::new();
+        let err = clf::write_header(&mut out, &header).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(out.is_empty());
+    }
+
+    // The packer reports an oversized vendor or target with its own error.
+    let options = clf::PackOptions {
+        target: "t".repeat(clf::format::MAX_HEADER_TEXT_LEN + 1),
+        ..clf::PackOptions::default()
+    };
+    assert!(matches!(
+        clf::pack_clf(
+            &mut std::io::Cursor::new(Vec::new()),
+            Vec::<(u32, Vec<u8>)>::new(),
+            &options
+        ),
+        Err(clf::PackError::TargetTooLong)
+    ));
+}
+
+/// Each version's features match the layout history, and only 1 through `CLF_VERSION` are
+/// supported.
+#[test]
+fn version_features_per_version() {
+    use clf::format::{is_supported, max_supported};
+    use clf::VersionFeatures;
+
+    let v1 = VersionFeatures::for_version(1);
+    assert!(!v1.has_kind && !v1.has_header_sections && !v1.has_exact_size && !v1.has_flags);
+    let v2 = VersionFeatures::for_version(2);
+    assert!(v2.has_kind && !v2.has_header_sections && !v2.has_exact_size && !v2.has_flags);
+    let v3 = VersionFeatures::for_version(3);
+    assert!(v3.has_kind && v3.has_header_sections && v3.has_exact_size && v3.has_flags);
+    assert_eq!(v1.names(), Vec::<&str>::new());
+    assert_eq!(v2.names(), ["kind byte"]);
+    for version in 0..=u8::MAX {
+        assert!(!VersionFeatures::for_version(version).has_64bit_offsets);
+        assert_eq!(
+            is_supported(version),
+            (1..=clf::CLF_VERSION).contains(&version)
+        );
+    }
+    assert_eq!(max_supported(), clf::CLF_VERSION);
+}
+
+/// Unsupported versions (0 and newer than `CLF_VERSION`) fail to parse with a message naming
+/// the supported range and what the file requires.
+#[test]
+fn unsupported_version_error_names_features() {
+    let mut bytes = full_v3_archive();
+    for version in [0, clf::CLF_VERSION + 1, u8::MAX] {
+        bytes[4] = version;
+        let err = clf::parse_header(&bytes).unwrap_err();
+        assert!(
+            matches!(err, ClfError::UnsupportedVersion(v, max) if v == version && max == clf::CLF_VERSION)
+        );
+        let message = err.to_string();
+        assert!(message.contains("supported: 1-3"), "{message}");
+        if version == 0 {
+            assert!(message.contains("not a CLF format version"), "{message}");
+        } else {
+            assert!(
+                message.contains("features introduced after v3")
+                    && message.contains("per-entry fields"),
+                "{message}"
+            );
+        }
+    }
+}
+
+/// `constant_time_eq` accepts equal digests and rejects a difference at any position or a
+/// length mismatch.
+#[test]
+fn constant_time_eq_compares_digests() {
+    use clf::format::constant_time_eq;
+
+    let digest = [0xA5u8; 32];
+    assert!(constant_time_eq(&digest, &digest.clone()));
+    assert!(constant_time_eq(&[], &[]));
+    for i in [0, 15, 31] {
+        let mut other = digest;
+        other[i] ^= 0x01;
+        assert!(!constant_time_eq(&digest, &other), "byte {i}");
+    }
+    assert!(!constant_time_eq(&digest, &digest[..31]));
+    assert!(!constant_time_eq(&digest[..0], &digest[..1]));
+}
+
+/// `TargetMatcher` ignores case and `-`/`_`, matches a target with its families in either
+/// direction (through wider families too) but not with its siblings, and takes new aliases at
+/// runtime, surviving a cycle.
+#[test]
+fn target_matcher_normalizes_and_follows_families() {
+    let mut matcher = TargetMatcher::new();
+    assert!(matcher.matches("CPU", "cpu"));
+    assert!(matcher.matches(" x86-64 ", "X86_64"));
+    assert!(matcher.matches("gfx90a", "CDNA2"));
+    assert!(matcher.matches("CDNA2", "gfx90a"));
+    assert!(matcher.matches("amd64", "cpu"));
+    assert!(matcher.matches("x86_64", "CPU"));
+    assert!(!matcher.matches("gfx90a", "gfx942"));
+    assert!(!matcher.matches("gfx90a", "cpu"));
+    assert!(!matcher.matches("x86_64", "aarch64"));
+
+    assert!(!matcher.matches("gfx1100", "rdna3"));
+    matcher.add_alias("GFX1100", "RDNA3");
+    assert!(matcher.matches("gfx1100", "rdna3"));
+    matcher.add_alias("loop_a", "loop_b");
+    matcher.add_alias("loop_b", "loop_a");
+    assert!(!matcher.matches("loop_a", "cpu"));
+}
@@ -14,6 +14,7 @@ fn golden_packed_header_starts_with_clf_magic() {
         kind: ClfKind::Compute,
         version: CLF_VERSION,
         sign: false,
+        ..PackOptions::default()
     };
     let mut buf = Cursor::new(Vec::new());
     pack_clf(&mut buf, &entries, &options).unwrap();
@@ -17,6 +17,7 @@ fn packager_clf_backend_produces_non_empty_code() {
         kind: ClfKind::Compute,
         version: 1,
         sign: false,
+        ..PackOptions::default()
     };
 
     let mut file = tempfile::NamedTempFile::new().unwrap();
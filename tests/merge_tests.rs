@@ -0,0 +1,184 @@
+//! Merge tests: combine packed archives with merge_clf and read the result back.
+
+use std::io::{Cursor, Write};
+
+use clf::{
+    merge_clf, merge_clf_to_path, pack_and_sign, plan_merge, ClfKind, ClfReader,
+    ClfReaderFromBytes, ConflictPolicy, MergeError, MergeOptions, MismatchPolicy, PackOptions,
+};
+use tempfile::NamedTempFile;
+
+/// Pack `entries` into a temp file and open it.
+fn archive(entries: &[(u32, Vec<u8>)], options: &PackOptions) -> (NamedTempFile, ClfReader) {
+    let mut file = NamedTempFile::new().unwrap();
+    pack_and_sign(&mut file, entries, options).unwrap();
+    file.flush().unwrap();
+    let reader = ClfReader::open(file.path()).unwrap();
+    (file, reader)
+}
+
+fn merge(readers: &[ClfReader], options: &MergeOptions) -> Result<Vec<u8>, MergeError> {
+    let mut out = Cursor::new(Vec::new());
+    merge_clf(readers, options, &mut out)?;
+    Ok(out.into_inner())
+}
+
+/// Disjoint inputs: every entry is present in the output with its name; input signatures are
+/// dropped.
+#[test]
+fn merge_disjoint_archives() {
+    let options = PackOptions {
+        vendor: "team".to_string(),
+        target: "GPU".to_string(),
+        sign: true,
+        ..PackOptions::default()
+    };
+    let named = |op_id: u32, name: &str| PackOptions {
+        entry_names: [(op_id, name.to_string())].into_iter().collect(),
+        ..options.clone()
+    };
+    let (_a, ra) = archive(
+        &[(1, b"add".to_vec()), (3, b"relu".to_vec())],
+        &named(1, "add_f32"),
+    );
+    let (_b, rb) = archive(&[(2, b"sub".to_vec())], &named(2, "sub_f32"));
+
+    let merged = merge(&[ra, rb], &MergeOptions::default()).unwrap();
+    let reader = ClfReaderFromBytes::open(&merged, None).unwrap();
+    assert_eq!(reader.header.vendor, "team");
+    assert_eq!(reader.header.target, "GPU");
+    assert!(!reader.signature_block_present());
+    let ids: Vec<u32> = reader.manifest_entries().iter().map(|e| e.op_id).collect();
+    assert_eq!(ids, vec![1, 2, 3]);
+    assert_eq!(reader.get_blob(1).unwrap().unwrap(), b"add");
+    assert_eq!(reader.get_blob(2).unwrap().unwrap(), b"sub");
+    assert_eq!(reader.get_blob(3).unwrap().unwrap(), b"relu");
+    assert_eq!(reader.entry_name(1), Some("add_f32"));
+    assert_eq!(reader.entry_name(2), Some("sub_f32"));
+    assert_eq!(reader.entry_name(3), None);
+}
+
+/// An op_id in both inputs errors by default and resolves per PreferFirst / PreferLast.
+#[test]
+fn merge_conflicting_op_per_policy() {
+    let options = PackOptions::default();
+    let (_a, ra) = archive(&[(1, b"first".to_vec())], &options);
+    let (_b, rb) = archive(&[(1, b"second".to_vec())], &options);
+    let readers = [ra, rb];
+
+    let err = merge(&readers, &MergeOptions::default()).unwrap_err();
+    assert!(matches!(err, MergeError::ConflictingOpId(1)), "{err}");
+
+    for (policy, expected) in [
+        (ConflictPolicy::PreferFirst, &b"first"[..]),
+        (ConflictPolicy::PreferLast, &b"second"[..]),
+    ] {
+        let merged = merge(
+            &readers,
+            &MergeOptions {
+                on_conflict: policy,
+                ..MergeOptions::default()
+            },
+        )
+        .unwrap();
+        let reader = ClfReaderFromBytes::open(&merged, None).unwrap();
+        assert_eq!(reader.get_blob(1).unwrap().unwrap(), expected, "{policy:?}");
+    }
+}
+
+/// Inputs of different kinds are rejected.
+#[test]
+fn merge_rejects_kind_mismatch() {
+    let (_a, ra) = archive(&[(1, vec![1])], &PackOptions::default());
+    let (_b, rb) = archive(
+        &[(2, vec![2])],
+        &PackOptions {
+            kind: ClfKind::MemoryMovement,
+            ..PackOptions::default()
+        },
+    );
+    let err = merge(&[ra, rb], &MergeOptions::default()).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            MergeError::KindMismatch {
+                expected: ClfKind::Compute,
+                actual: ClfKind::MemoryMovement
+            }
+        ),
+        "{err}"
+    );
+}
+
+/// Differing targets error by default or take the caller's value.
+#[test]
+fn merge_target_mismatch_policy() {
+    let cpu = PackOptions {
+        target: "CPU".to_string(),
+        ..PackOptions::default()
+    };
+    let gpu = PackOptions {
+        target: "GPU".to_string(),
+        ..PackOptions::default()
+    };
+    let (_a, ra) = archive(&[(1, vec![1])], &cpu);
+    let (_b, rb) = archive(&[(2, vec![2])], &gpu);
+    let readers = [ra, rb];
+
+    let err = merge(&readers, &MergeOptions::default()).unwrap_err();
+    assert!(matches!(err, MergeError::TargetMismatch { .. }), "{err}");
+
+    let merged = merge(
+        &readers,
+        &MergeOptions {
+            on_target_mismatch: MismatchPolicy::Use("CPU+GPU".to_string()),
+            ..MergeOptions::default()
+        },
+    )
+    .unwrap();
+    let reader = ClfReaderFromBytes::open(&merged, None).unwrap();
+    assert_eq!(reader.header.target, "CPU+GPU");
+}
+
+/// plan_merge reports where each op_id comes from and which conflicted; merge_clf_to_path
+/// applies `Override` even when the inputs agree and signs the output as requested.
+#[test]
+fn merge_plan_and_signed_output_file() {
+    let options = PackOptions {
+        vendor: "team".to_string(),
+        ..PackOptions::default()
+    };
+    let (_a, ra) = archive(&[(1, b"a1".to_vec()), (2, b"a2".to_vec())], &options);
+    let (_b, rb) = archive(&[(2, b"b2".to_vec()), (3, b"b3".to_vec())], &options);
+    let readers = [ra, rb];
+
+    let plan = plan_merge(&readers, ConflictPolicy::PreferLast).unwrap();
+    assert_eq!(plan.conflicts, vec![2]);
+    assert_eq!(plan.sources[&2], 1);
+    assert_eq!((plan.entries_from(0), plan.entries_from(1)), (1, 2));
+    assert!(matches!(
+        plan_merge(&readers, ConflictPolicy::Error),
+        Err(MergeError::ConflictingOpId(2))
+    ));
+
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("merged.clfc");
+    merge_clf_to_path(
+        &readers,
+        &MergeOptions {
+            on_conflict: ConflictPolicy::PreferLast,
+            on_vendor_mismatch: MismatchPolicy::Override("release".to_string()),
+            pack: PackOptions {
+                sign: true,
+                ..PackOptions::default()
+            },
+            ..MergeOptions::default()
+        },
+        &out,
+    )
+    .unwrap();
+    let mut reader = ClfReader::open(&out).unwrap();
+    assert_eq!(reader.header.vendor, "release");
+    assert!(reader.verify_signature().unwrap());
+    assert_eq!(reader.get_blob(2).unwrap().unwrap(), b"b2");
+}
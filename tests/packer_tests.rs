@@ -1,10 +1,12 @@
 //! Packer tests: produce a .clf and read it back with the reader.
 
-use std::io::{Cursor, Write};
+use std::io::{Cursor, Read, Write};
 
 use clf::{
-    append_signature, pack_clf, parse_op_blob_arg, ClfKind, ClfReader, PackOptions,
-    VerificationPolicy,
+    append_signature, op_type_from_name, pack_and_sign, pack_clf, pack_clf_from_sources,
+    pack_clf_streaming, pack_clf_streaming_to_path, pack_clf_to_path, pack_clf_with_summary,
+    parse_op_blob_arg, parse_op_id, BlobSource, ClfError, ClfKind, ClfReader, ClfReaderFromBytes,
+    ClfReaderOptions, OpType, PackError, PackOptions, VerificationPolicy, CLF_VERSION,
 };
 
 /// Produce a .clf in memory (two blobs), then read it back with ClfReader and verify blobs.
@@ -21,6 +23,7 @@ fn packer_produce_and_read_back() {
         kind: ClfKind::Compute,
         version: 1,
         sign: false,
+        ..PackOptions::default()
     };
 
     let mut buf = Cursor::new(Vec::new());
@@ -53,12 +56,12 @@ fn packer_signed_and_verify() {
         kind: ClfKind::Compute,
         version: 1,
         sign: true,
+        ..PackOptions::default()
     };
 
     let mut file = tempfile::NamedTempFile::new().unwrap();
-    let data_len = pack_clf(&mut file, &entries, &options).unwrap();
+    pack_and_sign(&mut file, &entries, &options).unwrap();
     file.flush().unwrap();
-    append_signature(&mut file, data_len).unwrap();
 
     let mut reader = ClfReader::open(file.path()).unwrap();
     let blob = reader.get_blob(10).unwrap().unwrap();
@@ -79,6 +82,7 @@ fn packer_kind_memory_movement() {
         kind: ClfKind::MemoryMovement,
         version: 2,
         sign: false,
+        ..PackOptions::default()
     };
 
     let mut buf = Cursor::new(Vec::new());
@@ -104,6 +108,7 @@ fn packer_blob_alignment() {
         kind: ClfKind::Compute,
         version: 1,
         sign: false,
+        ..PackOptions::default()
     };
 
     let mut buf = Cursor::new(Vec::new());
@@ -132,6 +137,23 @@ fn parse_op_blob_arg_rejects_missing_colon() {
     assert!(parse_op_blob_arg("nope").is_err());
 }
 
+/// Op names resolve case-insensitively, numeric ids (custom range included) pass through, and an
+/// unknown name suggests close canonical names.
+#[test]
+fn parse_op_blob_arg_accepts_op_names() {
+    assert_eq!(parse_op_blob_arg("matmul:mm.bin").unwrap().0, 50);
+    assert_eq!(parse_op_blob_arg("RELU:r.bin").unwrap().0, 10);
+    assert_eq!(parse_op_blob_arg("70000:x.bin").unwrap().0, 70000);
+    assert_eq!(op_type_from_name("Add"), Some(OpType::Add));
+    assert_eq!(op_type_from_name("Custom"), None);
+
+    let err = parse_op_blob_arg("matmull:mm.bin").unwrap_err();
+    assert!(err.contains("did you mean MatMul"), "{err}");
+    let err = parse_op_id("zzzzzzzz").unwrap_err();
+    assert!(err.contains("docs/op_ids.md"), "{err}");
+    assert!(parse_op_id("99999999999").is_err());
+}
+
 #[test]
 fn verify_with_policy_integrity_only_succeeds_for_signed_clf() {
     let entries: Vec<(u32, Vec<u8>)> = vec![(10, b"relu_kernel".to_vec())];
@@ -142,12 +164,12 @@ fn verify_with_policy_integrity_only_succeeds_for_signed_clf() {
         kind: ClfKind::Compute,
         version: 1,
         sign: true,
+        ..PackOptions::default()
     };
 
     let mut file = tempfile::NamedTempFile::new().unwrap();
-    let data_len = pack_clf(&mut file, &entries, &options).unwrap();
+    pack_and_sign(&mut file, &entries, &options).unwrap();
     file.flush().unwrap();
-    append_signature(&mut file, data_len).unwrap();
 
     let mut reader = ClfReader::open(file.path()).unwrap();
     let ok = reader
@@ -166,12 +188,12 @@ fn verify_with_policy_require_authenticity_is_not_supported_yet() {
         kind: ClfKind::Compute,
         version: 1,
         sign: true,
+        ..PackOptions::default()
     };
 
     let mut file = tempfile::NamedTempFile::new().unwrap();
-    let data_len = pack_clf(&mut file, &entries, &options).unwrap();
+    pack_and_sign(&mut file, &entries, &options).unwrap();
     file.flush().unwrap();
-    append_signature(&mut file, data_len).unwrap();
 
     let mut reader = ClfReader::open(file.path()).unwrap();
     let err = reader
@@ -182,3 +204,1834 @@ fn verify_with_policy_require_authenticity_is_not_supported_yet() {
         "unexpected error: {err}"
     );
 }
+
+/// v3 records the exact blob size: `get_blob` trims alignment padding, `get_blob_padded` keeps it.
+#[test]
+fn packer_v3_exact_size_trims_padding() {
+    let entries: Vec<(u32, Vec<u8>)> = vec![(1, vec![0x01, 0x02]), (2, vec![0xAA; 17])];
+    let options = PackOptions {
+        blob_alignment: 16,
+        version: 3,
+        ..PackOptions::default()
+    };
+
+    let mut buf = Cursor::new(Vec::new());
+    pack_clf(&mut buf, &entries, &options).unwrap();
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(&buf.into_inner()).unwrap();
+    file.flush().unwrap();
+
+    let mut reader = ClfReader::open(file.path()).unwrap();
+    assert_eq!(reader.header.version, 3);
+    let manifest = reader.manifest_entries();
+    assert_eq!((manifest[0].size, manifest[0].exact_size), (16, 2));
+    assert_eq!((manifest[1].size, manifest[1].exact_size), (32, 17));
+
+    assert_eq!(reader.get_blob(1).unwrap().unwrap(), vec![0x01, 0x02]);
+    assert_eq!(reader.get_blob(2).unwrap().unwrap(), vec![0xAA; 17]);
+    let padded = reader.get_blob_padded(1).unwrap().unwrap();
+    assert_eq!(padded.len(), 16);
+    assert_eq!(&padded[..2], &[0x01, 0x02]);
+
+    let code = reader
+        .build_code_section(&[1, 2], clf::MissingOpIdPolicy::Fail)
+        .unwrap();
+    assert_eq!(code.len(), 2 + 17);
+}
+
+/// `verify_signature` in `ClfReaderOptions` checks SIG0 during open; blobs stay readable.
+#[test]
+fn open_with_verify_signature_single_pass() {
+    let entries: Vec<(u32, Vec<u8>)> = vec![(1, b"add".to_vec()), (50, vec![7u8; 10_000])];
+    let options = PackOptions {
+        blob_alignment: 16,
+        ..PackOptions::default()
+    };
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    let data_len = pack_clf(&mut file, &entries, &options).unwrap();
+    file.flush().unwrap();
+    append_signature(&mut file, data_len).unwrap();
+
+    let verify = ClfReaderOptions {
+        verify_signature: true,
+        ..ClfReaderOptions::default()
+    };
+    let mut reader = ClfReader::open_with_options(file.path(), &verify).unwrap();
+    assert!(reader.signature_verified());
+    assert!(reader.signature_block_present());
+    assert_eq!(reader.get_blob(1).unwrap().unwrap(), b"add");
+    assert_eq!(reader.get_blob(50).unwrap().unwrap(), vec![7u8; 10_000]);
+
+    // Tampered blob byte: open fails.
+    let mut bytes = std::fs::read(file.path()).unwrap();
+    let tamper_at = reader.blob_store_offset() as usize + 1;
+    bytes[tamper_at] ^= 0xFF;
+    let tampered = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(tampered.path(), &bytes).unwrap();
+    let err = ClfReader::open_with_options(tampered.path(), &verify).unwrap_err();
+    assert!(matches!(err.root(), ClfError::SignatureInvalid), "{err}");
+
+    // Unsigned file: open fails in verify mode, succeeds normally.
+    let mut unsigned = tempfile::NamedTempFile::new().unwrap();
+    pack_clf(&mut unsigned, &entries, &options).unwrap();
+    unsigned.flush().unwrap();
+    let err = ClfReader::open_with_options(unsigned.path(), &verify).unwrap_err();
+    assert!(matches!(err.root(), ClfError::SignatureInvalid), "{err}");
+    assert!(!ClfReader::open(unsigned.path())
+        .unwrap()
+        .signature_verified());
+}
+
+/// Streaming pack from `Read` sources produces the same bytes as `pack_clf` for the same blobs.
+#[test]
+fn packer_streaming_matches_in_memory_pack() {
+    let entries: Vec<(u32, Vec<u8>)> = vec![(1, b"add".to_vec()), (50, vec![9u8; 70_000])];
+    let options = PackOptions {
+        blob_alignment: 16,
+        ..PackOptions::default()
+    };
+
+    let mut expected = Cursor::new(Vec::new());
+    let expected_len = pack_clf(&mut expected, &entries, &options).unwrap();
+
+    let sources: Vec<(u32, Box<dyn std::io::Read>)> = entries
+        .iter()
+        .map(|(id, blob)| (*id, Box::new(blob.as_slice()) as Box<dyn std::io::Read>))
+        .collect();
+    let mut streamed = Cursor::new(Vec::new());
+    let summary = pack_clf_streaming(&mut streamed, sources, &options).unwrap();
+
+    assert_eq!(summary.data_len, expected_len);
+    assert_eq!(streamed.position(), summary.data_len);
+    assert_eq!(streamed.into_inner(), expected.into_inner());
+}
+
+/// Blobs from a slice, a file path, a boxed reader and a source of our own pack to the same bytes
+/// as `pack_clf`, with and without dedup.
+#[test]
+fn pack_from_sources_matches_in_memory_pack() {
+    /// Generates its blob on demand, like an in-process compiler.
+    struct Compiled(u8, usize);
+    impl BlobSource for Compiled {
+        fn len(&self) -> Option<u64> {
+            Some(self.1 as u64)
+        }
+        fn read_into(&mut self, w: &mut dyn Write) -> std::io::Result<u64> {
+            for _ in 0..self.1 {
+                w.write_all(&[self.0])?;
+            }
+            Ok(self.1 as u64)
+        }
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let on_disk = dir.path().join("matmul.bin");
+    std::fs::write(&on_disk, vec![7u8; 70_000]).unwrap();
+    let entries: Vec<(u32, Vec<u8>)> = vec![
+        (1, b"add".to_vec()),
+        (2, b"add".to_vec()),
+        (50, vec![7u8; 70_000]),
+        (60, vec![3u8; 100]),
+    ];
+    for dedup_blobs in [false, true] {
+        let options = PackOptions {
+            blob_alignment: 16,
+            dedup_blobs,
+            ..PackOptions::default()
+        };
+        let mut expected = Cursor::new(Vec::new());
+        pack_clf(&mut expected, &entries, &options).unwrap();
+
+        let sources: Vec<(u32, Box<dyn BlobSource>)> = vec![
+            (1, Box::new(&b"add"[..])),
+            (2, Box::new(Box::new(&b"add"[..]) as Box<dyn Read>)),
+            (50, Box::new(on_disk.clone())),
+            (60, Box::new(Compiled(3, 100))),
+        ];
+        let mut packed = Cursor::new(Vec::new());
+        let summary = pack_clf_from_sources(&mut packed, sources, &options).unwrap();
+        let mut packed = packed.into_inner();
+        packed.truncate(summary.data_len as usize);
+        assert_eq!(packed, expected.into_inner(), "dedup_blobs {dedup_blobs}");
+    }
+}
+
+/// Source failures name the op_id they were feeding: a missing file is `Source` (its message
+/// names the path) and a source writing fewer bytes than it declared is `SourceLenMismatch`.
+#[test]
+fn pack_from_sources_reports_op_id_of_failing_source() {
+    struct ShortRead;
+    impl BlobSource for ShortRead {
+        fn len(&self) -> Option<u64> {
+            Some(10)
+        }
+        fn read_into(&mut self, w: &mut dyn Write) -> std::io::Result<u64> {
+            w.write_all(b"part")?;
+            Ok(4)
+        }
+    }
+
+    let missing = tempfile::tempdir().unwrap().path().join("gone.bin");
+    let sources: Vec<(u32, Box<dyn BlobSource>)> =
+        vec![(1, Box::new(&b"add"[..])), (9, Box::new(missing.clone()))];
+    let err = pack_clf_from_sources(
+        &mut Cursor::new(Vec::new()),
+        sources,
+        &PackOptions::default(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, PackError::Source { op_id: 9, .. }), "{err}");
+    assert!(err.to_string().contains("gone.bin"), "{err}");
+
+    let sources: Vec<(u32, Box<dyn BlobSource>)> = vec![(4, Box::new(ShortRead))];
+    let err = pack_clf_from_sources(
+        &mut Cursor::new(Vec::new()),
+        sources,
+        &PackOptions::default(),
+    )
+    .unwrap_err();
+    assert!(
+        matches!(
+            err,
+            PackError::SourceLenMismatch {
+                op_id: 4,
+                declared: 10,
+                actual: 4
+            }
+        ),
+        "{err}"
+    );
+}
+
+/// A source yielding more than u32::MAX bytes fails with BlobTooLarge for its op_id.
+#[test]
+fn packer_streaming_rejects_oversized_blob() {
+    let huge = std::io::repeat(0).take(u64::from(u32::MAX) + 1);
+    let sources: Vec<(u32, Box<dyn std::io::Read>)> = vec![(7, Box::new(huge))];
+    let err = pack_clf_streaming(&mut NullSink(0), sources, &PackOptions::default()).unwrap_err();
+    assert!(matches!(err, PackError::BlobTooLarge(7)), "{err}");
+}
+
+/// Discards writes but tracks a position, so oversized streams need no real storage.
+struct NullSink(u64);
+
+impl Write for NullSink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl std::io::Seek for NullSink {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        if let std::io::SeekFrom::Start(p) = pos {
+            self.0 = p;
+        }
+        Ok(self.0)
+    }
+}
+
+/// With blob_alignment > 0 every blob starts at a file offset that is a multiple of the
+/// alignment (including the first, via zero fill after the manifest); alignment 0 stays tight.
+#[test]
+fn packer_aligns_blob_file_offsets() {
+    let entries: Vec<(u32, Vec<u8>)> =
+        vec![(1, vec![0xAA; 3]), (2, vec![0xBB; 17]), (3, vec![0xCC; 1])];
+    for align in [4u8, 16, 64] {
+        let options = PackOptions {
+            vendor: "odd".to_string(),
+            blob_alignment: align,
+            ..PackOptions::default()
+        };
+        let mut cursor = Cursor::new(Vec::new());
+        pack_clf(&mut cursor, &entries, &options).unwrap();
+        let bytes = cursor.into_inner();
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&bytes).unwrap();
+        file.flush().unwrap();
+
+        let mut reader = ClfReader::open(file.path()).unwrap();
+        assert_eq!(reader.header.blob_alignment, align);
+        let store = reader.blob_store_offset();
+        for entry in reader.manifest_entries() {
+            let start = store + u64::from(entry.offset);
+            assert_eq!(
+                start % u64::from(align),
+                0,
+                "op {} align {align}",
+                entry.op_id
+            );
+            assert_eq!(entry.size % u32::from(align), 0);
+            let blob = &bytes[start as usize..start as usize + entry.exact_size as usize];
+            assert!(blob.iter().all(|&b| b != 0));
+        }
+        for (op_id, blob) in &entries {
+            assert_eq!(&reader.get_blob(*op_id).unwrap().unwrap(), blob);
+        }
+    }
+
+    let mut cursor = Cursor::new(Vec::new());
+    let tight = PackOptions {
+        vendor: "odd".to_string(),
+        ..PackOptions::default()
+    };
+    pack_clf(&mut cursor, &entries, &tight).unwrap();
+    let bytes = cursor.into_inner();
+    let reader = ClfReaderFromBytes::open(&bytes, None).unwrap();
+    let offsets: Vec<(u32, u32)> = reader
+        .manifest_entries()
+        .iter()
+        .map(|e| (e.offset, e.size))
+        .collect();
+    assert_eq!(offsets, vec![(0, 3), (3, 17), (20, 1)]);
+}
+
+/// Header round-trip per format version: target, alignment and (v2+) kind are written where
+/// the reader expects them; v1 has no kind byte and reads back as Compute.
+#[test]
+fn packer_header_round_trip_per_version() {
+    let entries: Vec<(u32, Vec<u8>)> = vec![(1, vec![1, 2, 3]), (2, vec![4, 5])];
+    for version in 1..=CLF_VERSION {
+        let options = PackOptions {
+            vendor: "acme".to_string(),
+            target: "CDNA".to_string(),
+            blob_alignment: 8,
+            kind: ClfKind::MemoryProtection,
+            version,
+            sign: false,
+            ..PackOptions::default()
+        };
+        let mut cursor = Cursor::new(Vec::new());
+        pack_clf(&mut cursor, &entries, &options).unwrap();
+        let bytes = cursor.into_inner();
+        let reader = ClfReaderFromBytes::open(&bytes, None).unwrap();
+        assert_eq!(reader.header.version, version);
+        assert_eq!(reader.header.vendor, "acme");
+        assert_eq!(reader.header.target, "CDNA");
+        assert_eq!(reader.header.blob_alignment, 8);
+        let expected_kind = if version >= 2 {
+            ClfKind::MemoryProtection
+        } else {
+            ClfKind::Compute
+        };
+        assert_eq!(reader.header.kind, expected_kind, "version {version}");
+        // v1/v2 return the padded stored bytes; the packed data is the prefix.
+        let blob = reader.get_blob(2).unwrap().unwrap();
+        assert_eq!(&blob[..2], &[4, 5]);
+    }
+}
+
+/// op_ids are 4-byte fields end to end: three entries (including ids above u16::MAX) read back
+/// byte-for-byte.
+#[test]
+fn packer_three_entries_round_trip_u32_op_ids() {
+    let entries: Vec<(u32, Vec<u8>)> = vec![
+        (3, b"first".to_vec()),
+        (70_000, b"second blob".to_vec()),
+        (u32::MAX, vec![0xFE; 33]),
+    ];
+    let mut cursor = Cursor::new(Vec::new());
+    pack_clf(&mut cursor, &entries, &PackOptions::default()).unwrap();
+    let bytes = cursor.into_inner();
+    let reader = ClfReaderFromBytes::open(&bytes, None).unwrap();
+    assert_eq!(reader.manifest_entries().len(), 3);
+    for (op_id, blob) in &entries {
+        assert_eq!(reader.get_blob(*op_id).unwrap().as_ref(), Some(blob));
+    }
+
+    let dup = vec![(70_000u32, vec![1]), (70_000u32, vec![2])];
+    let err = pack_clf(&mut Cursor::new(Vec::new()), &dup, &PackOptions::default()).unwrap_err();
+    assert!(matches!(err, PackError::DuplicateOpId(70_000)), "{err}");
+}
+
+/// Vendor and target lengths are 4-byte byte counts: a multi-byte UTF-8 vendor round-trips and
+/// the following fields stay in place.
+#[test]
+fn packer_utf8_vendor_round_trip() {
+    let options = PackOptions {
+        vendor: "Ångström GmbH".to_string(),
+        target: "GPU".to_string(),
+        kind: ClfKind::MemoryMovement,
+        ..PackOptions::default()
+    };
+    let mut cursor = Cursor::new(Vec::new());
+    pack_clf(&mut cursor, &[(9, vec![1, 2, 3])], &options).unwrap();
+    let bytes = cursor.into_inner();
+
+    let vendor_len = u32::from_le_bytes(bytes[5..9].try_into().unwrap());
+    assert_eq!(vendor_len as usize, "Ångström GmbH".len());
+    assert_ne!(vendor_len as usize, "Ångström GmbH".chars().count());
+
+    let reader = ClfReaderFromBytes::open(&bytes, None).unwrap();
+    assert_eq!(reader.header.vendor, "Ångström GmbH");
+    assert_eq!(reader.header.target, "GPU");
+    assert_eq!(reader.header.kind, ClfKind::MemoryMovement);
+    assert_eq!(reader.get_blob(9).unwrap(), Some(vec![1, 2, 3]));
+}
+
+/// dedup_blobs stores identical blobs once (in-memory and streaming packers agree byte for byte,
+/// even when the last entry is a duplicate), aliases pass validate(), and the summary reports the
+/// bytes saved.
+#[test]
+fn packer_dedup_identical_blobs() {
+    let kernel = vec![0x90u8; 40];
+    let entries: Vec<(u32, Vec<u8>)> = vec![
+        (1, kernel.clone()),
+        (2, b"other".to_vec()),
+        (3, kernel.clone()),
+    ];
+    let options = PackOptions {
+        blob_alignment: 16,
+        dedup_blobs: true,
+        ..PackOptions::default()
+    };
+
+    let mut plain = Cursor::new(Vec::new());
+    let plain_len = pack_clf(
+        &mut plain,
+        &entries,
+        &PackOptions {
+            dedup_blobs: false,
+            ..options.clone()
+        },
+    )
+    .unwrap();
+
+    let mut deduped = Cursor::new(Vec::new());
+    let summary = pack_clf_with_summary(&mut deduped, &entries, &options).unwrap();
+    assert_eq!(summary.dedup_bytes_saved, 48);
+    assert_eq!(summary.data_len, plain_len - 48);
+    assert_eq!(
+        summary
+            .entries
+            .iter()
+            .map(|e| e.deduplicated)
+            .collect::<Vec<_>>(),
+        [false, false, true]
+    );
+
+    let sources: Vec<(u32, Box<dyn Read>)> = entries
+        .iter()
+        .map(|(id, blob)| (*id, Box::new(blob.as_slice()) as Box<dyn Read>))
+        .collect();
+    let mut streamed = Cursor::new(Vec::new());
+    let streamed_summary = pack_clf_streaming(&mut streamed, sources, &options).unwrap();
+    assert_eq!(streamed_summary, summary);
+    // The trailing duplicate is never written, so nothing follows the archive.
+    assert_eq!(streamed.get_ref(), deduped.get_ref());
+    ClfReaderFromBytes::open(streamed.get_ref(), None)
+        .unwrap()
+        .validate()
+        .unwrap();
+    append_signature(&mut streamed, streamed_summary.data_len).unwrap();
+    let signed = ClfReaderFromBytes::open(streamed.get_ref(), None).unwrap();
+    assert!(signed.signature_block_present());
+    signed.validate().unwrap();
+
+    let reader = ClfReaderFromBytes::open(deduped.get_ref(), None).unwrap();
+    reader.validate().unwrap();
+    let entries_by_id = reader.manifest_entries();
+    assert_eq!(entries_by_id[0].offset, entries_by_id[2].offset);
+    assert_eq!(entries_by_id[0].size, entries_by_id[2].size);
+    for (op_id, blob) in &entries {
+        assert_eq!(reader.get_blob(*op_id).unwrap().as_ref(), Some(blob));
+    }
+}
+
+/// Deterministic bytes that zstd cannot shrink.
+#[cfg(feature = "compression")]
+fn incompressible(len: usize) -> Vec<u8> {
+    let mut state: u32 = 0x1234_5678;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect()
+}
+
+/// zstd compression: compressible blobs are stored compressed and flagged, incompressible ones
+/// stay raw; get_blob returns the original bytes either way (file, bytes and streaming paths).
+#[cfg(feature = "compression")]
+#[test]
+fn packer_zstd_round_trip() {
+    let compressible = b"mov r0, r1; add r0, r2; ".repeat(200);
+    let noise = incompressible(4096);
+    let entries: Vec<(u32, Vec<u8>)> = vec![(1, compressible.clone()), (2, noise.clone())];
+    let options = PackOptions {
+        blob_alignment: 16,
+        compression: Some(clf::Compression::Zstd(3)),
+        ..PackOptions::default()
+    };
+
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    pack_clf(&mut file, &entries, &options).unwrap();
+    file.flush().unwrap();
+    let mut reader = ClfReader::open(file.path()).unwrap();
+    let manifest = reader.manifest_entries();
+    assert_eq!(manifest[0].flags, clf::format::ENTRY_FLAG_ZSTD);
+    assert!(manifest[0].exact_size < manifest[0].raw_size);
+    assert_eq!(manifest[0].raw_size as usize, compressible.len());
+    assert_eq!(manifest[1].flags, 0);
+    assert_eq!(manifest[1].exact_size, manifest[1].raw_size);
+    assert_eq!(reader.get_blob(1).unwrap().unwrap(), compressible);
+    assert_eq!(reader.get_blob(2).unwrap().unwrap(), noise);
+
+    let sources: Vec<(u32, Box<dyn Read>)> = entries
+        .iter()
+        .map(|(id, blob)| (*id, Box::new(blob.as_slice()) as Box<dyn Read>))
+        .collect();
+    let mut streamed = Cursor::new(Vec::new());
+    pack_clf_streaming(&mut streamed, sources, &options).unwrap();
+    let bytes = streamed.into_inner();
+    let reader = ClfReaderFromBytes::open(&bytes, None).unwrap();
+    assert_eq!(reader.get_blob(1).unwrap().unwrap(), compressible);
+    assert_eq!(reader.get_blob(2).unwrap().unwrap(), noise);
+}
+
+/// Streamed sources that can be read twice (`&[u8]`, paths) fall back to a raw blob when zstd
+/// would grow it; a plain reader cannot, so its blob stays compressed. Both read back unchanged.
+#[cfg(feature = "compression")]
+#[test]
+fn pack_from_sources_stores_grown_blobs_raw_when_rereadable() {
+    let compressible = b"mov r0, r1; ".repeat(300);
+    let noise = incompressible(4096);
+    let options = PackOptions {
+        blob_alignment: 16,
+        compression: Some(clf::Compression::Zstd(3)),
+        per_blob_crc: true,
+        ..PackOptions::default()
+    };
+    let dir = tempfile::tempdir().unwrap();
+    let noise_path = dir.path().join("noise.bin");
+    std::fs::write(&noise_path, &noise).unwrap();
+    let sources: Vec<(u32, Box<dyn BlobSource>)> = vec![
+        (1, Box::new(noise.as_slice())),
+        (2, Box::new(noise_path)),
+        (3, Box::new(Box::new(noise.as_slice()) as Box<dyn Read>)),
+        (4, Box::new(compressible.as_slice())),
+    ];
+    let mut packed = Cursor::new(Vec::new());
+    pack_clf_from_sources(&mut packed, sources, &options).unwrap();
+    let bytes = packed.into_inner();
+
+    let reader = ClfReaderFromBytes::open(&bytes, None).unwrap();
+    let flags: Vec<u32> = reader.manifest_entries().iter().map(|e| e.flags).collect();
+    let zstd = clf::format::ENTRY_FLAG_ZSTD;
+    assert_eq!(flags, [0, 0, zstd, zstd]);
+    for op_id in 1..=3 {
+        assert_eq!(
+            reader.get_blob(op_id).unwrap().unwrap(),
+            noise,
+            "op_id {op_id}"
+        );
+    }
+    assert_eq!(reader.get_blob(4).unwrap().unwrap(), compressible);
+}
+
+/// When the last blob falls back to raw, the discarded compressed copy does not linger past
+/// `data_len`: the stream validates and signs as packed.
+#[cfg(feature = "compression")]
+#[test]
+fn pack_from_sources_raw_fallback_leaves_no_tail() {
+    let compressible = b"mov r0, r1; ".repeat(300);
+    let noise = incompressible(4096);
+    let options = PackOptions {
+        compression: Some(clf::Compression::Zstd(3)),
+        ..PackOptions::default()
+    };
+    let sources: Vec<(u32, Box<dyn BlobSource>)> = vec![
+        (1, Box::new(compressible.as_slice())),
+        (2, Box::new(noise.as_slice())),
+    ];
+    let mut packed = Cursor::new(Vec::new());
+    let summary = pack_clf_from_sources(&mut packed, sources, &options).unwrap();
+    assert_eq!(packed.get_ref().len() as u64, summary.data_len);
+    append_signature(&mut packed, summary.data_len).unwrap();
+
+    let reader = ClfReaderFromBytes::open(packed.get_ref(), None).unwrap();
+    reader.validate().unwrap();
+    assert_eq!(reader.manifest_entries()[1].flags, 0);
+    assert_eq!(reader.get_blob(2).unwrap().unwrap(), noise);
+}
+
+/// Compression needs a v3 manifest (and the feature); older versions are rejected at pack time.
+#[test]
+fn packer_compression_requires_v3_and_feature() {
+    let options = PackOptions {
+        version: 2,
+        compression: Some(clf::Compression::Zstd(3)),
+        ..PackOptions::default()
+    };
+    let err = pack_clf(&mut Cursor::new(Vec::new()), &[(1, vec![0; 64])], &options).unwrap_err();
+    if cfg!(feature = "compression") {
+        assert!(
+            matches!(err, PackError::RequiresV3 { version: 2, .. }),
+            "{err}"
+        );
+    } else {
+        assert!(matches!(err, PackError::CompressionUnsupported), "{err}");
+    }
+}
+
+/// AES-256-GCM encryption: blobs are stored encrypted and flagged, the header and manifest stay
+/// readable, and get_blob decrypts with the key set (file, bytes and streaming paths) but fails
+/// with `Encrypted` without one.
+#[cfg(feature = "encryption")]
+#[test]
+fn packer_encryption_round_trip() {
+    let kernel = b"licensed kernel: mov r0, r1; ".repeat(20);
+    let entries: Vec<(u32, Vec<u8>)> = vec![(1, kernel.clone()), (2, Vec::new())];
+    let key = clf::EncryptionKey::new([7; 32]);
+    let options = PackOptions {
+        vendor: "Acme".to_string(),
+        blob_alignment: 16,
+        per_blob_crc: true,
+        encrypt: Some(key.clone()),
+        ..PackOptions::default()
+    };
+
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    pack_clf(&mut file, &entries, &options).unwrap();
+    file.flush().unwrap();
+    let bytes = std::fs::read(file.path()).unwrap();
+    assert!(!bytes.windows(16).any(|w| w == &kernel[..16]));
+
+    let mut reader = ClfReader::open(file.path()).unwrap();
+    reader.validate().unwrap();
+    assert_eq!(reader.header.vendor, "Acme");
+    assert_eq!(reader.op_ids(), vec![1, 2]);
+    let manifest = reader.manifest_entries();
+    assert_eq!(manifest[0].flags, clf::format::ENTRY_FLAG_AES256GCM);
+    assert_eq!(manifest[0].raw_size as usize, kernel.len());
+    assert_eq!(manifest[0].exact_size as usize, kernel.len() + 28);
+    let err = reader.get_blob(1).unwrap_err();
+    assert!(matches!(err, ClfError::Encrypted(1)), "{err}");
+
+    reader.set_decryption_key(key.clone());
+    assert_eq!(reader.get_blob(1).unwrap().unwrap(), kernel);
+    assert_eq!(reader.get_blob(2).unwrap().unwrap(), Vec::<u8>::new());
+    let section = reader
+        .build_code_section(&[2, 1], clf::MissingOpIdPolicy::Fail)
+        .unwrap();
+    assert_eq!(section, kernel);
+
+    let sources: Vec<(u32, Box<dyn Read>)> = entries
+        .iter()
+        .map(|(id, blob)| (*id, Box::new(blob.as_slice()) as Box<dyn Read>))
+        .collect();
+    let mut streamed = Cursor::new(Vec::new());
+    pack_clf_streaming(&mut streamed, sources, &options).unwrap();
+    let mut reader = ClfReaderFromBytes::open(streamed.get_ref(), None).unwrap();
+    reader.set_decryption_key(key);
+    assert_eq!(reader.get_blob(1).unwrap().unwrap(), kernel);
+
+    #[cfg(feature = "compression")]
+    {
+        let compressed = PackOptions {
+            compression: Some(clf::Compression::Zstd(3)),
+            ..options.clone()
+        };
+        let mut buf = Cursor::new(Vec::new());
+        pack_clf(&mut buf, &entries, &compressed).unwrap();
+        let mut reader = ClfReaderFromBytes::open(buf.get_ref(), None).unwrap();
+        let entry = reader.manifest_entries()[0];
+        assert_eq!(
+            entry.flags,
+            clf::format::ENTRY_FLAG_ZSTD | clf::format::ENTRY_FLAG_AES256GCM
+        );
+        assert!((entry.exact_size as usize) < kernel.len());
+        reader.set_decryption_key(clf::EncryptionKey::new([7; 32]));
+        assert_eq!(reader.get_blob(1).unwrap().unwrap(), kernel);
+    }
+
+    let v2 = PackOptions {
+        version: 2,
+        ..options
+    };
+    let err = pack_clf(&mut Cursor::new(Vec::new()), &entries, &v2).unwrap_err();
+    assert!(
+        matches!(err, PackError::RequiresV3 { version: 2, .. }),
+        "{err}"
+    );
+}
+
+/// A wrong key and an altered ciphertext both fail authentication with `DecryptionFailed`;
+/// with a CRC-32 recorded, the altered bytes are reported as corruption first.
+#[cfg(feature = "encryption")]
+#[test]
+fn encrypted_blob_authentication_failure_is_distinct() {
+    let entries: Vec<(u32, Vec<u8>)> = vec![(9, b"secret kernel".to_vec())];
+    let pack = |per_blob_crc| {
+        let mut buf = Cursor::new(Vec::new());
+        let options = PackOptions {
+            per_blob_crc,
+            encrypt: Some(clf::EncryptionKey::new([1; 32])),
+            ..PackOptions::default()
+        };
+        pack_clf(&mut buf, &entries, &options).unwrap();
+        buf.into_inner()
+    };
+    let key = clf::EncryptionKey::new([1; 32]);
+
+    let bytes = pack(false);
+    let mut reader = ClfReaderFromBytes::open(&bytes, None).unwrap();
+    reader.set_decryption_key(clf::EncryptionKey::new([2; 32]));
+    let err = reader.get_blob(9).unwrap_err();
+    assert!(
+        matches!(err, ClfError::DecryptionFailed { op_id: 9 }),
+        "{err}"
+    );
+
+    let mut tampered = bytes.clone();
+    let last = tampered.len() - 20;
+    tampered[last] ^= 1;
+    let mut reader = ClfReaderFromBytes::open(&tampered, None).unwrap();
+    reader.set_decryption_key(key.clone());
+    let err = reader.get_blob(9).unwrap_err();
+    assert!(
+        matches!(err, ClfError::DecryptionFailed { op_id: 9 }),
+        "{err}"
+    );
+
+    let mut tampered = pack(true);
+    let last = tampered.len() - 20;
+    tampered[last] ^= 1;
+    let mut reader = ClfReaderFromBytes::open(&tampered, None).unwrap();
+    reader.set_decryption_key(key);
+    let err = reader.get_blob(9).unwrap_err();
+    assert!(
+        matches!(err, ClfError::BlobChecksumMismatch { op_id: 9, .. }),
+        "{err}"
+    );
+}
+
+/// Streaming and in-memory packers write identical CRC-32 fields.
+#[test]
+fn packer_streaming_per_blob_crc_matches() {
+    let entries: Vec<(u32, Vec<u8>)> = vec![(4, b"kernel".to_vec()), (5, vec![3u8; 100])];
+    let options = PackOptions {
+        blob_alignment: 8,
+        per_blob_crc: true,
+        ..PackOptions::default()
+    };
+    let mut expected = Cursor::new(Vec::new());
+    pack_clf(&mut expected, &entries, &options).unwrap();
+
+    let sources: Vec<(u32, Box<dyn Read>)> = entries
+        .iter()
+        .map(|(id, blob)| (*id, Box::new(blob.as_slice()) as Box<dyn Read>))
+        .collect();
+    let mut streamed = Cursor::new(Vec::new());
+    pack_clf_streaming(&mut streamed, sources, &options).unwrap();
+    assert_eq!(streamed.into_inner(), expected.into_inner());
+}
+
+/// pack_clf_to_path writes the same bytes as pack_clf (plus a valid signature when requested)
+/// and leaves no temp file behind.
+#[test]
+fn pack_to_path_writes_and_signs() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("lib.clfc");
+    let entries: Vec<(u32, Vec<u8>)> = vec![(1, b"add".to_vec()), (2, b"mul".to_vec())];
+    let options = PackOptions {
+        sign: true,
+        ..PackOptions::default()
+    };
+    let summary = pack_clf_to_path(&path, &entries, &options).unwrap();
+
+    let mut expected = Cursor::new(Vec::new());
+    pack_clf(&mut expected, &entries, &PackOptions::default()).unwrap();
+    let written = std::fs::read(&path).unwrap();
+    assert_eq!(summary.data_len, expected.get_ref().len() as u64);
+    assert_eq!(
+        &written[..summary.data_len as usize],
+        expected.get_ref().as_slice()
+    );
+    assert!(ClfReader::open(&path).unwrap().verify_signature().unwrap());
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+/// A pack that fails midway leaves the existing archive untouched and cleans up its temp file.
+#[test]
+fn pack_to_path_failure_keeps_existing_archive() {
+    struct Failing;
+    impl Read for Failing {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("disk went away"))
+        }
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("lib.clfc");
+    pack_clf_to_path(&path, &[(1, b"good".to_vec())], &PackOptions::default()).unwrap();
+    let good = std::fs::read(&path).unwrap();
+
+    let sources: Vec<(u32, Box<dyn Read>)> =
+        vec![(1, Box::new(&b"new"[..])), (2, Box::new(Failing))];
+    let err = pack_clf_streaming_to_path(&path, sources, &PackOptions::default()).unwrap_err();
+    assert!(matches!(err, PackError::Source { op_id: 2, .. }), "{err}");
+    assert_eq!(std::fs::read(&path).unwrap(), good);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+/// `max_total_size` counts the signature trailer: an archive of exactly the limit packs, one
+/// byte less fails before the last blob is written, and the atomic path leaves nothing behind.
+#[test]
+fn packer_enforces_max_total_size() {
+    let entries: Vec<(u32, Vec<u8>)> = (1..=3)
+        .map(|op_id| (op_id, vec![op_id as u8; 100]))
+        .collect();
+    let signed = PackOptions {
+        sign: true,
+        ..PackOptions::default()
+    };
+    let mut full = Cursor::new(Vec::new());
+    let data_len = pack_and_sign(&mut full, &entries, &signed)
+        .unwrap()
+        .data_len;
+    let full = full.into_inner();
+    let total = full.len() as u64;
+    assert_eq!(total, data_len + clf::SIG_BLOCK_LEN as u64);
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("lib.clfc");
+    let exact = PackOptions {
+        max_total_size: Some(total),
+        ..signed.clone()
+    };
+    let mut out = Cursor::new(Vec::new());
+    pack_and_sign(&mut out, &entries, &exact).unwrap();
+    assert_eq!(out.into_inner(), full);
+    pack_clf_to_path(&path, &entries, &exact).unwrap();
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), total);
+    std::fs::remove_file(&path).unwrap();
+
+    let over = PackOptions {
+        max_total_size: Some(total - 1),
+        ..signed.clone()
+    };
+    let expected = |err: PackError| {
+        assert!(
+            matches!(err, PackError::SizeLimitExceeded { limit, needed } if limit == total - 1 && needed == total),
+            "{err}"
+        );
+    };
+    let mut out = Cursor::new(Vec::new());
+    expected(pack_and_sign(&mut out, &entries, &over).unwrap_err());
+    assert!(out.get_ref().len() as u64 <= data_len - 100);
+    let mut buf = Cursor::new(Vec::new());
+    expected(pack_clf_with_summary(&mut buf, &entries, &over).unwrap_err());
+    assert!(buf.get_ref().len() as u64 <= data_len - 100);
+    let sources: Vec<(u32, Box<dyn BlobSource>)> = entries
+        .iter()
+        .map(|(op_id, blob)| (*op_id, Box::new(blob.as_slice()) as Box<dyn BlobSource>))
+        .collect();
+    let mut buf = Cursor::new(Vec::new());
+    expected(pack_clf_from_sources(&mut buf, sources, &over).unwrap_err());
+    assert_eq!(buf.get_ref().len() as u64, data_len - 100);
+    expected(pack_clf_to_path(&path, &entries, &over).unwrap_err());
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+
+    // A limit below the header and manifest fails before anything is written.
+    let tiny = PackOptions {
+        max_total_size: Some(16),
+        ..PackOptions::default()
+    };
+    let mut buf = Cursor::new(Vec::new());
+    let err = pack_clf(&mut buf, &entries, &tiny).unwrap_err();
+    assert!(
+        matches!(err, PackError::SizeLimitExceeded { limit: 16, .. }),
+        "{err}"
+    );
+    assert!(buf.get_ref().is_empty());
+}
+
+/// Header metadata round-trips through both packers and the readers; invalid metadata is
+/// rejected at pack time.
+#[test]
+fn packer_header_metadata_round_trip() {
+    let entries: Vec<(u32, Vec<u8>)> = vec![(1, b"add".to_vec())];
+    let metadata = vec![
+        ("driver".to_string(), "550.54".to_string()),
+        ("git_sha".to_string(), "0badc0de".to_string()),
+        ("flags".to_string(), String::new()),
+    ];
+    let options = PackOptions {
+        metadata: metadata.clone(),
+        ..PackOptions::default()
+    };
+    let mut in_memory = Cursor::new(Vec::new());
+    pack_clf(&mut in_memory, &entries, &options).unwrap();
+    let bytes = in_memory.into_inner();
+    let mut streamed = Cursor::new(Vec::new());
+    let sources: Vec<(u32, Box<dyn Read>)> = vec![(1, Box::new(&b"add"[..]))];
+    pack_clf_streaming(&mut streamed, sources, &options).unwrap();
+    assert_eq!(streamed.into_inner(), bytes);
+
+    let reader = ClfReaderFromBytes::open(&bytes, None).unwrap();
+    assert_eq!(reader.header.metadata, metadata);
+    assert_eq!(reader.get_meta("git_sha"), Some("0badc0de"));
+    assert_eq!(reader.get_meta("flags"), Some(""));
+    assert_eq!(reader.get_meta("missing"), None);
+    assert_eq!(reader.get_blob(1).unwrap().unwrap(), b"add");
+
+    let pack = |metadata: Vec<(String, String)>, version: u8| {
+        let options = PackOptions {
+            metadata,
+            version,
+            ..PackOptions::default()
+        };
+        pack_clf(&mut Cursor::new(Vec::new()), &entries, &options)
+    };
+    let dup = vec![
+        ("k".to_string(), "1".to_string()),
+        ("k".to_string(), "2".to_string()),
+    ];
+    assert!(matches!(
+        pack(dup, CLF_VERSION),
+        Err(PackError::DuplicateMetadataKey(k)) if k == "k"
+    ));
+    let long_key = vec![("k".repeat(256), String::new())];
+    assert!(matches!(
+        pack(long_key, CLF_VERSION),
+        Err(PackError::MetadataKeyTooLong(_))
+    ));
+    assert!(matches!(
+        pack(metadata, 2),
+        Err(PackError::RequiresV3 { version: 2, .. })
+    ));
+}
+
+/// Build timestamp and tool version round-trip; `deterministic` omits both so repeated packs
+/// are byte-identical to a pack that never set them.
+#[test]
+fn packer_build_info_round_trip_and_deterministic() {
+    let entries: Vec<(u32, Vec<u8>)> = vec![(1, b"add".to_vec())];
+    let stamped = PackOptions {
+        created_at: Some(1_700_000_000),
+        tool_version: Some("clf 9.9.9".to_string()),
+        ..PackOptions::default()
+    };
+    let mut out = Cursor::new(Vec::new());
+    pack_clf(&mut out, &entries, &stamped).unwrap();
+    let bytes = out.into_inner();
+    let reader = ClfReaderFromBytes::open(&bytes, None).unwrap();
+    assert_eq!(reader.header.created_at, Some(1_700_000_000));
+    assert_eq!(reader.header.tool_version.as_deref(), Some("clf 9.9.9"));
+    assert_eq!(reader.get_blob(1).unwrap().unwrap(), b"add");
+
+    let pack = |options: &PackOptions| {
+        let mut out = Cursor::new(Vec::new());
+        pack_clf(&mut out, &entries, options).unwrap();
+        out.into_inner()
+    };
+    let deterministic = PackOptions {
+        deterministic: true,
+        ..stamped.clone()
+    };
+    let later = PackOptions {
+        created_at: Some(1_800_000_000),
+        ..deterministic.clone()
+    };
+    let plain = pack(&PackOptions::default());
+    assert_eq!(pack(&deterministic), plain);
+    assert_eq!(pack(&later), plain);
+    let reader = ClfReaderFromBytes::open(&plain, None).unwrap();
+    assert_eq!(reader.header.created_at, None);
+    assert_eq!(reader.header.tool_version, None);
+
+    let v2 = PackOptions {
+        version: 2,
+        ..stamped
+    };
+    assert!(matches!(
+        pack_clf(&mut Cursor::new(Vec::new()), &entries, &v2),
+        Err(PackError::RequiresV3 { version: 2, .. })
+    ));
+}
+
+/// Seekable writer that only tracks its position, so multi-GiB packs cost no memory.
+#[derive(Default)]
+struct DiscardingSink {
+    pos: u64,
+    len: u64,
+}
+
+impl Write for DiscardingSink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.pos += buf.len() as u64;
+        self.len = self.len.max(self.pos);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl std::io::Seek for DiscardingSink {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        self.pos = match pos {
+            std::io::SeekFrom::Start(n) => n,
+            std::io::SeekFrom::Current(d) => self.pos.checked_add_signed(d).unwrap(),
+            std::io::SeekFrom::End(d) => self.len.checked_add_signed(d).unwrap(),
+        };
+        Ok(self.pos)
+    }
+}
+
+/// Source of `remaining` bytes that never fills the buffer, so reading GiBs is cheap.
+struct Sized(u64);
+
+impl Read for Sized {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = (buf.len() as u64).min(self.0) as usize;
+        self.0 -= n as u64;
+        Ok(n)
+    }
+}
+
+/// A blob store past u32::MAX fails with BlobStoreTooLarge at the entry that overflows, and a
+/// single blob over u32::MAX fails with BlobTooLarge, instead of writing clamped offsets.
+#[test]
+fn packer_rejects_blob_store_offset_overflow() {
+    const GIB: u64 = 1 << 30;
+    let sources: Vec<(u32, Box<dyn Read>)> = vec![
+        (1, Box::new(Sized(3 * GIB))),
+        // Ends exactly at u32::MAX, which still fits.
+        (2, Box::new(Sized(GIB - 1))),
+        (3, Box::new(Sized(1))),
+    ];
+    let err = pack_clf_streaming(
+        &mut DiscardingSink::default(),
+        sources,
+        &PackOptions::default(),
+    )
+    .unwrap_err();
+    assert!(
+        matches!(
+            err,
+            PackError::BlobStoreTooLarge {
+                at_op_id: 3,
+                total
+            } if total == 4 * GIB
+        ),
+        "{err}"
+    );
+
+    let sources: Vec<(u32, Box<dyn Read>)> = vec![(7, Box::new(Sized(4 * GIB)))];
+    let err = pack_clf_streaming(
+        &mut DiscardingSink::default(),
+        sources,
+        &PackOptions::default(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, PackError::BlobTooLarge(7)), "{err}");
+}
+
+/// With blob alignment the blob store ends on an aligned file offset (each blob is padded,
+/// including the last), so unsigned files are a multiple of the alignment long and a signature
+/// starts aligned. Trailing dedup'd duplicates do not leave an unaligned tail either.
+#[test]
+fn packer_blob_store_ends_aligned() {
+    let dir = tempfile::tempdir().unwrap();
+    let entries: Vec<(u32, Vec<u8>)> =
+        vec![(1, vec![0xAA; 5]), (2, vec![0xBB; 37]), (3, vec![0xAA; 5])];
+    for align in [16u8, 64, 255] {
+        for sign in [false, true] {
+            let options = PackOptions {
+                vendor: "odd".to_string(),
+                blob_alignment: align,
+                dedup_blobs: true,
+                sign,
+                ..PackOptions::default()
+            };
+            let in_memory = dir.path().join("a.clf");
+            pack_clf_to_path(&in_memory, &entries, &options).unwrap();
+            let streamed = dir.path().join("b.clf");
+            let sources: Vec<(u32, Box<dyn Read>)> = entries
+                .iter()
+                .map(|(op_id, blob)| (*op_id, Box::new(Cursor::new(blob.clone())) as Box<dyn Read>))
+                .collect();
+            pack_clf_streaming_to_path(&streamed, sources, &options).unwrap();
+
+            for path in [&in_memory, &streamed] {
+                let mut reader = ClfReader::open(path).unwrap();
+                let data_end = reader.blob_store_offset() + reader.blob_store_len();
+                assert_eq!(data_end % u64::from(align), 0, "align {align} sign {sign}");
+                let file_len = std::fs::metadata(path).unwrap().len();
+                if sign {
+                    assert!(reader.verify_signature().unwrap());
+                } else {
+                    assert_eq!(file_len, data_end);
+                }
+                assert_eq!(reader.get_blob(2).unwrap().unwrap(), vec![0xBB; 37]);
+            }
+        }
+    }
+}
+
+/// PackSummary describes every entry and accounts for every blob-store byte.
+#[test]
+fn packer_summary_describes_entries() {
+    let entries: Vec<(u32, Vec<u8>)> = vec![(4, vec![1; 3]), (9, vec![2; 20])];
+    let options = PackOptions {
+        vendor: "acme".to_string(),
+        kind: ClfKind::MemoryMovement,
+        blob_alignment: 8,
+        ..PackOptions::default()
+    };
+    let mut out = Cursor::new(Vec::new());
+    let summary = pack_clf_with_summary(&mut out, &entries, &options).unwrap();
+    assert_eq!(summary.vendor, "acme");
+    assert_eq!(summary.kind, ClfKind::MemoryMovement);
+    assert_eq!(summary.total_blob_bytes, 23);
+    let sizes: Vec<_> = summary
+        .entries
+        .iter()
+        .map(|e| (e.op_id, e.size, e.exact_size, e.raw_size))
+        .collect();
+    assert_eq!(sizes, [(4, 8, 3, 3), (9, 24, 20, 20)]);
+
+    let reader = ClfReaderFromBytes::open(out.get_ref(), None).unwrap();
+    assert_eq!(summary.data_len, out.get_ref().len() as u64);
+    assert_eq!(
+        reader.blob_store_len(),
+        summary.total_blob_bytes + summary.padding_bytes
+    );
+    for (packed, entry) in summary.entries.iter().zip(reader.manifest_entries()) {
+        assert_eq!((packed.op_id, packed.offset), (entry.op_id, entry.offset));
+    }
+}
+
+/// Entry names round-trip through the v3 name table; unnamed entries and files without a table
+/// report no name, and overlong names are rejected.
+#[test]
+fn packer_entry_names_round_trip() {
+    let entries: Vec<(u32, Vec<u8>)> = vec![(1, b"add".to_vec()), (2, b"mul".to_vec())];
+    let options = PackOptions {
+        entry_names: [(1, "add_f32_avx2".to_string())].into_iter().collect(),
+        ..PackOptions::default()
+    };
+    let mut out = Cursor::new(Vec::new());
+    pack_clf(&mut out, &entries, &options).unwrap();
+    let reader = ClfReaderFromBytes::open(out.get_ref(), None).unwrap();
+    assert_eq!(reader.entry_name(1), Some("add_f32_avx2"));
+    assert_eq!(reader.entry_name(2), None);
+    assert_eq!(reader.get_blob(2).unwrap().unwrap(), b"mul");
+
+    let mut plain = Cursor::new(Vec::new());
+    pack_clf(&mut plain, &entries, &PackOptions::default()).unwrap();
+    let reader = ClfReaderFromBytes::open(plain.get_ref(), None).unwrap();
+    assert_eq!(reader.entry_name(1), None);
+
+    let too_long = PackOptions {
+        entry_names: [(2, "x".repeat(256))].into_iter().collect(),
+        ..PackOptions::default()
+    };
+    assert!(matches!(
+        pack_clf(&mut Cursor::new(Vec::new()), &entries, &too_long),
+        Err(PackError::EntryNameTooLong(2))
+    ));
+}
+
+/// Header extension records round-trip in order and old-style files have none. Duplicate tags
+/// are refused by the packer, and an unknown must-understand tag makes the reader reject the
+/// file while an unknown optional tag is kept raw.
+#[test]
+fn packer_header_extensions_round_trip() {
+    let entries: Vec<(u32, Vec<u8>)> = vec![(1, b"add".to_vec())];
+    let options = PackOptions {
+        extensions: vec![(0x0042, b"future".to_vec()), (0x0007, Vec::new())],
+        ..PackOptions::default()
+    };
+    let mut out = Cursor::new(Vec::new());
+    pack_clf(&mut out, &entries, &options).unwrap();
+    let reader = ClfReaderFromBytes::open(out.get_ref(), None).unwrap();
+    assert_eq!(reader.header.extensions, options.extensions);
+    assert_eq!(reader.header.extension(0x0042), Some(&b"future"[..]));
+    assert_eq!(reader.header.extension(0x0007), Some(&[][..]));
+    assert_eq!(reader.header.extension(0x0001), None);
+    assert_eq!(reader.get_blob(1).unwrap().unwrap(), b"add");
+
+    let mut plain = Cursor::new(Vec::new());
+    pack_clf(&mut plain, &entries, &PackOptions::default()).unwrap();
+    let reader = ClfReaderFromBytes::open(plain.get_ref(), None).unwrap();
+    assert!(reader.header.extensions.is_empty());
+
+    let duplicate = PackOptions {
+        extensions: vec![(5, vec![1]), (5, vec![2])],
+        ..PackOptions::default()
+    };
+    assert!(matches!(
+        pack_clf(&mut Cursor::new(Vec::new()), &entries, &duplicate),
+        Err(PackError::DuplicateExtension(5))
+    ));
+
+    let critical = clf::EXTENSION_MUST_UNDERSTAND | 0x0042;
+    let must_understand = PackOptions {
+        extensions: vec![(critical, b"validity window".to_vec())],
+        ..PackOptions::default()
+    };
+    let mut out = Cursor::new(Vec::new());
+    pack_clf(&mut out, &entries, &must_understand).unwrap();
+    assert!(matches!(
+        ClfReaderFromBytes::open(out.get_ref(), None),
+        Err(ClfError::UnsupportedExtension(tag)) if tag == critical
+    ));
+
+    let v2 = PackOptions {
+        version: 2,
+        ..options
+    };
+    assert!(matches!(
+        pack_clf(&mut Cursor::new(Vec::new()), &entries, &v2),
+        Err(PackError::RequiresV3 { .. })
+    ));
+}
+
+/// Several variants of one op_id round-trip: `get_blob` serves variant 0, `get_blob_variant`
+/// the others, and a selector picks variants when building a code section.
+#[test]
+fn packer_blob_variants_round_trip() {
+    let entries: Vec<(u32, u16, Vec<u8>)> = vec![
+        (1, 0, b"add-default".to_vec()),
+        (1, 64, b"add-wave64".to_vec()),
+        (2, 0, b"mul".to_vec()),
+        (3, 7, b"only-variant".to_vec()),
+    ];
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    let summary = clf::pack_clf_variants(&mut file, &entries, &PackOptions::default()).unwrap();
+    file.flush().unwrap();
+    assert_eq!(summary.entries[1].variant, 64);
+
+    let mut reader = ClfReader::open(file.path()).unwrap();
+    reader.validate().unwrap();
+    assert_eq!(reader.op_ids(), [1, 2, 3]);
+    assert_eq!(reader.variants(1), [0, 64]);
+    assert_eq!(reader.variants(3), [7]);
+    assert!(reader.variants(9).is_empty());
+    assert_eq!(reader.get_blob(1).unwrap().unwrap(), b"add-default");
+    assert_eq!(
+        reader.get_blob_variant(1, 64).unwrap().unwrap(),
+        b"add-wave64"
+    );
+    assert_eq!(reader.get_blob(3).unwrap(), None);
+
+    let code = reader
+        .build_code_section_with_variants(
+            &[1, 2, 3],
+            clf::MissingOpIdPolicy::Fail,
+            |_, variants| *variants.last().unwrap(),
+        )
+        .unwrap();
+    assert_eq!(code, b"add-wave64mulonly-variant");
+    assert!(matches!(
+        reader.build_code_section(&[3], clf::MissingOpIdPolicy::Fail),
+        Err(ClfError::MissingOpId(3))
+    ));
+
+    // Variant-free input writes the same bytes as pack_clf.
+    let plain: Vec<(u32, Vec<u8>)> = vec![(1, b"add".to_vec())];
+    let mut a = Cursor::new(Vec::new());
+    pack_clf(&mut a, &plain, &PackOptions::default()).unwrap();
+    let mut b = Cursor::new(Vec::new());
+    clf::pack_clf_variants(&mut b, &[(1, 0, b"add".to_vec())], &PackOptions::default()).unwrap();
+    assert_eq!(a.into_inner(), b.into_inner());
+
+    let dup = [(1, 64, b"a".to_vec()), (1, 64, b"b".to_vec())];
+    assert!(matches!(
+        clf::pack_clf_variants(&mut Cursor::new(Vec::new()), &dup, &PackOptions::default()),
+        Err(PackError::DuplicateVariant {
+            op_id: 1,
+            variant: 64
+        })
+    ));
+    let v2 = PackOptions {
+        version: 2,
+        ..PackOptions::default()
+    };
+    assert!(matches!(
+        clf::pack_clf_variants(&mut Cursor::new(Vec::new()), &entries, &v2),
+        Err(PackError::RequiresV3 { version: 2, .. })
+    ));
+}
+
+/// Per-entry target tags round-trip through both pack paths: untagged entries inherit the header
+/// target, lookups filter by target, and `expected_target` accepts any target the archive carries.
+#[test]
+fn packer_entry_targets_round_trip() {
+    let entries: Vec<(u32, u16, Vec<u8>)> = vec![
+        (1, 0, b"add-cpu".to_vec()),
+        (1, 1, b"add-gpu".to_vec()),
+        (2, 0, b"mul-cpu".to_vec()),
+        (3, 1, b"relu-gpu".to_vec()),
+    ];
+    let options = PackOptions {
+        target: "x86_64".to_string(),
+        blob_alignment: 16,
+        entry_targets: [((1, 1), "sm_90".to_string()), ((3, 1), "sm_90".to_string())]
+            .into_iter()
+            .collect(),
+        ..PackOptions::default()
+    };
+    let mut buf = Cursor::new(Vec::new());
+    clf::pack_clf_variants(&mut buf, &entries, &options).unwrap();
+    let bytes = buf.into_inner();
+
+    let mut streamed = Cursor::new(Vec::new());
+    let sources: Vec<(u32, u16, Box<dyn Read>)> = entries
+        .iter()
+        .map(|(op_id, variant, blob)| {
+            let source: Box<dyn Read> = Box::new(Cursor::new(blob.clone()));
+            (*op_id, *variant, source)
+        })
+        .collect();
+    clf::pack_clf_streaming_variants(&mut streamed, sources, &options).unwrap();
+    assert_eq!(streamed.into_inner(), bytes);
+
+    let reader = ClfReaderFromBytes::open(&bytes, None).unwrap();
+    reader.validate().unwrap();
+    assert_eq!(reader.op_ids_for_target("x86_64"), [1, 2]);
+    assert_eq!(reader.op_ids_for_target("sm_90"), [1, 3]);
+    assert!(reader.op_ids_for_target("gfx942").is_empty());
+    assert_eq!(
+        reader.get_blob_for_target(1, "sm_90").unwrap().unwrap(),
+        b"add-gpu"
+    );
+    assert_eq!(
+        reader.get_blob_for_target(1, "x86_64").unwrap().unwrap(),
+        b"add-cpu"
+    );
+    assert_eq!(reader.get_blob_for_target(2, "sm_90").unwrap(), None);
+    let entry = reader.manifest_entries()[3];
+    assert_eq!(reader.entry_target(&entry), "sm_90");
+
+    let expecting = |target: &str| ClfReaderOptions {
+        expected_target: Some(target.to_string()),
+        ..ClfReaderOptions::default()
+    };
+    assert!(ClfReaderFromBytes::open_with_options(&bytes, &expecting("sm_90")).is_ok());
+    assert!(ClfReaderFromBytes::open_with_options(&bytes, &expecting("x86_64")).is_ok());
+    assert!(matches!(
+        ClfReaderFromBytes::open_with_options(&bytes, &expecting("gfx942")),
+        Err(ClfError::TargetMismatch { .. })
+    ));
+
+    // Tags equal to the header target add nothing: the file matches an untagged one.
+    let untagged = PackOptions {
+        target: "x86_64".to_string(),
+        ..PackOptions::default()
+    };
+    let redundant = PackOptions {
+        entry_targets: [((1, 0), "x86_64".to_string())].into_iter().collect(),
+        ..untagged.clone()
+    };
+    let mut a = Cursor::new(Vec::new());
+    clf::pack_clf_variants(&mut a, &entries, &untagged).unwrap();
+    let mut b = Cursor::new(Vec::new());
+    clf::pack_clf_variants(&mut b, &entries, &redundant).unwrap();
+    assert_eq!(a.into_inner(), b.into_inner());
+
+    let v2 = PackOptions {
+        version: 2,
+        entry_targets: [((2, 0), "sm_90".to_string())].into_iter().collect(),
+        ..PackOptions::default()
+    };
+    assert!(matches!(
+        pack_clf(&mut Cursor::new(Vec::new()), &[(2, b"mul".to_vec())], &v2),
+        Err(PackError::RequiresV3 { version: 2, .. })
+    ));
+    // Tags of entries that are not packed are ignored.
+    clf::pack_clf_variants(
+        &mut Cursor::new(Vec::new()),
+        &[(1, 0, b"add".to_vec())],
+        &v2,
+    )
+    .unwrap();
+}
+
+/// `pack_clf` consumes a lazy iterator of owned entries in one pass, writing the same bytes as the
+/// slice form, and still catches duplicates and iterators that misreport their length.
+#[test]
+fn packer_accepts_entry_iterator() {
+    let entries: Vec<(u32, Vec<u8>)> = (1..=4).map(|op_id| (op_id, vec![op_id as u8; 5])).collect();
+    let options = PackOptions {
+        blob_alignment: 8,
+        dedup_blobs: true,
+        ..PackOptions::default()
+    };
+    let mut from_slice = Cursor::new(Vec::new());
+    pack_clf(&mut from_slice, &entries, &options).unwrap();
+    let mut from_iter = Cursor::new(Vec::new());
+    let lazy = (1..5u32).map(|op_id| (op_id, vec![op_id as u8; 5]));
+    pack_clf(&mut from_iter, lazy, &options).unwrap();
+    assert_eq!(from_iter.get_ref(), from_slice.get_ref());
+    let reader = ClfReaderFromBytes::open(from_iter.get_ref(), None).unwrap();
+    assert_eq!(reader.get_blob(3).unwrap().unwrap(), [3; 5]);
+
+    let dup = [1u32, 2, 1].into_iter().map(|op_id| (op_id, vec![0u8]));
+    assert!(matches!(
+        pack_clf(&mut Cursor::new(Vec::new()), dup, &options),
+        Err(PackError::DuplicateOpId(1))
+    ));
+
+    /// Claims one entry more than it yields.
+    struct Overstated(std::vec::IntoIter<(u32, Vec<u8>)>);
+    impl Iterator for Overstated {
+        type Item = (u32, Vec<u8>);
+        fn next(&mut self) -> Option<Self::Item> {
+            self.0.next()
+        }
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            let n = self.0.len() + 1;
+            (n, Some(n))
+        }
+    }
+    impl ExactSizeIterator for Overstated {}
+    assert!(matches!(
+        pack_clf(
+            &mut Cursor::new(Vec::new()),
+            Overstated(entries.into_iter()),
+            &options
+        ),
+        Err(PackError::EntryCountMismatch {
+            expected: 5,
+            actual: 4
+        })
+    ));
+}
+
+/// Op_id ranges: canonical 1–255, vendor `0xF000_0000..=0xFFFF_FFFE`, custom in between.
+#[test]
+fn op_id_ranges_have_exact_boundaries() {
+    use clf::{clf_id_to_op_type, is_canonical_op_id, is_registered_op_id, is_vendor_op_id};
+
+    assert!(!is_canonical_op_id(0));
+    assert!(is_canonical_op_id(1));
+    assert!(is_canonical_op_id(255));
+    assert!(!is_canonical_op_id(256));
+    assert!(!is_vendor_op_id(0xEFFF_FFFF));
+    assert!(is_vendor_op_id(0xF000_0000));
+    assert!(is_vendor_op_id(0xFFFF_FFFE));
+    assert!(!is_vendor_op_id(u32::MAX));
+
+    assert_eq!(clf_id_to_op_type(256), OpType::Custom(256));
+    assert_eq!(clf_id_to_op_type(0xEFFF_FFFF), OpType::Custom(0xEFFF_FFFF));
+    assert_eq!(clf_id_to_op_type(0xF000_0000), OpType::Vendor(0xF000_0000));
+    assert_eq!(clf_id_to_op_type(0xFFFF_FFFE), OpType::Vendor(0xFFFF_FFFE));
+    assert_eq!(clf_id_to_op_type(u32::MAX), OpType::Custom(u32::MAX));
+    assert_eq!(
+        clf::op_type_to_clf_id(OpType::Vendor(0xF000_0001)),
+        0xF000_0001
+    );
+
+    assert!(is_registered_op_id(50));
+    assert!(!is_registered_op_id(5));
+    assert!(!is_registered_op_id(0xF000_0000));
+}
+
+/// `strict_op_ids` rejects unassigned canonical ids and still accepts registered, custom and
+/// vendor-range ids.
+#[test]
+fn packer_strict_op_ids_rejects_unregistered_canonical_ids() {
+    let strict = PackOptions {
+        strict_op_ids: true,
+        ..PackOptions::default()
+    };
+    let ok = vec![
+        (1, b"add".to_vec()),
+        (256, b"custom".to_vec()),
+        (0xF000_0000, b"vendor-lo".to_vec()),
+        (0xFFFF_FFFE, b"vendor-hi".to_vec()),
+    ];
+    pack_clf(&mut Cursor::new(Vec::new()), ok, &strict).unwrap();
+
+    for id in [5, 255] {
+        let entries = vec![(1, b"add".to_vec()), (id, b"x".to_vec())];
+        assert!(matches!(
+            pack_clf(&mut Cursor::new(Vec::new()), entries.clone(), &strict),
+            Err(PackError::UnregisteredOpId(bad)) if bad == id
+        ));
+        pack_clf(
+            &mut Cursor::new(Vec::new()),
+            entries,
+            &PackOptions::default(),
+        )
+        .unwrap();
+    }
+}
+
+/// Every registered op round-trips through its op_id, ids are canonical and ascending, and
+/// nothing outside `OpType::ALL` has a canonical id.
+#[test]
+fn registry_enumerates_all_ops() {
+    use clf::{all_op_ids, all_ops, clf_id_to_op_type, is_registered_op_id, op_type_to_clf_id};
+
+    assert!(!OpType::ALL.is_empty());
+    assert_eq!(all_ops().collect::<Vec<_>>(), OpType::ALL);
+    let ids: Vec<u32> = all_op_ids().collect();
+    assert!(ids.windows(2).all(|w| w[0] < w[1]), "{ids:?}");
+    for &op in OpType::ALL {
+        let id = op_type_to_clf_id(op);
+        assert!(clf::is_canonical_op_id(id), "{op:?} -> {id}");
+        assert_eq!(clf_id_to_op_type(id), op);
+        assert_eq!(op_type_from_name(&format!("{op:?}")), Some(op));
+    }
+    let registered = (0..=clf::CANONICAL_OP_ID_MAX).filter(|&id| is_registered_op_id(id));
+    assert!(registered.eq(ids.iter().copied()));
+}
+
+/// Every registered op lands in exactly one category, and ops outside the registry are `Other`.
+#[test]
+fn registry_categories_partition_ops() {
+    use clf::{category, ops_in_category, OpCategory};
+
+    let mut seen = Vec::new();
+    for cat in OpCategory::ALL {
+        for op in ops_in_category(cat) {
+            assert_eq!(category(op), cat);
+            assert!(!seen.contains(&op), "{op:?} in two categories");
+            seen.push(op);
+        }
+    }
+    assert_eq!(seen.len(), OpType::ALL.len());
+
+    assert_eq!(category(OpType::Add), OpCategory::Elementwise);
+    assert_eq!(category(OpType::ReduceMean), OpCategory::Reduction);
+    assert_eq!(category(OpType::MatMul), OpCategory::Contraction);
+    assert_eq!(category(OpType::Transpose), OpCategory::DataMovement);
+    assert_eq!(category(OpType::LayerNorm), OpCategory::Normalization);
+    assert_eq!(category(OpType::Relu), OpCategory::Activation);
+    for op in [
+        OpType::Unknown,
+        OpType::Custom(300),
+        OpType::Vendor(0xF000_0000),
+    ] {
+        assert_eq!(category(op), OpCategory::Other);
+    }
+}
+
+/// `OpRegistry` starts from the built-in table, accepts vendor ops only in the vendor range
+/// with unique names, and resolves both through the same lookups.
+#[test]
+fn op_registry_registers_vendor_ops() {
+    use clf::{OpCategory, OpRegistry, RegistryError};
+
+    let mut registry = OpRegistry::new();
+    assert_eq!(registry.iter().count(), OpType::ALL.len());
+    let matmul = registry.lookup_name("matmul").unwrap();
+    assert_eq!((matmul.op_id, matmul.op_type), (50, OpType::MatMul));
+    assert_eq!(matmul.category, OpCategory::Contraction);
+    assert_eq!(registry.name(1), Some("Add"));
+
+    registry
+        .register_vendor_op(0xF000_0001, "AcmeFusedAttn", OpCategory::Contraction)
+        .unwrap();
+    let fused = registry.lookup_id(0xF000_0001).unwrap();
+    assert_eq!(fused.op_type, OpType::Vendor(0xF000_0001));
+    assert_eq!(registry.lookup_name("acmefusedattn"), Some(fused));
+    assert_eq!(registry.parse_op_id("ACMEFUSEDATTN"), Ok(0xF000_0001));
+    let err = registry.parse_op_id("AcmeFusedAtn").unwrap_err();
+    assert!(err.contains("did you mean AcmeFusedAttn"), "{err}");
+    assert!(clf::parse_op_id("AcmeFusedAttn").is_err());
+
+    let other = OpCategory::Other;
+    assert_eq!(
+        registry.register_vendor_op(0xEFFF_FFFF, "x", other),
+        Err(RegistryError::NotVendorOpId(0xEFFF_FFFF))
+    );
+    assert_eq!(
+        registry.register_vendor_op(u32::MAX, "x", other),
+        Err(RegistryError::NotVendorOpId(u32::MAX))
+    );
+    assert_eq!(
+        registry.register_vendor_op(0xF000_0001, "Other", other),
+        Err(RegistryError::DuplicateOpId(0xF000_0001))
+    );
+    assert_eq!(
+        registry.register_vendor_op(0xF000_0002, "MATMUL", other),
+        Err(RegistryError::DuplicateName("MATMUL".to_string()))
+    );
+    for bad in ["", "123"] {
+        assert_eq!(
+            registry.register_vendor_op(0xF000_0002, bad, other),
+            Err(RegistryError::InvalidName(bad.to_string()))
+        );
+    }
+
+    // Readers label entries from the name table first, then the registry.
+    let options = PackOptions {
+        entry_names: [(1, "add_f32".to_string())].into_iter().collect(),
+        ..PackOptions::default()
+    };
+    let mut bytes = Vec::new();
+    let entries = vec![
+        (1, b"a".to_vec()),
+        (50, b"m".to_vec()),
+        (0xF000_0001, b"f".to_vec()),
+        (0xF000_0002, b"g".to_vec()),
+    ];
+    pack_clf(&mut Cursor::new(&mut bytes), entries, &options).unwrap();
+    let reader = ClfReaderFromBytes::open(&bytes, None).unwrap();
+    assert_eq!(reader.entry_label(1, &registry), Some("add_f32"));
+    assert_eq!(reader.entry_label(50, &registry), Some("MatMul"));
+    assert_eq!(
+        reader.entry_label(0xF000_0001, &registry),
+        Some("AcmeFusedAttn")
+    );
+    assert_eq!(reader.entry_label(0xF000_0002, &registry), None);
+}
+
+/// `OpType` round-trips through serde_json as its name (custom and vendor ops as their op_id),
+/// accepts aliases and integer op_ids, and rejects unknown names with a suggestion.
+#[cfg(feature = "serde")]
+#[test]
+fn op_type_serde_round_trip() {
+    let ops = vec![
+        OpType::Add,
+        OpType::MatMul,
+        OpType::GlobalAvgPool,
+        OpType::Unknown,
+        OpType::Custom(300),
+        OpType::Vendor(0xF000_0001),
+    ];
+    let json = serde_json::to_string(&ops).unwrap();
+    assert_eq!(
+        json,
+        r#"["Add","MatMul","GlobalAvgPool","Unknown","300","4026531841"]"#
+    );
+    assert_eq!(serde_json::from_str::<Vec<OpType>>(&json).unwrap(), ops);
+    let all = serde_json::to_string(OpType::ALL).unwrap();
+    assert_eq!(
+        serde_json::from_str::<Vec<OpType>>(&all).unwrap(),
+        OpType::ALL
+    );
+
+    let parsed: Vec<OpType> =
+        serde_json::from_str(r#"["conv", "Concat", "SUB", "matmul", 50, 0]"#).unwrap();
+    assert_eq!(
+        parsed,
+        [
+            OpType::Convolution,
+            OpType::Concatenate,
+            OpType::Subtract,
+            OpType::MatMul,
+            OpType::MatMul,
+            OpType::Unknown
+        ]
+    );
+
+    let err = serde_json::from_str::<OpType>(r#""matmull""#).unwrap_err();
+    assert!(err.to_string().contains("did you mean MatMul"), "{err}");
+    assert!(serde_json::from_str::<OpType>("4294967296").is_err());
+    assert_eq!("Relu".parse::<OpType>().unwrap().to_string(), "Relu");
+}
+
+/// `op_id_name` / `describe_op_id` name canonical, vendor and unknown ids, and `MissingOpId`
+/// messages carry the description.
+#[test]
+fn describe_op_id_names_raw_ids() {
+    use clf::{describe_op_id, op_id_name};
+
+    assert_eq!(op_id_name(50), Some("MatMul"));
+    assert_eq!(op_id_name(0), None);
+    assert_eq!(op_id_name(5), None);
+    assert_eq!(op_id_name(0xF000_0001), None);
+    assert_eq!(describe_op_id(50), "MatMul");
+    assert_eq!(describe_op_id(0xF000_0001), "vendor op 0xf0000001");
+    assert_eq!(describe_op_id(300), "unknown op 300");
+    assert_eq!(describe_op_id(0), "unknown op 0");
+    assert_eq!(
+        ClfError::MissingOpId(50).to_string(),
+        "missing op_id 50 (MatMul) in CLF (policy: Fail)"
+    );
+}
+
+/// An archive packed under a deprecated op_id is served to a caller asking with the canonical
+/// id through `get_blob_canonical`; plain `get_blob` keeps exact-id semantics.
+#[test]
+fn deprecated_op_ids_resolve_to_canonical() {
+    use clf::{canonical_op_id, is_deprecated, OpRegistry, RegistryError};
+
+    assert_eq!(canonical_op_id(50), 50);
+    assert!(!is_deprecated(50));
+
+    let mut registry = OpRegistry::new();
+    registry.deprecate_op_id(120, 50).unwrap();
+    assert_eq!(registry.canonical_op_id(120), 50);
+    assert_eq!(registry.canonical_op_id(50), 50);
+    assert!(registry.is_deprecated(120));
+    assert_eq!(registry.deprecated_aliases(50).collect::<Vec<_>>(), [120]);
+    assert_eq!(
+        registry.deprecate_op_id(121, 300),
+        Err(RegistryError::UnknownOpId(300))
+    );
+    assert_eq!(
+        registry.deprecate_op_id(51, 50),
+        Err(RegistryError::OpIdInUse(51))
+    );
+    assert_eq!(
+        registry.deprecate_op_id(120, 51),
+        Err(RegistryError::AlreadyDeprecated(120))
+    );
+
+    let entries = vec![(1, vec![1u8]), (120, vec![0xAA, 0xBB])];
+    let mut buf = Cursor::new(Vec::new());
+    pack_clf(&mut buf, &entries, &PackOptions::default()).unwrap();
+    let bytes = buf.into_inner();
+
+    let from_bytes = ClfReaderFromBytes::open(&bytes, None).unwrap();
+    assert_eq!(from_bytes.get_blob(50).unwrap(), None);
+    assert_eq!(from_bytes.resolve_op_id(50, &registry), Some(120));
+    assert_eq!(
+        from_bytes.get_blob_canonical(50, &registry).unwrap(),
+        Some(vec![0xAA, 0xBB])
+    );
+    assert_eq!(
+        from_bytes.get_blob_canonical(120, &registry).unwrap(),
+        Some(vec![0xAA, 0xBB])
+    );
+    assert_eq!(from_bytes.get_blob_canonical(2, &registry).unwrap(), None);
+    assert_eq!(from_bytes.resolve_op_id(50, OpRegistry::builtin()), None);
+
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(&bytes).unwrap();
+    file.flush().unwrap();
+    let mut reader = ClfReader::open(file.path()).unwrap();
+    assert_eq!(
+        reader.get_blob_canonical(50, &registry).unwrap(),
+        Some(vec![0xAA, 0xBB])
+    );
+    assert_eq!(
+        reader.get_blob_canonical(1, &registry).unwrap(),
+        Some(vec![1])
+    );
+}
+
+/// The JSON export covers every registered op with its id, category and aliases, and the
+/// Markdown export has one row per op.
+#[test]
+fn registry_exports_cover_every_op() {
+    use clf::op_registry::{export_json, export_markdown};
+
+    let doc: serde_json::Value = serde_json::from_str(&export_json()).unwrap();
+    assert_eq!(doc["vendor_op_id_min"], clf::VENDOR_OP_ID_MIN);
+    let ops = doc["ops"].as_array().unwrap();
+    assert_eq!(ops.len(), OpType::ALL.len());
+    for (json, &op) in ops.iter().zip(OpType::ALL) {
+        assert_eq!(json["op_id"], clf::op_type_to_clf_id(op));
+        assert_eq!(json["name"], op.name().unwrap());
+        assert_eq!(json["category"], clf::category(op).name());
+        assert_eq!(json["since"], op.since());
+        assert_eq!(
+            json["aliases"].as_array().unwrap().len(),
+            op.aliases().len()
+        );
+    }
+
+    let markdown = export_markdown();
+    assert_eq!(markdown.lines().count(), 2 + OpType::ALL.len());
+    assert!(markdown.contains("| 43 | Concatenate | data-movement | 0.1.0 | Concat |"));
+}
+
+/// Packing 50k small entries to a path, in memory and streamed, finishes promptly and reads back.
+/// The index is written in one go and file output is buffered, so this is not ~100k syscalls.
+#[test]
+fn packer_many_entries_smoke() {
+    const COUNT: u32 = 50_000;
+    let entries: Vec<(u32, Vec<u8>)> = (1..=COUNT)
+        .map(|op_id| (op_id, op_id.to_le_bytes().to_vec()))
+        .collect();
+    let options = PackOptions {
+        blob_alignment: 8,
+        sign: true,
+        ..PackOptions::default()
+    };
+    let dir = tempfile::tempdir().unwrap();
+    let in_memory = dir.path().join("a.clf");
+    pack_clf_to_path(&in_memory, &entries, &options).unwrap();
+    let streamed = dir.path().join("b.clf");
+    let sources: Vec<(u32, Box<dyn Read>)> = entries
+        .iter()
+        .map(|(op_id, blob)| (*op_id, Box::new(Cursor::new(blob.clone())) as Box<dyn Read>))
+        .collect();
+    pack_clf_streaming_to_path(&streamed, sources, &options).unwrap();
+
+    for path in [&in_memory, &streamed] {
+        let mut reader = ClfReader::open(path).unwrap();
+        assert_eq!(reader.op_ids().len(), COUNT as usize);
+        assert!(reader.verify_signature().unwrap());
+        assert_eq!(
+            reader.get_blob(COUNT).unwrap().unwrap(),
+            COUNT.to_le_bytes()
+        );
+    }
+    assert_eq!(
+        std::fs::read(&in_memory).unwrap(),
+        std::fs::read(&streamed).unwrap()
+    );
+}
+
+/// Batched blob hashing (parallel with the `parallel` feature) gives the same bytes as the
+/// one-at-a-time streaming packer, and `verify_blobs` reports the same first bad entry as
+/// reading blobs in order.
+#[test]
+fn batched_hashing_matches_serial_packing() {
+    let entries: Vec<(u32, Vec<u8>)> = (1..=200u32)
+        .map(|op_id| {
+            (
+                op_id,
+                vec![(op_id % 7) as u8; 100 + (op_id % 5) as usize * 300],
+            )
+        })
+        .collect();
+    let options = PackOptions {
+        blob_alignment: 16,
+        dedup_blobs: true,
+        per_blob_crc: true,
+        ..PackOptions::default()
+    };
+    let mut batched = Cursor::new(Vec::new());
+    pack_clf(&mut batched, &entries, &options).unwrap();
+    let sources: Vec<(u32, Box<dyn Read>)> = entries
+        .iter()
+        .map(|(op_id, blob)| (*op_id, Box::new(Cursor::new(blob.clone())) as Box<dyn Read>))
+        .collect();
+    let mut serial = Cursor::new(Vec::new());
+    let summary = pack_clf_streaming(&mut serial, sources, &options).unwrap();
+    let serial = &serial.get_ref()[..summary.data_len as usize];
+    assert_eq!(batched.get_ref(), serial);
+
+    let mut bytes = serial.to_vec();
+    let reader = ClfReaderFromBytes::open(&bytes, None).unwrap();
+    reader.verify_blobs().unwrap();
+    let store = reader.blob_store_offset() as usize;
+    let unique: Vec<_> = reader
+        .manifest_entries()
+        .into_iter()
+        .filter(|e| e.op_id <= 7)
+        .collect();
+    for entry in [unique[5], unique[2]] {
+        bytes[store + entry.offset as usize] ^= 0xFF;
+    }
+    let reader = ClfReaderFromBytes::open(&bytes, None).unwrap();
+    let first_bad = (1..=200u32)
+        .find_map(|op_id| reader.get_blob(op_id).err())
+        .unwrap();
+    assert!(matches!(
+        first_bad,
+        ClfError::BlobChecksumMismatch { op_id: 3, .. }
+    ));
+    assert_eq!(
+        reader.verify_blobs().unwrap_err().to_string(),
+        first_bad.to_string()
+    );
+}
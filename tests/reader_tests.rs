@@ -2,7 +2,10 @@
 
 use std::io::{Cursor, Write};
 
-use clf::{pack_clf, ClfKind, ClfReader, MissingOpIdPolicy, PackOptions};
+use clf::{
+    pack_clf, ClfError, ClfKind, ClfReader, ClfReaderFromBytes, ClfReaderOptions,
+    MissingOpIdPolicy, OpType, PackOptions, ReadOrder, TargetMatcher,
+};
 
 /// Build a minimal .clf in memory (op_id 1 and 50, fake blobs), then open with ClfReader and get_blob.
 #[test]
@@ -18,6 +21,7 @@ fn reader_parse_minimal_and_get_blob() {
         kind: ClfKind::Compute,
         version: 1,
         sign: false,
+        ..PackOptions::default()
     };
 
     let mut buf = Cursor::new(Vec::new());
@@ -70,7 +74,7 @@ fn reader_invalid_magic() {
     }
 }
 
-/// Truncated file (no manifest) → IO or clear error.
+/// Truncated file (no manifest): `Truncated` at the manifest entry count, byte 14 of a v1 file.
 #[test]
 fn reader_truncated_file() {
     let mut file = tempfile::NamedTempFile::new().unwrap();
@@ -82,9 +86,68 @@ fn reader_truncated_file() {
                                      // No manifest (num_entries) → read_exact will fail
     file.flush().unwrap();
 
-    match ClfReader::open(file.path()) {
-        Ok(_) => panic!("expected truncated file error"),
-        Err(e) => assert!(!e.to_string().is_empty()),
+    let err = ClfReader::open(file.path()).unwrap_err();
+    assert!(
+        matches!(
+            err.root(),
+            ClfError::Truncated {
+                at_offset: 14,
+                while_reading: "manifest entry count"
+            }
+        ),
+        "{err}"
+    );
+    assert!(err
+        .to_string()
+        .ends_with("unexpected end of data at byte 14 while reading manifest entry count"));
+}
+
+/// Cutting a v3 archive inside a field reports that field and its offset, which walks the
+/// layout: magic, version, vendor length and bytes, target length, alignment, kind, the header
+/// sections, then the manifest entry count and entry fields. (Entries cut short are caught
+/// earlier, by the entry count check against the file length.)
+#[test]
+fn reader_truncation_reports_field_and_offset() {
+    let entries: Vec<(u32, Vec<u8>)> = vec![(1, b"add".to_vec()), (50, b"matmul".to_vec())];
+    let options = PackOptions {
+        vendor: "acme".to_string(),
+        deterministic: true,
+        ..PackOptions::default()
+    };
+    let mut buf = Cursor::new(Vec::new());
+    pack_clf(&mut buf, &entries, &options).unwrap();
+    let bytes = buf.into_inner();
+    let manifest = ClfReaderFromBytes::open(&bytes, None)
+        .unwrap()
+        .header
+        .header_end;
+
+    let cases: [(u64, u64, &str); 11] = [
+        (3, 0, "magic"),
+        (4, 4, "version"),
+        (8, 5, "vendor length"),
+        (12, 9, "vendor"),
+        (16, 13, "target length"),
+        (17, 17, "blob alignment"),
+        (18, 18, "kind"),
+        (21, 19, "metadata section length"),
+        (26, 23, "build-info section length"),
+        (manifest + 1, manifest, "manifest entry count"),
+        (manifest + 6, manifest + 4, "manifest entry fields"),
+    ];
+    let file = tempfile::NamedTempFile::new().unwrap();
+    for (cut, offset, field) in cases {
+        std::fs::write(file.path(), &bytes[..cut as usize]).unwrap();
+        let err = ClfReader::open(file.path()).unwrap_err();
+        match err.root() {
+            ClfError::Truncated {
+                at_offset,
+                while_reading,
+            } => assert_eq!((*at_offset, *while_reading), (offset, field), "cut {cut}"),
+            other => panic!("cut {cut}: {other}"),
+        }
+        let err = ClfReaderFromBytes::open(&bytes[..cut as usize], None).unwrap_err();
+        assert!(matches!(err, ClfError::Truncated { at_offset, .. } if at_offset == offset));
     }
 }
 
@@ -119,6 +182,7 @@ fn reader_parse_kind_v2() {
         kind: ClfKind::MemoryMovement,
         version: 2,
         sign: false,
+        ..PackOptions::default()
     };
 
     let mut buf = Cursor::new(Vec::new());
@@ -144,6 +208,7 @@ fn reader_expected_kind_mismatch() {
         kind: ClfKind::Compute,
         version: 2,
         sign: false,
+        ..PackOptions::default()
     };
 
     let mut buf = Cursor::new(Vec::new());
@@ -169,6 +234,7 @@ fn reader_expected_kind_matches() {
         kind: ClfKind::MemoryProtection,
         version: 2,
         sign: false,
+        ..PackOptions::default()
     };
 
     let mut buf = Cursor::new(Vec::new());
@@ -183,6 +249,211 @@ fn reader_expected_kind_matches() {
     assert_eq!(reader.header.kind, ClfKind::MemoryProtection);
 }
 
+/// open_with_expected_target: an exact header target match opens, another target fails with
+/// `TargetMismatch` naming both, and the error carries the path.
+#[test]
+fn reader_expected_target_matches_exactly() {
+    let options = PackOptions {
+        target: "gfx942".to_string(),
+        ..PackOptions::default()
+    };
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    pack_clf(&mut file, &[(1, b"blob".to_vec())], &options).unwrap();
+    file.flush().unwrap();
+
+    let reader = ClfReader::open_with_expected_target(file.path(), "gfx942").unwrap();
+    assert_eq!(reader.header.target, "gfx942");
+
+    let err = ClfReader::open_with_expected_target(file.path(), "x86_64").unwrap_err();
+    assert_eq!(err.path(), Some(file.path()));
+    match err.root() {
+        ClfError::TargetMismatch { expected, actual } => {
+            assert_eq!(expected, "x86_64");
+            assert_eq!(actual, "gfx942");
+        }
+        other => panic!("expected TargetMismatch, got {other}"),
+    }
+    assert!(ClfReader::open_with_expected_target(file.path(), "gfx94").is_err());
+}
+
+/// An archive with an empty header target fails every expected target except the empty one,
+/// unless `empty_target_matches_any` makes it a wildcard; a set target is never one.
+#[test]
+fn reader_expected_target_empty_header_target() {
+    let pack = |target: &str| {
+        let options = PackOptions {
+            target: target.to_string(),
+            ..PackOptions::default()
+        };
+        let mut buf = Cursor::new(Vec::new());
+        pack_clf(&mut buf, &[(1, b"blob".to_vec())], &options).unwrap();
+        buf.into_inner()
+    };
+    let untargeted = pack("");
+    let expecting = |target: &str, wildcard: bool| ClfReaderOptions {
+        expected_target: Some(target.to_string()),
+        empty_target_matches_any: wildcard,
+        ..ClfReaderOptions::default()
+    };
+
+    assert!(matches!(
+        ClfReaderFromBytes::open_with_options(&untargeted, &expecting("x86_64", false)),
+        Err(ClfError::TargetMismatch { .. })
+    ));
+    assert!(ClfReaderFromBytes::open_with_options(&untargeted, &expecting("", false)).is_ok());
+    assert!(ClfReaderFromBytes::open_with_options(&untargeted, &expecting("x86_64", true)).is_ok());
+    assert!(matches!(
+        ClfReaderFromBytes::open_with_options(&pack("gfx942"), &expecting("x86_64", true)),
+        Err(ClfError::TargetMismatch { .. })
+    ));
+}
+
+/// With a `TargetMatcher`, `expected_target` and `matches_target_with` accept other spellings
+/// and families of the archive's target; plain `matches_target` stays exact.
+#[test]
+fn reader_target_matcher_accepts_families() {
+    let options = PackOptions {
+        target: "gfx90a".to_string(),
+        ..PackOptions::default()
+    };
+    let mut buf = Cursor::new(Vec::new());
+    pack_clf(&mut buf, &[(1, b"blob".to_vec())], &options).unwrap();
+    let bytes = buf.into_inner();
+    let matcher = TargetMatcher::new();
+    let expecting = |target: &str| ClfReaderOptions {
+        expected_target: Some(target.to_string()),
+        target_matcher: Some(matcher.clone()),
+        ..ClfReaderOptions::default()
+    };
+
+    let reader = ClfReaderFromBytes::open_with_options(&bytes, &expecting("CDNA2")).unwrap();
+    assert!(reader.matches_target_with(&matcher, "GFX90A"));
+    assert!(!reader.matches_target("GFX90A"));
+    assert!(!reader.matches_target_with(&matcher, "cpu"));
+    assert!(matches!(
+        ClfReaderFromBytes::open_with_options(&bytes, &expecting("cpu")),
+        Err(ClfError::TargetMismatch { .. })
+    ));
+}
+
+/// `blob_location` gives the file range of each blob: reading it straight from the file returns
+/// what `get_blob` does, and `aligned` follows the offset. Absent ops have no location.
+#[test]
+fn reader_blob_location_matches_get_blob() {
+    let entries: Vec<(u32, u16, Vec<u8>)> = vec![
+        (1, 0, b"add".to_vec()),
+        (1, 3, b"add_avx512".to_vec()),
+        (50, 0, b"matmul kernel".to_vec()),
+    ];
+    for blob_alignment in [0, 16] {
+        let options = PackOptions {
+            blob_alignment,
+            vendor: "v".to_string(),
+            ..PackOptions::default()
+        };
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        clf::pack_clf_variants(&mut file, &entries, &options).unwrap();
+        file.flush().unwrap();
+        let file_bytes = std::fs::read(file.path()).unwrap();
+
+        let mut reader = ClfReader::open(file.path()).unwrap();
+        for (op_id, variant, blob) in &entries {
+            let location = reader.blob_location_variant(*op_id, *variant).unwrap();
+            let start = location.file_offset as usize;
+            let range = &file_bytes[start..start + location.size as usize];
+            assert_eq!(range, blob.as_slice());
+            assert_eq!(
+                reader.get_blob_variant(*op_id, *variant).unwrap().unwrap(),
+                range
+            );
+            let align = u64::from(blob_alignment.max(1));
+            assert_eq!(location.aligned, location.file_offset % align == 0);
+        }
+        assert_eq!(reader.blob_location(1), reader.blob_location_variant(1, 0));
+        assert_eq!(reader.blob_location(2), None);
+        let from_bytes = ClfReaderFromBytes::open(&file_bytes, None).unwrap();
+        assert_eq!(from_bytes.blob_location(50), reader.blob_location(50));
+    }
+}
+
+/// Compressed blobs have no usable file location: their stored bytes are a zstd frame.
+#[cfg(feature = "compression")]
+#[test]
+fn reader_blob_location_skips_encoded_blobs() {
+    let options = PackOptions {
+        compression: Some(clf::Compression::Zstd(3)),
+        ..PackOptions::default()
+    };
+    let mut buf = Cursor::new(Vec::new());
+    let entries = [(1, b"nop; ".repeat(100)), (2, b"x".to_vec())];
+    pack_clf(&mut buf, &entries, &options).unwrap();
+    let reader = ClfReaderFromBytes::open(buf.get_ref(), None).unwrap();
+    assert_eq!(reader.blob_location(1), None);
+    assert!(reader.blob_location(2).is_some());
+}
+
+/// `get_blobs` answers positionally, out of file order, with `None` for absent ops and a full
+/// copy for each repeat, matching `get_blob` (compressed blobs too, when built with zstd); a
+/// corrupted blob fails the whole batch.
+#[test]
+fn reader_get_blobs_matches_get_blob() {
+    let entries: Vec<(u32, Vec<u8>)> = (1..=6)
+        .map(|op_id| {
+            (
+                op_id,
+                format!("kernel {op_id} ")
+                    .repeat(op_id as usize * 20)
+                    .into_bytes(),
+            )
+        })
+        .collect();
+    let layouts = [
+        PackOptions {
+            blob_alignment: 16,
+            per_blob_crc: true,
+            ..PackOptions::default()
+        },
+        #[cfg(feature = "compression")]
+        PackOptions {
+            compression: Some(clf::Compression::Zstd(3)),
+            per_blob_crc: true,
+            ..PackOptions::default()
+        },
+    ];
+    let request = [5, 1, 99, 5, 3, 1, 5];
+    for options in &layouts {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        pack_clf(&mut file, &entries, options).unwrap();
+        file.flush().unwrap();
+        let mut reader = ClfReader::open(file.path()).unwrap();
+
+        let blobs = reader.get_blobs(&request).unwrap();
+        assert_eq!(blobs.len(), request.len());
+        for (&op_id, blob) in request.iter().zip(&blobs) {
+            assert_eq!(blob, &reader.get_blob(op_id).unwrap(), "op_id {op_id}");
+        }
+        assert_eq!(blobs[2], None);
+        assert!(reader.get_blobs(&[]).unwrap().is_empty());
+        let bytes = std::fs::read(file.path()).unwrap();
+        let from_bytes = ClfReaderFromBytes::open(&bytes, None).unwrap();
+        assert_eq!(from_bytes.get_blobs(&request).unwrap(), blobs);
+    }
+
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    pack_clf(&mut file, &entries, &layouts[0]).unwrap();
+    file.flush().unwrap();
+    let mut bytes = std::fs::read(file.path()).unwrap();
+    let last = bytes.len() - 20;
+    bytes[last] ^= 0xFF;
+    std::fs::write(file.path(), &bytes).unwrap();
+    let mut reader = ClfReader::open(file.path()).unwrap();
+    let err = reader.get_blobs(&[1, 6]).unwrap_err();
+    assert!(
+        matches!(err.root(), ClfError::BlobChecksumMismatch { op_id: 6, .. }),
+        "{err}"
+    );
+}
+
 /// v1 file: kind defaults to Compute.
 #[test]
 fn reader_v1_kind_defaults_to_compute() {
@@ -194,6 +465,7 @@ fn reader_v1_kind_defaults_to_compute() {
         kind: ClfKind::Compute,
         version: 1,
         sign: false,
+        ..PackOptions::default()
     };
 
     let mut buf = Cursor::new(Vec::new());
@@ -286,7 +558,13 @@ fn reader_rejects_oversized_vendor_length() {
 
     let err = ClfReader::open(file.path()).unwrap_err();
     assert!(
-        err.to_string().contains("vendor too large"),
+        matches!(
+            err.root(),
+            ClfError::Malformed {
+                at_offset: 5,
+                what: "vendor too large"
+            }
+        ),
         "unexpected error: {err}"
     );
 }
@@ -317,3 +595,954 @@ fn reader_rejects_manifest_count_overflow() {
         "unexpected error: {err}"
     );
 }
+
+/// `get_blob_for` / `contains` / `build_code_section_for_ops` resolve `OpType` through the registry.
+#[test]
+fn reader_get_blob_for_op_type() {
+    let entries: Vec<(u32, Vec<u8>)> = vec![(1, b"add".to_vec()), (50, b"matmul".to_vec())];
+    let options = PackOptions::default();
+    let mut buf = Cursor::new(Vec::new());
+    pack_clf(&mut buf, &entries, &options).unwrap();
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(&buf.into_inner()).unwrap();
+    file.flush().unwrap();
+
+    let mut reader = ClfReader::open(file.path()).unwrap();
+    assert!(reader.contains(OpType::Add));
+    assert!(!reader.contains(OpType::Relu));
+    assert!(!reader.contains(OpType::Unknown));
+    assert_eq!(
+        reader.get_blob_for(OpType::MatMul).unwrap().unwrap(),
+        b"matmul"
+    );
+    assert!(reader.get_blob_for(OpType::Relu).unwrap().is_none());
+
+    let err = reader.get_blob_for(OpType::Unknown).unwrap_err();
+    assert!(matches!(err, ClfError::UnregisteredOpType(OpType::Unknown)));
+
+    let code = reader
+        .build_code_section_for_ops(&[OpType::MatMul, OpType::Add], MissingOpIdPolicy::Fail)
+        .unwrap();
+    assert_eq!(code, b"matmuladd");
+    let err = reader
+        .build_code_section_for_ops(&[OpType::Add, OpType::Unknown], MissingOpIdPolicy::Skip)
+        .unwrap_err();
+    assert!(matches!(err, ClfError::UnregisteredOpType(_)));
+}
+
+/// `coverage` splits required ops into present / missing without reading blobs.
+#[test]
+fn reader_coverage_report() {
+    let entries: Vec<(u32, Vec<u8>)> = vec![(1, b"add".to_vec()), (50, b"matmul".to_vec())];
+    let mut buf = Cursor::new(Vec::new());
+    pack_clf(&mut buf, &entries, &PackOptions::default()).unwrap();
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(&buf.into_inner()).unwrap();
+    file.flush().unwrap();
+
+    let reader = ClfReader::open(file.path()).unwrap();
+    let report = reader.coverage(&[50, 10, 1, 50, 99]);
+    assert_eq!(report.present, vec![50, 1]);
+    assert_eq!(report.missing, vec![10, 99]);
+    assert!((report.fraction_covered - 0.5).abs() < f64::EPSILON);
+    assert_eq!(report.present_bytes, 9);
+    assert!(!report.is_complete());
+
+    let full = reader.coverage(&[1, 50]);
+    assert!(full.is_complete());
+    assert!((full.fraction_covered - 1.0).abs() < f64::EPSILON);
+    assert!(reader.coverage(&[]).is_complete());
+
+    // Compressed blobs count at their decoded size.
+    #[cfg(feature = "compression")]
+    {
+        let kernel = b"mov r0, r1; ".repeat(100);
+        let options = PackOptions {
+            compression: Some(clf::Compression::Zstd(3)),
+            ..PackOptions::default()
+        };
+        let mut buf = Cursor::new(Vec::new());
+        pack_clf(&mut buf, &[(1, kernel.clone())], &options).unwrap();
+        let reader = ClfReaderFromBytes::open(buf.get_ref(), None).unwrap();
+        assert!(reader.manifest_entries()[0].exact_size < kernel.len() as u32);
+        assert_eq!(reader.coverage(&[1]).present_bytes, kernel.len() as u64);
+    }
+}
+
+/// `try_clone` yields an independent handle: interleaved and cross-thread reads stay correct.
+#[test]
+fn reader_try_clone_independent_reads() {
+    let entries: Vec<(u32, Vec<u8>)> = (0..32u32)
+        .map(|i| (i, vec![i as u8; 100 + i as usize]))
+        .collect();
+    let mut buf = Cursor::new(Vec::new());
+    pack_clf(&mut buf, &entries, &PackOptions::default()).unwrap();
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(&buf.into_inner()).unwrap();
+    file.flush().unwrap();
+
+    let mut reader = ClfReader::open(file.path()).unwrap();
+    let mut clone = reader.try_clone().unwrap();
+    assert_eq!(clone.header.vendor, reader.header.vendor);
+    assert_eq!(clone.op_ids(), reader.op_ids());
+    assert_eq!(clone.signature_verified(), reader.signature_verified());
+
+    let worker = std::thread::spawn(move || {
+        for _ in 0..50 {
+            for i in (0..32u32).rev() {
+                assert_eq!(
+                    clone.get_blob(i).unwrap().unwrap(),
+                    vec![i as u8; 100 + i as usize]
+                );
+            }
+        }
+    });
+    for _ in 0..50 {
+        for i in 0..32u32 {
+            assert_eq!(
+                reader.get_blob(i).unwrap().unwrap(),
+                vec![i as u8; 100 + i as usize]
+            );
+        }
+    }
+    worker.join().unwrap();
+}
+
+/// Errors from opening a file, and from reading it after open, name the file: `WithPath`
+/// displays as "<path>: <cause>" and `root` gives the cause.
+#[test]
+fn reader_errors_carry_the_path() {
+    let dir = tempfile::tempdir().unwrap();
+    let bad = dir.path().join("candidate-3.clfc");
+    std::fs::write(&bad, b"NOPE0000").unwrap();
+    let err = ClfReader::open(&bad).unwrap_err();
+    assert_eq!(err.path(), Some(bad.as_path()));
+    assert!(matches!(err.root(), ClfError::InvalidMagic), "{err}");
+    assert_eq!(
+        err.to_string(),
+        format!("{}: invalid magic: expected CLF1", bad.display())
+    );
+
+    let missing = dir.path().join("absent.clf");
+    let err = ClfReader::open(&missing).unwrap_err();
+    assert!(matches!(err.root(), ClfError::Io(_)), "{err}");
+    assert!(err.to_string().starts_with(&missing.display().to_string()));
+
+    let good = dir.path().join("good.clf");
+    let entries: Vec<(u32, Vec<u8>)> = vec![(1, b"blob_for_add".to_vec())];
+    pack_clf(
+        &mut std::fs::File::create(&good).unwrap(),
+        &entries,
+        &PackOptions::default(),
+    )
+    .unwrap();
+    let mut reader = ClfReader::open(&good).unwrap();
+    assert_eq!(reader.path(), good.as_path());
+    let len = std::fs::metadata(&good).unwrap().len();
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(&good)
+        .unwrap()
+        .set_len(len - 4)
+        .unwrap();
+    let err = reader.get_blob(1).unwrap_err();
+    assert_eq!(err.path(), Some(good.as_path()));
+    assert!(matches!(err.root(), ClfError::Io(_)), "{err}");
+}
+
+/// Kind byte 3 is Executor; 255 is rejected unless `lenient_kind` maps it to Compute.
+#[test]
+fn reader_kind_byte_strict_and_lenient() {
+    let header_with_kind = |kind: u8| {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&clf::CLF_MAGIC);
+        bytes.push(2); // version
+        bytes.extend_from_slice(&0u32.to_le_bytes()); // vendor len
+        bytes.extend_from_slice(&0u32.to_le_bytes()); // target len
+        bytes.push(0); // alignment
+        bytes.push(kind);
+        bytes.extend_from_slice(&0u32.to_le_bytes()); // num_entries
+        bytes
+    };
+
+    let executor = ClfReaderFromBytes::open(&header_with_kind(3), None).unwrap();
+    assert_eq!(executor.header.kind, ClfKind::Executor);
+
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(&header_with_kind(255)).unwrap();
+    file.flush().unwrap();
+    let err = ClfReader::open(file.path()).unwrap_err();
+    assert!(
+        matches!(err.root(), ClfError::InvalidKindByte(255)),
+        "{err}"
+    );
+    assert!(err.to_string().contains("lenient_kind"), "{err}");
+
+    let lenient = ClfReaderOptions {
+        lenient_kind: true,
+        ..ClfReaderOptions::default()
+    };
+    let reader = ClfReader::open_with_options(file.path(), &lenient).unwrap();
+    assert_eq!(reader.header.kind, ClfKind::Compute);
+    let from_bytes =
+        ClfReaderFromBytes::open_with_options(&header_with_kind(255), &lenient).unwrap();
+    assert_eq!(from_bytes.header.kind, ClfKind::Compute);
+
+    // Strictness still applies to the expected kind check.
+    let expect_mm = ClfReaderOptions {
+        expected_kind: Some(ClfKind::MemoryMovement),
+        lenient_kind: true,
+        ..ClfReaderOptions::default()
+    };
+    let err = ClfReader::open_with_options(file.path(), &expect_mm).unwrap_err();
+    assert!(matches!(err.root(), ClfError::KindMismatch { .. }), "{err}");
+}
+
+/// `check_extension` rejects a path whose extension names another kind; legacy `.clf` counts
+/// as Compute, and the check is off by default.
+#[test]
+fn reader_check_extension_matches_header_kind() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut bytes = Vec::new();
+    let options = PackOptions {
+        kind: ClfKind::MemoryMovement,
+        ..PackOptions::default()
+    };
+    pack_clf(
+        &mut Cursor::new(&mut bytes),
+        &[(1, b"mm".to_vec())],
+        &options,
+    )
+    .unwrap();
+    let checked = ClfReaderOptions {
+        check_extension: true,
+        ..ClfReaderOptions::default()
+    };
+
+    let good = dir.path().join("dma.CLFMM");
+    std::fs::write(&good, &bytes).unwrap();
+    ClfReader::open_with_options(&good, &checked).unwrap();
+
+    for name in ["dma.clfc", "dma.clf", "dma"] {
+        let bad = dir.path().join(name);
+        std::fs::write(&bad, &bytes).unwrap();
+        ClfReader::open(&bad).unwrap();
+        let err = ClfReader::open_with_options(&bad, &checked).unwrap_err();
+        assert!(
+            matches!(
+                err.root(),
+                ClfError::ExtensionMismatch {
+                    kind: ClfKind::MemoryMovement,
+                    ..
+                }
+            ),
+            "{name}: {err}"
+        );
+    }
+    assert!(ClfKind::Compute.matches_path(std::path::Path::new("legacy.clf")));
+}
+
+/// Progress callback fires once per op with running byte counts; Skip/Fail unchanged.
+#[test]
+fn reader_build_code_section_with_progress() {
+    let entries: Vec<(u32, Vec<u8>)> = vec![(1, b"aa".to_vec()), (2, b"bbb".to_vec())];
+    let mut buf = Cursor::new(Vec::new());
+    pack_clf(&mut buf, &entries, &PackOptions::default()).unwrap();
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(&buf.into_inner()).unwrap();
+    file.flush().unwrap();
+
+    let mut reader = ClfReader::open(file.path()).unwrap();
+    let mut events = Vec::new();
+    let code = reader
+        .build_code_section_with_progress(&[1, 99, 2], MissingOpIdPolicy::Skip, |e| events.push(e))
+        .unwrap();
+    assert_eq!(code, b"aabbb");
+    let seen: Vec<_> = events
+        .iter()
+        .map(|e| (e.ops_completed, e.ops_total, e.bytes_written))
+        .collect();
+    assert_eq!(seen, vec![(1, 3, 2), (2, 3, 2), (3, 3, 5)]);
+
+    let mut calls = 0;
+    let err = reader
+        .build_code_section_with_progress(&[1, 99, 2], MissingOpIdPolicy::Fail, |_| calls += 1)
+        .unwrap_err();
+    assert!(matches!(err, ClfError::MissingOpId(99)));
+    assert_eq!(calls, 1);
+}
+
+/// `raw_header` / `raw_manifest` return the exact serialized regions; blob reads still work.
+#[test]
+fn reader_raw_header_and_manifest() {
+    let entries: Vec<(u32, Vec<u8>)> = vec![(1, b"add".to_vec()), (50, b"matmul".to_vec())];
+    let options = PackOptions {
+        vendor: "acme".to_string(),
+        target: "CPU".to_string(),
+        ..PackOptions::default()
+    };
+    let mut buf = Cursor::new(Vec::new());
+    pack_clf(&mut buf, &entries, &options).unwrap();
+    let bytes = buf.into_inner();
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(&bytes).unwrap();
+    file.flush().unwrap();
+
+    let mut reader = ClfReader::open(file.path()).unwrap();
+    let header_len = reader.header_len() as usize;
+    // magic, version, vendor, target, alignment, kind, empty v3 metadata, build-info,
+    // name-table and extension-area lengths.
+    assert_eq!(header_len, 4 + 1 + 4 + 4 + 4 + 3 + 1 + 1 + 4 + 4 + 4 + 4);
+    assert_eq!(reader.raw_header().unwrap(), &bytes[..header_len]);
+    let manifest = reader.raw_manifest().unwrap();
+    assert_eq!(
+        manifest,
+        &bytes[header_len..reader.blob_store_offset() as usize]
+    );
+    assert_eq!(&manifest[..4], &2u32.to_le_bytes());
+    assert_eq!(reader.get_blob(50).unwrap().unwrap(), b"matmul");
+}
+
+/// validate() accepts a well-formed manifest and rejects partial overlaps and out-of-bounds entries.
+#[test]
+fn reader_validate_overlap_and_bounds() {
+    let entries: Vec<(u32, Vec<u8>)> = vec![(1, vec![1; 8]), (2, vec![2; 8])];
+    let mut cursor = Cursor::new(Vec::new());
+    pack_clf(&mut cursor, &entries, &PackOptions::default()).unwrap();
+    let bytes = cursor.into_inner();
+    let reader = ClfReaderFromBytes::open(&bytes, None).unwrap();
+    reader.validate().unwrap();
+
+    // Second manifest entry (file order) starts 16 bytes before the blob store; its offset follows the op_id.
+    let second_offset_at = reader.blob_store_offset() as usize - 16 + 4;
+
+    let mut overlapping = bytes.clone();
+    overlapping[second_offset_at..second_offset_at + 4].copy_from_slice(&4u32.to_le_bytes());
+    let err = ClfReaderFromBytes::open(&overlapping, None)
+        .unwrap()
+        .validate()
+        .unwrap_err();
+    assert!(
+        matches!(
+            err,
+            ClfError::OverlappingBlobs {
+                first: 1,
+                second: 2
+            }
+        ),
+        "{err}"
+    );
+
+    let mut out_of_bounds = bytes;
+    out_of_bounds[second_offset_at..second_offset_at + 4].copy_from_slice(&12u32.to_le_bytes());
+    let err = ClfReaderFromBytes::open(&out_of_bounds, None)
+        .unwrap()
+        .validate()
+        .unwrap_err();
+    assert!(
+        matches!(err, ClfError::BlobOutOfBounds { op_id: 2 }),
+        "{err}"
+    );
+}
+
+/// A manifest listing the same op_id twice is rejected at open rather than shadowing an entry.
+#[test]
+fn reader_rejects_duplicate_manifest_entries() {
+    let entries: Vec<(u32, Vec<u8>)> = vec![(1, vec![1; 8]), (2, vec![2; 8])];
+    let mut cursor = Cursor::new(Vec::new());
+    pack_clf(&mut cursor, &entries, &PackOptions::default()).unwrap();
+    let mut bytes = cursor.into_inner();
+    let second_op_id_at = ClfReaderFromBytes::open(&bytes, None)
+        .unwrap()
+        .blob_store_offset() as usize
+        - 16;
+    bytes[second_op_id_at..second_op_id_at + 4].copy_from_slice(&1u32.to_le_bytes());
+    let err = ClfReaderFromBytes::open(&bytes, None).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            ClfError::DuplicateEntry {
+                op_id: 1,
+                variant: 0
+            }
+        ),
+        "{err}"
+    );
+}
+
+/// Duplicates are caught after sorting even when the two entries are not adjacent in the file.
+#[test]
+fn reader_rejects_non_adjacent_duplicate_entries() {
+    let entries: Vec<(u32, Vec<u8>)> = vec![(5, vec![5; 8]), (6, vec![6; 8]), (7, vec![7; 8])];
+    let mut cursor = Cursor::new(Vec::new());
+    pack_clf(&mut cursor, &entries, &PackOptions::default()).unwrap();
+    let mut bytes = cursor.into_inner();
+    let third_op_id_at = ClfReaderFromBytes::open(&bytes, None)
+        .unwrap()
+        .blob_store_offset() as usize
+        - 16;
+    bytes[third_op_id_at..third_op_id_at + 4].copy_from_slice(&5u32.to_le_bytes());
+    let err = ClfReaderFromBytes::open(&bytes, None).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            ClfError::DuplicateEntry {
+                op_id: 5,
+                variant: 0
+            }
+        ),
+        "{err}"
+    );
+}
+
+/// 100k entries packed in scrambled op_id order: open, sorted op_ids, and pseudo-random hits
+/// and misses all resolve against the sorted manifest.
+#[test]
+fn reader_large_manifest_lookups() {
+    const COUNT: u32 = 100_000;
+    // 7919 is coprime to COUNT, so this visits every op_id in 1..=COUNT exactly once.
+    let entries: Vec<(u32, Vec<u8>)> = (0..COUNT)
+        .map(|i| {
+            let op_id = (i * 7919) % COUNT + 1;
+            (op_id, op_id.to_le_bytes().to_vec())
+        })
+        .collect();
+    let mut buf = Cursor::new(Vec::new());
+    pack_clf(&mut buf, &entries, &PackOptions::default()).unwrap();
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(buf.get_ref()).unwrap();
+    file.flush().unwrap();
+    let mut reader = ClfReader::open(file.path()).unwrap();
+
+    assert_eq!(reader.op_ids(), (1..=COUNT).collect::<Vec<u32>>());
+    let mut state = 0x2545_f491u32;
+    for _ in 0..10_000 {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let op_id = state % (COUNT + 1000);
+        let blob = reader.get_blob(op_id).unwrap();
+        if (1..=COUNT).contains(&op_id) {
+            assert_eq!(blob, Some(op_id.to_le_bytes().to_vec()), "op_id {op_id}");
+        } else {
+            assert_eq!(blob, None, "op_id {op_id}");
+        }
+    }
+}
+
+/// Hand-built v3 file with the compression entry fields and one flagged entry.
+fn v3_with_entry_flags(flags: u32) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"CLF1");
+    bytes.push(3);
+    bytes.extend_from_slice(&0u32.to_le_bytes()); // vendor
+    bytes.extend_from_slice(&0u32.to_le_bytes()); // target
+    bytes.extend_from_slice(&[0, 0]); // alignment, kind
+    bytes.extend_from_slice(&0u32.to_le_bytes()); // metadata section length
+    bytes.extend_from_slice(&0u32.to_le_bytes()); // build-info section length
+    bytes.extend_from_slice(&0u32.to_le_bytes()); // name table length
+    bytes.extend_from_slice(&0u32.to_le_bytes()); // extension area length
+    bytes.extend_from_slice(&1u32.to_le_bytes()); // num_entries
+    bytes.extend_from_slice(&1u32.to_le_bytes()); // entry_fields: compression
+    for field in [7u32, 0, 4, 4, flags, 10] {
+        bytes.extend_from_slice(&field.to_le_bytes());
+    }
+    bytes.extend_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]);
+    bytes
+}
+
+/// Encoded entries never come back as raw stored bytes: unknown flags (or zstd without the
+/// feature) fail with CompressionUnsupported; a bad zstd frame fails to decompress.
+#[test]
+fn reader_rejects_undecodable_entries() {
+    let reader = ClfReaderFromBytes::open(&v3_with_entry_flags(4), None).unwrap();
+    assert!(matches!(
+        reader.get_blob(7),
+        Err(ClfError::CompressionUnsupported { op_id: 7, flags: 4 })
+    ));
+
+    let reader = ClfReaderFromBytes::open(&v3_with_entry_flags(1), None).unwrap();
+    let err = reader.get_blob(7).unwrap_err();
+    if cfg!(feature = "compression") {
+        assert!(
+            matches!(err, ClfError::Decompress { op_id: 7, .. }),
+            "{err}"
+        );
+    } else {
+        assert!(
+            matches!(err, ClfError::CompressionUnsupported { op_id: 7, .. }),
+            "{err}"
+        );
+    }
+    // Stored bytes stay reachable for tooling.
+    assert_eq!(
+        reader.get_blob_padded(7).unwrap().unwrap(),
+        vec![0xDE, 0xAD, 0xBE, 0xEF]
+    );
+
+    let mut unknown_fields = v3_with_entry_flags(0);
+    unknown_fields[35..39].copy_from_slice(&0x8000_0001u32.to_le_bytes());
+    assert!(matches!(
+        ClfReaderFromBytes::open(&unknown_fields, None),
+        Err(ClfError::UnsupportedEntryFields(0x8000_0001))
+    ));
+}
+
+/// Per-blob CRC-32: flipping one stored byte fails only the affected op; files without
+/// checksums read unchanged.
+#[test]
+fn reader_per_blob_crc_detects_corruption() {
+    let entries: Vec<(u32, Vec<u8>)> = vec![(1, vec![0x11; 32]), (2, vec![0x22; 32])];
+    let options = PackOptions {
+        per_blob_crc: true,
+        ..PackOptions::default()
+    };
+    let mut cursor = Cursor::new(Vec::new());
+    pack_clf(&mut cursor, &entries, &options).unwrap();
+    let mut bytes = cursor.into_inner();
+
+    let reader = ClfReaderFromBytes::open(&bytes, None).unwrap();
+    let manifest = reader.manifest_entries();
+    assert_eq!(manifest[0].crc32, Some(crc32_of(&entries[0].1)));
+    let victim = reader.blob_store_offset() as usize + manifest[1].offset as usize + 5;
+    bytes[victim] ^= 0x01;
+
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(&bytes).unwrap();
+    file.flush().unwrap();
+    let mut reader = ClfReader::open(file.path()).unwrap();
+    assert_eq!(reader.get_blob(1).unwrap().unwrap(), entries[0].1);
+    let err = reader.get_blob(2).unwrap_err();
+    assert!(
+        matches!(err, ClfError::BlobChecksumMismatch { op_id: 2, expected, actual } if expected != actual),
+        "{err}"
+    );
+
+    let mut plain = Cursor::new(Vec::new());
+    pack_clf(&mut plain, &entries, &PackOptions::default()).unwrap();
+    let reader = ClfReaderFromBytes::open(plain.get_ref(), None).unwrap();
+    assert!(reader.manifest_entries().iter().all(|e| e.crc32.is_none()));
+    assert_eq!(reader.get_blob(2).unwrap().unwrap(), entries[1].1);
+}
+
+/// Reference CRC-32 (IEEE, reflected) so the test does not trust the implementation under test.
+fn crc32_of(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// With `serde`, header, manifest entries and reports round-trip through JSON; kinds are
+/// kebab-case strings and unknown kind strings are rejected.
+#[cfg(feature = "serde")]
+#[test]
+fn reader_types_round_trip_through_json() {
+    let options = PackOptions {
+        vendor: "acme".to_string(),
+        kind: ClfKind::MemoryMovement,
+        ..PackOptions::default()
+    };
+    let mut bytes = Vec::new();
+    pack_clf(
+        &mut Cursor::new(&mut bytes),
+        &[(1, b"mm".to_vec())],
+        &options,
+    )
+    .unwrap();
+    let reader = ClfReaderFromBytes::open(&bytes, None).unwrap();
+
+    let header = serde_json::to_value(&reader.header).unwrap();
+    assert_eq!(header["kind"], "memory-movement");
+    assert!(header.get("header_end").is_none());
+    let parsed: clf::ClfHeader = serde_json::from_value(header.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&parsed).unwrap(), header);
+
+    let entries = reader.manifest_entries();
+    let json = serde_json::to_string(&entries).unwrap();
+    let parsed: Vec<clf::ManifestEntry> = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, entries);
+
+    let coverage = reader.coverage(&[1, 2]);
+    let json = serde_json::to_string(&coverage).unwrap();
+    assert_eq!(
+        serde_json::from_str::<clf::CoverageReport>(&json).unwrap(),
+        coverage
+    );
+
+    assert_eq!(
+        serde_json::to_string(&ClfKind::Compute).unwrap(),
+        "\"compute\""
+    );
+    assert!(serde_json::from_str::<ClfKind>("\"vector\"").is_err());
+    assert!(serde_json::from_str::<ClfKind>("1").is_err());
+}
+
+/// The blob store length comes from the manifest at open; the file-side length (read from the
+/// end of the file on demand) is what `validate` compares it against.
+#[test]
+fn declared_and_file_blob_store_len() {
+    let entries: Vec<(u32, Vec<u8>)> = vec![(1, vec![0xAA; 40]), (2, vec![0xBB; 24])];
+    let options = PackOptions {
+        blob_alignment: 16,
+        ..PackOptions::default()
+    };
+    let mut buf = Cursor::new(Vec::new());
+    let data_len = pack_clf(&mut buf, &entries, &options).unwrap();
+    clf::append_signature(&mut buf, data_len).unwrap();
+    let signed = buf.into_inner();
+
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(&signed).unwrap();
+    file.flush().unwrap();
+    let reader = ClfReader::open(file.path()).unwrap();
+    let last = reader.manifest_entries()[1];
+    let declared = reader.declared_blob_store_len();
+    assert_eq!(declared, u64::from(last.offset + last.size));
+    assert_eq!(reader.blob_store_len(), declared);
+    assert_eq!(reader.file_blob_store_len(), declared);
+    assert!(reader.signature_block_present());
+    reader.validate().unwrap();
+
+    // Cut the signature and the end of the last blob: the manifest declares the same length.
+    let cut = u64::from(last.offset) + 10;
+    let truncated = &signed[..(reader.blob_store_offset() + cut) as usize];
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(truncated).unwrap();
+    file.flush().unwrap();
+    let mut reader = ClfReader::open(file.path()).unwrap();
+    assert_eq!(reader.declared_blob_store_len(), declared);
+    assert_eq!(reader.file_blob_store_len(), cut);
+    assert!(!reader.signature_block_present());
+    assert!(reader.validate().is_err());
+    assert_eq!(reader.get_blob(1).unwrap().unwrap(), vec![0xAA; 40]);
+    assert!(reader.get_blob(2).is_err());
+
+    let reader = ClfReaderFromBytes::open(truncated, None).unwrap();
+    assert_eq!(
+        (
+            reader.declared_blob_store_len(),
+            reader.file_blob_store_len()
+        ),
+        (declared, cut)
+    );
+}
+
+/// The code section is allocated once at the size the manifest predicts, padding excluded and
+/// skipped ops contributing nothing.
+#[test]
+fn build_code_section_preallocates_exact_size() {
+    let entries: Vec<(u32, Vec<u8>)> = vec![(1, vec![1; 5]), (2, vec![2; 33]), (3, vec![3; 16])];
+    let options = PackOptions {
+        blob_alignment: 16,
+        ..PackOptions::default()
+    };
+    let mut buf = Cursor::new(Vec::new());
+    pack_clf(&mut buf, &entries, &options).unwrap();
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(&buf.into_inner()).unwrap();
+    file.flush().unwrap();
+    let mut reader = ClfReader::open(file.path()).unwrap();
+
+    let op_ids = [3, 99, 1, 2];
+    let plan = reader
+        .plan_code_section(&op_ids, MissingOpIdPolicy::Skip)
+        .unwrap();
+    assert_eq!(plan.total_len, 54);
+    let planned: Vec<_> = plan.ops.iter().map(|(id, e)| (*id, e.is_some())).collect();
+    assert_eq!(planned, [(3, true), (99, false), (1, true), (2, true)]);
+    assert!(matches!(
+        reader.plan_code_section(&op_ids, MissingOpIdPolicy::Fail),
+        Err(ClfError::MissingOpId(99))
+    ));
+
+    let code = reader
+        .build_code_section(&op_ids, MissingOpIdPolicy::Skip)
+        .unwrap();
+    assert_eq!(code.len() as u64, plan.total_len);
+    assert_eq!(code.capacity(), code.len());
+    assert_eq!(code, [vec![3; 16], vec![1; 5], vec![2; 33]].concat());
+}
+
+/// Reading in file order (with merged reads) builds the same section as reading in the
+/// requested order, for a shuffled op list with repeats, aliases, CRCs and a skipped op.
+#[test]
+fn build_code_section_file_order_matches_requested() {
+    let entries: Vec<(u32, Vec<u8>)> = (1..=40u32)
+        .map(|op_id| {
+            // Ops 21..=40 repeat 1..=20, so dedup aliases them.
+            let k = op_id % 20;
+            let len = if k % 10 == 0 {
+                300_000
+            } else {
+                1 + k as usize * 37
+            };
+            (op_id, vec![k as u8; len])
+        })
+        .collect();
+    let options = PackOptions {
+        blob_alignment: 16,
+        per_blob_crc: true,
+        dedup_blobs: true,
+        ..PackOptions::default()
+    };
+    let mut buf = Cursor::new(Vec::new());
+    pack_clf(&mut buf, &entries, &options).unwrap();
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(buf.get_ref()).unwrap();
+    file.flush().unwrap();
+    let mut reader = ClfReader::open(file.path()).unwrap();
+
+    // A fixed shuffle: stride through the ops backwards, with repeats and an absent op.
+    let mut op_ids: Vec<u32> = (0..40u32).map(|i| 40 - (i * 17) % 40).collect();
+    op_ids.extend([40, 1, 999, 30, 30]);
+    let expected: Vec<u8> = op_ids
+        .iter()
+        .filter_map(|&op_id| reader.get_blob(op_id).unwrap())
+        .flatten()
+        .collect();
+    for order in [ReadOrder::Requested, ReadOrder::FileOrder, ReadOrder::Auto] {
+        let code = reader
+            .build_code_section_with_order(&op_ids, MissingOpIdPolicy::Skip, order)
+            .unwrap();
+        assert!(code == expected, "{order:?}");
+    }
+    assert!(matches!(
+        reader.build_code_section_with_order(
+            &op_ids,
+            MissingOpIdPolicy::Fail,
+            ReadOrder::FileOrder
+        ),
+        Err(ClfError::MissingOpId(999))
+    ));
+}
+
+/// Open an archive holding op_ids 1..=4, each blob its op_id repeated op_id times.
+fn open_four_ops() -> (tempfile::NamedTempFile, ClfReader) {
+    let entries: Vec<(u32, Vec<u8>)> = (1..=4u8)
+        .map(|op_id| (u32::from(op_id), vec![op_id; usize::from(op_id)]))
+        .collect();
+    let mut buf = Cursor::new(Vec::new());
+    pack_clf(&mut buf, &entries, &PackOptions::default()).unwrap();
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(buf.get_ref()).unwrap();
+    file.flush().unwrap();
+    let reader = ClfReader::open(file.path()).unwrap();
+    (file, reader)
+}
+
+/// `build_code_section_report` with nothing missing matches `build_code_section` and lays the
+/// blobs out back to back, repeats included.
+#[test]
+fn build_code_section_report_none_missing() {
+    let (_file, mut reader) = open_four_ops();
+    let op_ids = [3, 1, 3];
+    let report = reader.build_code_section_report(&op_ids).unwrap();
+    assert!(report.missing.is_empty());
+    assert_eq!(
+        report.bytes,
+        reader
+            .build_code_section(&op_ids, MissingOpIdPolicy::Collect)
+            .unwrap()
+    );
+    assert_eq!(report.layout, [(3, 0..3), (1, 3..4), (3, 4..7)]);
+}
+
+/// One missing op_id: the present ops are still built, the missing one is reported, and
+/// `Collect` fails with a one-element list.
+#[test]
+fn build_code_section_report_one_missing() {
+    let (_file, mut reader) = open_four_ops();
+    let report = reader.build_code_section_report(&[2, 99, 1]).unwrap();
+    assert_eq!(report.bytes, [2, 2, 1]);
+    assert_eq!(report.missing, [99]);
+    assert_eq!(report.layout, [(2, 0..2), (1, 2..3)]);
+    assert!(matches!(
+        reader.build_code_section(&[2, 99, 1], MissingOpIdPolicy::Collect),
+        Err(ClfError::MissingOpIds(ids)) if ids == [99]
+    ));
+}
+
+/// Many missing op_ids come back in request order without duplicates, from the report, from
+/// `Collect` in every read order and from the plan, where `Fail` stops at the first.
+#[test]
+fn build_code_section_report_many_missing() {
+    let (_file, mut reader) = open_four_ops();
+    let op_ids = [7, 4, 9, 7, 1, 8, 9];
+    let report = reader.build_code_section_report(&op_ids).unwrap();
+    assert_eq!(report.bytes, [4, 4, 4, 4, 1]);
+    assert_eq!(report.missing, [7, 9, 8]);
+    for order in [ReadOrder::Requested, ReadOrder::FileOrder, ReadOrder::Auto] {
+        let err = reader
+            .build_code_section_with_order(&op_ids, MissingOpIdPolicy::Collect, order)
+            .unwrap_err();
+        assert!(matches!(&err, ClfError::MissingOpIds(ids) if ids == &[7, 9, 8]));
+        assert_eq!(
+            err.to_string(),
+            "3 op_ids missing in CLF (policy: Collect): 7, 9, 8"
+        );
+    }
+    assert!(matches!(
+        reader.plan_code_section(&op_ids, MissingOpIdPolicy::Collect),
+        Err(ClfError::MissingOpIds(ids)) if ids == [7, 9, 8]
+    ));
+    assert!(matches!(
+        reader.plan_code_section(&op_ids, MissingOpIdPolicy::Fail),
+        Err(ClfError::MissingOpId(7))
+    ));
+}
+
+/// `build_unique_code_section` emits each distinct op once, in order of first request, and maps
+/// every op_id to its copy; `build_code_section` still emits one copy per request.
+#[test]
+fn build_unique_code_section_emits_each_op_once() {
+    let entries: Vec<(u32, Vec<u8>)> = vec![(1, b"add".to_vec()), (50, b"matmul".to_vec())];
+    let mut buf = Cursor::new(Vec::new());
+    pack_clf(&mut buf, &entries, &PackOptions::default()).unwrap();
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(buf.get_ref()).unwrap();
+    file.flush().unwrap();
+    let mut reader = ClfReader::open(file.path()).unwrap();
+
+    let op_ids = [1, 50, 1, 1, 50];
+    let unique = reader
+        .build_unique_code_section(&op_ids, MissingOpIdPolicy::Fail)
+        .unwrap();
+    assert_eq!(unique.bytes, b"addmatmul");
+    assert_eq!(unique.blobs.len(), 2);
+    assert_eq!(unique.blobs[&1], 0..3);
+    assert_eq!(unique.blobs[&50], 3..9);
+    let positional = reader
+        .build_code_section(&op_ids, MissingOpIdPolicy::Fail)
+        .unwrap();
+    assert_eq!(positional, b"addmatmuladdaddmatmul");
+
+    let skipped = reader
+        .build_unique_code_section(&[7, 50, 7, 1], MissingOpIdPolicy::Skip)
+        .unwrap();
+    assert_eq!(skipped.bytes, b"matmuladd");
+    assert!(!skipped.blobs.contains_key(&7));
+    assert!(matches!(
+        reader.build_unique_code_section(&[1, 7, 50, 8], MissingOpIdPolicy::Collect),
+        Err(ClfError::MissingOpIds(ids)) if ids == [7, 8]
+    ));
+}
+
+/// `blob_reader` streams the same bytes as `get_blob`, decoding zstd blobs as it goes, and a
+/// damaged stored byte fails the read with the CRC-32 mismatch at end of stream.
+#[test]
+fn blob_reader_streams_and_checks_crc() {
+    let big: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+    let entries: Vec<(u32, Vec<u8>)> = vec![(1, b"add".to_vec()), (50, big)];
+    let layouts = [
+        PackOptions {
+            per_blob_crc: true,
+            ..PackOptions::default()
+        },
+        #[cfg(feature = "compression")]
+        PackOptions {
+            per_blob_crc: true,
+            compression: Some(clf::Compression::Zstd(3)),
+            ..PackOptions::default()
+        },
+    ];
+    for options in &layouts {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        pack_clf(&mut file, &entries, options).unwrap();
+        file.flush().unwrap();
+        let mut reader = ClfReader::open(file.path()).unwrap();
+        for (op_id, blob) in &entries {
+            let mut streamed = Vec::new();
+            let mut source = reader.blob_reader(*op_id).unwrap().unwrap();
+            std::io::Read::read_to_end(&mut source, &mut streamed).unwrap();
+            assert_eq!(&streamed, blob);
+            assert_eq!(reader.get_blob(*op_id).unwrap().as_ref(), Some(blob));
+        }
+
+        let last = reader.manifest_entries()[1];
+        let mut bytes = std::fs::read(file.path()).unwrap();
+        let at = reader.blob_store_offset() + u64::from(last.offset + last.exact_size) - 1;
+        bytes[at as usize] ^= 0x01;
+        std::fs::write(file.path(), &bytes).unwrap();
+        let reader = ClfReader::open(file.path()).unwrap();
+        let mut source = reader.blob_reader(50).unwrap().unwrap();
+        let err = std::io::Read::read_to_end(&mut source, &mut Vec::new()).unwrap_err();
+        // A damaged zstd frame may fail to decode before the CRC-32 is checked.
+        let inner = err.get_ref().and_then(|e| e.downcast_ref::<ClfError>());
+        assert!(
+            matches!(
+                inner,
+                Some(ClfError::BlobChecksumMismatch { op_id: 50, .. })
+            ) || (options.compression.is_some()
+                && matches!(inner, Some(ClfError::Decompress { op_id: 50, .. }))),
+            "{err}"
+        );
+    }
+}
+
+/// A compressed entry whose manifest claims a decoded size over `max_blob_len` (default 256
+/// MiB) fails with `LimitExceeded` in every decode path, before a buffer of that size is
+/// allocated; the limit can be lowered or raised per reader.
+#[cfg(feature = "compression")]
+#[test]
+fn reader_limits_claimed_raw_size() {
+    let blob = b"mov r0, r1; ".repeat(100);
+    let options = PackOptions {
+        compression: Some(clf::Compression::Zstd(3)),
+        ..PackOptions::default()
+    };
+    let mut buf = Cursor::new(Vec::new());
+    pack_clf(&mut buf, &[(1, blob.clone())], &options).unwrap();
+    let honest = buf.into_inner();
+    let header_end = ClfReaderFromBytes::open(&honest, None)
+        .unwrap()
+        .header
+        .header_end as usize;
+    // Entry count and field mask, then op_id, offset, size, exact_size, flags and raw_size.
+    let raw_size_at = header_end + 8 + 20;
+    assert_eq!(
+        honest[raw_size_at..raw_size_at + 4],
+        (blob.len() as u32).to_le_bytes()
+    );
+    let mut crafted = honest.clone();
+    crafted[raw_size_at..raw_size_at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+
+    let over_limit = |err: ClfError| matches!(err.root(), ClfError::LimitExceeded { value, .. } if *value == u64::from(u32::MAX));
+    let from_bytes = ClfReaderFromBytes::open(&crafted, None).unwrap();
+    assert!(over_limit(from_bytes.get_blob(1).unwrap_err()));
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(&crafted).unwrap();
+    file.flush().unwrap();
+    let mut reader = ClfReader::open(file.path()).unwrap();
+    assert!(over_limit(reader.get_blob(1).unwrap_err()));
+    assert!(over_limit(reader.get_blobs(&[1]).unwrap_err()));
+    for order in [ReadOrder::Requested, ReadOrder::FileOrder] {
+        let err = reader
+            .build_code_section_with_order(&[1], MissingOpIdPolicy::Fail, order)
+            .unwrap_err();
+        assert!(over_limit(err));
+    }
+
+    let limited = |max_blob_len: usize| {
+        let options = ClfReaderOptions {
+            max_blob_len: Some(max_blob_len as u32),
+            ..ClfReaderOptions::default()
+        };
+        ClfReaderFromBytes::open_with_options(&honest, &options)
+            .unwrap()
+            .get_blob(1)
+    };
+    assert!(matches!(
+        limited(blob.len() - 1),
+        Err(ClfError::LimitExceeded { .. })
+    ));
+    assert_eq!(limited(blob.len()).unwrap().unwrap(), blob);
+}
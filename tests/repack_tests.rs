@@ -0,0 +1,280 @@
+//! Repack tests: subset packed archives with repack and read the result back.
+
+use std::io::{Cursor, Write};
+
+use clf::{
+    convert_version_to_path, pack_clf, repack, ClfError, ClfKind, ClfReader, ClfReaderFromBytes,
+    MissingOpIdPolicy, PackError, PackOptions, RepackError, RepackOptions, CLF_VERSION,
+};
+use tempfile::NamedTempFile;
+
+/// Pack `entries` into a temp file and open it.
+fn archive(entries: &[(u32, Vec<u8>)], options: &PackOptions) -> (NamedTempFile, ClfReader) {
+    let mut file = NamedTempFile::new().unwrap();
+    pack_clf(&mut file, entries, options).unwrap();
+    file.flush().unwrap();
+    let reader = ClfReader::open(file.path()).unwrap();
+    (file, reader)
+}
+
+fn five_entries() -> Vec<(u32, Vec<u8>)> {
+    (1..=5u32)
+        .map(|id| (id, vec![id as u8; 10 * id as usize]))
+        .collect()
+}
+
+/// Subsetting a 5-entry archive to 2 keeps exactly those blobs, their names and the source
+/// header.
+#[test]
+fn repack_subset_serves_only_kept_blobs() {
+    let options = PackOptions {
+        vendor: "Acme".to_string(),
+        target: "GPU".to_string(),
+        kind: ClfKind::MemoryMovement,
+        entry_names: [(2, "add".to_string()), (3, "mul".to_string())]
+            .into_iter()
+            .collect(),
+        ..PackOptions::default()
+    };
+    let (_file, mut source) = archive(&five_entries(), &options);
+
+    let mut out = Cursor::new(Vec::new());
+    repack(&mut source, &[4, 2], &RepackOptions::default(), &mut out).unwrap();
+    let bytes = out.into_inner();
+
+    let reader = ClfReaderFromBytes::open(&bytes, None).unwrap();
+    assert_eq!(reader.header.vendor, "Acme");
+    assert_eq!(reader.header.target, "GPU");
+    assert_eq!(reader.header.kind, ClfKind::MemoryMovement);
+    assert!(!reader.signature_block_present());
+    let mut ids: Vec<u32> = reader.manifest_entries().iter().map(|e| e.op_id).collect();
+    ids.sort_unstable();
+    assert_eq!(ids, vec![2, 4]);
+    assert_eq!(reader.get_blob(2).unwrap().unwrap(), vec![2u8; 20]);
+    assert_eq!(reader.get_blob(4).unwrap().unwrap(), vec![4u8; 40]);
+    assert!(reader.get_blob(1).unwrap().is_none());
+    assert_eq!(reader.entry_name(2), Some("add"));
+    assert_eq!(reader.header.entry_names.len(), 1);
+}
+
+/// Missing op_ids fail by default (all at once with `Collect`) or are skipped; overrides and
+/// re-signing apply.
+#[test]
+fn repack_missing_policy_overrides_and_sign() {
+    let (_file, mut source) = archive(&five_entries(), &PackOptions::default());
+
+    let err = repack(
+        &mut source,
+        &[1, 9],
+        &RepackOptions::default(),
+        Cursor::new(Vec::new()),
+    )
+    .unwrap_err();
+    assert!(
+        matches!(err, RepackError::Read(ClfError::MissingOpId(9))),
+        "{err}"
+    );
+    let options = RepackOptions {
+        on_missing: MissingOpIdPolicy::Collect,
+        ..RepackOptions::default()
+    };
+    let err = repack(&mut source, &[9, 1, 8], &options, Cursor::new(Vec::new())).unwrap_err();
+    assert!(
+        matches!(&err, RepackError::Read(ClfError::MissingOpIds(ids)) if ids == &[9, 8]),
+        "{err}"
+    );
+
+    let options = RepackOptions {
+        on_missing: MissingOpIdPolicy::Skip,
+        target: Some("NPU".to_string()),
+        pack: PackOptions {
+            sign: true,
+            ..PackOptions::default()
+        },
+        ..RepackOptions::default()
+    };
+    let mut out = NamedTempFile::new().unwrap();
+    repack(&mut source, &[1, 9], &options, out.as_file_mut()).unwrap();
+    out.flush().unwrap();
+    let mut reader = ClfReader::open(out.path()).unwrap();
+    assert_eq!(reader.header.target, "NPU");
+    assert!(reader.signature_block_present());
+    assert!(reader.verify_signature().unwrap());
+    assert_eq!(reader.op_ids(), vec![1]);
+}
+
+/// Signed repacks verify when the packer drops a trailing duplicate (`dedup_blobs`) or stores
+/// an incompressible last blob raw after compressing it: nothing is left past the data.
+#[test]
+fn repack_signs_deduplicated_and_compressed_output() {
+    let kernel = vec![0x90u8; 64];
+    let (_file, mut source) = archive(
+        &[(1, kernel.clone()), (2, b"other".to_vec()), (3, kernel)],
+        &PackOptions::default(),
+    );
+    let options = RepackOptions {
+        pack: PackOptions {
+            dedup_blobs: true,
+            sign: true,
+            ..PackOptions::default()
+        },
+        ..RepackOptions::default()
+    };
+    let mut out = Cursor::new(Vec::new());
+    repack(&mut source, &[1, 2, 3], &options, &mut out).unwrap();
+    let reader = ClfReaderFromBytes::open(out.get_ref(), None).unwrap();
+    reader.validate().unwrap();
+    assert!(reader.signature_block_present());
+
+    #[cfg(feature = "compression")]
+    {
+        let mut state: u32 = 0x1234_5678;
+        let noise: Vec<u8> = (0..4096)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect();
+        let (_file, mut source) = archive(
+            &[(1, b"mov r0, r1; ".repeat(300)), (2, noise.clone())],
+            &PackOptions::default(),
+        );
+        let options = RepackOptions {
+            pack: PackOptions {
+                compression: Some(clf::Compression::Zstd(3)),
+                sign: true,
+                ..PackOptions::default()
+            },
+            ..RepackOptions::default()
+        };
+        let mut out = NamedTempFile::new().unwrap();
+        repack(&mut source, &[1, 2], &options, out.as_file_mut()).unwrap();
+        out.flush().unwrap();
+        let mut reader = ClfReader::open(out.path()).unwrap();
+        reader.validate().unwrap();
+        assert!(reader.verify_signature().unwrap());
+        assert_eq!(reader.get_blob(2).unwrap().unwrap(), noise);
+    }
+}
+
+/// Repacking keeps every variant of a kept op_id.
+#[test]
+fn repack_keeps_all_variants() {
+    let mut file = NamedTempFile::new().unwrap();
+    let entries = [
+        (1, 0, b"a0".to_vec()),
+        (1, 3, b"a3".to_vec()),
+        (2, 0, b"b0".to_vec()),
+    ];
+    clf::pack_clf_variants(&mut file, &entries, &PackOptions::default()).unwrap();
+    file.flush().unwrap();
+    let mut source = ClfReader::open(file.path()).unwrap();
+
+    let mut out = Cursor::new(Vec::new());
+    repack(&mut source, &[1], &RepackOptions::default(), &mut out).unwrap();
+    let reader = ClfReaderFromBytes::open(out.get_ref(), None).unwrap();
+    assert_eq!(reader.variants(1), [0, 3]);
+    assert_eq!(reader.get_blob_variant(1, 3).unwrap().unwrap(), b"a3");
+    assert!(reader.variants(2).is_empty());
+}
+
+/// A v1 archive converts in place to the newest version with an explicit kind and a signature,
+/// keeping the vendor and every blob byte for byte.
+#[test]
+fn convert_version_upgrades_v1_in_place() {
+    let v1 = PackOptions {
+        vendor: "Acme".to_string(),
+        version: 1,
+        blob_alignment: 4,
+        ..PackOptions::default()
+    };
+    let (file, mut source) = archive(&five_entries(), &v1);
+    let before: Vec<_> = source
+        .op_ids()
+        .into_iter()
+        .map(|op_id| source.get_blob(op_id).unwrap().unwrap())
+        .collect();
+
+    let options = RepackOptions {
+        kind: Some(ClfKind::MemoryMovement),
+        pack: PackOptions {
+            blob_alignment: 4,
+            sign: true,
+            ..PackOptions::default()
+        },
+        ..RepackOptions::default()
+    };
+    let summary = convert_version_to_path(&mut source, &options, file.path()).unwrap();
+    assert_eq!(summary.entries.len(), 5);
+
+    let mut reader = ClfReader::open(file.path()).unwrap();
+    assert_eq!(reader.header.version, CLF_VERSION);
+    assert_eq!(reader.header.kind, ClfKind::MemoryMovement);
+    assert_eq!(reader.header.vendor, "Acme");
+    assert!(reader.verify_signature().unwrap());
+    for (op_id, blob) in reader.op_ids().into_iter().zip(before) {
+        assert_eq!(reader.get_blob(op_id).unwrap().unwrap(), blob);
+    }
+}
+
+/// Converting to an older version fails, without writing the output, when that version cannot
+/// express the kind, the exact blob sizes or the header sections the archive needs.
+#[test]
+fn convert_version_refuses_lossy_downgrades() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("old.clf");
+    let downgrade = |options: &PackOptions, version: u8, align: u8| {
+        let (_file, mut source) = archive(&five_entries(), options);
+        let options = RepackOptions {
+            pack: PackOptions {
+                version,
+                blob_alignment: align,
+                ..PackOptions::default()
+            },
+            ..RepackOptions::default()
+        };
+        convert_version_to_path(&mut source, &options, &out)
+    };
+
+    let executor = PackOptions {
+        kind: ClfKind::Executor,
+        ..PackOptions::default()
+    };
+    let err = downgrade(&executor, 1, 0).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            RepackError::Downgrade {
+                version: 1,
+                feature: "kind byte"
+            }
+        ),
+        "{err}"
+    );
+    let err = downgrade(&PackOptions::default(), 2, 16).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            RepackError::Downgrade {
+                version: 2,
+                feature: "exact blob sizes"
+            }
+        ),
+        "{err}"
+    );
+    let with_metadata = PackOptions {
+        metadata: vec![("driver".to_string(), "1.0".to_string())],
+        ..PackOptions::default()
+    };
+    let err = downgrade(&with_metadata, 2, 0).unwrap_err();
+    assert!(
+        matches!(err, RepackError::Pack(PackError::RequiresV3 { .. })),
+        "{err}"
+    );
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+
+    downgrade(&PackOptions::default(), 2, 0).unwrap();
+    assert_eq!(ClfReader::open(&out).unwrap().header.version, 2);
+}
@@ -0,0 +1,786 @@
+//! Signature tests: keyed signature blocks (SIG1) alongside the legacy SIG0 hash.
+
+use std::io::Write;
+
+#[cfg(feature = "ed25519")]
+use clf::ed25519_dalek::SigningKey;
+use clf::{
+    append_signature, append_signature_hmac, pack_clf, pack_clf_to_path, resign, strip_signature,
+    ClfError, ClfReader, ClfReaderFromBytes, HmacKey, PackError, PackOptions, SIG_BLOCK_LEN,
+    SIG_MAGIC,
+};
+use tempfile::{tempdir, NamedTempFile};
+
+fn entries() -> Vec<(u32, Vec<u8>)> {
+    vec![(1, b"add kernel".to_vec()), (2, vec![0xAB; 300])]
+}
+
+/// Pack `entries()` to a fresh file in `dir` signed with `key`.
+#[cfg(feature = "ed25519")]
+fn signed_with(dir: &std::path::Path, key: &SigningKey) -> std::path::PathBuf {
+    let path = dir.join("signed.clf");
+    let options = PackOptions {
+        sign_key: Some(key.clone()),
+        ..PackOptions::default()
+    };
+    pack_clf_to_path(&path, entries(), &options).unwrap();
+    path
+}
+
+/// An Ed25519-signed archive verifies with its key and still serves its blobs.
+#[test]
+#[cfg(feature = "ed25519")]
+fn ed25519_sign_and_verify() {
+    let dir = tempdir().unwrap();
+    let key = SigningKey::from_bytes(&[7u8; 32]);
+    let path = signed_with(dir.path(), &key);
+
+    let mut reader = ClfReader::open(&path).unwrap();
+    assert!(reader.signature_block_present());
+    assert!(reader.verify_signature_with(&key.verifying_key()).unwrap());
+    assert!(reader.signature_verified());
+    reader.validate().unwrap();
+    assert_eq!(reader.get_blob(2).unwrap().unwrap(), vec![0xAB; 300]);
+    // The SIG0 check does not mistake the SIG1 block for a hash.
+    assert!(!reader.verify_signature().unwrap());
+}
+
+/// Flipping one byte of the signed data fails verification.
+#[test]
+#[cfg(feature = "ed25519")]
+fn ed25519_rejects_tampered_byte() {
+    let dir = tempdir().unwrap();
+    let key = SigningKey::from_bytes(&[7u8; 32]);
+    let path = signed_with(dir.path(), &key);
+
+    let mut bytes = std::fs::read(&path).unwrap();
+    let blob_store = ClfReader::open(&path).unwrap().blob_store_offset() as usize;
+    bytes[blob_store + 3] ^= 0x01;
+    std::fs::write(&path, &bytes).unwrap();
+
+    let err = ClfReader::open(&path)
+        .unwrap()
+        .verify_signature_with(&key.verifying_key())
+        .unwrap_err();
+    assert!(matches!(err, ClfError::SignatureInvalid), "{err}");
+}
+
+/// Verifying with a different public key fails.
+#[test]
+#[cfg(feature = "ed25519")]
+fn ed25519_rejects_wrong_key() {
+    let dir = tempdir().unwrap();
+    let path = signed_with(dir.path(), &SigningKey::from_bytes(&[7u8; 32]));
+
+    let other = SigningKey::from_bytes(&[8u8; 32]);
+    let err = ClfReader::open(&path)
+        .unwrap()
+        .verify_signature_with(&other.verifying_key())
+        .unwrap_err();
+    assert!(matches!(err, ClfError::SignatureInvalid), "{err}");
+}
+
+/// `verify_against` names the trusted key that signed the archive, rejects a valid signature
+/// from a key outside the set, and rejects a tampered file even when its key is trusted.
+#[test]
+#[cfg(feature = "ed25519")]
+fn verify_against_trusted_keys() {
+    use clf::{SignatureScope, TrustedKeys};
+
+    let dir = tempdir().unwrap();
+    let vendor = SigningKey::from_bytes(&[7u8; 32]);
+    let other = SigningKey::from_bytes(&[9u8; 32]);
+    let path = signed_with(dir.path(), &vendor);
+
+    let mut trusted = TrustedKeys::new();
+    trusted.add(other.verifying_key());
+    assert!(trusted.add_named("acme", vendor.verifying_key()));
+    assert!(!trusted.add(vendor.verifying_key()));
+    assert_eq!(trusted.len(), 2);
+    let mut reader = ClfReader::open(&path).unwrap();
+    let verified = reader.verify_against(&trusted).unwrap();
+    assert_eq!(verified.key.name.as_deref(), Some("acme"));
+    assert_eq!(verified.key.key, vendor.verifying_key());
+    assert_eq!(verified.scope, SignatureScope::Full);
+    assert!(reader.signature_verified());
+
+    let untrusted: TrustedKeys = [other.verifying_key()].into_iter().collect();
+    let mut reader = ClfReader::open(&path).unwrap();
+    assert!(reader
+        .verify_signature_with(&vendor.verifying_key())
+        .unwrap());
+    let err = reader.verify_against(&untrusted).unwrap_err();
+    let fingerprint = clf::format::key_fingerprint(vendor.verifying_key().as_bytes());
+    assert!(
+        matches!(&err, ClfError::UntrustedKey { fingerprint: f } if f[..] == fingerprint),
+        "{err}"
+    );
+    assert!(err.to_string().contains("not trusted"));
+
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes[40] ^= 0xFF;
+    std::fs::write(&path, &bytes).unwrap();
+    assert!(matches!(
+        ClfReader::open(&path).unwrap().verify_against(&trusted),
+        Err(ClfError::SignatureInvalid)
+    ));
+}
+
+/// Legacy SIG0 files keep verifying and report no Ed25519 signature.
+#[test]
+fn legacy_sig0_still_verifies() {
+    let mut file = NamedTempFile::new().unwrap();
+    let data_len = pack_clf(&mut file, entries(), &PackOptions::default()).unwrap();
+    append_signature(&mut file, data_len).unwrap();
+    file.flush().unwrap();
+
+    let mut reader = ClfReader::open(file.path()).unwrap();
+    assert!(reader.signature_block_present());
+    #[cfg(feature = "ed25519")]
+    {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        assert!(!reader.verify_signature_with(&key.verifying_key()).unwrap());
+    }
+    // A SIG0 hash is not a MAC: the HMAC check reports no keyed signature.
+    assert!(!reader.verify_signature_hmac(b"shared secret").unwrap());
+    assert!(!reader.signature_verified());
+    assert!(reader.verify_signature().unwrap());
+}
+
+/// An HMAC-signed archive verifies with the shared secret; the wrong key or a tampered byte fails.
+#[test]
+fn hmac_sign_verify_wrong_key_and_tamper() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("mac.clf");
+    let options = PackOptions {
+        hmac_key: Some(HmacKey::new(b"shared secret".to_vec())),
+        ..PackOptions::default()
+    };
+    assert!(!format!("{options:?}").contains("shared secret"));
+    pack_clf_to_path(&path, entries(), &options).unwrap();
+
+    let mut reader = ClfReader::open(&path).unwrap();
+    assert!(reader.signature_block_present());
+    assert!(reader.verify_signature_hmac(b"shared secret").unwrap());
+    assert!(reader.signature_verified());
+    assert_eq!(reader.get_blob(1).unwrap().unwrap(), b"add kernel");
+
+    let err = ClfReader::open(&path)
+        .unwrap()
+        .verify_signature_hmac(b"other secret")
+        .unwrap_err();
+    assert!(matches!(err, ClfError::SignatureInvalid), "{err}");
+
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes[reader.blob_store_offset() as usize] ^= 0x80;
+    std::fs::write(&path, &bytes).unwrap();
+    let err = ClfReader::open(&path)
+        .unwrap()
+        .verify_signature_hmac(b"shared secret")
+        .unwrap_err();
+    assert!(matches!(err, ClfError::SignatureInvalid), "{err}");
+}
+
+/// `append_signature_hmac` on an in-memory pack matches what the reader expects.
+#[test]
+fn hmac_append_to_open_file() {
+    let mut file = NamedTempFile::new().unwrap();
+    let data_len = pack_clf(&mut file, entries(), &PackOptions::default()).unwrap();
+    append_signature_hmac(&mut file, data_len, b"k").unwrap();
+    file.flush().unwrap();
+
+    let mut reader = ClfReader::open(file.path()).unwrap();
+    assert_eq!(
+        reader.blob_store_offset() + reader.blob_store_len(),
+        data_len
+    );
+    assert!(reader.verify_signature_hmac(b"k").unwrap());
+    assert!(!reader.verify_signature().unwrap());
+}
+
+/// strip_signature removes SIG0 and SIG1 trailers and leaves unsigned files alone.
+#[test]
+fn strip_signature_removes_trailers() {
+    let dir = tempdir().unwrap();
+    let unsigned = dir.path().join("unsigned.clf");
+    pack_clf_to_path(&unsigned, entries(), &PackOptions::default()).unwrap();
+    let unsigned_bytes = std::fs::read(&unsigned).unwrap();
+
+    for (name, options) in [
+        (
+            "sig0.clf",
+            PackOptions {
+                sign: true,
+                ..PackOptions::default()
+            },
+        ),
+        (
+            "hmac.clf",
+            PackOptions {
+                hmac_key: Some(HmacKey::new(b"k".to_vec())),
+                ..PackOptions::default()
+            },
+        ),
+    ] {
+        let path = dir.path().join(name);
+        pack_clf_to_path(&path, entries(), &options).unwrap();
+        assert!(strip_signature(&path).unwrap(), "{name}");
+        assert_eq!(std::fs::read(&path).unwrap(), unsigned_bytes, "{name}");
+        assert!(!strip_signature(&path).unwrap(), "{name}");
+    }
+
+    assert!(!strip_signature(&unsigned).unwrap());
+    assert_eq!(std::fs::read(&unsigned).unwrap(), unsigned_bytes);
+}
+
+/// An unsigned file whose last blob ends in something shaped like a SIG0 block is not truncated.
+#[test]
+fn strip_signature_ignores_blob_ending_in_magic() {
+    let mut tail = SIG_MAGIC.to_vec();
+    tail.extend_from_slice(&[0x5A; 32]);
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("lookalike.clf");
+    pack_clf_to_path(
+        &path,
+        &[(1, b"head".to_vec()), (2, tail.clone())],
+        &PackOptions::default(),
+    )
+    .unwrap();
+    let before = std::fs::read(&path).unwrap();
+    assert!(ClfReader::open(&path).unwrap().signature_block_present());
+
+    assert!(!strip_signature(&path).unwrap());
+    assert_eq!(std::fs::read(&path).unwrap(), before);
+}
+
+/// resign signs unsigned files, replaces a stale SIG0 or SHA-256 SIG1 block in place, is safe to
+/// repeat, and refuses keyed blocks.
+#[test]
+fn resign_signed_unsigned_and_after_edit() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("a.clf");
+    pack_clf_to_path(&path, entries(), &PackOptions::default()).unwrap();
+    let unsigned_len = std::fs::metadata(&path).unwrap().len();
+
+    let open_rw = || {
+        std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .unwrap()
+    };
+    let verifies = || ClfReader::open(&path).unwrap().verify_signature().unwrap();
+
+    // Unsigned -> signed; twice in a row stays a single valid block.
+    resign(&mut open_rw()).unwrap();
+    assert!(verifies());
+    resign(&mut open_rw()).unwrap();
+    assert!(verifies());
+    assert_eq!(
+        std::fs::metadata(&path).unwrap().len(),
+        unsigned_len + SIG_BLOCK_LEN as u64
+    );
+
+    // Edit a blob byte: the old signature is stale until resign.
+    let blob_start = ClfReader::open(&path).unwrap().blob_store_offset() as usize;
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes[blob_start] ^= 0xFF;
+    std::fs::write(&path, &bytes).unwrap();
+    assert!(ClfReader::open(&path).unwrap().verify_signature().is_err());
+    resign(&mut open_rw()).unwrap();
+    assert!(verifies());
+
+    // An unkeyed SHA-256 SIG1 block (index-only here) is replaced in kind.
+    let sha = dir.path().join("sha.clf");
+    let options = PackOptions {
+        vendor: "acme".to_string(),
+        sign: true,
+        sign_index_only: true,
+        ..PackOptions::default()
+    };
+    pack_clf_to_path(&sha, entries(), &options).unwrap();
+    let signed_len = std::fs::metadata(&sha).unwrap().len();
+    let mut bytes = std::fs::read(&sha).unwrap();
+    let vendor_at = bytes.windows(4).position(|w| w == b"acme").unwrap();
+    bytes[vendor_at..vendor_at + 4].copy_from_slice(b"ACME");
+    std::fs::write(&sha, &bytes).unwrap();
+    assert!(ClfReader::open(&sha).unwrap().verify_signature().is_err());
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&sha)
+        .unwrap();
+    resign(&mut file).unwrap();
+    resign(&mut file).unwrap();
+    drop(file);
+    assert_eq!(std::fs::metadata(&sha).unwrap().len(), signed_len);
+    let mut reader = ClfReader::open(&sha).unwrap();
+    assert_eq!(
+        reader.verify_signature_scope().unwrap(),
+        clf::SignatureScope::IndexOnly
+    );
+
+    // A keyed signature cannot be replaced by the unkeyed one in place.
+    let mac = dir.path().join("mac.clf");
+    let options = PackOptions {
+        hmac_key: Some(HmacKey::new(b"k".to_vec())),
+        ..PackOptions::default()
+    };
+    pack_clf_to_path(&mac, entries(), &options).unwrap();
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&mac)
+        .unwrap();
+    assert!(matches!(
+        resign(&mut file),
+        Err(PackError::KeyedSignaturePresent)
+    ));
+}
+
+/// `pack_and_sign` writes exactly what an unsigned `pack_clf` followed by the matching append
+/// call produces, for every signature kind `PackOptions` can request. `pack_clf` itself refuses
+/// options that request a signature.
+#[test]
+fn pack_and_sign_matches_two_step_signing() {
+    let two_step = |append: &dyn Fn(&mut std::io::Cursor<Vec<u8>>, u64)| {
+        let mut out = std::io::Cursor::new(Vec::new());
+        let data_len = pack_clf(&mut out, entries(), &PackOptions::default()).unwrap();
+        append(&mut out, data_len);
+        out.into_inner()
+    };
+    let one_step = |options: &PackOptions| {
+        let mut out = std::io::Cursor::new(Vec::new());
+        let summary = clf::pack_and_sign(&mut out, entries(), options).unwrap();
+        (out.into_inner(), summary.data_len)
+    };
+
+    let unsigned = PackOptions::default();
+    let (bytes, data_len) = one_step(&unsigned);
+    assert_eq!(bytes, two_step(&|_, _| {}));
+    assert_eq!(data_len, bytes.len() as u64);
+
+    let sig0 = PackOptions {
+        sign: true,
+        ..PackOptions::default()
+    };
+    let (bytes, data_len) = one_step(&sig0);
+    assert_eq!(
+        bytes,
+        two_step(&|out, len| append_signature(out, len).unwrap())
+    );
+    assert_eq!(data_len + SIG_BLOCK_LEN as u64, bytes.len() as u64);
+    assert!(matches!(
+        pack_clf(&mut std::io::Cursor::new(Vec::new()), entries(), &sig0),
+        Err(PackError::SigningUnsupported)
+    ));
+
+    let hmac = PackOptions {
+        hmac_key: Some(HmacKey::new(b"secret".to_vec())),
+        ..PackOptions::default()
+    };
+    assert_eq!(
+        one_step(&hmac).0,
+        two_step(&|out, len| append_signature_hmac(out, len, b"secret").unwrap())
+    );
+
+    #[cfg(feature = "ed25519")]
+    {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let ed25519 = PackOptions {
+            sign_key: Some(key.clone()),
+            ..PackOptions::default()
+        };
+        assert_eq!(
+            one_step(&ed25519).0,
+            two_step(&|out, len| { clf::append_signature_ed25519(out, len, &key).unwrap() })
+        );
+    }
+}
+
+/// An index-only signature covers the header and manifest: it verifies at open and afterwards
+/// with `IndexOnly` scope, a tampered manifest byte fails it, and a tampered blob is left to the
+/// per-blob CRC-32, so `verify_signature` does not count it as verified. `pack_and_sign`
+/// produces the same bytes as `append_signature_scoped`.
+#[test]
+fn index_only_signature_covers_header_and_manifest() {
+    use clf::{append_signature_scoped, ClfReaderOptions, SignatureScope};
+
+    let options = PackOptions {
+        sign: true,
+        sign_index_only: true,
+        per_blob_crc: true,
+        blob_alignment: 16,
+        ..PackOptions::default()
+    };
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    let summary = clf::pack_and_sign(&mut bytes, entries(), &options).unwrap();
+    let bytes = bytes.into_inner();
+    let unsigned = PackOptions {
+        sign: false,
+        ..options.clone()
+    };
+    let mut two_step = std::io::Cursor::new(Vec::new());
+    let data_len = pack_clf(&mut two_step, entries(), &unsigned).unwrap();
+    append_signature_scoped(&mut two_step, data_len, SignatureScope::IndexOnly).unwrap();
+    assert_eq!(bytes, two_step.into_inner());
+
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(&bytes).unwrap();
+    file.flush().unwrap();
+    let mut reader = ClfReader::open(file.path()).unwrap();
+    assert_eq!(reader.signature_scope(), SignatureScope::None);
+    assert_eq!(
+        reader.verify_signature_scope().unwrap(),
+        SignatureScope::IndexOnly
+    );
+    assert!(reader.signature_verified());
+    assert!(!reader.verify_signature().unwrap());
+    assert_eq!(
+        reader.blob_store_len(),
+        summary.data_len - reader.blob_store_offset()
+    );
+    let verify_on_open = ClfReaderOptions {
+        verify_signature: true,
+        ..ClfReaderOptions::default()
+    };
+    let opened = ClfReader::open_with_options(file.path(), &verify_on_open).unwrap();
+    assert_eq!(opened.signature_scope(), SignatureScope::IndexOnly);
+
+    let manifest_byte = reader.blob_store_offset() as usize - 1;
+    let blob = reader.manifest_entries()[1];
+    let blob_byte = (reader.blob_store_offset() + u64::from(blob.offset)) as usize;
+    let mut tampered = bytes.clone();
+    tampered[manifest_byte] ^= 0xFF;
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(&tampered).unwrap();
+    file.flush().unwrap();
+    assert!(matches!(
+        ClfReader::open(file.path()).unwrap().verify_signature(),
+        Err(ClfError::SignatureInvalid)
+    ));
+    assert!(ClfReader::open_with_options(file.path(), &verify_on_open).is_err());
+
+    let mut tampered = bytes.clone();
+    tampered[blob_byte] ^= 0xFF;
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(&tampered).unwrap();
+    file.flush().unwrap();
+    let mut reader = ClfReader::open_with_options(file.path(), &verify_on_open).unwrap();
+    assert_eq!(reader.signature_scope(), SignatureScope::IndexOnly);
+    assert!(!reader.verify_signature().unwrap());
+    assert!(matches!(
+        reader.get_blob(2),
+        Err(ClfError::BlobChecksumMismatch { op_id: 2, .. })
+    ));
+
+    let hmac = PackOptions {
+        hmac_key: Some(HmacKey::new(b"secret".to_vec())),
+        sign_index_only: true,
+        ..PackOptions::default()
+    };
+    let mut file = NamedTempFile::new().unwrap();
+    clf::pack_and_sign(&mut file, entries(), &hmac).unwrap();
+    file.flush().unwrap();
+    let mut reader = ClfReader::open(file.path()).unwrap();
+    assert_eq!(
+        reader.verify_signature_scope().unwrap(),
+        SignatureScope::None
+    );
+    assert!(reader.verify_signature_hmac(b"secret").unwrap());
+    assert_eq!(reader.signature_scope(), SignatureScope::IndexOnly);
+}
+
+/// `append_signature_with` writes a SIG1 block for each algorithm, which the reader reports and
+/// verifies; SIG0 files report SHA-256 and an unknown algorithm id is named in the error. A block
+/// whose signature overflows its u16 length prefix is not serialized.
+#[test]
+fn append_signature_with_records_algorithm() {
+    use clf::format::SignatureBlock;
+    use clf::{append_signature_with, SignWith, SignatureAlgorithm, SignatureScope};
+
+    let signed = |sign: SignWith<'_>| {
+        let mut out = std::io::Cursor::new(Vec::new());
+        let data_len = pack_clf(&mut out, entries(), &PackOptions::default()).unwrap();
+        append_signature_with(&mut out, data_len, sign, SignatureScope::Full).unwrap();
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&out.into_inner()).unwrap();
+        file.flush().unwrap();
+        file
+    };
+
+    let file = signed(SignWith::Sha256);
+    let mut reader = ClfReader::open(file.path()).unwrap();
+    assert_eq!(
+        reader.signature_algorithm().unwrap(),
+        Some(SignatureAlgorithm::Sha256)
+    );
+    assert_eq!(
+        reader.verify_signature_scope().unwrap(),
+        SignatureScope::Full
+    );
+
+    let file = signed(SignWith::HmacSha256(b"secret"));
+    let mut reader = ClfReader::open(file.path()).unwrap();
+    assert_eq!(
+        reader.signature_algorithm().unwrap(),
+        Some(SignatureAlgorithm::HmacSha256)
+    );
+    assert!(reader.verify_signature_hmac(b"secret").unwrap());
+
+    #[cfg(feature = "ed25519")]
+    {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let file = signed(SignWith::Ed25519(&key));
+        let mut reader = ClfReader::open(file.path()).unwrap();
+        assert_eq!(
+            reader.signature_algorithm().unwrap(),
+            Some(SignatureAlgorithm::Ed25519)
+        );
+        assert!(reader.verify_signature_with(&key.verifying_key()).unwrap());
+    }
+
+    let mut out = std::io::Cursor::new(Vec::new());
+    let data_len = pack_clf(&mut out, entries(), &PackOptions::default()).unwrap();
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(out.get_ref()).unwrap();
+    file.flush().unwrap();
+    assert_eq!(
+        ClfReader::open(file.path())
+            .unwrap()
+            .signature_algorithm()
+            .unwrap(),
+        None
+    );
+    append_signature(&mut out, data_len).unwrap();
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(out.get_ref()).unwrap();
+    file.flush().unwrap();
+    assert_eq!(
+        ClfReader::open(file.path())
+            .unwrap()
+            .signature_algorithm()
+            .unwrap(),
+        Some(SignatureAlgorithm::Sha256)
+    );
+
+    let mut bytes = out.into_inner();
+    bytes.truncate(data_len as usize);
+    let unknown = SignatureBlock {
+        algorithm: 9,
+        index_only: false,
+        key_fingerprint: Vec::new(),
+        signature: vec![0; 4],
+    };
+    bytes.extend_from_slice(&unknown.to_bytes().unwrap());
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(&bytes).unwrap();
+    file.flush().unwrap();
+    assert!(matches!(
+        ClfReader::open(file.path()).unwrap().signature_algorithm(),
+        Err(ClfError::UnknownSignatureAlgorithm(9))
+    ));
+    assert_eq!(
+        SignatureAlgorithm::from_id(2),
+        Some(SignatureAlgorithm::HmacSha256)
+    );
+    assert_eq!(SignatureAlgorithm::Ed25519.to_string(), "ed25519");
+
+    let oversized = SignatureBlock {
+        signature: vec![0; 65536],
+        ..unknown
+    };
+    assert!(matches!(
+        oversized.to_bytes(),
+        Err(ClfError::LimitExceeded { value: 65536, .. })
+    ));
+}
+
+/// Bytes appended after a signature block (1 byte or 1 KiB) are reported as `TrailingGarbage` by
+/// `validate` and every verification path instead of being folded into the blob store.
+#[test]
+fn appended_data_after_signature_is_rejected() {
+    use clf::{ClfReaderOptions, SignWith, SignatureScope};
+
+    for sign in [None, Some(SignWith::Sha256)] {
+        let mut out = std::io::Cursor::new(Vec::new());
+        let data_len = pack_clf(&mut out, entries(), &PackOptions::default()).unwrap();
+        match sign {
+            Some(sign) => {
+                clf::append_signature_with(&mut out, data_len, sign, SignatureScope::Full).unwrap()
+            }
+            None => append_signature(&mut out, data_len).unwrap(),
+        }
+        let signed = out.into_inner();
+        ClfReaderFromBytes::open(&signed, None)
+            .unwrap()
+            .validate()
+            .unwrap();
+
+        for junk in [1usize, 1024] {
+            let mut bytes = signed.clone();
+            bytes.extend(std::iter::repeat(0x5A).take(junk));
+            let expected = ClfError::TrailingGarbage {
+                expected_len: signed.len() as u64,
+                actual_len: bytes.len() as u64,
+            };
+            let mut file = NamedTempFile::new().unwrap();
+            file.write_all(&bytes).unwrap();
+            file.flush().unwrap();
+
+            let mut reader = ClfReader::open(file.path()).unwrap();
+            assert_eq!(reader.trailing_data_len(), junk as u64);
+            assert_eq!(
+                reader.validate().unwrap_err().to_string(),
+                expected.to_string()
+            );
+            assert!(matches!(
+                reader.verify_signature(),
+                Err(ClfError::TrailingGarbage { .. })
+            ));
+            assert!(!reader.signature_verified());
+            let verify_on_open = ClfReaderOptions {
+                verify_signature: true,
+                ..ClfReaderOptions::default()
+            };
+            let err = ClfReader::open_with_options(file.path(), &verify_on_open).unwrap_err();
+            assert!(
+                matches!(err.root(), ClfError::TrailingGarbage { .. }),
+                "{err}"
+            );
+            let from_bytes = ClfReaderFromBytes::open(&bytes, None).unwrap();
+            assert_eq!(from_bytes.trailing_data_len(), junk as u64);
+            assert!(from_bytes.validate().is_err());
+        }
+    }
+}
+
+/// `verify_signature_with_progress` reports increasing byte counts up to the hashed total and
+/// aborts with `Cancelled` when the callback breaks.
+#[test]
+fn verify_signature_reports_progress_and_cancels() {
+    use std::ops::ControlFlow;
+
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("big.clf");
+    let options = PackOptions {
+        sign: true,
+        ..PackOptions::default()
+    };
+    let big = vec![(1, vec![0x11; 1 << 20]), (2, vec![0x22; 100])];
+    pack_clf_to_path(&path, big, &options).unwrap();
+    let hashed = std::fs::metadata(&path).unwrap().len() - SIG_BLOCK_LEN as u64;
+
+    let mut calls = Vec::new();
+    let mut reader = ClfReader::open(&path).unwrap();
+    let scope = reader
+        .verify_signature_with_progress(|done, total| {
+            calls.push((done, total));
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+    assert_eq!(scope, clf::SignatureScope::Full);
+    assert!(calls.len() >= 4, "{calls:?}");
+    assert!(calls.windows(2).all(|w| w[0].0 < w[1].0));
+    assert!(calls.iter().all(|&(_, total)| total == hashed));
+    assert_eq!(calls.last().unwrap().0, hashed);
+
+    let mut reader = ClfReader::open(&path).unwrap();
+    let mut seen = 0;
+    let result = reader.verify_signature_with_progress(|_, _| {
+        seen += 1;
+        ControlFlow::Break(())
+    });
+    assert!(matches!(result, Err(ClfError::Cancelled)));
+    assert_eq!(seen, 1);
+    assert!(!reader.signature_verified());
+}
+
+/// A `data_len` past the end of the stream is an error instead of a signature over fewer bytes,
+/// and nothing is appended.
+#[test]
+fn append_signature_rejects_short_data() {
+    let mut out = std::io::Cursor::new(Vec::new());
+    let data_len = pack_clf(&mut out, entries(), &PackOptions::default()).unwrap();
+    let err = append_signature(&mut out, data_len + 10).unwrap_err();
+    assert!(
+        matches!(err, PackError::TruncatedData { expected, got } if expected == data_len + 10 && got == data_len),
+        "{err}"
+    );
+    assert_eq!(out.get_ref().len() as u64, data_len);
+}
+
+/// Bytes past `data_len` that are not a signature block are an error instead of being signed
+/// over, and nothing is appended.
+#[test]
+fn append_signature_rejects_trailing_data() {
+    let mut out = std::io::Cursor::new(Vec::new());
+    let data_len = pack_clf(&mut out, entries(), &PackOptions::default()).unwrap();
+    out.get_mut().extend_from_slice(&[0xEE; 10]);
+    let err = append_signature(&mut out, data_len).unwrap_err();
+    assert!(
+        matches!(err, PackError::TrailingData { expected, got } if expected == data_len && got == data_len + 10),
+        "{err}"
+    );
+    assert_eq!(out.get_ref().len() as u64, data_len + 10);
+}
+
+/// Signing an already signed stream again is refused for both trailer kinds, leaving the first
+/// signature valid.
+#[test]
+fn append_signature_refuses_second_signature() {
+    let mut file = NamedTempFile::new().unwrap();
+    let data_len = pack_clf(&mut file, entries(), &PackOptions::default()).unwrap();
+    append_signature(&mut file, data_len).unwrap();
+    assert!(matches!(
+        append_signature(&mut file, data_len),
+        Err(PackError::AlreadySigned)
+    ));
+    assert!(matches!(
+        append_signature_hmac(&mut file, data_len, b"k"),
+        Err(PackError::AlreadySigned)
+    ));
+    file.flush().unwrap();
+    let mut reader = ClfReader::open(file.path()).unwrap();
+    assert!(reader.verify_signature().unwrap());
+
+    let mut mac = std::io::Cursor::new(Vec::new());
+    let data_len = pack_clf(&mut mac, entries(), &PackOptions::default()).unwrap();
+    append_signature_hmac(&mut mac, data_len, b"k").unwrap();
+    assert!(matches!(
+        append_signature(&mut mac, data_len),
+        Err(PackError::AlreadySigned)
+    ));
+}
+
+/// `signed_len` is `None` for an unsigned archive, everything before a SIG0 block, and the
+/// header and manifest for an index-only signature.
+#[test]
+fn signed_len_reports_covered_prefix() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("lib.clfc");
+    let len_with = |options: &PackOptions| {
+        pack_clf_to_path(&path, entries(), options).unwrap();
+        let reader = ClfReader::open(&path).unwrap();
+        let file_len = std::fs::metadata(&path).unwrap().len();
+        (
+            reader.signed_len().unwrap(),
+            file_len,
+            reader.blob_store_offset(),
+        )
+    };
+
+    let (signed, _, _) = len_with(&PackOptions::default());
+    assert_eq!(signed, None);
+    let (signed, file_len, _) = len_with(&PackOptions {
+        sign: true,
+        ..PackOptions::default()
+    });
+    assert_eq!(signed, Some(file_len - SIG_BLOCK_LEN as u64));
+    let (signed, _, blob_store_offset) = len_with(&PackOptions {
+        sign: true,
+        sign_index_only: true,
+        ..PackOptions::default()
+    });
+    assert_eq!(signed, Some(blob_store_offset));
+}
@@ -0,0 +1,60 @@
+//! ClfSlice tests: borrow blobs from an archive in memory, reject archives whose entries leave it.
+
+use std::io::Cursor;
+
+use clf::{
+    pack_clf, pack_clf_variants, ClfError, ClfKind, ClfReaderFromBytes, ClfSlice, PackOptions,
+};
+
+/// Blobs, op_ids and variants match `ClfReaderFromBytes`, and blobs are subslices of the input.
+#[test]
+fn slice_matches_reader_from_bytes() {
+    let entries: Vec<(u32, u16, Vec<u8>)> = vec![
+        (1, 0, b"add".to_vec()),
+        (1, 2, b"add_avx2".to_vec()),
+        (50, 0, b"matmul".to_vec()),
+    ];
+    let options = PackOptions {
+        blob_alignment: 16,
+        ..PackOptions::default()
+    };
+    let mut buf = Cursor::new(Vec::new());
+    pack_clf_variants(&mut buf, &entries, &options).unwrap();
+    let bytes = buf.into_inner();
+
+    let slice = ClfSlice::open(&bytes, Some(ClfKind::Compute)).unwrap();
+    let reader = ClfReaderFromBytes::open(&bytes, None).unwrap();
+    assert_eq!(slice.op_ids().collect::<Vec<_>>(), [1, 50]);
+    assert_eq!(slice.manifest_entries(), reader.manifest_entries());
+    assert_eq!(slice.variants(1), [0, 2]);
+    assert_eq!(slice.blob_store_offset() as u64, reader.blob_store_offset());
+    for (op_id, variant, blob) in &entries {
+        let got = slice.get_blob_variant(*op_id, *variant).unwrap();
+        assert_eq!(got, blob.as_slice());
+        assert!(bytes.as_ptr_range().contains(&got.as_ptr()));
+    }
+    assert_eq!(slice.get_blob(2), None);
+    assert!(matches!(
+        ClfSlice::open(&bytes, Some(ClfKind::Executor)),
+        Err(ClfError::KindMismatch { .. })
+    ));
+}
+
+/// Cutting the blob store short fails at open with `BlobOutOfBounds`, and no prefix of an
+/// archive panics.
+#[test]
+fn slice_rejects_truncated_archives() {
+    let entries: Vec<(u32, Vec<u8>)> = vec![(1, vec![1; 8]), (2, vec![2; 8])];
+    let mut buf = Cursor::new(Vec::new());
+    pack_clf(&mut buf, &entries, &PackOptions::default()).unwrap();
+    let bytes = buf.into_inner();
+
+    let err = ClfSlice::open(&bytes[..bytes.len() - 1], None).unwrap_err();
+    assert!(
+        matches!(err, ClfError::BlobOutOfBounds { op_id: 2 }),
+        "{err}"
+    );
+    for len in 0..bytes.len() {
+        assert!(ClfSlice::open(&bytes[..len], None).is_err(), "len {len}");
+    }
+}
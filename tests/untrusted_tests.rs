@@ -0,0 +1,173 @@
+//! parse_untrusted tests: full checks of an in-memory archive, limits enforced before
+//! allocating, and no panic on any prefix or bit flip of a valid archive.
+
+use std::io::Cursor;
+
+use clf::{
+    pack_and_sign, pack_clf, pack_clf_variants, parse_untrusted, ClfError, ClfLimits, PackOptions,
+    SignatureAlgorithm,
+};
+
+fn pack(entries: &[(u32, Vec<u8>)], options: &PackOptions) -> Vec<u8> {
+    let mut buf = Cursor::new(Vec::new());
+    pack_and_sign(&mut buf, entries, options).unwrap();
+    buf.into_inner()
+}
+
+/// An archive with CRCs and variants parses and returns its blobs, and a flipped blob byte fails
+/// the CRC check during the parse. A signed one reports its trailer algorithm, and rejects data
+/// appended after the trailer.
+#[test]
+fn parse_untrusted_checks_whole_archive() {
+    let entries: Vec<(u32, u16, Vec<u8>)> = vec![
+        (1, 0, b"add".to_vec()),
+        (1, 2, b"add_avx2".to_vec()),
+        (50, 0, b"matmul".to_vec()),
+    ];
+    let options = PackOptions {
+        blob_alignment: 16,
+        per_blob_crc: true,
+        ..PackOptions::default()
+    };
+    let mut buf = Cursor::new(Vec::new());
+    pack_clf_variants(&mut buf, &entries, &options).unwrap();
+    let mut bytes = buf.into_inner();
+
+    let parsed = parse_untrusted(&bytes, &ClfLimits::default()).unwrap();
+    assert_eq!(parsed.manifest.entries.len(), 3);
+    assert_eq!(parsed.signature_algorithm, None);
+    for (op_id, variant, blob) in &entries {
+        let got = parsed.get_blob_variant(*op_id, *variant).unwrap();
+        assert_eq!(got.as_deref(), Some(blob.as_slice()));
+    }
+    assert_eq!(parsed.get_blob(2).unwrap(), None);
+
+    let last = bytes.len() - 16;
+    bytes[last] ^= 0xFF;
+    let err = parse_untrusted(&bytes, &ClfLimits::default()).unwrap_err();
+    assert!(
+        matches!(err, ClfError::BlobChecksumMismatch { op_id: 50, .. }),
+        "{err}"
+    );
+
+    let signed = pack(
+        &[(1, b"add".to_vec())],
+        &PackOptions {
+            sign: true,
+            ..PackOptions::default()
+        },
+    );
+    let parsed = parse_untrusted(&signed, &ClfLimits::default()).unwrap();
+    assert_eq!(parsed.signature_algorithm, Some(SignatureAlgorithm::Sha256));
+    let mut appended = signed.clone();
+    appended.push(0);
+    assert!(matches!(
+        parse_untrusted(&appended, &ClfLimits::default()),
+        Err(ClfError::TrailingGarbage { .. })
+    ));
+}
+
+/// Counts and lengths over the limits fail with `LimitExceeded`, and a section length larger
+/// than the whole input is rejected the same way instead of being allocated.
+#[test]
+fn parse_untrusted_enforces_limits() {
+    let entries: Vec<(u32, Vec<u8>)> = (1..=4).map(|op_id| (op_id, vec![0; 8])).collect();
+    let options = PackOptions {
+        metadata: vec![("driver".to_string(), "x".repeat(64))],
+        ..PackOptions::default()
+    };
+    let bytes = pack(&entries, &options);
+    parse_untrusted(&bytes, &ClfLimits::default()).unwrap();
+
+    let limits = ClfLimits {
+        max_entries: 3,
+        ..ClfLimits::default()
+    };
+    let err = parse_untrusted(&bytes, &limits).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            ClfError::LimitExceeded {
+                what: "manifest entry count",
+                value: 4,
+                limit: 3
+            }
+        ),
+        "{err}"
+    );
+    let limits = ClfLimits {
+        max_section_len: 32,
+        ..ClfLimits::default()
+    };
+    let err = parse_untrusted(&bytes, &limits).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            ClfError::LimitExceeded {
+                what: "header section",
+                ..
+            }
+        ),
+        "{err}"
+    );
+
+    // Empty vendor and target: the metadata section length sits at byte 15. 1 MiB passes the
+    // default limit but not the length of the input.
+    let mut huge = pack(&entries, &PackOptions::default());
+    huge[15..19].copy_from_slice(&(1u32 << 20).to_le_bytes());
+    let err = parse_untrusted(&huge, &ClfLimits::default()).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            ClfError::LimitExceeded {
+                what: "header section",
+                ..
+            }
+        ),
+        "{err}"
+    );
+}
+
+/// Every prefix and every single-bit flip of a valid archive returns (an error or a parse)
+/// without panicking, and so does fetching each blob of whatever parses. Every proper prefix of
+/// an unsigned archive is an error.
+#[test]
+fn parse_untrusted_never_panics_on_damaged_input() {
+    let entries: Vec<(u32, Vec<u8>)> = vec![(1, b"add".to_vec()), (50, b"matmul".to_vec())];
+    let options = PackOptions {
+        blob_alignment: 8,
+        per_blob_crc: true,
+        metadata: vec![("k".to_string(), "v".to_string())],
+        sign: true,
+        ..PackOptions::default()
+    };
+    let bytes = pack(&entries, &options);
+    let mut unsigned = Cursor::new(Vec::new());
+    pack_clf(&mut unsigned, &entries, &PackOptions::default()).unwrap();
+
+    let limits = ClfLimits::default();
+    let exercise = |input: &[u8]| {
+        if let Ok(parsed) = parse_untrusted(input, &limits) {
+            for entry in &parsed.manifest.entries {
+                let _ = parsed.get_blob_variant(entry.op_id, entry.variant);
+            }
+        }
+    };
+    let unsigned = unsigned.into_inner();
+    for len in 0..unsigned.len() {
+        assert!(
+            parse_untrusted(&unsigned[..len], &limits).is_err(),
+            "len {len}"
+        );
+    }
+    for input in [&bytes, &unsigned] {
+        for len in 0..input.len() {
+            exercise(&input[..len]);
+        }
+        for bit in 0..input.len() * 8 {
+            let mut flipped = input.clone();
+            flipped[bit / 8] ^= 1 << (bit % 8);
+            exercise(&flipped);
+        }
+    }
+}
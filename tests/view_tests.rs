@@ -0,0 +1,139 @@
+//! ManifestView tests: snapshots of the header and manifest, their JSON form, and diffs between
+//! two snapshots.
+
+use std::io::{Cursor, Write};
+
+use clf::{
+    append_signature, pack_clf_variants, ClfKind, ClfReader, ClfReaderFromBytes, ManifestView,
+    ManifestViewEntry, PackOptions,
+};
+
+fn view(entries: &[(u32, u16, Vec<u8>)], options: &PackOptions) -> ManifestView {
+    let mut buf = Cursor::new(Vec::new());
+    pack_clf_variants(&mut buf, entries, options).unwrap();
+    ClfReaderFromBytes::open(buf.get_ref(), None)
+        .unwrap()
+        .manifest_view()
+}
+
+/// The view records the header, signature presence and each entry with its CRC-32, target and
+/// name, in (op_id, variant) order; both readers agree, and it survives a JSON round trip.
+#[test]
+fn manifest_view_snapshots_header_and_entries() {
+    let entries: Vec<(u32, u16, Vec<u8>)> = vec![
+        (50, 0, b"matmul".to_vec()),
+        (1, 2, b"add_avx2".to_vec()),
+        (1, 0, b"add".to_vec()),
+    ];
+    let options = PackOptions {
+        vendor: "acme".to_string(),
+        target: "x86_64".to_string(),
+        kind: ClfKind::Compute,
+        blob_alignment: 16,
+        per_blob_crc: true,
+        entry_names: [(50, "tuned_matmul".to_string())].into_iter().collect(),
+        entry_targets: [((1, 2), "x86_64_v3".to_string())].into_iter().collect(),
+        ..PackOptions::default()
+    };
+    let mut buf = Cursor::new(Vec::new());
+    let summary = pack_clf_variants(&mut buf, &entries, &options).unwrap();
+    append_signature(&mut buf, summary.data_len).unwrap();
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(buf.get_ref()).unwrap();
+    file.flush().unwrap();
+
+    let reader = ClfReader::open(file.path()).unwrap();
+    let snapshot = reader.manifest_view();
+    assert_eq!(
+        snapshot,
+        ClfReaderFromBytes::open(buf.get_ref(), None)
+            .unwrap()
+            .manifest_view()
+    );
+    assert_eq!(snapshot.vendor, "acme");
+    assert_eq!(snapshot.target, "x86_64");
+    assert_eq!(snapshot.blob_alignment, 16);
+    assert!(snapshot.signature_present);
+    let keys: Vec<(u32, u16)> = snapshot
+        .entries
+        .iter()
+        .map(|e| (e.op_id, e.variant))
+        .collect();
+    assert_eq!(keys, [(1, 0), (1, 2), (50, 0)]);
+    let matmul = &snapshot.entries[2];
+    assert_eq!(matmul.exact_size, 6);
+    assert_eq!(matmul.crc32, reader.manifest_entries()[2].crc32);
+    assert!(matmul.crc32.is_some());
+    assert_eq!(matmul.name.as_deref(), Some("tuned_matmul"));
+    assert_eq!(snapshot.entries[1].target, "x86_64_v3");
+    assert_eq!(snapshot.entries[0].target, "x86_64");
+
+    #[cfg(feature = "serde")]
+    {
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let back: ManifestView = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, snapshot);
+        assert!(back.diff(&snapshot).is_empty());
+    }
+}
+
+/// `diff` lists changed header fields and added, removed and changed entries, and ignores an
+/// entry that only moved because an earlier blob changed size.
+#[test]
+fn manifest_view_diff_reports_entry_changes() {
+    let options = PackOptions {
+        per_blob_crc: true,
+        ..PackOptions::default()
+    };
+    let old = view(
+        &[
+            (1, 0, b"add".to_vec()),
+            (2, 0, b"sub".to_vec()),
+            (50, 0, b"matmul".to_vec()),
+        ],
+        &options,
+    );
+    let new = view(
+        &[
+            (1, 0, b"add v2.1".to_vec()),
+            (50, 0, b"matmul".to_vec()),
+            (60, 0, b"conv".to_vec()),
+        ],
+        &PackOptions {
+            vendor: "acme".to_string(),
+            ..options.clone()
+        },
+    );
+    assert_ne!(old.entries[2].offset, new.entries[1].offset);
+
+    let diff = old.diff(&new);
+    assert_eq!(
+        diff.header,
+        [("vendor".to_string(), String::new(), "acme".to_string())]
+    );
+    let ids =
+        |entries: &[ManifestViewEntry]| -> Vec<u32> { entries.iter().map(|e| e.op_id).collect() };
+    assert_eq!(ids(&diff.added), [60]);
+    assert_eq!(ids(&diff.removed), [2]);
+    assert_eq!(diff.changed.len(), 1);
+    let (before, after) = &diff.changed[0];
+    assert_eq!(
+        (before.op_id, before.exact_size, after.exact_size),
+        (1, 3, 8)
+    );
+    assert!(!diff.is_empty());
+    assert!(old.diff(&old).is_empty());
+
+    // Same length, different bytes: only the CRC-32 tells them apart.
+    let edited = view(
+        &[
+            (1, 0, b"ADD".to_vec()),
+            (2, 0, b"sub".to_vec()),
+            (50, 0, b"matmul".to_vec()),
+        ],
+        &options,
+    );
+    let changed = old.diff(&edited).changed;
+    assert_eq!(changed.len(), 1);
+    assert_eq!(changed[0].0.op_id, 1);
+}